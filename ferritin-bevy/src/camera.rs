@@ -0,0 +1,382 @@
+//! Camera
+//!
+//! An orbit camera for inspecting structures: left-drag rotates around the focus point,
+//! right- or middle-drag pans and the scroll wheel zooms. Cameras with
+//! [`OrbitCamera::auto_frame`] set are re-framed on the bounding box of all structures
+//! whenever one is added.
+//!
+//! Views saved in PyMOL sessions can be reproduced with [`PymolView`], built from the
+//! 25-element `SceneView` array parsed by ferritin-pymol.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use ferritin_bevy::{CameraControlPlugin, OrbitCamera, StructurePlugin};
+//!
+//! fn setup(mut commands: Commands) {
+//!     commands.spawn((Camera3d::default(), OrbitCamera::default()));
+//! }
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(StructurePlugin::new().with_file("examples/1fap.cif", None))
+//!     .add_plugins(CameraControlPlugin)
+//!     .add_systems(Startup, setup)
+//!     .run();
+//! ```
+use super::Structure;
+use bevy::input::mouse::{AccumulatedMouseMotion, AccumulatedMouseScroll, MouseScrollUnit};
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use ferritin_core::AtomCollection;
+
+/// PyMOL's default vertical field of view, in degrees
+const PYMOL_FOV_DEGREES: f32 = 20.0;
+/// Scroll lines that halve or double the camera distance
+const ZOOM_LINES_PER_OCTAVE: f32 = 5.0;
+/// Scroll pixels per line for touchpads
+const PIXELS_PER_LINE: f32 = 16.0;
+
+pub struct CameraControlPlugin;
+
+impl Plugin for CameraControlPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<SetCameraView>().add_systems(
+            Update,
+            (
+                auto_frame_structures,
+                apply_camera_views,
+                orbit_camera_input,
+                update_orbit_transforms,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Orbit state of a camera; the camera's [`Transform`] is derived from it every frame.
+#[derive(Component, Clone, Debug)]
+pub struct OrbitCamera {
+    /// Point the camera orbits around and looks at
+    pub focus: Vec3,
+    /// Distance from the focus point
+    pub distance: f32,
+    /// Camera orientation; the camera looks down its local -Z axis
+    pub rotation: Quat,
+    /// Radians per pixel of mouse drag
+    pub orbit_sensitivity: f32,
+    /// Frame all structures when a new one is added
+    pub auto_frame: bool,
+}
+
+impl Default for OrbitCamera {
+    fn default() -> Self {
+        Self {
+            focus: Vec3::ZERO,
+            distance: 100.0,
+            rotation: Quat::IDENTITY,
+            orbit_sensitivity: 0.005,
+            auto_frame: true,
+        }
+    }
+}
+
+impl OrbitCamera {
+    pub fn transform(&self) -> Transform {
+        Transform {
+            translation: self.focus + self.rotation * Vec3::Z * self.distance,
+            rotation: self.rotation,
+            ..default()
+        }
+    }
+    /// Look at the box spanned by `min` and `max`, keeping the current orientation
+    pub fn frame_bounds(&mut self, min: Vec3, max: Vec3, fov: f32) {
+        let (focus, distance) = frame_bounds(min, max, fov);
+        self.focus = focus;
+        self.distance = distance;
+    }
+}
+
+/// Set every [`OrbitCamera`] to a PyMOL view
+#[derive(Event, Clone, Debug)]
+pub struct SetCameraView(pub PymolView);
+
+/// A PyMOL camera view.
+///
+/// PyMOL stores the view as a model-to-camera rotation, the position of the origin of
+/// rotation in camera space (the z component is minus the camera distance), the origin
+/// of rotation in model space, the front and rear clipping distances and an orthoscopic
+/// flag.
+#[derive(Clone, Debug, PartialEq)]
+pub struct PymolView {
+    pub rotation: Mat3,
+    pub position: Vec3,
+    pub origin: Vec3,
+    pub front: f32,
+    pub rear: f32,
+    pub orthoscopic: bool,
+}
+
+impl PymolView {
+    /// Read the 25-element view stored in a PyMOL session, i.e. the output of
+    /// `SceneView::to_array` in ferritin-pymol. The first 16 values are a column-major
+    /// 4x4 rotation matrix.
+    pub fn from_array(view: [f64; 25]) -> Self {
+        let v = view.map(|x| x as f32);
+        PymolView {
+            rotation: Mat3::from_cols_array(&[
+                v[0], v[1], v[2], v[4], v[5], v[6], v[8], v[9], v[10],
+            ]),
+            position: Vec3::new(v[16], v[17], v[18]),
+            origin: Vec3::new(v[19], v[20], v[21]),
+            front: v[22],
+            rear: v[23],
+            orthoscopic: v[24] > 0.5,
+        }
+    }
+    /// Read the 18-element view printed by PyMOL's `get_view` command, which holds the
+    /// 3x3 rotation instead of the full 4x4 matrix.
+    pub fn from_get_view(view: [f64; 18]) -> Self {
+        let mut full = [0.0; 25];
+        for col in 0..3 {
+            full[col * 4..col * 4 + 3].copy_from_slice(&view[col * 3..col * 3 + 3]);
+        }
+        full[15] = 1.0;
+        full[16..24].copy_from_slice(&view[9..17]);
+        full[24] = view[17];
+        Self::from_array(full)
+    }
+    /// Orbit state reproducing this view
+    pub fn orbit(&self) -> OrbitCamera {
+        // camera space -> model space is the transpose of PyMOL's model -> camera rotation
+        let rotation = Quat::from_mat3(&self.rotation.transpose()).normalize();
+        OrbitCamera {
+            focus: self.origin - rotation * Vec3::new(self.position.x, self.position.y, 0.0),
+            distance: -self.position.z,
+            rotation,
+            ..default()
+        }
+    }
+    /// Camera transform reproducing this view
+    pub fn transform(&self) -> Transform {
+        self.orbit().transform()
+    }
+    /// Projection with PyMOL's field of view and clipping planes
+    pub fn projection(&self) -> Projection {
+        let near = self.front.max(0.1);
+        let far = self.rear.max(near + 1.0);
+        if self.orthoscopic {
+            // match the visible height at the origin of rotation
+            let height = 2.0 * -self.position.z * (PYMOL_FOV_DEGREES.to_radians() / 2.0).tan();
+            Projection::Orthographic(OrthographicProjection {
+                near,
+                far,
+                scaling_mode: bevy::render::camera::ScalingMode::FixedVertical {
+                    viewport_height: height,
+                },
+                ..OrthographicProjection::default_3d()
+            })
+        } else {
+            Projection::Perspective(PerspectiveProjection {
+                fov: PYMOL_FOV_DEGREES.to_radians(),
+                near,
+                far,
+                ..default()
+            })
+        }
+    }
+}
+
+/// Center of the box spanned by `min` and `max` and the camera distance at which its
+/// bounding sphere fills a vertical field of view `fov`
+pub fn frame_bounds(min: Vec3, max: Vec3, fov: f32) -> (Vec3, f32) {
+    let center = (min + max) / 2.0;
+    let radius = ((max - min).length() / 2.0).max(1.0);
+    (center, radius / (fov / 2.0).sin())
+}
+
+/// Axis-aligned bounding box of the atoms in `ac`
+pub fn bounding_box(ac: &AtomCollection) -> Option<(Vec3, Vec3)> {
+    ac.get_coords()
+        .iter()
+        .map(|c| Vec3::from_array(*c))
+        .fold(None, |bounds, c| {
+            Some(match bounds {
+                None => (c, c),
+                Some((min, max)) => (min.min(c), max.max(c)),
+            })
+        })
+}
+
+/// A camera transform looking down -Z at the bounding box of `ac`, with the box's
+/// bounding sphere (scaled by `margin`) fitting a vertical field of view `fov`.
+pub fn framing_camera(ac: &AtomCollection, fov: f32, margin: f32) -> Transform {
+    let Some((min, max)) = bounding_box(ac) else {
+        return Transform::from_xyz(0.0, 0.0, 10.0).looking_at(Vec3::ZERO, Vec3::Y);
+    };
+    let (focus, distance) = frame_bounds(min, max, fov);
+    OrbitCamera {
+        focus,
+        distance: distance * margin,
+        ..default()
+    }
+    .transform()
+}
+
+// Systems --------------------------------------
+
+fn vertical_fov(projection: Option<&Projection>) -> f32 {
+    match projection {
+        Some(Projection::Perspective(perspective)) => perspective.fov,
+        _ => PerspectiveProjection::default().fov,
+    }
+}
+
+fn auto_frame_structures(
+    added: Query<(), Added<Structure>>,
+    structures: Query<(&Structure, &Transform)>,
+    mut cameras: Query<(&mut OrbitCamera, Option<&Projection>)>,
+) {
+    if added.is_empty() {
+        return;
+    }
+    let bounds = structures
+        .iter()
+        .filter_map(|(structure, transform)| {
+            let (min, max) = bounding_box(structure.get_pdb())?;
+            // transform all eight corners so rotated structures stay inside the box
+            (0..8)
+                .map(|i| {
+                    let corner =
+                        Vec3::select(BVec3::new(i & 1 != 0, i & 2 != 0, i & 4 != 0), max, min);
+                    transform.transform_point(corner)
+                })
+                .fold(None, |bounds: Option<(Vec3, Vec3)>, c| {
+                    Some(bounds.map_or((c, c), |(lo, hi)| (lo.min(c), hi.max(c))))
+                })
+        })
+        .reduce(|(min1, max1), (min2, max2)| (min1.min(min2), max1.max(max2)));
+    let Some((min, max)) = bounds else {
+        return;
+    };
+    for (mut orbit, projection) in &mut cameras {
+        if orbit.auto_frame {
+            orbit.frame_bounds(min, max, vertical_fov(projection));
+        }
+    }
+}
+
+fn apply_camera_views(
+    mut events: EventReader<SetCameraView>,
+    mut cameras: Query<(&mut OrbitCamera, &mut Projection)>,
+) {
+    let Some(SetCameraView(view)) = events.read().last() else {
+        return;
+    };
+    for (mut orbit, mut projection) in &mut cameras {
+        let imported = view.orbit();
+        orbit.focus = imported.focus;
+        orbit.distance = imported.distance;
+        orbit.rotation = imported.rotation;
+        // keep later structure loads from overriding the imported view
+        orbit.auto_frame = false;
+        *projection = view.projection();
+    }
+}
+
+fn orbit_camera_input(
+    mouse: Res<ButtonInput<MouseButton>>,
+    motion: Res<AccumulatedMouseMotion>,
+    scroll: Res<AccumulatedMouseScroll>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut cameras: Query<(&mut OrbitCamera, Option<&Projection>)>,
+) {
+    let delta = motion.delta;
+    let lines = match scroll.unit {
+        MouseScrollUnit::Line => scroll.delta.y,
+        MouseScrollUnit::Pixel => scroll.delta.y / PIXELS_PER_LINE,
+    };
+    let window_height = windows
+        .get_single()
+        .map(|window| window.height())
+        .unwrap_or(720.0);
+    for (mut orbit, projection) in &mut cameras {
+        if mouse.pressed(MouseButton::Left) && delta != Vec2::ZERO {
+            let yaw = Quat::from_rotation_y(-delta.x * orbit.orbit_sensitivity);
+            let pitch = Quat::from_rotation_x(-delta.y * orbit.orbit_sensitivity);
+            orbit.rotation = (orbit.rotation * yaw * pitch).normalize();
+        }
+        if (mouse.pressed(MouseButton::Right) || mouse.pressed(MouseButton::Middle))
+            && delta != Vec2::ZERO
+        {
+            // move the focus so the point under the cursor follows it
+            let world_per_pixel =
+                2.0 * orbit.distance * (vertical_fov(projection) / 2.0).tan() / window_height;
+            let pan = orbit.rotation * Vec3::new(-delta.x, delta.y, 0.0) * world_per_pixel;
+            orbit.focus += pan;
+        }
+        if lines != 0.0 {
+            orbit.distance *= 2f32.powf(-lines / ZOOM_LINES_PER_OCTAVE);
+            orbit.distance = orbit.distance.max(1.0);
+        }
+    }
+}
+
+fn update_orbit_transforms(
+    mut cameras: Query<(&OrbitCamera, &mut Transform), Changed<OrbitCamera>>,
+) {
+    for (orbit, mut transform) in &mut cameras {
+        *transform = orbit.transform();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_framing_camera() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_04().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let fov = std::f32::consts::FRAC_PI_4;
+        let camera = framing_camera(&ac, fov, 1.1);
+        // every atom lies inside the view cone
+        let forward = camera.forward();
+        for coord in ac.get_coords() {
+            let to_atom = Vec3::from_array(*coord) - camera.translation;
+            assert!(to_atom.angle_between(*forward) < fov / 2.0);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn test_pymol_view() {
+        // PyMOL rotated 90 degrees about y: model x points away from the camera
+        let mut view = [0.0; 25];
+        let rotation = Mat3::from_rotation_y(std::f32::consts::FRAC_PI_2);
+        for col in 0..3 {
+            for row in 0..3 {
+                view[col * 4 + row] = rotation.col(col)[row] as f64;
+            }
+        }
+        view[15] = 1.0;
+        view[16..19].copy_from_slice(&[0.0, 0.0, -50.0]);
+        view[19..22].copy_from_slice(&[10.0, 5.0, 0.0]);
+        view[22] = 40.0;
+        view[23] = 60.0;
+
+        let view = PymolView::from_array(view);
+        let transform = view.transform();
+        // the origin of rotation maps to (0, 0, -50) in camera space
+        let origin = transform
+            .compute_matrix()
+            .inverse()
+            .transform_point3(view.origin);
+        assert!(origin.abs_diff_eq(Vec3::new(0.0, 0.0, -50.0), 1e-4));
+        // and the model x axis points away from the camera
+        let x_in_camera = transform.rotation.inverse() * Vec3::X;
+        assert!(x_in_camera.abs_diff_eq(rotation * Vec3::X, 1e-4));
+        assert!(matches!(view.projection(), Projection::Perspective(_)));
+    }
+}
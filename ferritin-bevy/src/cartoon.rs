@@ -0,0 +1,272 @@
+//! Cartoon
+//!
+//! Builds a cartoon mesh from the protein backbone. The CA trace of each unbroken
+//! chain segment is smoothed with a Catmull-Rom spline and swept with an elliptical
+//! profile whose size follows the secondary structure: thin tubes for coils, flat
+//! ribbons for helices and wide ribbons ending in an arrowhead for strands. The
+//! ribbon plane is oriented by the CA -> O direction of each residue.
+use bevy::prelude::{Color, Mesh, Vec3};
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use ferritin_core::{AtomCollection, SecondaryStructure};
+use std::f32::consts::TAU;
+
+/// Spline points per residue
+pub(crate) const SEGMENTS_PER_RESIDUE: usize = 8;
+/// Vertices around the cross-section
+pub(crate) const PROFILE_SEGMENTS: usize = 12;
+/// Consecutive CA atoms further apart than this start a new segment
+const MAX_CA_GAP: f32 = 4.2;
+
+const COIL_RADIUS: f32 = 0.3;
+const HELIX_WIDTH: f32 = 1.4;
+const HELIX_THICKNESS: f32 = 0.35;
+const STRAND_WIDTH: f32 = 1.6;
+const STRAND_THICKNESS: f32 = 0.4;
+const ARROW_WIDTH: f32 = 2.4;
+
+struct ControlPoint {
+    ca: Vec3,
+    guide: Vec3,
+    ss: SecondaryStructure,
+    color: [f32; 4],
+}
+
+/// Cross-section (width, thickness) for a secondary structure type
+fn profile(ss: SecondaryStructure) -> (f32, f32) {
+    match ss {
+        SecondaryStructure::Helix => (HELIX_WIDTH, HELIX_THICKNESS),
+        SecondaryStructure::Strand => (STRAND_WIDTH, STRAND_THICKNESS),
+        SecondaryStructure::Coil => (2.0 * COIL_RADIUS, 2.0 * COIL_RADIUS),
+    }
+}
+
+/// Catmull-Rom spline interpolation
+fn catmull_rom(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f32) -> Vec3 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    let v0 = (p2 - p0) * 0.5;
+    let v1 = (p3 - p1) * 0.5;
+    (2.0 * p1 - 2.0 * p2 + v0 + v1) * t3 + (-3.0 * p1 + 3.0 * p2 - 2.0 * v0 - v1) * t2 + v0 * t + p1
+}
+
+/// Split the amino acid backbone into unbroken segments of control points
+fn control_segments(ac: &AtomCollection, colors: &[Color]) -> Vec<Vec<ControlPoint>> {
+    let mut segments: Vec<Vec<ControlPoint>> = Vec::new();
+    let mut last_chain: Option<String> = None;
+    for (residue, ss) in ac.iter_residues_aminoacid().zip(ac.secondary_structure()) {
+        let Some(ca_atom) = residue.find_atom_by_name("CA") else {
+            continue;
+        };
+        let ca = Vec3::from_array(*ca_atom.coords);
+        let color = colors[ca_atom.index].to_srgba();
+        let color = [color.red, color.green, color.blue, color.alpha];
+
+        let previous = segments.last().and_then(|segment| segment.last());
+        let continues = last_chain.as_deref() == Some(residue.chain_id.as_str())
+            && previous.is_some_and(|p| p.ca.distance(ca) <= MAX_CA_GAP);
+
+        // keep the guide vectors pointing the same way so the ribbon does not twist
+        let mut guide = residue
+            .find_atom_by_name("O")
+            .map(|o| (Vec3::from_array(*o.coords) - ca).normalize_or_zero())
+            .unwrap_or(Vec3::ZERO);
+        if let Some(previous) = previous.filter(|_| continues) {
+            if guide == Vec3::ZERO {
+                guide = previous.guide;
+            } else if guide.dot(previous.guide) < 0.0 {
+                guide = -guide;
+            }
+        }
+
+        let point = ControlPoint {
+            ca,
+            guide,
+            ss,
+            color,
+        };
+        match segments.last_mut() {
+            Some(segment) if continues => segment.push(point),
+            _ => segments.push(vec![point]),
+        }
+        last_chain = Some(residue.chain_id.clone());
+    }
+    segments.retain(|segment| segment.len() > 1);
+    segments
+}
+
+/// A frame along the spline: center, ribbon width direction, thickness direction,
+/// cross-section size and color
+struct Ring {
+    center: Vec3,
+    normal: Vec3,
+    binormal: Vec3,
+    width: f32,
+    thickness: f32,
+    color: [f32; 4],
+}
+
+/// Spline position, normal guide, (width, thickness) and color along a segment
+type Sample = (Vec3, Vec3, (f32, f32), [f32; 4]);
+
+fn segment_rings(points: &[ControlPoint]) -> Vec<Ring> {
+    let n = points.len();
+    let mut samples: Vec<Sample> = Vec::new();
+    for i in 0..n - 1 {
+        let p0 = points[i.saturating_sub(1)].ca;
+        let p1 = points[i].ca;
+        let p2 = points[i + 1].ca;
+        let p3 = points[(i + 2).min(n - 1)].ca;
+
+        // the last residue of a strand carries the arrowhead
+        let strand_end = |k: usize| {
+            points[k].ss == SecondaryStructure::Strand
+                && !points
+                    .get(k + 1)
+                    .is_some_and(|p| p.ss == SecondaryStructure::Strand)
+        };
+        let arrow = strand_end(i) || (i + 2 == n && strand_end(i + 1));
+
+        let steps = if i + 2 == n {
+            SEGMENTS_PER_RESIDUE + 1
+        } else {
+            SEGMENTS_PER_RESIDUE
+        };
+        for s in 0..steps {
+            let t = s as f32 / SEGMENTS_PER_RESIDUE as f32;
+            let position = catmull_rom(p0, p1, p2, p3, t);
+            let guide = points[i].guide.lerp(points[i + 1].guide, t);
+            let size = if arrow {
+                (ARROW_WIDTH * (1.0 - t), STRAND_THICKNESS)
+            } else {
+                let (w0, h0) = profile(points[i].ss);
+                let (w1, h1) = profile(points[i + 1].ss);
+                let blend = t * t * (3.0 - 2.0 * t);
+                (w0 + (w1 - w0) * blend, h0 + (h1 - h0) * blend)
+            };
+            let color = if t < 0.5 {
+                points[i].color
+            } else {
+                points[i + 1].color
+            };
+            samples.push((position, guide, size, color));
+        }
+    }
+
+    let m = samples.len();
+    (0..m)
+        .map(|k| {
+            let (center, guide, (width, thickness), color) = samples[k];
+            let tangent = (samples[(k + 1).min(m - 1)].0 - samples[k.saturating_sub(1)].0)
+                .normalize_or_zero();
+            let mut normal = (guide - tangent * guide.dot(tangent)).normalize_or_zero();
+            if normal == Vec3::ZERO {
+                normal = tangent.any_orthonormal_vector();
+            }
+            Ring {
+                center,
+                normal,
+                binormal: tangent.cross(normal),
+                width: width.max(0.01),
+                thickness,
+                color,
+            }
+        })
+        .collect()
+}
+
+#[derive(Default)]
+pub(crate) struct MeshBuffers {
+    positions: Vec<[f32; 3]>,
+    normals: Vec<[f32; 3]>,
+    colors: Vec<[f32; 4]>,
+    indices: Vec<u32>,
+}
+
+impl MeshBuffers {
+    pub(crate) fn push_vertex(&mut self, position: Vec3, normal: Vec3, color: [f32; 4]) -> u32 {
+        self.positions.push(position.to_array());
+        self.normals.push(normal.to_array());
+        self.colors.push(color);
+        (self.positions.len() - 1) as u32
+    }
+
+    /// Append indices relative to the vertex `base`
+    pub(crate) fn push_indices(&mut self, base: u32, indices: &[u32]) {
+        self.indices.extend(indices.iter().map(|i| base + i));
+    }
+
+    fn sweep(&mut self, rings: &[Ring]) {
+        let start = self.positions.len() as u32;
+        let ring_size = PROFILE_SEGMENTS as u32;
+        for ring in rings {
+            let (a, b) = (ring.width / 2.0, ring.thickness / 2.0);
+            for j in 0..PROFILE_SEGMENTS {
+                let angle = j as f32 / PROFILE_SEGMENTS as f32 * TAU;
+                let (cos, sin) = (angle.cos(), angle.sin());
+                let position = ring.center + ring.normal * (a * cos) + ring.binormal * (b * sin);
+                let normal = (ring.normal * (cos / a) + ring.binormal * (sin / b)).normalize();
+                self.push_vertex(position, normal, ring.color);
+            }
+        }
+        for i in 0..rings.len().saturating_sub(1) as u32 {
+            let current = start + i * ring_size;
+            let next = current + ring_size;
+            for j in 0..ring_size {
+                let next_j = (j + 1) % ring_size;
+                self.indices.extend([
+                    current + j,
+                    current + next_j,
+                    next + j,
+                    current + next_j,
+                    next + next_j,
+                    next + j,
+                ]);
+            }
+        }
+    }
+
+    /// Close the end of a tube with a triangle fan facing along `direction`
+    fn cap(&mut self, ring: &Ring, direction: Vec3) {
+        let center = self.push_vertex(ring.center, direction, ring.color);
+        let (a, b) = (ring.width / 2.0, ring.thickness / 2.0);
+        let first = self.positions.len() as u32;
+        for j in 0..PROFILE_SEGMENTS {
+            let angle = j as f32 / PROFILE_SEGMENTS as f32 * TAU;
+            let position =
+                ring.center + ring.normal * (a * angle.cos()) + ring.binormal * (b * angle.sin());
+            self.push_vertex(position, direction, ring.color);
+        }
+        for j in 0..PROFILE_SEGMENTS as u32 {
+            let next_j = (j + 1) % PROFILE_SEGMENTS as u32;
+            // wind the fan so it faces outward at either end
+            if direction.dot(ring.normal.cross(ring.binormal)) > 0.0 {
+                self.indices.extend([center, first + j, first + next_j]);
+            } else {
+                self.indices.extend([center, first + next_j, first + j]);
+            }
+        }
+    }
+
+    pub(crate) fn into_mesh(self, topology: PrimitiveTopology) -> Mesh {
+        let mut mesh = Mesh::new(topology, RenderAssetUsages::all());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, self.colors);
+        mesh.insert_indices(Indices::U32(self.indices));
+        mesh
+    }
+}
+
+/// Cartoon mesh for all amino acid chains in `ac`, colored by the CA atom colors
+pub(crate) fn cartoon_mesh(ac: &AtomCollection, colors: &[Color]) -> Mesh {
+    let mut buffers = MeshBuffers::default();
+    for segment in control_segments(ac, colors) {
+        let rings = segment_rings(&segment);
+        buffers.sweep(&rings);
+        let (first, last) = (&rings[0], &rings[rings.len() - 1]);
+        buffers.cap(first, -first.normal.cross(first.binormal));
+        buffers.cap(last, last.normal.cross(last.binormal));
+    }
+    buffers.into_mesh(PrimitiveTopology::TriangleList)
+}
@@ -1,8 +1,20 @@
 //! Colors
 //!
 //! This module defines the color mapping used for rendering.
+//!
+//! Schemes that only need the element ([`ColorScheme::Solid`], [`ColorScheme::ByAtomType`]
+//! and [`ColorScheme::ByElement`]) can be evaluated atom by atom with
+//! [`ColorScheme::get_color`]. The remaining schemes need residue, chain or secondary
+//! structure context and are evaluated for a whole structure with
+//! [`ColorScheme::atom_colors`].
 use bevy::prelude::Color;
-use pdbtbx::Element;
+use ferritin_core::{AtomCollection, SecondaryStructure};
+use pdbtbx::{Element, PDB};
+use std::collections::HashMap;
+use std::sync::Arc;
+
+/// Per-atom coloring function for [`ColorScheme::Custom`]
+pub type ColorFn = Arc<dyn Fn(&AtomCollection, usize) -> Color + Send + Sync>;
 
 /// Represents different color schemes for rendering atoms.
 #[derive(Clone)]
@@ -11,21 +23,159 @@ pub enum ColorScheme {
     Solid(Color),
     /// Colors atoms based on their element type.
     ByAtomType,
-    // /// Colors atoms based on the chain they belong to.
-    // ByChain(Box<dyn Fn(&Chain) -> Color>),
-    // /// Colors atoms based on the secondary structure of their residue.
-    // BySecondaryStructure(Box<dyn Fn(&Residue) -> Color>),
-    // /// Colors atoms based on their residue type.
-    // ByResidueType(Box<dyn Fn(&Residue) -> Color>),
-    // /// Custom coloring function that takes atom, residue, and chain information.
-    // Custom(Box<dyn Fn(&Atom, &Residue, &Chain) -> Color>),
+    /// Colors atoms with a full periodic-table palette.
+    ByElement(ElementPalette),
+    /// Colors atoms based on the chain they belong to.
+    ByChain,
+    /// Colors atoms based on the secondary structure of their residue.
+    BySecondaryStructure,
+    /// Colors atoms based on their residue type.
+    ByResidueType,
+    /// Maps a numeric value per atom or residue onto a gradient.
+    Gradient {
+        values: PropertyValues,
+        gradient: Gradient,
+        /// Value range mapped onto the gradient; defaults to the range of `values`
+        range: Option<(f32, f32)>,
+    },
+    /// Custom coloring function taking the structure and an atom index.
+    Custom(ColorFn),
+}
+
+/// Element color conventions
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ElementPalette {
+    /// Jmol colors, defined for elements 1-109
+    Jmol,
+    /// RasMol CPK colors
+    Cpk,
+}
+
+/// Numeric values to color by
+#[derive(Clone, Debug)]
+pub enum PropertyValues {
+    /// One value per atom, e.g. B-factors
+    PerAtom(Vec<f32>),
+    /// One value per residue in [`AtomCollection::iter_residues_all`] order,
+    /// e.g. pLDDT or MPNN log-probabilities
+    PerResidue(Vec<f32>),
+}
+
+/// Piecewise color ramp over normalized positions in `[0, 1]`
+#[derive(Clone, Debug)]
+pub struct Gradient {
+    stops: Vec<(f32, Color)>,
+    /// Blend between stops; otherwise each stop colors everything up to the next one
+    interpolate: bool,
+}
+
+impl Gradient {
+    /// Smooth gradient through `stops`, given as (position, color) pairs.
+    pub fn new(mut stops: Vec<(f32, Color)>) -> Self {
+        stops.sort_by(|a, b| a.0.total_cmp(&b.0));
+        Self {
+            stops,
+            interpolate: true,
+        }
+    }
+
+    /// Banded gradient: each stop colors values from its position up to the next stop.
+    pub fn discrete(stops: Vec<(f32, Color)>) -> Self {
+        Self {
+            interpolate: false,
+            ..Self::new(stops)
+        }
+    }
+
+    pub fn blue_white_red() -> Self {
+        Self::new(vec![
+            (0.0, hex(0x0000FF)),
+            (0.5, hex(0xFFFFFF)),
+            (1.0, hex(0xFF0000)),
+        ])
+    }
+
+    pub fn rainbow() -> Self {
+        Self::new(vec![
+            (0.0, hex(0x0000FF)),
+            (0.25, hex(0x00FFFF)),
+            (0.5, hex(0x00FF00)),
+            (0.75, hex(0xFFFF00)),
+            (1.0, hex(0xFF0000)),
+        ])
+    }
+
+    pub fn viridis() -> Self {
+        Self::new(vec![
+            (0.0, hex(0x440154)),
+            (0.25, hex(0x3B528B)),
+            (0.5, hex(0x21918C)),
+            (0.75, hex(0x5EC962)),
+            (1.0, hex(0xFDE725)),
+        ])
+    }
+
+    /// AlphaFold confidence bands; use with a (0, 100) range.
+    pub fn plddt() -> Self {
+        Self::discrete(vec![
+            (0.0, hex(0xFF7D45)),
+            (0.5, hex(0xFFDB13)),
+            (0.7, hex(0x65CBF3)),
+            (0.9, hex(0x0053D6)),
+        ])
+    }
+
+    /// Color at normalized position `t`, clamped to `[0, 1]`.
+    pub fn sample(&self, t: f32) -> Color {
+        let t = if t.is_nan() { 0.0 } else { t.clamp(0.0, 1.0) };
+        let Some(&(_, first)) = self.stops.first() else {
+            return Color::WHITE;
+        };
+        let upper = self.stops.iter().position(|&(pos, _)| pos > t);
+        match upper {
+            None => self.stops[self.stops.len() - 1].1,
+            Some(0) => first,
+            Some(k) => {
+                let (p0, c0) = self.stops[k - 1];
+                let (p1, c1) = self.stops[k];
+                if !self.interpolate {
+                    return c0;
+                }
+                let f = (t - p0) / (p1 - p0);
+                let (a, b) = (c0.to_srgba(), c1.to_srgba());
+                Color::srgba(
+                    a.red + (b.red - a.red) * f,
+                    a.green + (b.green - a.green) * f,
+                    a.blue + (b.blue - a.blue) * f,
+                    a.alpha + (b.alpha - a.alpha) * f,
+                )
+            }
+        }
+    }
 }
 
-// ColorScheme::ByChain(func) => func(chain),
-// ColorScheme::BySecondaryStructure(func) => func(residue),
-// ColorScheme::ByResidueType(func) => func(residue),
-// ColorScheme::Custom(func) => func(atom, residue, chain),
 impl ColorScheme {
+    /// Gradient over per-atom B-factors read from `pdb`, in the atom order used by
+    /// `AtomCollection::from(&pdb)`.
+    pub fn b_factor(pdb: &PDB, gradient: Gradient) -> Self {
+        let values = pdb
+            .chains()
+            .flat_map(|chain| chain.residues())
+            .flat_map(|residue| residue.atoms())
+            .filter(|atom| atom.element().is_some())
+            .map(|atom| atom.b_factor() as f32)
+            .collect();
+        ColorScheme::Gradient {
+            values: PropertyValues::PerAtom(values),
+            gradient,
+            range: None,
+        }
+    }
+
+    /// Color for an atom from its element alone.
+    ///
+    /// Schemes that need more context than the element return white; use
+    /// [`ColorScheme::atom_colors`] for those.
     pub fn get_color(&self, atom: &Element) -> Color {
         match &self {
             ColorScheme::Solid(color) => *color,
@@ -38,13 +188,173 @@ impl ColorScheme {
                     _ => Color::srgb(1.0, 1.0, 1.0),          // Other: White
                 }
             }
+            ColorScheme::ByElement(palette) => element_color(atom, *palette),
+            _ => Color::WHITE,
+        }
+    }
+
+    /// Colors for every atom in `ac`.
+    pub fn atom_colors(&self, ac: &AtomCollection) -> Vec<Color> {
+        let n = ac.get_size();
+        match self {
+            ColorScheme::Solid(_) | ColorScheme::ByAtomType | ColorScheme::ByElement(_) => ac
+                .get_elements()
+                .iter()
+                .map(|element| self.get_color(element))
+                .collect(),
+            ColorScheme::ByChain => {
+                let mut chain_order: HashMap<&String, usize> = HashMap::new();
+                (0..n)
+                    .map(|i| {
+                        let next = chain_order.len();
+                        let k = *chain_order.entry(ac.get_chain_id(i)).or_insert(next);
+                        hex(CHAIN_COLORS[k % CHAIN_COLORS.len()])
+                    })
+                    .collect()
+            }
+            ColorScheme::BySecondaryStructure => {
+                let mut colors = vec![hex(COIL_COLOR); n];
+                for (residue, ss) in ac.iter_residues_aminoacid().zip(ac.secondary_structure()) {
+                    let color = match ss {
+                        SecondaryStructure::Helix => hex(HELIX_COLOR),
+                        SecondaryStructure::Strand => hex(STRAND_COLOR),
+                        SecondaryStructure::Coil => hex(COIL_COLOR),
+                    };
+                    colors[residue.start_idx..residue.end_idx].fill(color);
+                }
+                colors
+            }
+            ColorScheme::ByResidueType => (0..n)
+                .map(|i| residue_type_color(ac.get_res_name(i)))
+                .collect(),
+            ColorScheme::Gradient {
+                values,
+                gradient,
+                range,
+            } => {
+                let per_atom: Vec<Option<f32>> = match values {
+                    PropertyValues::PerAtom(values) => {
+                        (0..n).map(|i| values.get(i).copied()).collect()
+                    }
+                    PropertyValues::PerResidue(values) => {
+                        let mut per_atom = vec![None; n];
+                        for (residue, value) in ac.iter_residues_all().zip(values) {
+                            per_atom[residue.start_idx..residue.end_idx].fill(Some(*value));
+                        }
+                        per_atom
+                    }
+                };
+                let (low, high) = range.unwrap_or_else(|| {
+                    per_atom
+                        .iter()
+                        .flatten()
+                        .fold((f32::MAX, f32::MIN), |(lo, hi), &v| (lo.min(v), hi.max(v)))
+                });
+                let span = if high > low { high - low } else { 1.0 };
+                per_atom
+                    .iter()
+                    .map(|value| match value {
+                        Some(v) => gradient.sample((v - low) / span),
+                        None => Color::WHITE,
+                    })
+                    .collect()
+            }
+            ColorScheme::Custom(func) => (0..n).map(|i| func(ac, i)).collect(),
         }
     }
 }
 
+// Helper Fns --------------------------------------
+
+fn hex(rgb: u32) -> Color {
+    Color::srgb_u8((rgb >> 16) as u8, (rgb >> 8) as u8, rgb as u8)
+}
+
+const HELIX_COLOR: u32 = 0xFF0080;
+const STRAND_COLOR: u32 = 0xFFC800;
+const COIL_COLOR: u32 = 0xFFFFFF;
+
+#[rustfmt::skip]
+const CHAIN_COLORS: [u32; 10] = [
+    0x33FF33, 0x00FFFF, 0xFF33CC, 0xFFFF00, 0xFF9999,
+    0xE5E5E5, 0x7F7FFF, 0xFF7F00, 0x92E3A0, 0x1F77B4,
+];
+
+/// RasMol "amino" colors
+fn residue_type_color(res_name: &str) -> Color {
+    hex(match res_name {
+        "ASP" | "GLU" => 0xE60A0A,
+        "CYS" | "MET" => 0xE6E600,
+        "LYS" | "ARG" => 0x145AFF,
+        "SER" | "THR" => 0xFA9600,
+        "PHE" | "TYR" => 0x3232AA,
+        "ASN" | "GLN" => 0x00DCDC,
+        "GLY" => 0xEBEBEB,
+        "LEU" | "VAL" | "ILE" => 0x0F820F,
+        "ALA" => 0xC8C8C8,
+        "TRP" => 0xB45AB4,
+        "HIS" => 0x8282D2,
+        "PRO" => 0xDC9682,
+        _ => 0xBEA06E,
+    })
+}
+
+fn element_color(element: &Element, palette: ElementPalette) -> Color {
+    match palette {
+        ElementPalette::Jmol => {
+            let number = element.atomic_number();
+            hex(JMOL_COLORS
+                .get(number.wrapping_sub(1))
+                .copied()
+                .unwrap_or(0xFF1493))
+        }
+        ElementPalette::Cpk => hex(match element {
+            Element::C => 0xC8C8C8,
+            Element::O => 0xF00000,
+            Element::H => 0xFFFFFF,
+            Element::N => 0x8F8FFF,
+            Element::S => 0xFFC832,
+            Element::Cl | Element::B => 0x00FF00,
+            Element::P | Element::Fe | Element::Ba => 0xFFA500,
+            Element::Na => 0x0000FF,
+            Element::Mg => 0x228B22,
+            Element::Zn | Element::Cu | Element::Ni | Element::Br => 0xA52A2A,
+            Element::Ca | Element::Mn | Element::Al | Element::Ti | Element::Cr | Element::Ag => {
+                0x808090
+            }
+            Element::F | Element::Si | Element::Au => 0xDAA520,
+            Element::I => 0xA020F0,
+            Element::Li => 0xB22222,
+            Element::He => 0xFFC0CB,
+            _ => 0xFF1493,
+        }),
+    }
+}
+
+/// Jmol element colors indexed by atomic number - 1
+#[rustfmt::skip]
+const JMOL_COLORS: [u32; 109] = [
+    0xFFFFFF, 0xD9FFFF, 0xCC80FF, 0xC2FF00, 0xFFB5B5, 0x909090, 0x3050F8, 0xFF0D0D, // H-O
+    0x90E050, 0xB3E3F5, 0xAB5CF2, 0x8AFF00, 0xBFA6A6, 0xF0C8A0, 0xFF8000, 0xFFFF30, // F-S
+    0x1FF01F, 0x80D1E3, 0x8F40D4, 0x3DFF00, 0xE6E6E6, 0xBFC2C7, 0xA6A6AB, 0x8A99C7, // Cl-Cr
+    0x9C7AC7, 0xE06633, 0xF090A0, 0x50D050, 0xC88033, 0x7D80B0, 0xC28F8F, 0x668F8F, // Mn-Ge
+    0xBD80E3, 0xFFA100, 0xA62929, 0x5CB8D1, 0x702EB0, 0x00FF00, 0x94FFFF, 0x94E0E0, // As-Zr
+    0x73C2C9, 0x54B5B5, 0x3B9E9E, 0x248F8F, 0x0A7D8C, 0x006985, 0xC0C0C0, 0xFFD98F, // Nb-Cd
+    0xA67573, 0x668080, 0x9E63B5, 0xD47A00, 0x940094, 0x429EB0, 0x57178F, 0x00C900, // In-Ba
+    0x70D4FF, 0xFFFFC7, 0xD9FFC7, 0xC7FFC7, 0xA3FFC7, 0x8FFFC7, 0x61FFC7, 0x45FFC7, // La-Gd
+    0x30FFC7, 0x1FFFC7, 0x00FF9C, 0x00E675, 0x00D452, 0x00BF38, 0x00AB24, 0x4DC2FF, // Tb-Hf
+    0x4DA6FF, 0x2194D6, 0x267DAB, 0x266696, 0x175487, 0xD0D0E0, 0xFFD123, 0xB8B8D0, // Ta-Hg
+    0xA6544D, 0x575961, 0x9E4FB5, 0xAB5C00, 0x754F45, 0x428296, 0x420066, 0x007D00, // Tl-Ra
+    0x70ABFA, 0x00BAFF, 0x00A1FF, 0x008FFF, 0x0080FF, 0x006BFF, 0x545CF2, 0x785CE3, // Ac-Cm
+    0x8A4FE3, 0xA136D4, 0xB31FD4, 0xB31FBA, 0xB30DA6, 0xBD0D87, 0xC70066, 0xCC0059, // Bk-Rf
+    0xD1004F, 0xD90045, 0xE00038, 0xE6002E, 0xEB0026,                               // Db-Mt
+];
+
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ferritin_test_data::TestFile;
+
     #[test]
     fn test_get_color() {
         let by_atom_scheme = ColorScheme::ByAtomType;
@@ -65,4 +375,58 @@ mod tests {
             Color::srgb(1.0, 1.0, 0.0)
         );
     }
+
+    #[test]
+    fn test_element_palettes() {
+        let jmol = ColorScheme::ByElement(ElementPalette::Jmol);
+        assert_eq!(jmol.get_color(&Element::C), hex(0x909090));
+        assert_eq!(jmol.get_color(&Element::Fe), hex(0xE06633));
+        assert_eq!(jmol.get_color(&Element::Mt), hex(0xEB0026));
+        let cpk = ColorScheme::ByElement(ElementPalette::Cpk);
+        assert_eq!(cpk.get_color(&Element::O), hex(0xF00000));
+    }
+
+    #[test]
+    fn test_gradient() {
+        let gradient = Gradient::blue_white_red();
+        assert_eq!(gradient.sample(0.0), hex(0x0000FF));
+        assert_eq!(gradient.sample(0.5), hex(0xFFFFFF));
+        assert_eq!(gradient.sample(2.0), hex(0xFF0000));
+        let plddt = Gradient::plddt();
+        assert_eq!(plddt.sample(0.95), hex(0x0053D6));
+        assert_eq!(plddt.sample(0.65), hex(0xFFDB13));
+    }
+
+    #[test]
+    fn test_atom_colors() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_04().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        let by_chain = ColorScheme::ByChain.atom_colors(&ac);
+        assert_eq!(by_chain.len(), ac.get_size());
+        assert_eq!(by_chain[0], hex(CHAIN_COLORS[0]));
+        let last = ac.get_size() - 1;
+        assert_ne!(ac.get_chain_id(0), ac.get_chain_id(last));
+        assert_ne!(by_chain[0], by_chain[last]);
+
+        let by_ss = ColorScheme::BySecondaryStructure.atom_colors(&ac);
+        assert!(by_ss.contains(&hex(HELIX_COLOR)));
+        assert!(by_ss.contains(&hex(STRAND_COLOR)));
+
+        let b_factors = ColorScheme::b_factor(&pdb, Gradient::viridis()).atom_colors(&ac);
+        assert_eq!(b_factors.len(), ac.get_size());
+
+        let residues = ac.iter_residues_all().count();
+        let per_residue = ColorScheme::Gradient {
+            values: PropertyValues::PerResidue((0..residues).map(|r| r as f32).collect()),
+            gradient: Gradient::rainbow(),
+            range: None,
+        }
+        .atom_colors(&ac);
+        assert_eq!(per_residue[0], hex(0x0000FF));
+        let last_residue = ac.iter_residues_all().last().unwrap();
+        assert_eq!(per_residue[last_residue.start_idx], hex(0xFF0000));
+        Ok(())
+    }
 }
@@ -0,0 +1,419 @@
+//! Export
+//!
+//! Write the meshes produced by [`Structure`] to common 3D formats so they can be used
+//! outside Bevy: glTF for web viewers, OBJ for modelling tools and STL for 3D printing.
+//! Only the mesh data is used, so no app or renderer needs to be running.
+//!
+//! - glTF (`.gltf` with an embedded buffer, or binary `.glb`) keeps vertex colors and
+//!   writes one node per chain.
+//! - OBJ writes one object per chain with the common `v x y z r g b` color extension.
+//! - STL is binary, triangles only and without color.
+//!
+//! Wireframe meshes are line lists: glTF and OBJ store them as lines, STL rejects them.
+//!
+//! ```no_run
+//! use ferritin_bevy::{RenderOptions, Structure};
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> anyhow::Result<()> {
+//! let structure = Structure::builder()
+//!     .pdb(ac)
+//!     .rendertype(RenderOptions::Cartoon)
+//!     .build();
+//! structure.export("cartoon.glb")?;
+//! structure.export("cartoon.stl")?;
+//! # Ok(())
+//! # }
+//! ```
+use super::Structure;
+use anyhow::{anyhow, bail, Result};
+use bevy::prelude::Mesh;
+use bevy::render::mesh::{PrimitiveTopology, VertexAttributeValues};
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use std::path::Path;
+
+/// glTF accessor component types and buffer targets
+const GL_FLOAT: u32 = 5126;
+const GL_UNSIGNED_INT: u32 = 5125;
+const GL_ARRAY_BUFFER: u32 = 34962;
+const GL_ELEMENT_ARRAY_BUFFER: u32 = 34963;
+/// glTF primitive modes
+const GL_LINES: u32 = 1;
+const GL_TRIANGLES: u32 = 4;
+
+impl Structure {
+    /// Write the structure's mesh to `path`, picking the format from the extension:
+    /// `gltf`, `glb`, `obj` or `stl`.
+    pub fn export<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(|ext| ext.to_lowercase())
+            .unwrap_or_default();
+        let parts = self.to_chain_meshes();
+        let mut writer = BufWriter::new(File::create(path)?);
+        match extension.as_str() {
+            "gltf" => write_gltf(&parts, &mut writer)?,
+            "glb" => write_glb(&parts, &mut writer)?,
+            "obj" => write_obj(&parts, &mut writer)?,
+            "stl" => write_stl(&parts, &mut writer)?,
+            _ => bail!("Unsupported export format: {:?}", path),
+        }
+        writer.flush()?;
+        Ok(())
+    }
+}
+
+/// Vertex data pulled out of a Bevy mesh
+struct MeshData {
+    positions: Vec<[f32; 3]>,
+    normals: Option<Vec<[f32; 3]>>,
+    colors: Option<Vec<[f32; 4]>>,
+    indices: Vec<u32>,
+    lines: bool,
+}
+
+impl MeshData {
+    fn from_mesh(mesh: &Mesh) -> Result<Self> {
+        let lines = match mesh.primitive_topology() {
+            PrimitiveTopology::TriangleList => false,
+            PrimitiveTopology::LineList => true,
+            other => bail!("Cannot export {:?} meshes", other),
+        };
+        let positions = match mesh.attribute(Mesh::ATTRIBUTE_POSITION) {
+            Some(VertexAttributeValues::Float32x3(values)) => values.clone(),
+            _ => bail!("Mesh has no positions"),
+        };
+        let normals = match mesh.attribute(Mesh::ATTRIBUTE_NORMAL) {
+            Some(VertexAttributeValues::Float32x3(values)) => Some(values.clone()),
+            _ => None,
+        };
+        let colors = match mesh.attribute(Mesh::ATTRIBUTE_COLOR) {
+            Some(VertexAttributeValues::Float32x4(values)) => Some(values.clone()),
+            _ => None,
+        };
+        let indices = match mesh.indices() {
+            Some(indices) => indices.iter().map(|i| i as u32).collect(),
+            None => (0..positions.len() as u32).collect(),
+        };
+        Ok(MeshData {
+            positions,
+            normals,
+            colors,
+            indices,
+            lines,
+        })
+    }
+
+    fn triangles(&self) -> impl Iterator<Item = [[f32; 3]; 3]> + '_ {
+        self.indices.chunks_exact(3).map(|tri| {
+            [
+                self.positions[tri[0] as usize],
+                self.positions[tri[1] as usize],
+                self.positions[tri[2] as usize],
+            ]
+        })
+    }
+}
+
+fn mesh_data(parts: &[(String, Mesh)]) -> Result<Vec<(&str, MeshData)>> {
+    parts
+        .iter()
+        .map(|(name, mesh)| Ok((name.as_str(), MeshData::from_mesh(mesh)?)))
+        .collect()
+}
+
+/// Write a glTF document with the binary buffer embedded as a base64 data URI.
+pub fn write_gltf<W: Write>(parts: &[(String, Mesh)], writer: &mut W) -> Result<()> {
+    let (mut document, buffer) = gltf_document(parts)?;
+    document["buffers"] = json!([{
+        "byteLength": buffer.len(),
+        "uri": format!("data:application/octet-stream;base64,{}", base64(&buffer)),
+    }]);
+    serde_json::to_writer_pretty(&mut *writer, &document)?;
+    Ok(())
+}
+
+/// Write a binary glTF (GLB) file.
+pub fn write_glb<W: Write>(parts: &[(String, Mesh)], writer: &mut W) -> Result<()> {
+    let (mut document, mut buffer) = gltf_document(parts)?;
+    document["buffers"] = json!([{ "byteLength": buffer.len() }]);
+    let mut json = serde_json::to_vec(&document)?;
+    // chunks are 4-byte aligned: JSON is padded with spaces, binary data with zeros
+    while json.len() % 4 != 0 {
+        json.push(b' ');
+    }
+    while buffer.len() % 4 != 0 {
+        buffer.push(0);
+    }
+    let total = 12 + 8 + json.len() + 8 + buffer.len();
+    writer.write_all(b"glTF")?;
+    writer.write_all(&2u32.to_le_bytes())?;
+    writer.write_all(&(total as u32).to_le_bytes())?;
+    writer.write_all(&(json.len() as u32).to_le_bytes())?;
+    writer.write_all(b"JSON")?;
+    writer.write_all(&json)?;
+    writer.write_all(&(buffer.len() as u32).to_le_bytes())?;
+    writer.write_all(b"BIN\0")?;
+    writer.write_all(&buffer)?;
+    Ok(())
+}
+
+/// Build the glTF JSON (without `buffers`) and its binary buffer: one mesh and node per part.
+fn gltf_document(parts: &[(String, Mesh)]) -> Result<(Value, Vec<u8>)> {
+    let mut buffer: Vec<u8> = Vec::new();
+    let mut views: Vec<Value> = Vec::new();
+    let mut accessors: Vec<Value> = Vec::new();
+    let mut meshes: Vec<Value> = Vec::new();
+    let mut nodes: Vec<Value> = Vec::new();
+
+    // append a float or index array as its own buffer view and return the accessor index
+    let mut push_accessor = |data: &[u8], target: u32, mut accessor: Value| -> usize {
+        views.push(json!({
+            "buffer": 0,
+            "byteOffset": buffer.len(),
+            "byteLength": data.len(),
+            "target": target,
+        }));
+        buffer.extend_from_slice(data);
+        accessor["bufferView"] = json!(views.len() - 1);
+        accessors.push(accessor);
+        accessors.len() - 1
+    };
+
+    for (name, data) in mesh_data(parts)? {
+        let (mut min, mut max) = ([f32::MAX; 3], [f32::MIN; 3]);
+        for position in &data.positions {
+            min = std::array::from_fn(|axis| min[axis].min(position[axis]));
+            max = std::array::from_fn(|axis| max[axis].max(position[axis]));
+        }
+        let mut attributes = serde_json::Map::new();
+        let position = push_accessor(
+            bytemuck::cast_slice(&data.positions),
+            GL_ARRAY_BUFFER,
+            json!({
+                "componentType": GL_FLOAT,
+                "count": data.positions.len(),
+                "type": "VEC3",
+                "min": min,
+                "max": max,
+            }),
+        );
+        attributes.insert("POSITION".into(), json!(position));
+        if let Some(normals) = &data.normals {
+            let normal = push_accessor(
+                bytemuck::cast_slice(normals),
+                GL_ARRAY_BUFFER,
+                json!({ "componentType": GL_FLOAT, "count": normals.len(), "type": "VEC3" }),
+            );
+            attributes.insert("NORMAL".into(), json!(normal));
+        }
+        if let Some(colors) = &data.colors {
+            let color = push_accessor(
+                bytemuck::cast_slice(colors),
+                GL_ARRAY_BUFFER,
+                json!({ "componentType": GL_FLOAT, "count": colors.len(), "type": "VEC4" }),
+            );
+            attributes.insert("COLOR_0".into(), json!(color));
+        }
+        let indices = push_accessor(
+            bytemuck::cast_slice(&data.indices),
+            GL_ELEMENT_ARRAY_BUFFER,
+            json!({
+                "componentType": GL_UNSIGNED_INT,
+                "count": data.indices.len(),
+                "type": "SCALAR",
+            }),
+        );
+        meshes.push(json!({
+            "name": name,
+            "primitives": [{
+                "attributes": attributes,
+                "indices": indices,
+                "mode": if data.lines { GL_LINES } else { GL_TRIANGLES },
+            }],
+        }));
+        nodes.push(json!({ "name": name, "mesh": meshes.len() - 1 }));
+    }
+
+    let document = json!({
+        "asset": { "version": "2.0", "generator": "ferritin-bevy" },
+        "scene": 0,
+        "scenes": [{ "nodes": (0..nodes.len()).collect::<Vec<_>>() }],
+        "nodes": nodes,
+        "meshes": meshes,
+        "accessors": accessors,
+        "bufferViews": views,
+    });
+    Ok((document, buffer))
+}
+
+/// Write a Wavefront OBJ file with one object per part and per-vertex colors.
+pub fn write_obj<W: Write>(parts: &[(String, Mesh)], writer: &mut W) -> Result<()> {
+    writeln!(writer, "# ferritin-bevy")?;
+    // OBJ indices are 1-based and global across objects
+    let mut offset = 1;
+    for (name, data) in mesh_data(parts)? {
+        writeln!(writer, "o {}", name)?;
+        for (i, [x, y, z]) in data.positions.iter().enumerate() {
+            match &data.colors {
+                Some(colors) => {
+                    let [r, g, b, _] = colors[i];
+                    writeln!(writer, "v {} {} {} {} {} {}", x, y, z, r, g, b)?;
+                }
+                None => writeln!(writer, "v {} {} {}", x, y, z)?,
+            }
+        }
+        if let Some(normals) = &data.normals {
+            for [x, y, z] in normals {
+                writeln!(writer, "vn {} {} {}", x, y, z)?;
+            }
+        }
+        if data.lines {
+            for line in data.indices.chunks_exact(2) {
+                writeln!(writer, "l {} {}", line[0] + offset, line[1] + offset)?;
+            }
+        } else {
+            for tri in data.indices.chunks_exact(3) {
+                let [a, b, c] = [tri[0] + offset, tri[1] + offset, tri[2] + offset];
+                if data.normals.is_some() {
+                    writeln!(writer, "f {a}//{a} {b}//{b} {c}//{c}")?;
+                } else {
+                    writeln!(writer, "f {a} {b} {c}")?;
+                }
+            }
+        }
+        offset += data.positions.len() as u32;
+    }
+    Ok(())
+}
+
+/// Write a binary STL file containing the triangles of all parts.
+pub fn write_stl<W: Write>(parts: &[(String, Mesh)], writer: &mut W) -> Result<()> {
+    let data = mesh_data(parts)?;
+    if data.iter().any(|(_, data)| data.lines) {
+        return Err(anyhow!("STL cannot store line meshes such as wireframes"));
+    }
+    let count: usize = data.iter().map(|(_, data)| data.indices.len() / 3).sum();
+    let mut header = [0u8; 80];
+    header[..13].copy_from_slice(b"ferritin-bevy");
+    writer.write_all(&header)?;
+    writer.write_all(&(count as u32).to_le_bytes())?;
+    for (_, data) in &data {
+        for [a, b, c] in data.triangles() {
+            let (u, v) = (sub(b, a), sub(c, a));
+            let normal = [
+                u[1] * v[2] - u[2] * v[1],
+                u[2] * v[0] - u[0] * v[2],
+                u[0] * v[1] - u[1] * v[0],
+            ];
+            let length = normal.iter().map(|x| x * x).sum::<f32>().sqrt();
+            let normal = if length > 0.0 {
+                normal.map(|x| x / length)
+            } else {
+                [0.0; 3]
+            };
+            for vector in [normal, a, b, c] {
+                writer.write_all(bytemuck::cast_slice(&vector))?;
+            }
+            writer.write_all(&0u16.to_le_bytes())?;
+        }
+    }
+    Ok(())
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+fn base64(data: &[u8]) -> String {
+    const TABLE: &[u8; 64] = b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+    let mut out = String::with_capacity(data.len().div_ceil(3) * 4);
+    for chunk in data.chunks(3) {
+        let bytes = [
+            chunk[0],
+            *chunk.get(1).unwrap_or(&0),
+            *chunk.get(2).unwrap_or(&0),
+        ];
+        let n = (bytes[0] as u32) << 16 | (bytes[1] as u32) << 8 | bytes[2] as u32;
+        for i in 0..4 {
+            if i <= chunk.len() {
+                out.push(TABLE[(n >> (18 - 6 * i) & 63) as usize] as char);
+            } else {
+                out.push('=');
+            }
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderOptions;
+    use ferritin_core::AtomCollection;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_export_formats() {
+        let (prot_file, _temp) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let structure = Structure::builder()
+            .pdb(ac)
+            .rendertype(RenderOptions::Cartoon)
+            .build();
+        let parts = structure.to_chain_meshes();
+        assert!(!parts.is_empty());
+        let vertices: usize = parts.iter().map(|(_, mesh)| mesh.count_vertices()).sum();
+        let triangles: usize = parts
+            .iter()
+            .map(|(_, mesh)| mesh.indices().unwrap().len() / 3)
+            .sum();
+
+        let mut glb = Vec::new();
+        write_glb(&parts, &mut glb).unwrap();
+        assert_eq!(&glb[..4], b"glTF");
+        assert_eq!(
+            u32::from_le_bytes(glb[8..12].try_into().unwrap()) as usize,
+            glb.len()
+        );
+        let json_len = u32::from_le_bytes(glb[12..16].try_into().unwrap()) as usize;
+        let document: Value = serde_json::from_slice(&glb[20..20 + json_len]).unwrap();
+        assert_eq!(document["nodes"].as_array().unwrap().len(), parts.len());
+        assert_eq!(document["nodes"][0]["name"], parts[0].0.as_str());
+        assert!(document["meshes"][0]["primitives"][0]["attributes"]["COLOR_0"].is_u64());
+
+        let mut obj = Vec::new();
+        write_obj(&parts, &mut obj).unwrap();
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("v ")).count(),
+            vertices
+        );
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("f ")).count(),
+            triangles
+        );
+        assert_eq!(
+            obj.lines().filter(|l| l.starts_with("o ")).count(),
+            parts.len()
+        );
+
+        let mut stl = Vec::new();
+        write_stl(&parts, &mut stl).unwrap();
+        assert_eq!(stl.len(), 84 + 50 * triangles);
+    }
+
+    #[test]
+    fn test_base64() {
+        assert_eq!(base64(b""), "");
+        assert_eq!(base64(b"f"), "Zg==");
+        assert_eq!(base64(b"fo"), "Zm8=");
+        assert_eq!(base64(b"foobar"), "Zm9vYmFy");
+    }
+}
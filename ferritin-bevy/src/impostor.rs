@@ -0,0 +1,702 @@
+//! Impostor
+//!
+//! GPU-instanced rendering of atoms and bonds for large structures. Instead of merging a
+//! sphere mesh per atom, every atom and half-bond becomes one instance of a small proxy
+//! box; the fragment shader ray-casts the exact sphere or cylinder inside it. Memory and
+//! upload cost scale with the number of atoms rather than the number of triangles.
+//!
+//! Each structure also carries a coarse level of detail with one sphere per residue. When
+//! atoms shrink below [`LOD_PIXEL_RADIUS`] on screen the coarse level is drawn instead,
+//! which keeps assemblies with millions of atoms interactive when zoomed out.
+//!
+//! The instanced path is used by [`StructurePlugin`](crate::StructurePlugin) for the
+//! `Solid` and `BallAndStick` render types. Impostors do their own shading, so structures
+//! with a custom material are drawn as meshes. The regular mesh path remains available as
+//! a CPU fallback via [`RenderBackend::Mesh`].
+use super::{RenderOptions, Structure};
+use bevy::core_pipeline::core_3d::Transparent3d;
+use bevy::ecs::query::QueryItem;
+use bevy::ecs::system::lifetimeless::{Read, SRes};
+use bevy::ecs::system::SystemParamItem;
+use bevy::pbr::{
+    MeshPipeline, MeshPipelineKey, RenderMeshInstances, SetMeshBindGroup, SetMeshViewBindGroup,
+};
+use bevy::prelude::*;
+use bevy::render::extract_component::{ExtractComponent, ExtractComponentPlugin};
+use bevy::render::mesh::allocator::MeshAllocator;
+use bevy::render::mesh::{MeshVertexBufferLayoutRef, RenderMesh, RenderMeshBufferInfo};
+use bevy::render::render_asset::RenderAssets;
+use bevy::render::render_phase::{
+    AddRenderCommand, DrawFunctions, PhaseItem, PhaseItemExtraIndex, RenderCommand,
+    RenderCommandResult, SetItemPipeline, TrackedRenderPass, ViewSortedRenderPhases,
+};
+use bevy::render::render_resource::binding_types::uniform_buffer;
+use bevy::render::render_resource::{
+    BindGroup, BindGroupEntries, BindGroupLayout, BindGroupLayoutEntries, Buffer,
+    BufferInitDescriptor, BufferUsages, PipelineCache, RenderPipelineDescriptor, ShaderStages,
+    SpecializedMeshPipeline, SpecializedMeshPipelineError, SpecializedMeshPipelines,
+    VertexAttribute, VertexBufferLayout, VertexFormat, VertexStepMode,
+};
+use bevy::render::renderer::{RenderDevice, RenderQueue};
+use bevy::render::sync_world::MainEntity;
+use bevy::render::view::{ExtractedView, NoFrustumCulling};
+use bevy::render::{Render, RenderApp, RenderSet};
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+
+const IMPOSTOR_SHADER_HANDLE: Handle<Shader> =
+    Handle::weak_from_u128(0x6a1c_52e9_0f3b_4d7a_9c8e_2b41_d05f_7e13);
+
+/// Atoms projecting to fewer pixels than this switch the structure to its coarse level
+pub const LOD_PIXEL_RADIUS: f32 = 1.5;
+/// Radius of atoms in ball-and-stick
+pub const BALL_RADIUS: f32 = 0.5;
+/// Radius of bonds in ball-and-stick
+pub const STICK_RADIUS: f32 = 0.2;
+/// Added to the residue extent for the coarse spheres
+const COARSE_PADDING: f32 = 1.0;
+
+/// How `Solid` and `BallAndStick` structures are drawn
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum RenderBackend {
+    /// GPU-instanced ray-cast impostors
+    #[default]
+    Instanced,
+    /// One merged triangle mesh, built on the CPU
+    Mesh,
+}
+
+/// One sphere or cylinder, laid out as the shader's per-instance vertex attributes
+#[derive(Clone, Copy, Debug, PartialEq, Pod, Zeroable)]
+#[repr(C)]
+pub struct ImpostorInstance {
+    /// Sphere center or cylinder start, and the radius
+    pub start_radius: [f32; 4],
+    /// Cylinder end, with `w = 1.0` for cylinders and `0.0` for spheres
+    pub end_kind: [f32; 4],
+    pub color: [f32; 4],
+}
+
+impl ImpostorInstance {
+    pub fn sphere(center: [f32; 3], radius: f32, color: Color) -> Self {
+        Self {
+            start_radius: [center[0], center[1], center[2], radius],
+            end_kind: [0.0; 4],
+            color: linear(color),
+        }
+    }
+    pub fn cylinder(start: [f32; 3], end: [f32; 3], radius: f32, color: Color) -> Self {
+        Self {
+            start_radius: [start[0], start[1], start[2], radius],
+            end_kind: [end[0], end[1], end[2], 1.0],
+            color: linear(color),
+        }
+    }
+}
+
+fn linear(color: Color) -> [f32; 4] {
+    let c = color.to_linear();
+    [c.red, c.green, c.blue, c.alpha]
+}
+
+/// Instances drawn for a structure, with a full and a coarse level of detail.
+#[derive(Component, Clone, Debug)]
+pub struct ImpostorInstances {
+    levels: [Arc<Vec<ImpostorInstance>>; 2],
+    /// Atoms each full-detail instance is placed on
+    sources: Arc<Vec<InstanceSource>>,
+    /// Shown atoms of the residue behind each coarse sphere
+    residues: Arc<Vec<Vec<usize>>>,
+    /// Index of the level being drawn: 0 is full detail, 1 is coarse
+    active: usize,
+    /// Center and radius of the bounding sphere, in local space
+    bounds: (Vec3, f32),
+}
+
+/// The atoms that position an instance
+#[derive(Clone, Copy, Debug)]
+enum InstanceSource {
+    /// A sphere on the atom
+    Atom(usize),
+    /// The half of a bond from the first atom to the bond's midpoint
+    HalfBond(usize, usize),
+}
+
+impl InstanceSource {
+    /// `instance` moved onto `coords`, keeping its radius, kind and color
+    fn place(self, instance: &ImpostorInstance, coords: &[[f32; 3]]) -> ImpostorInstance {
+        let mut placed = *instance;
+        match self {
+            InstanceSource::Atom(a) => placed.start_radius[..3].copy_from_slice(&coords[a]),
+            InstanceSource::HalfBond(a, b) => {
+                let (pa, pb) = (Vec3::from_array(coords[a]), Vec3::from_array(coords[b]));
+                placed.start_radius[..3].copy_from_slice(&coords[a]);
+                placed.end_kind[..3].copy_from_slice(&((pa + pb) / 2.0).to_array());
+            }
+        }
+        placed
+    }
+}
+
+impl ImpostorInstances {
+    /// Instances for a structure's current render type, or `None` if the render type is
+    /// not drawn with impostors.
+    pub fn from_structure(structure: &Structure) -> Option<Self> {
+        if !is_default_material(&structure.get_material()) {
+            return None;
+        }
+        let ac = structure.get_pdb();
+        let colors = structure.get_color_scheme().atom_colors(ac);
+        let mut shown = vec![structure.get_selection().is_none(); ac.get_size()];
+        if let Some(selection) = structure.get_selection() {
+            for &i in selection.indices() {
+                shown[i] = true;
+            }
+        }
+        let atoms: Vec<usize> = (0..ac.get_size()).filter(|&i| shown[i]).collect();
+
+        let mut full: Vec<ImpostorInstance> = match structure.get_rendertype() {
+            RenderOptions::Solid => atoms
+                .iter()
+                .map(|&i| {
+                    let radius = ac
+                        .get_element(i)
+                        .atomic_radius()
+                        .van_der_waals
+                        .unwrap_or(1.5) as f32;
+                    ImpostorInstance::sphere(*ac.get_coord(i), radius, colors[i])
+                })
+                .collect(),
+            RenderOptions::BallAndStick => atoms
+                .iter()
+                .map(|&i| ImpostorInstance::sphere(*ac.get_coord(i), BALL_RADIUS, colors[i]))
+                .collect(),
+            _ => return None,
+        };
+        let mut sources: Vec<InstanceSource> =
+            atoms.iter().map(|&i| InstanceSource::Atom(i)).collect();
+        if let (RenderOptions::BallAndStick, Some(bonds)) =
+            (structure.get_rendertype(), ac.get_bonds())
+        {
+            // two half-bonds, each colored by its atom
+            for bond in bonds {
+                let (a, b) = bond.get_atom_indices();
+                let (a, b) = (a as usize, b as usize);
+                if !(shown[a] && shown[b]) {
+                    continue;
+                }
+                for (from, to) in [(a, b), (b, a)] {
+                    let source = InstanceSource::HalfBond(from, to);
+                    let half =
+                        ImpostorInstance::cylinder([0.0; 3], [0.0; 3], STICK_RADIUS, colors[from]);
+                    full.push(source.place(&half, ac.get_coords()));
+                    sources.push(source);
+                }
+            }
+        }
+        let residues: Vec<Vec<usize>> = ac
+            .iter_residues_all()
+            .map(|res| {
+                (res.start_idx..res.end_idx)
+                    .filter(|&i| shown[i])
+                    .collect::<Vec<_>>()
+            })
+            .filter(|atoms| !atoms.is_empty())
+            .collect();
+        let coarse = residues
+            .iter()
+            .map(|atoms| {
+                let (center, radius) = residue_sphere(atoms, ac.get_coords());
+                ImpostorInstance::sphere(center.to_array(), radius, colors[atoms[0]])
+            })
+            .collect();
+        let bounds = instance_bounds(&full);
+        Some(Self {
+            levels: [Arc::new(full), Arc::new(coarse)],
+            sources: Arc::new(sources),
+            residues: Arc::new(residues),
+            active: 0,
+            bounds,
+        })
+    }
+    /// Move every instance to new atom coordinates, e.g. the next frame of a trajectory.
+    /// Radii, colors and the active level are kept, so nothing has to be rebuilt.
+    pub fn set_coords(&mut self, coords: &[[f32; 3]]) {
+        let full: Vec<ImpostorInstance> = self.levels[0]
+            .iter()
+            .zip(self.sources.iter())
+            .map(|(instance, source)| source.place(instance, coords))
+            .collect();
+        let coarse = self.levels[1]
+            .iter()
+            .zip(self.residues.iter())
+            .map(|(instance, atoms)| {
+                let (center, radius) = residue_sphere(atoms, coords);
+                ImpostorInstance {
+                    start_radius: [center.x, center.y, center.z, radius],
+                    ..*instance
+                }
+            })
+            .collect();
+        self.bounds = instance_bounds(&full);
+        self.levels = [Arc::new(full), Arc::new(coarse)];
+    }
+    /// Instances of the level currently drawn
+    pub fn active(&self) -> &[ImpostorInstance] {
+        &self.levels[self.active]
+    }
+    pub fn full(&self) -> &[ImpostorInstance] {
+        &self.levels[0]
+    }
+    pub fn coarse(&self) -> &[ImpostorInstance] {
+        &self.levels[1]
+    }
+    pub fn is_coarse(&self) -> bool {
+        self.active == 1
+    }
+}
+
+/// Whether `material` looks like bevy's default one; anything else needs the mesh path
+fn is_default_material(material: &StandardMaterial) -> bool {
+    let default = StandardMaterial::default();
+    material.base_color == default.base_color
+        && material.base_color_texture.is_none()
+        && material.emissive == default.emissive
+        && material.metallic == default.metallic
+        && material.perceptual_roughness == default.perceptual_roughness
+        && material.reflectance == default.reflectance
+        && material.alpha_mode == default.alpha_mode
+        && material.unlit == default.unlit
+}
+
+/// Sphere enclosing a residue's shown atoms, for the coarse level
+fn residue_sphere(atoms: &[usize], coords: &[[f32; 3]]) -> (Vec3, f32) {
+    let (center, extent) = bounding_sphere(atoms.iter().map(|&i| Vec3::from_array(coords[i])));
+    (center, extent + COARSE_PADDING)
+}
+
+fn instance_bounds(instances: &[ImpostorInstance]) -> (Vec3, f32) {
+    bounding_sphere(instances.iter().map(|inst| {
+        let [x, y, z, _] = inst.start_radius;
+        Vec3::new(x, y, z)
+    }))
+}
+
+fn bounding_sphere(points: impl Iterator<Item = Vec3>) -> (Vec3, f32) {
+    let points: Vec<Vec3> = points.collect();
+    if points.is_empty() {
+        return (Vec3::ZERO, 0.0);
+    }
+    let center = points.iter().sum::<Vec3>() / points.len() as f32;
+    let radius = points
+        .iter()
+        .map(|p| p.distance(center))
+        .fold(0.0, f32::max);
+    (center, radius)
+}
+
+/// Proxy box drawn once per instance
+pub(crate) fn proxy_mesh() -> Mesh {
+    Cuboid::new(2.0, 2.0, 2.0).mesh().build()
+}
+
+/// The proxy box shared by all impostor structures. Only present when [`ImpostorPlugin`]
+/// found a renderer to draw impostors with.
+#[derive(Resource, Clone)]
+pub(crate) struct ImpostorProxy(pub(crate) Handle<Mesh>);
+
+/// Bundle drawing `instances` on a structure entity
+pub(crate) fn impostor_bundle(
+    instances: ImpostorInstances,
+    proxy: &ImpostorProxy,
+) -> (Mesh3d, ImpostorInstances, NoFrustumCulling) {
+    (Mesh3d(proxy.0.clone()), instances, NoFrustumCulling)
+}
+
+pub struct ImpostorPlugin;
+
+impl Plugin for ImpostorPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(ExtractComponentPlugin::<ImpostorInstances>::default())
+            .add_systems(PostUpdate, select_level_of_detail);
+        let Some(render_app) = app.get_sub_app_mut(RenderApp) else {
+            return;
+        };
+        render_app
+            .add_render_command::<Transparent3d, DrawImpostors>()
+            .init_resource::<SpecializedMeshPipelines<ImpostorPipeline>>()
+            .add_systems(
+                Render,
+                (
+                    queue_impostors.in_set(RenderSet::QueueMeshes),
+                    prepare_impostor_buffers.in_set(RenderSet::PrepareResources),
+                ),
+            );
+        bevy::asset::load_internal_asset!(
+            app,
+            IMPOSTOR_SHADER_HANDLE,
+            "impostor.wgsl",
+            Shader::from_wgsl
+        );
+        let proxy = app
+            .world_mut()
+            .resource_mut::<Assets<Mesh>>()
+            .add(proxy_mesh());
+        app.insert_resource(ImpostorProxy(proxy));
+    }
+
+    fn finish(&self, app: &mut App) {
+        if let Some(render_app) = app.get_sub_app_mut(RenderApp) {
+            render_app.init_resource::<ImpostorPipeline>();
+        }
+    }
+}
+
+/// Switch each structure between its full and coarse level based on how large an atom
+/// appears on screen from the closest active camera.
+fn select_level_of_detail(
+    cameras: Query<(&Camera, &GlobalTransform, &Projection)>,
+    mut structures: Query<(&mut ImpostorInstances, &GlobalTransform)>,
+) {
+    for (mut instances, transform) in &mut structures {
+        let (center, radius) = instances.bounds;
+        let center = transform.transform_point(center);
+        let scale = transform.compute_transform().scale.max_element();
+        let pixels = cameras
+            .iter()
+            .filter(|(camera, _, _)| camera.is_active)
+            .filter_map(|(camera, camera_transform, projection)| {
+                let height = camera.logical_viewport_size()?.y;
+                // distance to the nearest point of the structure
+                let distance =
+                    (camera_transform.translation().distance(center) - radius * scale).max(1.0);
+                let world_height = match projection {
+                    Projection::Perspective(p) => 2.0 * distance * (p.fov / 2.0).tan(),
+                    Projection::Orthographic(o) => o.area.height(),
+                };
+                Some(BALL_RADIUS * scale * height / world_height)
+            })
+            .fold(0.0_f32, f32::max);
+        // hysteresis keeps the level from flickering at the threshold
+        let active = match instances.active {
+            0 if pixels < LOD_PIXEL_RADIUS => 1,
+            1 if pixels > 1.5 * LOD_PIXEL_RADIUS => 0,
+            level => level,
+        };
+        if active != instances.active && !instances.levels[active].is_empty() {
+            instances.active = active;
+        }
+    }
+}
+
+// Render World --------------------------------------
+
+/// Instances and transform copied to the render world each frame. Only the `Arc` is
+/// cloned; the instance buffer is re-uploaded when the active level changes.
+#[derive(Component)]
+pub struct ExtractedImpostors {
+    instances: Arc<Vec<ImpostorInstance>>,
+    world_from_local: Mat4,
+}
+
+impl ExtractComponent for ImpostorInstances {
+    type QueryData = (&'static ImpostorInstances, &'static GlobalTransform);
+    type QueryFilter = ();
+    type Out = ExtractedImpostors;
+
+    fn extract_component(
+        (instances, transform): QueryItem<'_, Self::QueryData>,
+    ) -> Option<Self::Out> {
+        Some(ExtractedImpostors {
+            instances: instances.levels[instances.active].clone(),
+            world_from_local: transform.compute_matrix(),
+        })
+    }
+}
+
+#[derive(Component)]
+struct ImpostorBuffers {
+    /// The instance data this buffer was built from
+    source: Arc<Vec<ImpostorInstance>>,
+    instances: Buffer,
+    length: usize,
+    transform: Buffer,
+    bind_group: BindGroup,
+}
+
+fn prepare_impostor_buffers(
+    mut commands: Commands,
+    query: Query<(Entity, &ExtractedImpostors, Option<&ImpostorBuffers>)>,
+    pipeline: Res<ImpostorPipeline>,
+    render_device: Res<RenderDevice>,
+    render_queue: Res<RenderQueue>,
+) {
+    for (entity, extracted, buffers) in &query {
+        let matrix = extracted.world_from_local.to_cols_array();
+        match buffers {
+            Some(buffers) if Arc::ptr_eq(&buffers.source, &extracted.instances) => {
+                render_queue.write_buffer(&buffers.transform, 0, bytemuck::cast_slice(&matrix));
+            }
+            _ => {
+                let instances = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("impostor instance buffer"),
+                    contents: bytemuck::cast_slice(extracted.instances.as_slice()),
+                    usage: BufferUsages::VERTEX | BufferUsages::COPY_DST,
+                });
+                let transform = render_device.create_buffer_with_data(&BufferInitDescriptor {
+                    label: Some("impostor transform buffer"),
+                    contents: bytemuck::cast_slice(&matrix),
+                    usage: BufferUsages::UNIFORM | BufferUsages::COPY_DST,
+                });
+                let bind_group = render_device.create_bind_group(
+                    "impostor_bind_group",
+                    &pipeline.transform_layout,
+                    &BindGroupEntries::single(transform.as_entire_binding()),
+                );
+                commands.entity(entity).insert(ImpostorBuffers {
+                    source: extracted.instances.clone(),
+                    instances,
+                    length: extracted.instances.len(),
+                    transform,
+                    bind_group,
+                });
+            }
+        }
+    }
+}
+
+#[derive(Resource)]
+struct ImpostorPipeline {
+    mesh_pipeline: MeshPipeline,
+    transform_layout: BindGroupLayout,
+}
+
+impl FromWorld for ImpostorPipeline {
+    fn from_world(world: &mut World) -> Self {
+        let render_device = world.resource::<RenderDevice>();
+        let transform_layout = render_device.create_bind_group_layout(
+            "impostor_transform_layout",
+            &BindGroupLayoutEntries::single(
+                ShaderStages::VERTEX_FRAGMENT,
+                uniform_buffer::<Mat4>(false),
+            ),
+        );
+        ImpostorPipeline {
+            mesh_pipeline: world.resource::<MeshPipeline>().clone(),
+            transform_layout,
+        }
+    }
+}
+
+impl SpecializedMeshPipeline for ImpostorPipeline {
+    type Key = MeshPipelineKey;
+
+    fn specialize(
+        &self,
+        key: Self::Key,
+        layout: &MeshVertexBufferLayoutRef,
+    ) -> Result<RenderPipelineDescriptor, SpecializedMeshPipelineError> {
+        let mut descriptor = self.mesh_pipeline.specialize(key, layout)?;
+        descriptor.layout.push(self.transform_layout.clone());
+        descriptor.vertex.shader = IMPOSTOR_SHADER_HANDLE;
+        // locations 0-2 hold the proxy mesh's position, normal and uv
+        let vec4 = VertexFormat::Float32x4;
+        descriptor.vertex.buffers.push(VertexBufferLayout {
+            array_stride: size_of::<ImpostorInstance>() as u64,
+            step_mode: VertexStepMode::Instance,
+            attributes: (0..3)
+                .map(|i| VertexAttribute {
+                    format: vec4,
+                    offset: i * vec4.size(),
+                    shader_location: 3 + i as u32,
+                })
+                .collect(),
+        });
+        descriptor.fragment.as_mut().unwrap().shader = IMPOSTOR_SHADER_HANDLE;
+        Ok(descriptor)
+    }
+}
+
+#[allow(clippy::too_many_arguments)]
+fn queue_impostors(
+    draw_functions: Res<DrawFunctions<Transparent3d>>,
+    impostor_pipeline: Res<ImpostorPipeline>,
+    mut pipelines: ResMut<SpecializedMeshPipelines<ImpostorPipeline>>,
+    pipeline_cache: Res<PipelineCache>,
+    meshes: Res<RenderAssets<RenderMesh>>,
+    render_mesh_instances: Res<RenderMeshInstances>,
+    impostors: Query<(Entity, &MainEntity), With<ExtractedImpostors>>,
+    mut phases: ResMut<ViewSortedRenderPhases<Transparent3d>>,
+    views: Query<(Entity, &ExtractedView, &Msaa)>,
+) {
+    let draw_impostors = draw_functions.read().id::<DrawImpostors>();
+    for (view_entity, view, msaa) in &views {
+        let Some(phase) = phases.get_mut(&view_entity) else {
+            continue;
+        };
+        let view_key = MeshPipelineKey::from_msaa_samples(msaa.samples())
+            | MeshPipelineKey::from_hdr(view.hdr);
+        let rangefinder = view.rangefinder3d();
+        for (entity, main_entity) in &impostors {
+            let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(*main_entity)
+            else {
+                continue;
+            };
+            let Some(mesh) = meshes.get(mesh_instance.mesh_asset_id) else {
+                continue;
+            };
+            let key =
+                view_key | MeshPipelineKey::from_primitive_topology(mesh.primitive_topology());
+            let Ok(pipeline) =
+                pipelines.specialize(&pipeline_cache, &impostor_pipeline, key, &mesh.layout)
+            else {
+                continue;
+            };
+            phase.add(Transparent3d {
+                entity: (entity, *main_entity),
+                pipeline,
+                draw_function: draw_impostors,
+                distance: rangefinder.distance_translation(&mesh_instance.translation),
+                batch_range: 0..1,
+                extra_index: PhaseItemExtraIndex::NONE,
+            });
+        }
+    }
+}
+
+type DrawImpostors = (
+    SetItemPipeline,
+    SetMeshViewBindGroup<0>,
+    SetMeshBindGroup<1>,
+    DrawImpostorInstances,
+);
+
+struct DrawImpostorInstances;
+
+impl<P: PhaseItem> RenderCommand<P> for DrawImpostorInstances {
+    type Param = (
+        SRes<RenderAssets<RenderMesh>>,
+        SRes<RenderMeshInstances>,
+        SRes<MeshAllocator>,
+    );
+    type ViewQuery = ();
+    type ItemQuery = Read<ImpostorBuffers>;
+
+    #[inline]
+    fn render<'w>(
+        item: &P,
+        _view: (),
+        buffers: Option<&'w ImpostorBuffers>,
+        (meshes, render_mesh_instances, mesh_allocator): SystemParamItem<'w, '_, Self::Param>,
+        pass: &mut TrackedRenderPass<'w>,
+    ) -> RenderCommandResult {
+        let mesh_allocator = mesh_allocator.into_inner();
+        let Some(mesh_instance) = render_mesh_instances.render_mesh_queue_data(item.main_entity())
+        else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(gpu_mesh) = meshes.into_inner().get(mesh_instance.mesh_asset_id) else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(buffers) = buffers else {
+            return RenderCommandResult::Skip;
+        };
+        let Some(vertex_slice) = mesh_allocator.mesh_vertex_slice(&mesh_instance.mesh_asset_id)
+        else {
+            return RenderCommandResult::Skip;
+        };
+
+        pass.set_bind_group(2, &buffers.bind_group, &[]);
+        pass.set_vertex_buffer(0, vertex_slice.buffer.slice(..));
+        pass.set_vertex_buffer(1, buffers.instances.slice(..));
+        let instances = 0..buffers.length as u32;
+        match &gpu_mesh.buffer_info {
+            RenderMeshBufferInfo::Indexed {
+                index_format,
+                count,
+            } => {
+                let Some(index_slice) =
+                    mesh_allocator.mesh_index_slice(&mesh_instance.mesh_asset_id)
+                else {
+                    return RenderCommandResult::Skip;
+                };
+                pass.set_index_buffer(index_slice.buffer.slice(..), 0, *index_format);
+                pass.draw_indexed(
+                    index_slice.range.start..(index_slice.range.start + count),
+                    vertex_slice.range.start as i32,
+                    instances,
+                );
+            }
+            RenderMeshBufferInfo::NonIndexed => {
+                pass.draw(vertex_slice.range, instances);
+            }
+        }
+        RenderCommandResult::Success
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_core::AtomCollection;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_impostor_instances() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_01().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let mut ac: AtomCollection = AtomCollection::from(&pdb)
+            .iter_residues_aminoacid()
+            .collect();
+        ac.connect_via_residue_names();
+        let atoms = ac.get_size();
+        let bonds = ac.get_bonds().map_or(0, |bonds| bonds.len());
+        let residues = ac.iter_residues_all().count();
+
+        let structure = Structure::builder()
+            .pdb(ac)
+            .rendertype(RenderOptions::BallAndStick)
+            .build();
+        let instances = ImpostorInstances::from_structure(&structure).unwrap();
+        // a sphere per atom and two half-bond cylinders per bond
+        assert_eq!(instances.full().len(), atoms + 2 * bonds);
+        assert_eq!(instances.coarse().len(), residues);
+        assert!(!instances.is_coarse());
+
+        // new coordinates move the instances and keep their radii and colors
+        let shifted: Vec<[f32; 3]> = structure
+            .get_pdb()
+            .get_coords()
+            .iter()
+            .map(|[x, y, z]| [x + 1.0, *y, *z])
+            .collect();
+        let mut moved = instances.clone();
+        moved.set_coords(&shifted);
+        for (before, after) in instances.full().iter().zip(moved.full()) {
+            assert!((after.start_radius[0] - before.start_radius[0] - 1.0).abs() < 1e-4);
+            assert!((after.end_kind[0] - before.end_kind[0] - before.end_kind[3]).abs() < 1e-4);
+            assert_eq!(after.start_radius[3], before.start_radius[3]);
+            assert_eq!(after.color, before.color);
+        }
+        assert_eq!(moved.coarse().len(), residues);
+
+        let cartoon = Structure::builder()
+            .pdb(AtomCollection::from(&pdb))
+            .rendertype(RenderOptions::Cartoon)
+            .build();
+        assert!(ImpostorInstances::from_structure(&cartoon).is_none());
+
+        // custom materials keep the mesh path so they are not lost
+        let chalky = Structure::builder()
+            .pdb(AtomCollection::from(&pdb))
+            .rendertype(RenderOptions::Solid)
+            .material(StandardMaterial {
+                base_color: Color::srgb(0.9, 0.9, 0.9),
+                perceptual_roughness: 1.0,
+                ..default()
+            })
+            .build();
+        assert!(ImpostorInstances::from_structure(&chalky).is_none());
+        Ok(())
+    }
+}
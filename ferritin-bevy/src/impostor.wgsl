@@ -0,0 +1,150 @@
+// Ray-cast impostors for atoms and bonds.
+//
+// Each instance is drawn as a box that encloses a sphere or a capped cylinder. The
+// fragment shader intersects the view ray with the exact primitive, discards fragments
+// that miss and writes the depth of the hit so impostors intersect correctly with each
+// other and with regular meshes.
+
+#import bevy_pbr::mesh_view_bindings::view
+
+@group(2) @binding(0) var<uniform> world_from_local: mat4x4<f32>;
+
+struct Vertex {
+    @location(0) position: vec3<f32>,
+    @location(1) normal: vec3<f32>,
+    @location(2) uv: vec2<f32>,
+
+    // sphere: center and radius; cylinder: start and radius
+    @location(3) i_start_radius: vec4<f32>,
+    // cylinder end point, w = 1 for cylinders and 0 for spheres
+    @location(4) i_end_kind: vec4<f32>,
+    @location(5) i_color: vec4<f32>,
+};
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) world_position: vec3<f32>,
+    @location(1) start_radius: vec4<f32>,
+    @location(2) end_kind: vec4<f32>,
+    @location(3) color: vec4<f32>,
+};
+
+struct FragmentOutput {
+    @location(0) color: vec4<f32>,
+    @builtin(frag_depth) depth: f32,
+};
+
+fn to_world(p: vec3<f32>) -> vec3<f32> {
+    return (world_from_local * vec4<f32>(p, 1.0)).xyz;
+}
+
+@vertex
+fn vertex(vertex: Vertex) -> VertexOutput {
+    let scale = length(world_from_local[0].xyz);
+    let start = to_world(vertex.i_start_radius.xyz);
+    let radius = vertex.i_start_radius.w * scale;
+    var world: vec3<f32>;
+    var end = start;
+    if vertex.i_end_kind.w > 0.5 {
+        end = to_world(vertex.i_end_kind.xyz);
+        // orient the unit box along the bond axis
+        let axis = end - start;
+        let dir = normalize(axis);
+        var helper = vec3<f32>(1.0, 0.0, 0.0);
+        if abs(dir.x) > 0.9 {
+            helper = vec3<f32>(0.0, 1.0, 0.0);
+        }
+        let u = normalize(cross(dir, helper));
+        let v = cross(dir, u);
+        let along = (vertex.position.y * 0.5 + 0.5) * length(axis);
+        world = start + dir * along + (u * vertex.position.x + v * vertex.position.z) * radius;
+    } else {
+        world = start + vertex.position * radius;
+    }
+
+    var out: VertexOutput;
+    out.clip_position = view.clip_from_world * vec4<f32>(world, 1.0);
+    out.world_position = world;
+    out.start_radius = vec4<f32>(start, radius);
+    out.end_kind = vec4<f32>(end, vertex.i_end_kind.w);
+    out.color = vertex.i_color;
+    return out;
+}
+
+// Ray-sphere intersection: (t, normal), t < 0 on a miss
+fn hit_sphere(ro: vec3<f32>, rd: vec3<f32>, center: vec3<f32>, radius: f32) -> vec4<f32> {
+    let oc = ro - center;
+    let b = dot(oc, rd);
+    let c = dot(oc, oc) - radius * radius;
+    let h = b * b - c;
+    if h < 0.0 {
+        return vec4<f32>(-1.0);
+    }
+    let t = -b - sqrt(h);
+    return vec4<f32>(t, (ro + t * rd - center) / radius);
+}
+
+// Ray-capped cylinder intersection: (t, normal), t < 0 on a miss
+fn hit_cylinder(ro: vec3<f32>, rd: vec3<f32>, pa: vec3<f32>, pb: vec3<f32>, radius: f32) -> vec4<f32> {
+    let ba = pb - pa;
+    let oc = ro - pa;
+    let baba = dot(ba, ba);
+    let bard = dot(ba, rd);
+    let baoc = dot(ba, oc);
+    let k2 = baba - bard * bard;
+    let k1 = baba * dot(oc, rd) - baoc * bard;
+    let k0 = baba * dot(oc, oc) - baoc * baoc - radius * radius * baba;
+    var h = k1 * k1 - k2 * k0;
+    if h < 0.0 {
+        return vec4<f32>(-1.0);
+    }
+    h = sqrt(h);
+    var t = (-k1 - h) / k2;
+    let y = baoc + t * bard;
+    if y > 0.0 && y < baba {
+        return vec4<f32>(t, (oc + t * rd - ba * y / baba) / radius);
+    }
+    // caps
+    t = (select(baba, 0.0, y < 0.0) - baoc) / bard;
+    if abs(k1 + k2 * t) < h {
+        return vec4<f32>(t, ba * sign(y) / sqrt(baba));
+    }
+    return vec4<f32>(-1.0);
+}
+
+@fragment
+fn fragment(in: VertexOutput) -> FragmentOutput {
+    // orthographic views have parallel rays along the camera's forward axis
+    let forward = -normalize(view.world_from_view[2].xyz);
+    var rd = normalize(in.world_position - view.world_position);
+    if view.clip_from_view[3][3] == 1.0 {
+        rd = forward;
+    }
+    // start on the proxy surface, which always lies in front of the primitive
+    let ro = in.world_position;
+
+    var hit: vec4<f32>;
+    if in.end_kind.w > 0.5 {
+        hit = hit_cylinder(ro, rd, in.start_radius.xyz, in.end_kind.xyz, in.start_radius.w);
+    } else {
+        hit = hit_sphere(ro, rd, in.start_radius.xyz, in.start_radius.w);
+    }
+    if hit.x < 0.0 {
+        discard;
+    }
+    let position = ro + hit.x * rd;
+    let normal = normalize(hit.yzw);
+
+    // headlight from above the viewer's right shoulder
+    let up = normalize(view.world_from_view[1].xyz);
+    let right = normalize(view.world_from_view[0].xyz);
+    let light = normalize(-rd + 0.5 * up + 0.3 * right);
+    let diffuse = max(dot(normal, light), 0.0);
+    let specular = pow(max(dot(reflect(-light, normal), -rd), 0.0), 32.0);
+
+    let clip = view.clip_from_world * vec4<f32>(position, 1.0);
+    var out: FragmentOutput;
+    out.color = vec4<f32>(in.color.rgb * (0.3 + 0.7 * diffuse) + vec3<f32>(0.25 * specular), in.color.a);
+    out.depth = clip.z / clip.w;
+    return out;
+}
@@ -10,11 +10,39 @@
 //! - 3D visualization of protein structures
 //! - Configurable coloring schemes
 //! - Interactive camera controls
+//! - Atom picking, hover labels and selection
+//! - Headless rendering to PNG
+//! - glTF, OBJ and STL export of structure meshes
+//! - GPU-instanced impostors with level of detail for large assemblies
+//! - Playback of trajectories and NMR ensembles
 //! - Support for multiple visualization styles
 //!
+pub mod camera;
+mod cartoon;
 pub mod colors;
+pub mod export;
+pub mod impostor;
+pub mod picking;
 pub mod plugin;
+pub mod snapshot;
 pub mod structure;
-pub use colors::ColorScheme;
-pub use plugin::{StructurePlugin, StructureSettings};
+pub mod surface;
+pub mod trajectory;
+pub use camera::{
+    bounding_box, frame_bounds, framing_camera, CameraControlPlugin, OrbitCamera, PymolView,
+    SetCameraView,
+};
+pub use colors::{ColorScheme, ElementPalette, Gradient, PropertyValues};
+pub use impostor::{ImpostorInstance, ImpostorInstances, ImpostorPlugin, RenderBackend};
+pub use picking::{
+    AtomPick, AtomPicked, HoveredAtom, PickingSettings, SelectionMode, StructurePickingPlugin,
+    StructureSelection,
+};
+pub use plugin::{
+    LoadProteinEvent, StructureLoaded, StructurePlugin, StructureSettings, StructureSource,
+    UnloadProteinEvent, UpdateRepresentationEvent,
+};
+pub use snapshot::Snapshot;
 pub use structure::{RenderOptions, Structure};
+pub use surface::{SurfaceMesh, SurfaceSettings, SurfaceType};
+pub use trajectory::{Trajectory, TrajectoryControl, TrajectoryPlugin};
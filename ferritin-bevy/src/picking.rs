@@ -0,0 +1,480 @@
+//! Picking
+//!
+//! Mouse interaction with loaded structures. A ray is cast from the cursor into the scene
+//! and tested against a sphere per atom, sized to match the structure's render type. The
+//! atom under the cursor is shown in a hover label; clicking selects the atom, its residue
+//! or its chain depending on the [`SelectionMode`], and shift-click extends the selection.
+//! Selected atoms are drawn with a translucent highlight and reported as [`AtomPicked`]
+//! events so that other systems can respond.
+//!
+//! ```no_run
+//! use bevy::prelude::*;
+//! use ferritin_bevy::{SelectionMode, StructurePickingPlugin, StructurePlugin};
+//!
+//! App::new()
+//!     .add_plugins(DefaultPlugins)
+//!     .add_plugins(StructurePlugin::new().with_file("examples/1fap.cif", None))
+//!     .add_plugins(StructurePickingPlugin {
+//!         mode: SelectionMode::Residue,
+//!         ..default()
+//!     })
+//!     .run();
+//! ```
+use super::Structure;
+use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use ferritin_core::{AtomCollection, Selection};
+
+/// Cursor movement in pixels between press and release that still counts as a click
+const CLICK_TOLERANCE: f32 = 4.0;
+/// Highlight spheres are drawn slightly larger than the atoms they cover
+const HIGHLIGHT_SCALE: f32 = 1.15;
+
+/// What a click selects
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum SelectionMode {
+    #[default]
+    Atom,
+    Residue,
+    Chain,
+}
+
+pub struct StructurePickingPlugin {
+    pub mode: SelectionMode,
+    pub highlight_color: Color,
+}
+
+impl Default for StructurePickingPlugin {
+    fn default() -> Self {
+        Self {
+            mode: SelectionMode::Atom,
+            highlight_color: Color::srgba(1.0, 0.85, 0.1, 0.5),
+        }
+    }
+}
+
+impl Plugin for StructurePickingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(PickingSettings {
+            mode: self.mode,
+            highlight_color: self.highlight_color,
+        })
+        .init_resource::<HoveredAtom>()
+        .init_resource::<ClickState>()
+        .add_event::<AtomPicked>()
+        .add_systems(Startup, spawn_hover_label)
+        .add_systems(
+            Update,
+            (
+                update_hovered_atom,
+                update_hover_label,
+                handle_clicks,
+                highlight_selection,
+            )
+                .chain(),
+        );
+    }
+}
+
+/// Runtime picking configuration; can be changed while the app is running.
+#[derive(Resource, Clone, Debug)]
+pub struct PickingSettings {
+    pub mode: SelectionMode,
+    pub highlight_color: Color,
+}
+
+/// An atom hit by the cursor ray
+#[derive(Clone, Debug, PartialEq)]
+pub struct AtomPick {
+    /// The entity holding the [`Structure`]
+    pub entity: Entity,
+    /// Atom index within the structure's [`AtomCollection`]
+    pub atom: usize,
+    pub chain_id: String,
+    pub res_id: i32,
+    pub res_name: String,
+    pub atom_name: String,
+    /// World-space position of the atom
+    pub position: Vec3,
+}
+
+impl AtomPick {
+    /// Short description used by the hover label, e.g. `A/LYS 42/CA`
+    pub fn label(&self) -> String {
+        format!(
+            "{}/{} {}/{}",
+            self.chain_id, self.res_name, self.res_id, self.atom_name
+        )
+    }
+}
+
+/// The atom currently under the cursor, if any
+#[derive(Resource, Default, Debug)]
+pub struct HoveredAtom(pub Option<AtomPick>);
+
+/// Sent when a click selects an atom
+#[derive(Event, Clone, Debug)]
+pub struct AtomPicked {
+    pub pick: AtomPick,
+    /// True when the click extended the existing selection (shift-click)
+    pub extend: bool,
+}
+
+/// Selected atoms of a structure, stored on the same entity as the [`Structure`].
+#[derive(Component, Clone, Debug, Default)]
+pub struct StructureSelection {
+    atoms: Vec<usize>,
+}
+
+impl StructureSelection {
+    /// Sorted atom indices of the selection
+    pub fn atoms(&self) -> &[usize] {
+        &self.atoms
+    }
+    pub fn is_empty(&self) -> bool {
+        self.atoms.is_empty()
+    }
+    pub fn selection(&self) -> Selection {
+        Selection::new(self.atoms.clone())
+    }
+    pub fn clear(&mut self) {
+        self.atoms.clear();
+    }
+    /// Replace the selection with `atoms`
+    pub fn set(&mut self, atoms: Vec<usize>) {
+        self.atoms = atoms;
+        self.atoms.sort_unstable();
+        self.atoms.dedup();
+    }
+    /// Add `atoms` to the selection, or remove them if they are all already selected
+    pub fn toggle(&mut self, atoms: &[usize]) {
+        if atoms.iter().all(|a| self.atoms.binary_search(a).is_ok()) {
+            self.atoms.retain(|a| !atoms.contains(a));
+        } else {
+            let mut merged = std::mem::take(&mut self.atoms);
+            merged.extend_from_slice(atoms);
+            self.set(merged);
+        }
+    }
+}
+
+/// Marker for the hover label UI node
+#[derive(Component)]
+struct HoverLabel;
+
+/// Marker for highlight spheres spawned as children of a structure
+#[derive(Component)]
+struct SelectionHighlight;
+
+/// Cursor position at the last left mouse press
+#[derive(Resource, Default)]
+struct ClickState {
+    pressed_at: Option<Vec2>,
+}
+
+/// Find the first atom of `structure` hit by the ray `origin + t * direction`.
+///
+/// The ray is given in the structure's local space and `direction` need not be normalized.
+/// Only atoms in the structure's drawn selection are considered.
+/// Returns the atom index and the ray parameter `t` of the hit.
+pub fn ray_pick(structure: &Structure, origin: Vec3, direction: Vec3) -> Option<(usize, f32)> {
+    let dd = direction.length_squared();
+    if dd == 0.0 {
+        return None;
+    }
+    let ac = structure.get_pdb();
+    let mut best: Option<(usize, f32)> = None;
+    let atoms: Vec<usize> = match structure.get_selection() {
+        Some(selection) => selection.indices().to_vec(),
+        None => (0..ac.get_size()).collect(),
+    };
+    for atom in atoms {
+        let Some(radius) = structure.pick_radius(atom) else {
+            continue;
+        };
+        let center = Vec3::from_array(*ac.get_coord(atom));
+        let t_closest = (center - origin).dot(direction) / dd;
+        let miss2 = (origin + direction * t_closest).distance_squared(center);
+        if miss2 > radius * radius {
+            continue;
+        }
+        let t = t_closest - ((radius * radius - miss2) / dd).sqrt();
+        if t_closest < 0.0 || best.is_some_and(|(_, best_t)| best_t <= t) {
+            continue;
+        }
+        best = Some((atom, t));
+    }
+    best
+}
+
+/// The atoms selected by clicking `atom` in the given mode
+pub fn expand_selection(ac: &AtomCollection, atom: usize, mode: SelectionMode) -> Vec<usize> {
+    match mode {
+        SelectionMode::Atom => vec![atom],
+        SelectionMode::Residue => ac
+            .iter_residues_all()
+            .find(|res| (res.start_idx..res.end_idx).contains(&atom))
+            .map(|res| (res.start_idx..res.end_idx).collect())
+            .unwrap_or_else(|| vec![atom]),
+        SelectionMode::Chain => {
+            let chain = ac.get_chain_id(atom);
+            (0..ac.get_size())
+                .filter(|&i| ac.get_chain_id(i) == chain)
+                .collect()
+        }
+    }
+}
+
+// Systems --------------------------------------
+
+fn spawn_hover_label(mut commands: Commands) {
+    commands.spawn((
+        HoverLabel,
+        Text::new(""),
+        TextFont {
+            font_size: 14.0,
+            ..default()
+        },
+        Node {
+            position_type: PositionType::Absolute,
+            padding: UiRect::all(Val::Px(4.0)),
+            ..default()
+        },
+        BackgroundColor(Color::srgba(0.0, 0.0, 0.0, 0.7)),
+        Visibility::Hidden,
+    ));
+}
+
+fn update_hovered_atom(
+    windows: Query<&Window, With<PrimaryWindow>>,
+    cameras: Query<(&Camera, &GlobalTransform)>,
+    structures: Query<(Entity, &Structure, &GlobalTransform)>,
+    mut hovered: ResMut<HoveredAtom>,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    let ray = cursor.and_then(|cursor| {
+        cameras
+            .iter()
+            .filter(|(camera, _)| camera.is_active)
+            .find_map(|(camera, transform)| camera.viewport_to_world(transform, cursor).ok())
+    });
+    let Some(ray) = ray else {
+        if hovered.0.is_some() {
+            hovered.0 = None;
+        }
+        return;
+    };
+
+    let mut best: Option<(f32, AtomPick)> = None;
+    for (entity, structure, transform) in &structures {
+        let to_local = transform.affine().inverse();
+        let origin = to_local.transform_point3(ray.origin);
+        let direction = to_local.transform_vector3(*ray.direction);
+        let Some((atom, t)) = ray_pick(structure, origin, direction) else {
+            continue;
+        };
+        if best.as_ref().is_some_and(|(best_t, _)| *best_t <= t) {
+            continue;
+        }
+        let ac = structure.get_pdb();
+        let pick = AtomPick {
+            entity,
+            atom,
+            chain_id: ac.get_chain_id(atom).clone(),
+            res_id: *ac.get_res_id(atom),
+            res_name: ac.get_res_name(atom).clone(),
+            atom_name: ac.get_atom_name(atom).clone(),
+            position: transform.transform_point(Vec3::from_array(*ac.get_coord(atom))),
+        };
+        best = Some((t, pick));
+    }
+    let pick = best.map(|(_, pick)| pick);
+    if hovered.0 != pick {
+        hovered.0 = pick;
+    }
+}
+
+fn update_hover_label(
+    hovered: Res<HoveredAtom>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    mut labels: Query<(&mut Text, &mut Node, &mut Visibility), With<HoverLabel>>,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    for (mut text, mut node, mut visibility) in &mut labels {
+        match (&hovered.0, cursor) {
+            (Some(pick), Some(cursor)) => {
+                text.0 = pick.label();
+                node.left = Val::Px(cursor.x + 12.0);
+                node.top = Val::Px(cursor.y + 12.0);
+                *visibility = Visibility::Visible;
+            }
+            _ => *visibility = Visibility::Hidden,
+        }
+    }
+}
+
+/// A press and release without dragging selects the hovered atom; clicking empty space
+/// clears the selection. Drags are left to camera controls.
+#[allow(clippy::too_many_arguments)]
+fn handle_clicks(
+    mut commands: Commands,
+    mouse: Res<ButtonInput<MouseButton>>,
+    keys: Res<ButtonInput<KeyCode>>,
+    windows: Query<&Window, With<PrimaryWindow>>,
+    hovered: Res<HoveredAtom>,
+    settings: Res<PickingSettings>,
+    mut click: ResMut<ClickState>,
+    mut structures: Query<(Entity, &Structure, Option<&mut StructureSelection>)>,
+    mut picked: EventWriter<AtomPicked>,
+) {
+    let cursor = windows.get_single().ok().and_then(|w| w.cursor_position());
+    if mouse.just_pressed(MouseButton::Left) {
+        click.pressed_at = cursor;
+    }
+    if !mouse.just_released(MouseButton::Left) {
+        return;
+    }
+    let is_click = matches!(
+        (click.pressed_at.take(), cursor),
+        (Some(start), Some(end)) if start.distance(end) <= CLICK_TOLERANCE
+    );
+    if !is_click {
+        return;
+    }
+    let extend = keys.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight]);
+
+    for (entity, structure, selection) in &mut structures {
+        let hit = hovered.0.as_ref().filter(|pick| pick.entity == entity);
+        let atoms = hit.map(|pick| expand_selection(structure.get_pdb(), pick.atom, settings.mode));
+        match (selection, atoms) {
+            (Some(mut selection), Some(atoms)) => {
+                if extend {
+                    selection.toggle(&atoms);
+                } else {
+                    selection.set(atoms);
+                }
+            }
+            (None, Some(atoms)) => {
+                let mut selection = StructureSelection::default();
+                selection.set(atoms);
+                commands.entity(entity).insert(selection);
+            }
+            (Some(mut selection), None) if !extend && !selection.is_empty() => selection.clear(),
+            _ => {}
+        }
+    }
+    if let Some(pick) = &hovered.0 {
+        picked.send(AtomPicked {
+            pick: pick.clone(),
+            extend,
+        });
+    }
+}
+
+/// Rebuild the highlight spheres of every structure whose selection changed
+fn highlight_selection(
+    mut commands: Commands,
+    settings: Res<PickingSettings>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    selections: Query<(Entity, &Structure, &StructureSelection), Changed<StructureSelection>>,
+    highlights: Query<(Entity, &Parent), With<SelectionHighlight>>,
+) {
+    for (entity, structure, selection) in &selections {
+        for (highlight, parent) in &highlights {
+            if parent.get() == entity {
+                commands.entity(highlight).despawn_recursive();
+            }
+        }
+        if selection.is_empty() {
+            continue;
+        }
+
+        let ac = structure.get_pdb();
+        let mesh = selection
+            .atoms()
+            .iter()
+            .filter_map(|&atom| {
+                let radius = structure.pick_radius(atom)? * HIGHLIGHT_SCALE;
+                let center = Vec3::from_array(*ac.get_coord(atom));
+                Some(
+                    Sphere::new(radius)
+                        .mesh()
+                        .ico(2)
+                        .ok()?
+                        .translated_by(center),
+                )
+            })
+            .reduce(|mut acc, mesh| {
+                acc.merge(&mesh);
+                acc
+            });
+        let Some(mesh) = mesh else {
+            continue;
+        };
+
+        let color = settings.highlight_color;
+        let material = StandardMaterial {
+            base_color: color,
+            emissive: color.to_linear() * 0.5,
+            alpha_mode: AlphaMode::Blend,
+            unlit: true,
+            ..default()
+        };
+        let (mesh, material) = (meshes.add(mesh), materials.add(material));
+        commands.entity(entity).with_children(|parent| {
+            parent.spawn((SelectionHighlight, Mesh3d(mesh), MeshMaterial3d(material)));
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderOptions;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_ray_pick_and_expand() -> anyhow::Result<()> {
+        let (prot_file, _temp) = TestFile::protein_04().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        // aim straight down the z axis at the topmost CA atom so nothing is in front of it
+        let ca = (0..ac.get_size())
+            .filter(|&i| ac.get_atom_name(i) == "CA")
+            .max_by(|&a, &b| ac.get_coord(a)[2].total_cmp(&ac.get_coord(b)[2]))
+            .unwrap();
+        let target = Vec3::from_array(*ac.get_coord(ca));
+        let origin = target + Vec3::new(0.0, 0.0, 500.0);
+
+        let structure = Structure::builder()
+            .pdb(ac)
+            .rendertype(RenderOptions::Cartoon)
+            .build();
+        let ac = structure.get_pdb();
+        let (atom, t) = ray_pick(&structure, origin, Vec3::NEG_Z).unwrap();
+        assert_eq!(atom, ca);
+        assert!(t > 490.0 && t < 500.0);
+        assert!(ray_pick(&structure, origin, Vec3::Z).is_none());
+
+        let residue = expand_selection(ac, ca, SelectionMode::Residue);
+        assert!(residue.contains(&ca));
+        assert!(residue
+            .iter()
+            .all(|&i| ac.get_res_id(i) == ac.get_res_id(ca)));
+        let chain = expand_selection(ac, ca, SelectionMode::Chain);
+        assert!(chain.len() > residue.len());
+        assert!(chain
+            .iter()
+            .all(|&i| ac.get_chain_id(i) == ac.get_chain_id(ca)));
+
+        let mut selection = StructureSelection::default();
+        selection.set(residue.clone());
+        selection.toggle(&[ca]);
+        assert_eq!(selection.atoms().len(), residue.len() - 1);
+        selection.toggle(&residue);
+        assert_eq!(selection.atoms().len(), residue.len());
+        Ok(())
+    }
+}
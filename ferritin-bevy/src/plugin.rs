@@ -1,9 +1,13 @@
 //! Module for loading PDBs into Bevy via the Plugin system
 //!
 //! Over time this would be a good candidate for factoring out
+use super::impostor::{
+    impostor_bundle, ImpostorInstances, ImpostorPlugin, ImpostorProxy, RenderBackend,
+};
 use super::{ColorScheme, RenderOptions, Structure};
 use bevy::prelude::*;
-use ferritin_core::AtomCollection;
+use bevy::render::view::NoFrustumCulling;
+use ferritin_core::{AtomCollection, Selection};
 use std::path::Path;
 use std::path::PathBuf;
 
@@ -26,14 +30,23 @@ impl Default for StructureSettings {
 // adding this for integration with Bevy
 pub struct StructurePlugin {
     initial_files: Vec<(PathBuf, StructureSettings)>,
+    backend: RenderBackend,
 }
 
 impl StructurePlugin {
     pub fn new() -> Self {
         Self {
             initial_files: Vec::new(),
+            backend: RenderBackend::default(),
         }
     }
+    /// Draw `Solid` and `BallAndStick` structures with instanced impostors (the default)
+    /// or with CPU-built meshes. Without a renderer, or when the structure has a custom
+    /// material, meshes are used either way.
+    pub fn with_backend(mut self, backend: RenderBackend) -> Self {
+        self.backend = backend;
+        self
+    }
     pub fn with_file<P: Into<PathBuf>>(
         mut self,
         path: P,
@@ -48,55 +61,267 @@ impl StructurePlugin {
 // adding this for integration with Bevy
 impl Plugin for StructurePlugin {
     fn build(&self, app: &mut App) {
+        if self.backend == RenderBackend::Instanced {
+            app.add_plugins(ImpostorPlugin);
+        }
         app.insert_resource(StructureFiles(self.initial_files.clone()))
+            .add_event::<LoadProteinEvent>()
+            .add_event::<UnloadProteinEvent>()
+            .add_event::<UpdateRepresentationEvent>()
+            .add_event::<StructureLoaded>()
             .add_systems(Startup, load_initial_proteins)
-            .add_event::<LoadProteinEvent>();
+            .add_systems(
+                Update,
+                (
+                    handle_load_events,
+                    handle_unload_events,
+                    handle_update_events,
+                    remesh_structures,
+                )
+                    .chain(),
+            );
+    }
+
+    fn finish(&self, app: &mut App) {
+        // without a renderer there is nothing to instance; fall back to meshes
+        let backend = match app.world().contains_resource::<ImpostorProxy>() {
+            true => self.backend,
+            false => RenderBackend::Mesh,
+        };
+        app.insert_resource(backend);
     }
 }
 
 #[derive(Resource)]
 struct StructureFiles(Vec<(PathBuf, StructureSettings)>);
 
-#[derive(Event)]
-pub struct LoadProteinEvent(pub PathBuf);
+/// Load a structure file and spawn it with the given settings.
+#[derive(Event, Clone)]
+pub struct LoadProteinEvent {
+    pub path: PathBuf,
+    pub settings: StructureSettings,
+}
+
+impl LoadProteinEvent {
+    pub fn new<P: Into<PathBuf>>(path: P) -> Self {
+        Self {
+            path: path.into(),
+            settings: StructureSettings::default(),
+        }
+    }
+    pub fn with_settings(mut self, settings: StructureSettings) -> Self {
+        self.settings = settings;
+        self
+    }
+}
+
+/// Despawn a structure entity along with its children.
+#[derive(Event, Clone, Copy)]
+pub struct UnloadProteinEvent(pub Entity);
+
+/// Change how a loaded structure is drawn. The mesh is rebuilt in place.
+#[derive(Event, Clone)]
+pub enum UpdateRepresentationEvent {
+    RenderType(Entity, RenderOptions),
+    ColorScheme(Entity, ColorScheme),
+    /// Draw only the selected atoms; `None` draws the whole structure
+    Selection(Entity, Option<Selection>),
+}
+
+/// Sent once a structure from a [`LoadProteinEvent`] has been spawned.
+#[derive(Event, Clone)]
+pub struct StructureLoaded {
+    pub entity: Entity,
+    pub path: PathBuf,
+}
+
+/// The file a structure entity was loaded from.
+#[derive(Component, Clone, Debug)]
+pub struct StructureSource(pub PathBuf);
+
+/// Read a structure file into a [`Structure`].
+///
+/// Todo: revisit this portion about the right default visuals later on.
+/// By default only the amino acids are kept.
+fn load_structure(file_path: &Path, settings: &StructureSettings) -> Option<Structure> {
+    // check valid filepath
+    if !file_path.exists() {
+        eprintln!("Error: File not found: {:?}", file_path);
+        return None;
+    }
+    let (pdb, _errors) = match pdbtbx::open(file_path.to_str().unwrap_or_default()) {
+        Ok(result) => result,
+        Err(errors) => {
+            eprintln!("Error: Could not read {:?}: {:?}", file_path, errors);
+            return None;
+        }
+    };
+    let mut ac: AtomCollection = AtomCollection::from(&pdb)
+        .iter_residues_aminoacid()
+        .collect();
+
+    // add the bonds back in as they are removed during the collection process above.
+    ac.connect_via_residue_names();
+
+    Some(
+        Structure::builder()
+            .pdb(ac)
+            .rendertype(settings.render_type.clone())
+            .color_scheme(settings.color_scheme.clone())
+            .material(settings.material.clone())
+            .build(),
+    )
+}
 
 fn load_initial_proteins(
     structure_files: Res<StructureFiles>,
+    mut events: EventWriter<LoadProteinEvent>,
+) {
+    for (file_path, settings) in &structure_files.0 {
+        events.send(LoadProteinEvent::new(file_path.clone()).with_settings(settings.clone()));
+    }
+}
+
+fn handle_load_events(
+    mut events: EventReader<LoadProteinEvent>,
+    mut loaded: EventWriter<StructureLoaded>,
+    backend: Res<RenderBackend>,
+    proxy: Option<Res<ImpostorProxy>>,
     mut commands: Commands,
     mut meshes: ResMut<Assets<Mesh>>,
     mut materials: ResMut<Assets<StandardMaterial>>,
 ) {
-    for (file_path, settings) in &structure_files.0 {
-        // check valid filepath
-        if !Path::new(file_path).exists() {
-            eprintln!("Error: File not found: {:?}", file_path);
+    for event in events.read() {
+        let Some(structure) = load_structure(&event.path, &event.settings) else {
+            continue;
+        };
+        let mut entity = commands.spawn(StructureSource(event.path.clone()));
+        insert_visuals(
+            &mut entity,
+            &structure,
+            impostor_proxy(*backend, proxy.as_deref()),
+            (None, None),
+            &mut meshes,
+            &mut materials,
+        );
+        let entity = entity.insert(structure).id();
+        loaded.send(StructureLoaded {
+            entity,
+            path: event.path.clone(),
+        });
+    }
+}
+
+fn handle_unload_events(
+    mut events: EventReader<UnloadProteinEvent>,
+    mut commands: Commands,
+    structures: Query<(), With<Structure>>,
+) {
+    for UnloadProteinEvent(entity) in events.read() {
+        if structures.contains(*entity) {
+            commands.entity(*entity).despawn_recursive();
+        }
+    }
+}
+
+fn handle_update_events(
+    mut events: EventReader<UpdateRepresentationEvent>,
+    mut structures: Query<&mut Structure>,
+) {
+    for event in events.read() {
+        let entity = match event {
+            UpdateRepresentationEvent::RenderType(entity, _)
+            | UpdateRepresentationEvent::ColorScheme(entity, _)
+            | UpdateRepresentationEvent::Selection(entity, _) => *entity,
+        };
+        let Ok(mut structure) = structures.get_mut(entity) else {
+            continue;
+        };
+        match event {
+            UpdateRepresentationEvent::RenderType(_, render_type) => {
+                structure.set_rendertype(render_type.clone())
+            }
+            UpdateRepresentationEvent::ColorScheme(_, color_scheme) => {
+                structure.set_color_scheme(color_scheme.clone())
+            }
+            UpdateRepresentationEvent::Selection(_, selection) => {
+                structure.set_selection(selection.clone())
+            }
+        }
+    }
+}
+
+/// Rebuild the visuals of any structure modified after it was spawned.
+#[allow(clippy::type_complexity)]
+fn remesh_structures(
+    mut commands: Commands,
+    backend: Res<RenderBackend>,
+    proxy: Option<Res<ImpostorProxy>>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    structures: Query<(
+        Entity,
+        Ref<Structure>,
+        Option<&Mesh3d>,
+        Option<&MeshMaterial3d<StandardMaterial>>,
+    )>,
+) {
+    let proxy = impostor_proxy(*backend, proxy.as_deref());
+    for (entity, structure, mesh, material) in &structures {
+        if !structure.is_changed() || structure.is_added() {
             continue;
         }
+        insert_visuals(
+            &mut commands.entity(entity),
+            &structure,
+            proxy,
+            (mesh, material),
+            &mut meshes,
+            &mut materials,
+        );
+    }
+}
 
-        if let Ok((pdb, _errors)) = pdbtbx::open(file_path.to_str().unwrap_or_default()) {
-            // Todo: revisit this portion about the right default visuals later on
-            // by default lets only keep the amino acids.
-            let mut ac: AtomCollection = AtomCollection::from(&pdb)
-                .iter_residues_aminoacid()
-                .collect();
-
-            // add the bonds back in as they are removed during the collection process above.
-            ac.connect_via_residue_names();
-
-            let structure = Structure::builder()
-                .pdb(ac)
-                .rendertype(settings.render_type.clone())
-                .color_scheme(settings.color_scheme.clone())
-                .material(settings.material.clone())
-                .build();
-
-            let mesh = structure.to_mesh();
-            let material = structure.get_material();
-
-            commands.spawn((
-                Mesh3d(meshes.add(mesh)),
-                MeshMaterial3d(materials.add(material)),
-            ));
+/// The shared proxy mesh, if structures are drawn with impostors
+fn impostor_proxy(backend: RenderBackend, proxy: Option<&ImpostorProxy>) -> Option<&ImpostorProxy> {
+    proxy.filter(|_| backend == RenderBackend::Instanced)
+}
+
+/// Insert the components that draw `structure`: impostor instances on the shared proxy
+/// mesh when the backend and render type allow it, otherwise a mesh with the structure's
+/// material. The entity's current mesh and material assets are updated in place rather
+/// than replaced, so remeshing every frame does not pile up assets.
+fn insert_visuals(
+    entity: &mut EntityCommands,
+    structure: &Structure,
+    proxy: Option<&ImpostorProxy>,
+    (mesh, material): (Option<&Mesh3d>, Option<&MeshMaterial3d<StandardMaterial>>),
+    meshes: &mut Assets<Mesh>,
+    materials: &mut Assets<StandardMaterial>,
+) {
+    let instances = proxy.and_then(|proxy| {
+        ImpostorInstances::from_structure(structure).map(|instances| (instances, proxy))
+    });
+    if let Some((instances, proxy)) = instances {
+        entity
+            .remove::<MeshMaterial3d<StandardMaterial>>()
+            .insert(impostor_bundle(instances, proxy));
+        return;
+    }
+    entity.remove::<(ImpostorInstances, NoFrustumCulling)>();
+
+    // the proxy box is shared between structures and never rewritten
+    let own_mesh = mesh.filter(|mesh| proxy.is_none_or(|proxy| mesh.0 != proxy.0));
+    match own_mesh.and_then(|mesh| meshes.get_mut(&mesh.0)) {
+        Some(existing) => *existing = structure.to_mesh(),
+        None => {
+            entity.insert(Mesh3d(meshes.add(structure.to_mesh())));
+        }
+    }
+    match material.and_then(|material| materials.get_mut(&material.0)) {
+        Some(existing) => *existing = structure.get_material(),
+        None => {
+            entity.insert(MeshMaterial3d(materials.add(structure.get_material())));
         }
     }
 }
@@ -0,0 +1,320 @@
+//! Snapshot
+//!
+//! Headless rendering of a structure to a PNG file. No window is opened: the scene is drawn
+//! into an offscreen image that is read back and written to disk once the renderer has
+//! warmed up, after which the app exits. This makes figure generation usable from batch
+//! jobs and tests on machines without a display.
+//!
+//! Rendering still goes through wgpu. On a box without a GPU, a software Vulkan driver such
+//! as Mesa's lavapipe can be used by enabling [`Snapshot`]'s `software` option, which creates
+//! the renderer on wgpu's fallback adapter for this app only.
+//!
+//! ```no_run
+//! use ferritin_bevy::{RenderOptions, Snapshot, StructureSettings};
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> anyhow::Result<()> {
+//! Snapshot::builder()
+//!     .pdb(ac)
+//!     .settings(StructureSettings {
+//!         render_type: RenderOptions::Cartoon,
+//!         ..Default::default()
+//!     })
+//!     .width(800)
+//!     .height(600)
+//!     .build()
+//!     .save("cartoon.png")?;
+//! # Ok(())
+//! # }
+//! ```
+use super::camera::framing_camera;
+use super::{Structure, StructureSettings};
+use anyhow::{anyhow, Result};
+use bevy::app::{AppExit, ScheduleRunnerPlugin};
+use bevy::prelude::*;
+use bevy::render::camera::RenderTarget;
+use bevy::render::render_asset::RenderAssetUsages;
+use bevy::render::render_resource::{Extent3d, TextureDimension, TextureFormat, TextureUsages};
+use bevy::render::renderer::{initialize_renderer, RenderInstance, WgpuWrapper};
+use bevy::render::settings::{RenderCreation, WgpuSettings};
+use bevy::render::view::screenshot::{Screenshot, ScreenshotCaptured};
+use bevy::render::RenderPlugin;
+use bevy::tasks::block_on;
+use bevy::window::ExitCondition;
+use bevy::winit::WinitPlugin;
+use bon::Builder;
+use ferritin_core::AtomCollection;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+/// Vertical field of view used for snapshot cameras, in radians
+const FOV: f32 = std::f32::consts::FRAC_PI_4;
+/// Extra space around the structure when framing the camera
+const FRAMING_MARGIN: f32 = 1.1;
+
+/// A single offscreen render of a structure
+#[derive(Builder)]
+pub struct Snapshot {
+    pdb: AtomCollection,
+    #[builder(default)]
+    settings: StructureSettings,
+    /// Camera placement; by default the camera looks down -Z at the whole structure.
+    camera: Option<Transform>,
+    #[builder(default = 1024)]
+    width: u32,
+    #[builder(default = 768)]
+    height: u32,
+    #[builder(default = Color::WHITE)]
+    background: Color,
+    /// Frames rendered before capturing, giving pipelines time to compile
+    #[builder(default = 3)]
+    warmup_frames: u32,
+    /// Request wgpu's fallback (software) adapter
+    #[builder(default = false)]
+    software: bool,
+}
+
+impl Snapshot {
+    /// Render the structure and write it to `path` as a PNG.
+    pub fn save<P: AsRef<Path>>(self, path: P) -> Result<()> {
+        let render_plugin = render_plugin(self.software)?;
+        let camera = self
+            .camera
+            .unwrap_or_else(|| framing_camera(&self.pdb, FOV, FRAMING_MARGIN));
+        let structure = Structure::builder()
+            .pdb(self.pdb)
+            .rendertype(self.settings.render_type)
+            .color_scheme(self.settings.color_scheme)
+            .material(self.settings.material)
+            .build();
+
+        let outcome: Arc<Mutex<Option<Result<()>>>> = Arc::new(Mutex::new(None));
+        App::new()
+            .add_plugins(
+                DefaultPlugins
+                    .set(WindowPlugin {
+                        primary_window: None,
+                        exit_condition: ExitCondition::DontExit,
+                        close_when_requested: false,
+                    })
+                    .set(render_plugin)
+                    .disable::<WinitPlugin>(),
+            )
+            .add_plugins(ScheduleRunnerPlugin::run_loop(Duration::from_secs_f64(
+                1.0 / 60.0,
+            )))
+            .insert_resource(SnapshotScene {
+                mesh: Some(structure.to_mesh()),
+                material: structure.get_material(),
+                camera,
+                width: self.width,
+                height: self.height,
+                background: self.background,
+            })
+            .insert_resource(SnapshotCapture {
+                path: path.as_ref().to_path_buf(),
+                warmup_frames: self.warmup_frames,
+                target: None,
+                outcome: outcome.clone(),
+            })
+            .add_systems(Startup, setup_scene)
+            .add_systems(Update, capture_after_warmup)
+            .run();
+
+        let result = outcome.lock().unwrap().take();
+        result.unwrap_or_else(|| Err(anyhow!("Renderer exited before the snapshot was taken")))
+    }
+}
+
+/// Renderer setup for a snapshot app. With `software`, the device is created up front on
+/// wgpu's fallback adapter; otherwise bevy picks the adapter as usual.
+fn render_plugin(software: bool) -> Result<RenderPlugin> {
+    if !software {
+        return Ok(RenderPlugin::default());
+    }
+    let settings = WgpuSettings::default();
+    let instance = wgpu::Instance::new(wgpu::InstanceDescriptor {
+        backends: settings.backends.unwrap_or(wgpu::Backends::all()),
+        flags: settings.instance_flags,
+        dx12_shader_compiler: settings.dx12_shader_compiler.clone(),
+        gles_minor_version: settings.gles3_minor_version,
+    });
+    let options = wgpu::RequestAdapterOptions {
+        power_preference: settings.power_preference,
+        force_fallback_adapter: true,
+        compatible_surface: None,
+    };
+    if block_on(instance.request_adapter(&options)).is_none() {
+        return Err(anyhow!(
+            "No fallback adapter found; install a software driver such as lavapipe"
+        ));
+    }
+    let (device, queue, adapter_info, adapter) =
+        block_on(initialize_renderer(&instance, &settings, &options));
+    Ok(RenderPlugin {
+        render_creation: RenderCreation::Manual(
+            device,
+            queue,
+            adapter_info,
+            adapter,
+            RenderInstance(Arc::new(WgpuWrapper::new(instance))),
+        ),
+        ..default()
+    })
+}
+
+#[derive(Resource)]
+struct SnapshotScene {
+    mesh: Option<Mesh>,
+    material: StandardMaterial,
+    camera: Transform,
+    width: u32,
+    height: u32,
+    background: Color,
+}
+
+#[derive(Resource)]
+struct SnapshotCapture {
+    path: PathBuf,
+    warmup_frames: u32,
+    target: Option<Handle<Image>>,
+    outcome: Arc<Mutex<Option<Result<()>>>>,
+}
+
+fn setup_scene(
+    mut commands: Commands,
+    mut scene: ResMut<SnapshotScene>,
+    mut capture: ResMut<SnapshotCapture>,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<StandardMaterial>>,
+    mut images: ResMut<Assets<Image>>,
+) {
+    let size = Extent3d {
+        width: scene.width,
+        height: scene.height,
+        depth_or_array_layers: 1,
+    };
+    let mut image = Image::new_fill(
+        size,
+        TextureDimension::D2,
+        &[0, 0, 0, 0],
+        TextureFormat::Rgba8UnormSrgb,
+        RenderAssetUsages::default(),
+    );
+    image.texture_descriptor.usage = TextureUsages::TEXTURE_BINDING
+        | TextureUsages::COPY_SRC
+        | TextureUsages::COPY_DST
+        | TextureUsages::RENDER_ATTACHMENT;
+    let target = images.add(image);
+    capture.target = Some(target.clone());
+
+    if let Some(mesh) = scene.mesh.take() {
+        commands.spawn((
+            Mesh3d(meshes.add(mesh)),
+            MeshMaterial3d(materials.add(scene.material.clone())),
+        ));
+    }
+    commands.spawn((
+        Camera3d::default(),
+        Camera {
+            target: RenderTarget::Image(target),
+            clear_color: ClearColorConfig::Custom(scene.background),
+            ..default()
+        },
+        Projection::Perspective(PerspectiveProjection {
+            fov: FOV,
+            ..default()
+        }),
+        scene.camera,
+    ));
+    // key light over the camera's shoulder
+    commands.spawn((
+        DirectionalLight {
+            illuminance: 8000.0,
+            ..default()
+        },
+        Transform::from_translation(scene.camera.translation + scene.camera.up() * 20.0)
+            .looking_at(
+                scene.camera.translation + scene.camera.forward() * 50.0,
+                Vec3::Y,
+            ),
+    ));
+    commands.insert_resource(AmbientLight {
+        color: Color::WHITE,
+        brightness: 400.0,
+    });
+}
+
+/// Count frames in a system-local counter and request the capture once warmed up
+fn capture_after_warmup(
+    mut commands: Commands,
+    capture: Res<SnapshotCapture>,
+    mut frames: Local<u32>,
+) {
+    *frames += 1;
+    if *frames != capture.warmup_frames.max(1) {
+        return;
+    }
+    let Some(target) = capture.target.clone() else {
+        return;
+    };
+    let path = capture.path.clone();
+    let outcome = capture.outcome.clone();
+    commands.spawn(Screenshot::image(target)).observe(
+        move |trigger: Trigger<ScreenshotCaptured>, mut exit: EventWriter<AppExit>| {
+            let result = trigger
+                .event()
+                .0
+                .clone()
+                .try_into_dynamic()
+                .map_err(|e| anyhow!("Could not convert the rendered image: {:?}", e))
+                .and_then(|image| {
+                    image
+                        .to_rgba8()
+                        .save(&path)
+                        .map_err(|e| anyhow!("Could not write {:?}: {}", path, e))
+                });
+            *outcome.lock().unwrap() = Some(result);
+            exit.send(AppExit::Success);
+        },
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::RenderOptions;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_snapshot_save() {
+        // software rendering needs a driver such as lavapipe, which not every box has
+        if let Err(e) = render_plugin(true) {
+            eprintln!("Skipping test_snapshot_save: {}", e);
+            return;
+        }
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let path =
+            std::env::temp_dir().join(format!("ferritin-snapshot-{}.png", std::process::id()));
+        Snapshot::builder()
+            .pdb(AtomCollection::from(&pdb))
+            .settings(StructureSettings {
+                render_type: RenderOptions::Cartoon,
+                ..Default::default()
+            })
+            .width(64)
+            .height(48)
+            .software(true)
+            .build()
+            .save(&path)
+            .unwrap();
+
+        // PNG signature, then the IHDR chunk holding width and height
+        let png = std::fs::read(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(&png[..8], b"\x89PNG\r\n\x1a\n");
+        assert_eq!(u32::from_be_bytes(png[16..20].try_into().unwrap()), 64);
+        assert_eq!(u32::from_be_bytes(png[20..24].try_into().unwrap()), 48);
+    }
+}
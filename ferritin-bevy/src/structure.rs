@@ -4,17 +4,22 @@
 //!
 //!
 
+use super::cartoon::{cartoon_mesh, MeshBuffers};
+use super::impostor::{BALL_RADIUS, STICK_RADIUS};
+use super::surface::{SurfaceMesh, SurfaceSettings};
 use super::ColorScheme;
 use bevy::log::tracing_subscriber::reload::Error;
-use bevy::math::Vec4;
+use bevy::log::warn;
 use bevy::prelude::{
-    default, Color, Component, Cylinder, Mesh, MeshBuilder, Meshable, Quat, Sphere,
-    StandardMaterial, Transform, Vec3,
+    Color, Component, Cylinder, Mesh, MeshBuilder, Meshable, Quat, Sphere, StandardMaterial,
+    Transform, Vec3,
 };
 use bevy::render::mesh::{Indices, PrimitiveTopology};
 use bevy::render::render_asset::RenderAssetUsages;
 use bon::Builder;
-use ferritin_core::AtomCollection;
+use ferritin_core::{AtomCollection, Selection};
+use std::collections::HashSet;
+use std::sync::Arc;
 
 /// Enum representing various rendering options.
 ///
@@ -29,6 +34,7 @@ pub enum RenderOptions {
     BallAndStick,
     Solid,
     Putty,
+    Surface(SurfaceSettings),
 }
 
 /// Define Everything Needed to render
@@ -41,121 +47,227 @@ pub struct Structure {
     color_scheme: ColorScheme,
     #[builder(default = StandardMaterial::default())]
     material: StandardMaterial,
+    /// Atoms to draw; `None` draws the whole structure
+    selection: Option<Selection>,
 }
 
 impl Structure {
     pub fn to_mesh(&self) -> Mesh {
+        if let Some(selection) = &self.selection {
+            return self.selection_mesh(selection);
+        }
         match &self.rendertype {
             RenderOptions::Wireframe => self.render_wireframe(),
             RenderOptions::Cartoon => self.render_cartoon(),
             RenderOptions::BallAndStick => self.render_ballandstick(),
             RenderOptions::Solid => self.render_spheres(),
             RenderOptions::Putty => self.render_putty().unwrap(),
+            RenderOptions::Surface(settings) => self.render_surface(settings),
         }
     }
+    /// One mesh per chain, in order of first appearance, each named by its chain ID.
+    ///
+    /// The current selection still applies and chains without anything to draw are left out.
+    /// Surfaces are computed for each chain on its own.
+    pub fn to_chain_meshes(&self) -> Vec<(String, Mesh)> {
+        let mut chains: Vec<String> = Vec::new();
+        for i in 0..self.pdb.get_size() {
+            let chain = self.pdb.get_chain_id(i);
+            if !chains.contains(chain) {
+                chains.push(chain.clone());
+            }
+        }
+        let shown: Option<HashSet<usize>> = self
+            .selection
+            .as_ref()
+            .map(|selection| selection.indices().iter().copied().collect());
+        chains
+            .into_iter()
+            .filter_map(|chain| {
+                let indices: Vec<usize> = self
+                    .pdb
+                    .select_by_chain(&chain)
+                    .indices()
+                    .iter()
+                    .copied()
+                    .filter(|i| shown.as_ref().is_none_or(|shown| shown.contains(i)))
+                    .collect();
+                if indices.is_empty() {
+                    return None;
+                }
+                let mesh = self.selection_mesh(&Selection::new(indices));
+                (mesh.count_vertices() > 0).then_some((chain, mesh))
+            })
+            .collect()
+    }
+    /// Mesh of a subset of the atoms, colored as they would be in the full structure.
+    fn selection_mesh(&self, selection: &Selection) -> Mesh {
+        // color against the full structure so per-atom schemes keep their indexing
+        let colors = self.color_scheme.atom_colors(&self.pdb);
+        let colors: Vec<Color> = selection.indices().iter().map(|&i| colors[i]).collect();
+        let shown = Structure {
+            pdb: self.pdb.subset(selection),
+            rendertype: self.rendertype.clone(),
+            color_scheme: ColorScheme::Custom(Arc::new(move |_: &AtomCollection, i: usize| {
+                colors[i]
+            })),
+            material: self.material.clone(),
+            selection: None,
+        };
+        shown.to_mesh()
+    }
     pub fn get_material(&self) -> StandardMaterial {
         self.material.clone()
     }
+    pub fn get_pdb(&self) -> &AtomCollection {
+        &self.pdb
+    }
+    pub fn get_rendertype(&self) -> &RenderOptions {
+        &self.rendertype
+    }
+    pub fn get_color_scheme(&self) -> &ColorScheme {
+        &self.color_scheme
+    }
+    pub fn get_selection(&self) -> Option<&Selection> {
+        self.selection.as_ref()
+    }
+    pub fn set_rendertype(&mut self, rendertype: RenderOptions) {
+        self.rendertype = rendertype;
+    }
+    pub fn set_color_scheme(&mut self, color_scheme: ColorScheme) {
+        self.color_scheme = color_scheme;
+    }
+    pub fn set_selection(&mut self, selection: Option<Selection>) {
+        self.selection = selection;
+    }
+    /// Move the atoms; topology, colors and selection are kept.
+    pub fn set_coords(&mut self, coords: Vec<[f32; 3]>) {
+        self.pdb.set_coords(coords);
+    }
+    /// Radius used to hit-test an atom with a ray, matching how it is drawn.
+    ///
+    /// Cartoon and putty only expose their CA atoms; `None` means the atom is not pickable.
+    pub fn pick_radius(&self, atom: usize) -> Option<f32> {
+        let vdw = || {
+            self.pdb
+                .get_element(atom)
+                .atomic_radius()
+                .van_der_waals
+                .map(|r| r as f32)
+        };
+        match &self.rendertype {
+            RenderOptions::Solid | RenderOptions::Surface(_) => vdw(),
+            RenderOptions::BallAndStick => Some(0.5),
+            RenderOptions::Wireframe => Some(0.4),
+            RenderOptions::Cartoon | RenderOptions::Putty => {
+                (self.pdb.get_atom_name(atom) == "CA").then_some(1.5)
+            }
+        }
+    }
+    /// Bonds drawn as lines, each half colored by its atom.
     fn render_wireframe(&self) -> Mesh {
-        todo!()
+        let coords = self.pdb.get_coords();
+        let atom_colors = self.color_scheme.atom_colors(&self.pdb);
+        let mut positions: Vec<[f32; 3]> = Vec::new();
+        let mut colors: Vec<[f32; 4]> = Vec::new();
+        if let Some(bonds) = self.pdb.get_bonds() {
+            for bond in bonds {
+                let (atom1, atom2) = bond.get_atom_indices();
+                let (atom1, atom2) = (atom1 as usize, atom2 as usize);
+                let pos1 = Vec3::from_array(coords[atom1]);
+                let pos2 = Vec3::from_array(coords[atom2]);
+                let midpoint = (pos1 + pos2) / 2.0;
+                for (start, end, atom) in [(pos1, midpoint, atom1), (midpoint, pos2, atom2)] {
+                    let color = atom_colors[atom].to_srgba();
+                    let color = [color.red, color.green, color.blue, color.alpha];
+                    positions.extend([start.to_array(), end.to_array()]);
+                    colors.extend([color, color]);
+                }
+            }
+        } else {
+            warn!("No bonds found")
+        }
+        let mut mesh = Mesh::new(PrimitiveTopology::LineList, RenderAssetUsages::all());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, positions);
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh
     }
+    /// Molecular surface colored by the closest atom.
+    fn render_surface(&self, settings: &SurfaceSettings) -> Mesh {
+        SurfaceMesh::new(&self.pdb, settings).to_mesh(&self.pdb, &self.color_scheme)
+    }
+    /// Secondary-structure cartoon of the amino acid chains.
     fn render_cartoon(&self) -> Mesh {
-        todo!()
+        cartoon_mesh(&self.pdb, &self.color_scheme.atom_colors(&self.pdb))
     }
+    /// Spheres of [`BALL_RADIUS`] joined by half-bond sticks colored by their atoms.
     fn render_ballandstick(&self) -> Mesh {
-        let radius = 0.5;
-        let mut combined_mesh = self
-            .pdb
-            .iter_coords_and_elements()
-            .map(|(coord, element_str)| {
-                let center = Vec3::new(coord[0], coord[1], coord[2]);
-                let mut sphere_mesh = Sphere::new(radius).mesh().build();
-                let vertex_count = sphere_mesh.count_vertices();
-                let color = self.color_scheme.get_color(element_str).to_srgba();
-                let color_array =
-                    vec![Vec4::new(color.red, color.green, color.blue, color.alpha); vertex_count];
-                sphere_mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, color_array);
-                sphere_mesh = sphere_mesh.translated_by(center);
-                sphere_mesh.compute_smooth_normals();
-                sphere_mesh
-            })
-            .reduce(|mut acc, mesh| {
-                acc.merge(&mesh);
-                acc
-            })
-            .unwrap();
+        let colors = self.color_scheme.atom_colors(&self.pdb);
+        let sphere = MeshTemplate::new(
+            Sphere::new(BALL_RADIUS)
+                .mesh()
+                .ico(sphere_subdivisions(self.pdb.get_size()))
+                .unwrap(),
+        );
+        let mut buffers = MeshBuffers::default();
+        for (coord, color) in self.pdb.get_coords().iter().zip(&colors) {
+            sphere.append_to(
+                &mut buffers,
+                &Transform::from_translation(Vec3::from_array(*coord)),
+                *color,
+            );
+        }
 
-        // Add bond cylinders using iterators
+        // unit-height stick, stretched along its axis for each half-bond
+        let stick = MeshTemplate::new(
+            Cylinder {
+                radius: STICK_RADIUS,
+                half_height: 0.5,
+            }
+            .mesh()
+            .build(),
+        );
         if let Some(bonds) = self.pdb.get_bonds() {
             let coords = self.pdb.get_coords();
-            bonds
-                .iter()
-                .filter_map(|bond| {
-                    let (atom1, atom2) = bond.get_atom_indices();
-                    let pos1 = Vec3::from_array(*coords.get(atom1 as usize)?);
-                    let pos2 = Vec3::from_array(*coords.get(atom2 as usize)?);
-
-                    // Calculate cylinder properties
-                    let center = (pos1 + pos2) / 2.0;
-                    let direction = pos2 - pos1;
-                    let height = direction.length();
-                    let rotation = Quat::from_rotation_arc(Vec3::Y, direction.normalize());
-
-                    // Create and transform cylinder mesh
-                    let mut cylinder_mesh = Cylinder {
-                        radius: 0.5,
-                        half_height: height / 2.0, // Note: we divide height by 2 since it expects half_height
-                    }
-                    .mesh()
-                    .build();
-
-                    // Apply transformation
-                    cylinder_mesh = cylinder_mesh.transformed_by(Transform {
-                        translation: center,
-                        rotation,
-                        ..default()
-                    });
-
-                    // Add colors
-                    let cylinder_vertex_count = cylinder_mesh.count_vertices();
-                    let cylinder_colors =
-                        vec![Vec4::new(0.5, 0.5, 0.5, 0.5); cylinder_vertex_count];
-                    cylinder_mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, cylinder_colors);
-                    Some(cylinder_mesh)
-                })
-                .for_each(|cylinder_mesh| {
-                    combined_mesh.merge(&cylinder_mesh);
-                });
+            for bond in bonds {
+                let (atom1, atom2) = bond.get_atom_indices();
+                let (atom1, atom2) = (atom1 as usize, atom2 as usize);
+                let pos1 = Vec3::from_array(coords[atom1]);
+                let pos2 = Vec3::from_array(coords[atom2]);
+                let midpoint = (pos1 + pos2) / 2.0;
+                for (start, end, atom) in [(pos1, midpoint, atom1), (midpoint, pos2, atom2)] {
+                    let direction = end - start;
+                    let transform = Transform {
+                        translation: (start + end) / 2.0,
+                        rotation: Quat::from_rotation_arc(Vec3::Y, direction.normalize()),
+                        scale: Vec3::new(1.0, direction.length(), 1.0),
+                    };
+                    stick.append_to(&mut buffers, &transform, colors[atom]);
+                }
+            }
         } else {
-            println!("No-Bonds found!!")
+            warn!("No bonds found")
         }
-        combined_mesh
+        buffers.into_mesh(PrimitiveTopology::TriangleList)
     }
     /// Internal fn for rendering spheres.
     fn render_spheres(&self) -> Mesh {
-        self.pdb
-            .iter_coords_and_elements()
-            .map(|(coord, element)| {
-                let center = Vec3::new(coord[0], coord[1], coord[2]);
-                let radius = element
-                    .atomic_radius()
-                    .van_der_waals
-                    .expect("Van der waals not defined") as f32;
-                let mut sphere_mesh = Sphere::new(radius).mesh().build();
-                let vertex_count = sphere_mesh.count_vertices();
-                let color = self.color_scheme.get_color(element).to_srgba();
-                let color_array =
-                    vec![Vec4::new(color.red, color.green, color.blue, color.alpha); vertex_count];
-                sphere_mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, color_array);
-                sphere_mesh = sphere_mesh.translated_by(center);
-                sphere_mesh.compute_smooth_normals();
-                sphere_mesh
-            })
-            .reduce(|mut acc, mesh| {
-                acc.merge(&mesh);
-                acc
-            })
-            .unwrap()
+        let colors = self.color_scheme.atom_colors(&self.pdb);
+        // unit sphere scaled to each atom's van der Waals radius
+        let sphere = MeshTemplate::new(
+            Sphere::new(1.0)
+                .mesh()
+                .ico(sphere_subdivisions(self.pdb.get_size()))
+                .unwrap(),
+        );
+        let mut buffers = MeshBuffers::default();
+        for ((coord, element), color) in self.pdb.iter_coords_and_elements().zip(&colors) {
+            let radius = element.atomic_radius().van_der_waals.unwrap_or(1.5) as f32;
+            let transform = Transform::from_translation(Vec3::from_array(*coord))
+                .with_scale(Vec3::splat(radius));
+            sphere.append_to(&mut buffers, &transform, *color);
+        }
+        buffers.into_mesh(PrimitiveTopology::TriangleList)
     }
     fn render_putty(&self) -> Result<Mesh, Error> {
         fn create_smooth_curve(points: &[Vec3], segments: usize) -> Vec<Vec3> {
@@ -259,12 +371,75 @@ impl Structure {
                 Vec3::from_array(ca.coords.clone())
             })
             .collect();
+        if c_alphas.len() < 2 {
+            return Ok(Mesh::new(
+                PrimitiveTopology::TriangleList,
+                RenderAssetUsages::all(),
+            ));
+        }
         let curve = create_smooth_curve(&c_alphas, 3);
         let tube_mesh = generate_tube_mesh(&curve, 0.3, 16);
         Ok(tube_mesh)
     }
 }
 
+// Helper Fns --------------------------------------
+
+/// Sphere detail for the CPU mesh path: fewer triangles per atom as structures grow, so
+/// that large assemblies still fit in memory. Small structures keep bevy's default detail.
+fn sphere_subdivisions(atoms: usize) -> u32 {
+    match atoms {
+        0..=20_000 => 5,
+        20_001..=100_000 => 2,
+        100_001..=500_000 => 1,
+        _ => 0,
+    }
+}
+
+/// A mesh built once and copied for every atom or bond, instead of rebuilding and
+/// merging a mesh per copy
+struct MeshTemplate {
+    positions: Vec<Vec3>,
+    normals: Vec<Vec3>,
+    indices: Vec<u32>,
+}
+
+impl MeshTemplate {
+    fn new(mesh: Mesh) -> Self {
+        let float3 = |attribute| {
+            mesh.attribute(attribute)
+                .and_then(|values| values.as_float3())
+                .map(|values| values.iter().map(|v| Vec3::from_array(*v)).collect())
+                .unwrap_or_default()
+        };
+        MeshTemplate {
+            positions: float3(Mesh::ATTRIBUTE_POSITION),
+            normals: float3(Mesh::ATTRIBUTE_NORMAL),
+            indices: mesh
+                .indices()
+                .map(|indices| indices.iter().map(|i| i as u32).collect())
+                .unwrap_or_default(),
+        }
+    }
+
+    fn append_to(&self, buffers: &mut MeshBuffers, transform: &Transform, color: Color) {
+        let color = color.to_srgba();
+        let color = [color.red, color.green, color.blue, color.alpha];
+        let mut base = None;
+        for (position, normal) in self.positions.iter().zip(&self.normals) {
+            let index = buffers.push_vertex(
+                transform.transform_point(*position),
+                transform.rotation * *normal,
+                color,
+            );
+            base.get_or_insert(index);
+        }
+        if let Some(base) = base {
+            buffers.push_indices(base, &self.indices);
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -280,4 +455,57 @@ mod tests {
         assert_eq!(mesh.count_vertices(), 779748);
         Ok(())
     }
+
+    #[test]
+    fn test_selection_mesh() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_04().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let mut structure = Structure::builder()
+            .pdb(AtomCollection::from(&pdb))
+            .selection(Selection::new((0..10).collect()))
+            .build();
+        assert_eq!(structure.to_mesh().count_vertices(), 10 * 362);
+        structure.set_selection(None);
+        assert_eq!(structure.to_mesh().count_vertices(), 779748);
+        Ok(())
+    }
+
+    #[test]
+    fn test_cartoon_mesh() -> anyhow::Result<()> {
+        use crate::cartoon::{PROFILE_SEGMENTS, SEGMENTS_PER_RESIDUE};
+        let (molfile, _handle) = TestFile::protein_01().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let structure = Structure::builder()
+            .pdb(AtomCollection::from(&pdb))
+            .rendertype(RenderOptions::Cartoon)
+            .build();
+        let mesh = structure.to_mesh();
+        // one unbroken chain of 154 residues: a swept tube plus two end caps
+        let rings = 153 * SEGMENTS_PER_RESIDUE + 1;
+        assert_eq!(
+            mesh.count_vertices(),
+            rings * PROFILE_SEGMENTS + 2 * (PROFILE_SEGMENTS + 1)
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn test_wireframe_mesh() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_01().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let mut ac: AtomCollection = AtomCollection::from(&pdb)
+            .iter_residues_aminoacid()
+            .collect();
+        ac.connect_via_residue_names();
+        let bond_count = ac.get_bonds().map_or(0, |bonds| bonds.len());
+        assert!(bond_count > 0);
+        let structure = Structure::builder()
+            .pdb(ac)
+            .rendertype(RenderOptions::Wireframe)
+            .build();
+        let mesh = structure.to_mesh();
+        assert_eq!(mesh.primitive_topology(), PrimitiveTopology::LineList);
+        assert_eq!(mesh.count_vertices(), 4 * bond_count);
+        Ok(())
+    }
 }
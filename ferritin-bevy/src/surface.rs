@@ -0,0 +1,459 @@
+//! Surface
+//!
+//! Molecular surfaces computed on a regular grid and triangulated with marching
+//! tetrahedra (the marching cubes variant that splits each cell into six tetrahedra,
+//! which keeps the output watertight without lookup tables).
+//!
+//! Three surfaces are supported:
+//!
+//! - [`SurfaceType::Gaussian`]: isosurface of a sum of Gaussian atom densities
+//! - [`SurfaceType::SolventAccessible`]: the envelope of atoms inflated by the probe radius
+//! - [`SurfaceType::SolventExcluded`]: the surface traced by the inner face of the probe
+//!
+//! [`SurfaceMesh`] holds plain vertex and index buffers, plus the closest atom for each
+//! vertex, so the surface can be colored like any other representation or exported.
+use super::ColorScheme;
+use bevy::prelude::{Mesh, Vec3};
+use bevy::render::mesh::{Indices, PrimitiveTopology};
+use bevy::render::render_asset::RenderAssetUsages;
+use ferritin_core::AtomCollection;
+use std::collections::HashMap;
+
+/// Falloff of the Gaussian atom densities; larger values give tighter surfaces
+const BLOBBINESS: f32 = 2.0;
+const DEFAULT_RADIUS: f32 = 1.8;
+
+/// Kind of molecular surface
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SurfaceType {
+    Gaussian,
+    SolventAccessible,
+    SolventExcluded,
+}
+
+/// Parameters for surface generation
+#[derive(Clone, Debug)]
+pub struct SurfaceSettings {
+    pub surface_type: SurfaceType,
+    /// Grid spacing in Å
+    pub resolution: f32,
+    /// Solvent probe radius in Å
+    pub probe_radius: f32,
+}
+
+impl Default for SurfaceSettings {
+    fn default() -> Self {
+        Self {
+            surface_type: SurfaceType::SolventExcluded,
+            resolution: 0.6,
+            probe_radius: 1.4,
+        }
+    }
+}
+
+/// Triangulated surface with outward-facing normals
+#[derive(Clone, Debug, Default)]
+pub struct SurfaceMesh {
+    pub positions: Vec<[f32; 3]>,
+    pub normals: Vec<[f32; 3]>,
+    /// Index of the atom whose surface each vertex lies on
+    pub atoms: Vec<usize>,
+    pub indices: Vec<u32>,
+}
+
+impl SurfaceMesh {
+    /// Compute the surface of all atoms in `ac`.
+    pub fn new(ac: &AtomCollection, settings: &SurfaceSettings) -> Self {
+        let radii: Vec<f32> = ac
+            .get_elements()
+            .iter()
+            .map(|element| {
+                element
+                    .atomic_radius()
+                    .van_der_waals
+                    .map_or(DEFAULT_RADIUS, |r| r as f32)
+            })
+            .collect();
+        let field = ScalarField::new(ac.get_coords(), &radii, settings);
+        field.triangulate()
+    }
+
+    /// Bevy mesh with vertices colored by their closest atom.
+    pub fn to_mesh(&self, ac: &AtomCollection, color_scheme: &ColorScheme) -> Mesh {
+        let atom_colors = color_scheme.atom_colors(ac);
+        let colors: Vec<[f32; 4]> = self
+            .atoms
+            .iter()
+            .map(|&atom| {
+                let color = atom_colors[atom].to_srgba();
+                [color.red, color.green, color.blue, color.alpha]
+            })
+            .collect();
+        let mut mesh = Mesh::new(PrimitiveTopology::TriangleList, RenderAssetUsages::all());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_POSITION, self.positions.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_NORMAL, self.normals.clone());
+        mesh.insert_attribute(Mesh::ATTRIBUTE_COLOR, colors);
+        mesh.insert_indices(Indices::U32(self.indices.clone()));
+        mesh
+    }
+}
+
+/// Signed field sampled on a grid: negative inside the molecule, positive outside
+struct ScalarField {
+    origin: Vec3,
+    spacing: f32,
+    dims: [usize; 3],
+    values: Vec<f32>,
+    /// Atom dominating each grid point
+    atoms: Vec<usize>,
+}
+
+impl ScalarField {
+    fn new(coords: &[[f32; 3]], radii: &[f32], settings: &SurfaceSettings) -> Self {
+        let spacing = settings.resolution;
+        let probe = settings.probe_radius;
+        let max_radius = radii.iter().copied().fold(0.0, f32::max);
+        let padding = max_radius + probe + 3.0 * spacing;
+
+        let mut min = Vec3::splat(f32::MAX);
+        let mut max = Vec3::splat(f32::MIN);
+        for coord in coords {
+            min = min.min(Vec3::from_array(*coord));
+            max = max.max(Vec3::from_array(*coord));
+        }
+        if coords.is_empty() {
+            (min, max) = (Vec3::ZERO, Vec3::ZERO);
+        }
+        let origin = min - Vec3::splat(padding);
+        let extent = (max - min) + Vec3::splat(2.0 * padding);
+        let dims = [
+            (extent.x / spacing).ceil() as usize + 1,
+            (extent.y / spacing).ceil() as usize + 1,
+            (extent.z / spacing).ceil() as usize + 1,
+        ];
+        let size = dims[0] * dims[1] * dims[2];
+        let mut field = ScalarField {
+            origin,
+            spacing,
+            dims,
+            values: Vec::new(),
+            atoms: Vec::new(),
+        };
+        let mut owner = vec![0; size];
+
+        match settings.surface_type {
+            SurfaceType::Gaussian => {
+                // density = sum exp(-B (d^2 / r^2 - 1)), isosurface at density 1
+                let mut density = vec![0.0f32; size];
+                let mut strongest = vec![0.0f32; size];
+                for (atom, (coord, &r)) in coords.iter().zip(radii).enumerate() {
+                    let center = Vec3::from_array(*coord);
+                    let cutoff = r * (1.0 + 6.0 / BLOBBINESS).sqrt();
+                    field.for_each_near(center, cutoff, |idx, d2| {
+                        let value = (-BLOBBINESS * (d2 / (r * r) - 1.0)).exp();
+                        density[idx] += value;
+                        if value > strongest[idx] {
+                            strongest[idx] = value;
+                            owner[idx] = atom;
+                        }
+                    });
+                }
+                field.values = density.iter().map(|&rho| 1.0 - rho).collect();
+            }
+            SurfaceType::SolventAccessible | SurfaceType::SolventExcluded => {
+                // distance to the probe-inflated atom spheres
+                let far = 3.0 * spacing;
+                let mut sas = vec![far; size];
+                for (atom, (coord, &r)) in coords.iter().zip(radii).enumerate() {
+                    let center = Vec3::from_array(*coord);
+                    let expanded = r + probe;
+                    field.for_each_near(center, expanded + far, |idx, d2| {
+                        let value = d2.sqrt() - expanded;
+                        if value < sas[idx] {
+                            sas[idx] = value;
+                            owner[idx] = atom;
+                        }
+                    });
+                }
+                field.values = if settings.surface_type == SurfaceType::SolventAccessible {
+                    sas
+                } else {
+                    field.solvent_excluded(&sas, probe)
+                };
+            }
+        }
+        field.atoms = owner;
+        field
+    }
+
+    /// Shrink the solvent accessible volume back by the probe radius: grid points
+    /// within `probe` of the accessible region are outside the excluded surface.
+    fn solvent_excluded(&self, sas: &[f32], probe: f32) -> Vec<f32> {
+        let far = probe + self.spacing;
+        let mut reach: Vec<f32> = sas
+            .iter()
+            .map(|&value| if value > 0.0 { 0.0 } else { far })
+            .collect();
+        let [nx, ny, nz] = self.dims;
+        for z in 0..nz {
+            for y in 0..ny {
+                for x in 0..nx {
+                    let idx = self.index(x, y, z);
+                    if sas[idx] <= 0.0 {
+                        continue;
+                    }
+                    // only accessible points bordering the inaccessible region matter
+                    let border = self.neighbors(x, y, z).any(|neighbor| sas[neighbor] <= 0.0);
+                    if !border {
+                        continue;
+                    }
+                    let point = self.position(x, y, z);
+                    self.for_each_near(point, far, |near, d2| {
+                        if sas[near] <= 0.0 {
+                            let d = d2.sqrt();
+                            if d < reach[near] {
+                                reach[near] = d;
+                            }
+                        }
+                    });
+                }
+            }
+        }
+        reach.iter().map(|&d| probe - d).collect()
+    }
+
+    fn index(&self, x: usize, y: usize, z: usize) -> usize {
+        x + self.dims[0] * (y + self.dims[1] * z)
+    }
+
+    fn position(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        self.origin + Vec3::new(x as f32, y as f32, z as f32) * self.spacing
+    }
+
+    fn neighbors(&self, x: usize, y: usize, z: usize) -> impl Iterator<Item = usize> + '_ {
+        let [nx, ny, nz] = self.dims;
+        [
+            (x > 0).then(|| self.index(x - 1, y, z)),
+            (x + 1 < nx).then(|| self.index(x + 1, y, z)),
+            (y > 0).then(|| self.index(x, y - 1, z)),
+            (y + 1 < ny).then(|| self.index(x, y + 1, z)),
+            (z > 0).then(|| self.index(x, y, z - 1)),
+            (z + 1 < nz).then(|| self.index(x, y, z + 1)),
+        ]
+        .into_iter()
+        .flatten()
+    }
+
+    /// Visit grid points within `radius` of `center` with their squared distance
+    fn for_each_near(&self, center: Vec3, radius: f32, mut visit: impl FnMut(usize, f32)) {
+        let lo = ((center - Vec3::splat(radius) - self.origin) / self.spacing).floor();
+        let hi = ((center + Vec3::splat(radius) - self.origin) / self.spacing).ceil();
+        let clamp = |v: f32, n: usize| (v.max(0.0) as usize).min(n - 1);
+        let r2 = radius * radius;
+        for z in clamp(lo.z, self.dims[2])..=clamp(hi.z, self.dims[2]) {
+            for y in clamp(lo.y, self.dims[1])..=clamp(hi.y, self.dims[1]) {
+                for x in clamp(lo.x, self.dims[0])..=clamp(hi.x, self.dims[0]) {
+                    let d2 = self.position(x, y, z).distance_squared(center);
+                    if d2 <= r2 {
+                        visit(self.index(x, y, z), d2);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Central-difference gradient, pointing out of the molecule
+    fn gradient(&self, x: usize, y: usize, z: usize) -> Vec3 {
+        let [nx, ny, nz] = self.dims;
+        let diff = |lo: usize, hi: usize, steps: usize| {
+            (self.values[hi] - self.values[lo]) / (steps as f32 * self.spacing)
+        };
+        let (x0, x1) = (x.saturating_sub(1), (x + 1).min(nx - 1));
+        let (y0, y1) = (y.saturating_sub(1), (y + 1).min(ny - 1));
+        let (z0, z1) = (z.saturating_sub(1), (z + 1).min(nz - 1));
+        Vec3::new(
+            diff(self.index(x0, y, z), self.index(x1, y, z), x1 - x0),
+            diff(self.index(x, y0, z), self.index(x, y1, z), y1 - y0),
+            diff(self.index(x, y, z0), self.index(x, y, z1), z1 - z0),
+        )
+    }
+
+    fn triangulate(&self) -> SurfaceMesh {
+        // Kuhn triangulation: six tetrahedra around the cell diagonal from corner 0 to 7,
+        // with corners numbered x + 2y + 4z; neighboring cells share face diagonals.
+        const TETRAHEDRA: [[usize; 4]; 6] = [
+            [0, 1, 3, 7],
+            [0, 1, 5, 7],
+            [0, 2, 3, 7],
+            [0, 2, 6, 7],
+            [0, 4, 5, 7],
+            [0, 4, 6, 7],
+        ];
+        let mut mesh = SurfaceMesh::default();
+        let mut edge_vertices: HashMap<(usize, usize), u32> = HashMap::new();
+        // midpoint of the grid edge behind each vertex, used to orient triangles
+        let mut midpoints: Vec<Vec3> = Vec::new();
+        let [nx, ny, nz] = self.dims;
+        for z in 0..nz.saturating_sub(1) {
+            for y in 0..ny.saturating_sub(1) {
+                for x in 0..nx.saturating_sub(1) {
+                    let corners: [(usize, usize, usize); 8] = std::array::from_fn(|c| {
+                        (x + (c & 1), y + ((c >> 1) & 1), z + ((c >> 2) & 1))
+                    });
+                    let inside: Vec<bool> = corners
+                        .iter()
+                        .map(|&(cx, cy, cz)| self.values[self.index(cx, cy, cz)] < 0.0)
+                        .collect();
+                    if inside.iter().all(|&v| v) || inside.iter().all(|&v| !v) {
+                        continue;
+                    }
+                    for tet in TETRAHEDRA {
+                        let (ins, outs): (Vec<usize>, Vec<usize>) =
+                            tet.iter().copied().partition(|&c| inside[c]);
+                        let mut vertex = |a: usize, b: usize| {
+                            self.edge_vertex(
+                                corners[a],
+                                corners[b],
+                                &mut edge_vertices,
+                                &mut midpoints,
+                                &mut mesh,
+                            )
+                        };
+                        let polygon: Vec<u32> = match (ins.len(), outs.len()) {
+                            (1, 3) => outs.iter().map(|&o| vertex(ins[0], o)).collect(),
+                            (3, 1) => ins.iter().map(|&i| vertex(i, outs[0])).collect(),
+                            (2, 2) => vec![
+                                vertex(ins[0], outs[0]),
+                                vertex(ins[0], outs[1]),
+                                vertex(ins[1], outs[1]),
+                                vertex(ins[1], outs[0]),
+                            ],
+                            _ => continue,
+                        };
+                        let outward = tet_outward(&corners, &ins, &outs);
+                        for k in 1..polygon.len() - 1 {
+                            mesh.push_triangle(
+                                [polygon[0], polygon[k], polygon[k + 1]],
+                                outward,
+                                &midpoints,
+                            );
+                        }
+                    }
+                }
+            }
+        }
+        mesh
+    }
+
+    /// Shared vertex where the surface crosses the grid edge between two points
+    fn edge_vertex(
+        &self,
+        a: (usize, usize, usize),
+        b: (usize, usize, usize),
+        cache: &mut HashMap<(usize, usize), u32>,
+        midpoints: &mut Vec<Vec3>,
+        mesh: &mut SurfaceMesh,
+    ) -> u32 {
+        let ia = self.index(a.0, a.1, a.2);
+        let ib = self.index(b.0, b.1, b.2);
+        let key = (ia.min(ib), ia.max(ib));
+        if let Some(&vertex) = cache.get(&key) {
+            return vertex;
+        }
+        let (va, vb) = (self.values[ia], self.values[ib]);
+        let t = (va / (va - vb)).clamp(0.0, 1.0);
+        let pa = self.position(a.0, a.1, a.2);
+        let pb = self.position(b.0, b.1, b.2);
+        let normal = self
+            .gradient(a.0, a.1, a.2)
+            .lerp(self.gradient(b.0, b.1, b.2), t)
+            .normalize_or_zero();
+        // the closer inside point owns the vertex
+        let atom = if va < 0.0 {
+            self.atoms[ia]
+        } else {
+            self.atoms[ib]
+        };
+        let vertex = mesh.positions.len() as u32;
+        mesh.positions.push(pa.lerp(pb, t).to_array());
+        mesh.normals.push(normal.to_array());
+        mesh.atoms.push(atom);
+        midpoints.push(pa.lerp(pb, 0.5));
+        cache.insert(key, vertex);
+        vertex
+    }
+}
+
+impl SurfaceMesh {
+    /// Append a triangle wound counter-clockwise when seen from `outward`. The winding is
+    /// decided on the edge midpoints: interpolated vertices can sit arbitrarily close to a
+    /// grid corner and make the triangle degenerate, but the orientation does not depend
+    /// on where along its edge each vertex lies.
+    fn push_triangle(&mut self, [a, b, c]: [u32; 3], outward: Vec3, midpoints: &[Vec3]) {
+        let p = |i: u32| midpoints[i as usize];
+        let normal = (p(b) - p(a)).cross(p(c) - p(a));
+        if normal.dot(outward) >= 0.0 {
+            self.indices.extend([a, b, c]);
+        } else {
+            self.indices.extend([a, c, b]);
+        }
+    }
+}
+
+// Helper Fns --------------------------------------
+
+/// Direction from the inside corners of a tetrahedron towards its outside corners
+fn tet_outward(corners: &[(usize, usize, usize); 8], ins: &[usize], outs: &[usize]) -> Vec3 {
+    let mean = |set: &[usize]| {
+        set.iter()
+            .map(|&c| {
+                let (x, y, z) = corners[c];
+                Vec3::new(x as f32, y as f32, z as f32)
+            })
+            .sum::<Vec3>()
+            / set.len() as f32
+    };
+    mean(outs) - mean(ins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+    use std::collections::HashSet;
+
+    #[test]
+    fn test_surface_is_closed() -> anyhow::Result<()> {
+        let (molfile, _handle) = TestFile::protein_01().create_temp()?;
+        let (pdb, _errors) = pdbtbx::open(molfile).unwrap();
+        let ac: AtomCollection = AtomCollection::from(&pdb)
+            .iter_residues_aminoacid()
+            .collect();
+
+        for surface_type in [
+            SurfaceType::Gaussian,
+            SurfaceType::SolventAccessible,
+            SurfaceType::SolventExcluded,
+        ] {
+            let settings = SurfaceSettings {
+                surface_type,
+                resolution: 1.0,
+                ..Default::default()
+            };
+            let surface = SurfaceMesh::new(&ac, &settings);
+            assert!(!surface.indices.is_empty());
+            assert!(surface.atoms.iter().all(|&atom| atom < ac.get_size()));
+
+            // a closed, consistently wound surface uses every directed edge exactly once
+            // and always together with its reverse
+            let edges: HashSet<(u32, u32)> = surface
+                .indices
+                .chunks(3)
+                .flat_map(|t| [(t[0], t[1]), (t[1], t[2]), (t[2], t[0])])
+                .collect();
+            assert_eq!(edges.len(), surface.indices.len());
+            assert!(edges.iter().all(|&(a, b)| edges.contains(&(b, a))));
+        }
+        Ok(())
+    }
+}
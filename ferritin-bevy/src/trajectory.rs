@@ -0,0 +1,429 @@
+//! Trajectory
+//!
+//! Playback of several coordinate frames that share one topology, such as the models of an
+//! NMR ensemble or conformations sampled by a simulation. A [`Trajectory`] component sits
+//! next to a [`Structure`] and, while playing, moves the structure's atoms to the current
+//! frame, optionally interpolating linearly between neighbouring frames. Impostor instances
+//! are moved in place when the shown coordinates change; mesh representations such as
+//! cartoons depend on more than atom positions and are rebuilt.
+//!
+//! Playback is controlled through the component's methods, [`TrajectoryControl`] events or,
+//! when enabled, the keyboard: space toggles playback and the left and right arrows step
+//! one frame.
+//!
+//! ```no_run
+//! use ferritin_bevy::{Structure, Trajectory};
+//! use ferritin_core::AtomCollection;
+//!
+//! # fn example(pdb: pdbtbx::PDB) -> anyhow::Result<()> {
+//! // prepare every model the same way as the topology
+//! let prepare = |ac: AtomCollection| -> AtomCollection {
+//!     let mut ac: AtomCollection = ac.iter_residues_aminoacid().collect();
+//!     ac.connect_via_residue_names();
+//!     ac
+//! };
+//! let trajectory = Trajectory::from_models(&pdb, prepare)?.with_interpolation(true);
+//! // `AtomCollection::from(&pdb)` would hold the atoms of every model
+//! let topology = AtomCollection::from(pdb.model(0).unwrap());
+//! let structure = Structure::builder().pdb(prepare(topology)).build();
+//! // spawn both on one entity in an app with the `TrajectoryPlugin`
+//! # let _ = (structure, trajectory);
+//! # Ok(())
+//! # }
+//! ```
+use super::{ImpostorInstances, Structure};
+use anyhow::{bail, Result};
+use bevy::prelude::*;
+use ferritin_core::AtomCollection;
+
+pub struct TrajectoryPlugin {
+    /// Space toggles playback, the arrow keys step through frames
+    pub keyboard: bool,
+}
+
+impl Default for TrajectoryPlugin {
+    fn default() -> Self {
+        Self { keyboard: true }
+    }
+}
+
+impl Plugin for TrajectoryPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<TrajectoryControl>().add_systems(
+            Update,
+            (
+                handle_control_events,
+                advance_trajectories,
+                apply_trajectory_frames,
+            )
+                .chain(),
+        );
+        if self.keyboard {
+            app.add_systems(Update, keyboard_controls.before(handle_control_events));
+        }
+    }
+}
+
+/// Control playback of the [`Trajectory`] on an entity.
+#[derive(Event, Clone, Copy, Debug)]
+pub enum TrajectoryControl {
+    Play(Entity),
+    Pause(Entity),
+    Toggle(Entity),
+    /// Pause and move by a number of frames
+    Step(Entity, i32),
+    /// Jump to a (fractional) frame position
+    Seek(Entity, f32),
+}
+
+/// Coordinate frames for the atoms of a [`Structure`] and the playback state.
+#[derive(Component, Clone, Debug)]
+pub struct Trajectory {
+    frames: Vec<Vec<[f32; 3]>>,
+    /// Current position in frames; the fractional part is the interpolation weight
+    position: f32,
+    /// Position whose coordinates were last written to the structure
+    shown: Option<f32>,
+    pub playing: bool,
+    /// Playback speed in frames per second
+    pub fps: f32,
+    /// Blend linearly between frames instead of jumping
+    pub interpolate: bool,
+    /// Start over after the last frame instead of stopping
+    pub looping: bool,
+}
+
+impl Trajectory {
+    /// Frames must be non-empty and all hold one coordinate per atom.
+    pub fn new(frames: Vec<Vec<[f32; 3]>>) -> Result<Self> {
+        let Some(first) = frames.first() else {
+            bail!("A trajectory needs at least one frame");
+        };
+        if let Some(i) = frames.iter().position(|frame| frame.len() != first.len()) {
+            bail!(
+                "Frame {} has {} atoms, expected {}",
+                i,
+                frames[i].len(),
+                first.len()
+            );
+        }
+        Ok(Trajectory {
+            frames,
+            position: 0.0,
+            shown: None,
+            playing: false,
+            fps: 10.0,
+            interpolate: false,
+            looping: true,
+        })
+    }
+    /// One frame per model of a multi-model file, e.g. an NMR ensemble.
+    ///
+    /// `prepare` is applied to every model and should match how the topology was built
+    /// from the first model, for example keeping only the amino acids.
+    pub fn from_models<F>(pdb: &pdbtbx::PDB, prepare: F) -> Result<Self>
+    where
+        F: Fn(AtomCollection) -> AtomCollection,
+    {
+        let frames = pdb
+            .models()
+            .map(|model| prepare(AtomCollection::from(model)).get_coords().clone())
+            .collect();
+        Trajectory::new(frames)
+    }
+    pub fn with_fps(mut self, fps: f32) -> Self {
+        self.fps = fps;
+        self
+    }
+    pub fn with_interpolation(mut self, interpolate: bool) -> Self {
+        self.interpolate = interpolate;
+        self
+    }
+    pub fn with_looping(mut self, looping: bool) -> Self {
+        self.looping = looping;
+        self
+    }
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+    pub fn get_frame(&self, frame: usize) -> &[[f32; 3]] {
+        &self.frames[frame]
+    }
+    pub fn current_frame(&self) -> usize {
+        self.position.floor() as usize
+    }
+    pub fn position(&self) -> f32 {
+        self.position
+    }
+    pub fn play(&mut self) {
+        // replaying a finished, non-looping trajectory starts from the beginning
+        if !self.looping && self.position >= self.last() {
+            self.position = 0.0;
+        }
+        self.playing = true;
+    }
+    pub fn pause(&mut self) {
+        self.playing = false;
+    }
+    pub fn toggle(&mut self) {
+        if self.playing {
+            self.pause()
+        } else {
+            self.play()
+        }
+    }
+    /// Pause and move `delta` frames, wrapping around when looping.
+    pub fn step(&mut self, delta: i32) {
+        self.pause();
+        let frame = self.position.round() as i64 + delta as i64;
+        let frame = if self.looping {
+            frame.rem_euclid(self.len() as i64)
+        } else {
+            frame.clamp(0, self.len() as i64 - 1)
+        };
+        self.position = frame as f32;
+    }
+    /// Jump to a frame position, clamped to the trajectory.
+    pub fn seek(&mut self, position: f32) {
+        self.position = position.clamp(0.0, self.last());
+    }
+    /// Move the playhead forward by `seconds` of playback.
+    pub fn advance(&mut self, seconds: f32) {
+        if !self.playing {
+            return;
+        }
+        let last = self.last();
+        if last == 0.0 {
+            self.playing = false;
+            return;
+        }
+        // with interpolation playback reaches the last frame and jumps back to the first
+        // (there is no blend between them); without it the last frame is held for a full
+        // frame before wrapping
+        let end = match self.interpolate {
+            true => last,
+            false => self.len() as f32,
+        };
+        self.position += seconds * self.fps;
+        if self.position >= end {
+            if self.looping {
+                self.position = self.position.rem_euclid(end);
+            } else {
+                self.position = last;
+                self.playing = false;
+            }
+        }
+    }
+    /// Coordinates at a frame position, interpolated when enabled.
+    pub fn coords_at(&self, position: f32) -> Vec<[f32; 3]> {
+        let position = position.clamp(0.0, self.last());
+        let frame = position.floor() as usize;
+        let t = position.fract();
+        if !self.interpolate || t == 0.0 || frame + 1 >= self.len() {
+            return self.frames[frame].clone();
+        }
+        self.frames[frame]
+            .iter()
+            .zip(&self.frames[frame + 1])
+            .map(|(a, b)| std::array::from_fn(|axis| a[axis] + (b[axis] - a[axis]) * t))
+            .collect()
+    }
+    /// Position whose coordinates should be on screen
+    fn target(&self) -> f32 {
+        if self.interpolate {
+            self.position
+        } else {
+            self.position.floor()
+        }
+    }
+    fn last(&self) -> f32 {
+        (self.len() - 1) as f32
+    }
+}
+
+// Systems ---------------------------------------------------------------
+
+fn keyboard_controls(
+    keys: Res<ButtonInput<KeyCode>>,
+    trajectories: Query<Entity, With<Trajectory>>,
+    mut events: EventWriter<TrajectoryControl>,
+) {
+    for entity in &trajectories {
+        if keys.just_pressed(KeyCode::Space) {
+            events.send(TrajectoryControl::Toggle(entity));
+        }
+        if keys.just_pressed(KeyCode::ArrowRight) {
+            events.send(TrajectoryControl::Step(entity, 1));
+        }
+        if keys.just_pressed(KeyCode::ArrowLeft) {
+            events.send(TrajectoryControl::Step(entity, -1));
+        }
+    }
+}
+
+fn handle_control_events(
+    mut events: EventReader<TrajectoryControl>,
+    mut trajectories: Query<&mut Trajectory>,
+) {
+    for event in events.read() {
+        let entity = match event {
+            TrajectoryControl::Play(entity)
+            | TrajectoryControl::Pause(entity)
+            | TrajectoryControl::Toggle(entity)
+            | TrajectoryControl::Step(entity, _)
+            | TrajectoryControl::Seek(entity, _) => *entity,
+        };
+        let Ok(mut trajectory) = trajectories.get_mut(entity) else {
+            continue;
+        };
+        match event {
+            TrajectoryControl::Play(_) => trajectory.play(),
+            TrajectoryControl::Pause(_) => trajectory.pause(),
+            TrajectoryControl::Toggle(_) => trajectory.toggle(),
+            TrajectoryControl::Step(_, delta) => trajectory.step(*delta),
+            TrajectoryControl::Seek(_, position) => trajectory.seek(*position),
+        }
+    }
+}
+
+fn advance_trajectories(time: Res<Time>, mut trajectories: Query<&mut Trajectory>) {
+    for mut trajectory in &mut trajectories {
+        if trajectory.playing {
+            trajectory.advance(time.delta_secs());
+        }
+    }
+}
+
+/// Write the current frame into the structure. Impostor instances are moved directly;
+/// other representations are rebuilt by the structure plugin. Structures are only touched
+/// when the shown coordinates change.
+fn apply_trajectory_frames(
+    mut trajectories: Query<(
+        &mut Trajectory,
+        &mut Structure,
+        Option<&mut ImpostorInstances>,
+    )>,
+) {
+    for (mut trajectory, mut structure, instances) in &mut trajectories {
+        let target = trajectory.target();
+        if trajectory.shown == Some(target) {
+            continue;
+        }
+        if trajectory.frames[0].len() != structure.get_pdb().get_size() {
+            warn!("Trajectory frames do not match the structure's atoms");
+            trajectory.shown = Some(target);
+            continue;
+        }
+        let coords = trajectory.coords_at(target);
+        match instances {
+            Some(mut instances) => {
+                instances.set_coords(&coords);
+                // the instances already show the new frame; skip the remesh
+                structure.bypass_change_detection().set_coords(coords);
+            }
+            None => structure.set_coords(coords),
+        }
+        trajectory.shown = Some(target);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+
+    fn frames() -> Vec<Vec<[f32; 3]>> {
+        vec![
+            vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0]],
+            vec![[2.0, 0.0, 0.0], [1.0, 2.0, 0.0]],
+            vec![[4.0, 0.0, 0.0], [1.0, 4.0, 0.0]],
+        ]
+    }
+
+    #[test]
+    fn test_trajectory_playback() {
+        assert!(Trajectory::new(vec![]).is_err());
+        assert!(Trajectory::new(vec![vec![[0.0; 3]], vec![]]).is_err());
+
+        let mut trajectory = Trajectory::new(frames()).unwrap().with_fps(2.0);
+        trajectory.play();
+        trajectory.advance(0.25);
+        assert_eq!(trajectory.position(), 0.5);
+        assert_eq!(trajectory.current_frame(), 0);
+        // without interpolation the current frame is shown as is
+        assert_eq!(trajectory.coords_at(0.5), frames()[0]);
+        trajectory.interpolate = true;
+        assert_eq!(
+            trajectory.coords_at(0.5),
+            vec![[1.0, 0.0, 0.0], [1.0, 1.0, 0.0]]
+        );
+
+        // looping wraps past the last frame, otherwise playback stops there
+        trajectory.advance(1.0);
+        assert_eq!(trajectory.position(), 0.5);
+        trajectory.looping = false;
+        trajectory.advance(1.0);
+        assert_eq!(trajectory.position(), 2.0);
+        assert!(!trajectory.playing);
+
+        trajectory.step(1);
+        assert_eq!(trajectory.current_frame(), 2);
+        trajectory.looping = true;
+        trajectory.step(1);
+        assert_eq!(trajectory.current_frame(), 0);
+        trajectory.step(-1);
+        assert_eq!(trajectory.current_frame(), 2);
+
+        // without interpolation the last frame is shown for a full frame before wrapping
+        let mut pair = Trajectory::new(frames()[..2].to_vec())
+            .unwrap()
+            .with_fps(1.0);
+        pair.play();
+        pair.advance(1.0);
+        assert_eq!(pair.current_frame(), 1);
+        pair.advance(0.5);
+        assert_eq!(pair.current_frame(), 1);
+        pair.advance(0.5);
+        assert_eq!(pair.current_frame(), 0);
+        assert!(pair.playing);
+    }
+
+    #[test]
+    fn test_trajectory_from_models() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (mut pdb, _) = pdbtbx::open(prot_file).unwrap();
+        // a three-model ensemble: copies of the first model shifted along x
+        let model = pdb.model(0).unwrap().clone();
+        for shift in 1..3 {
+            let mut copy = model.clone();
+            copy.set_serial_number(shift + 1);
+            for atom in copy.atoms_mut() {
+                let (x, y, z) = atom.pos();
+                atom.set_pos((x + shift as f64, y, z)).unwrap();
+            }
+            pdb.add_model(copy);
+        }
+        let prepare = |ac: AtomCollection| -> AtomCollection {
+            let ac: AtomCollection = ac.iter_residues_aminoacid().collect();
+            ac
+        };
+        let trajectory = Trajectory::from_models(&pdb, prepare).unwrap();
+        assert_eq!(trajectory.len(), 3);
+
+        // the whole file holds every model, so the topology comes from the first one
+        let first = AtomCollection::from(pdb.model(0).unwrap());
+        assert_eq!(AtomCollection::from(&pdb).get_size(), 3 * first.get_size());
+        let topology = prepare(first);
+        assert_eq!(trajectory.get_frame(0), topology.get_coords().as_slice());
+        for frame in 1..3 {
+            let positions = trajectory.get_frame(frame);
+            assert_eq!(positions.len(), topology.get_size());
+            assert_ne!(positions, trajectory.get_frame(0));
+            let moved = positions[0][0] - topology.get_coords()[0][0];
+            assert!((moved - frame as f32).abs() < 1e-3);
+        }
+    }
+}
@@ -1,4 +1,5 @@
-use ferritin_cellscape::cellscape::StructureFlatten;
+use ferritin_cellscape::cellscape::{CellscapeConfig, StructureFlatten};
+use ferritin_cellscape::view::View;
 use ferritin_core::AtomCollection;
 use pdbtbx;
 use std::error::Error;
@@ -6,7 +7,11 @@ use std::error::Error;
 fn main() -> Result<(), Box<dyn Error>> {
     let (pdb, _) = pdbtbx::open("data/101m.cif").unwrap();
     let ac = AtomCollection::from(&pdb);
-    let doc = ac.flatten_structure();
+    let config = CellscapeConfig {
+        view: View::principal_axes(&ac),
+        ..Default::default()
+    };
+    let doc = ac.flatten_with(&config);
     svg::save("simple_02.svg", &doc)?;
     println!("SVG has been created as 'simple_02.svg'");
     Ok(())
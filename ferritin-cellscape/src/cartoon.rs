@@ -0,0 +1,391 @@
+//! Cartoon
+//!
+//! Cellscape's cartoon mode for cell-scale illustrations. Instead of one silhouette per
+//! residue, each chain (or each user-supplied domain) becomes a single smooth blob: the
+//! representative atoms are drawn as large circles, merged, simplified and smoothed, and
+//! holes are filled in. Blobs are written as one SVG group per chain, ordered back-to-front,
+//! above an optional membrane slab.
+//!
+//! ```no_run
+//! use ferritin_cellscape::cartoon::{CartoonConfig, Membrane};
+//! use ferritin_cellscape::cellscape::StructureFlatten;
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> std::io::Result<()> {
+//! let config = CartoonConfig {
+//!     membrane: Some(Membrane::default()),
+//!     ..Default::default()
+//! };
+//! svg::save("cartoon.svg", &ac.flatten_cartoon(&config))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::cellscape::{
+    atom_radius, chain_order, create_circle, depth_range, fitted_document, outline_path, project,
+    shape_bounds, union_all, AtomRadius, CellscapeConfig, Outline,
+};
+use ferritin_core::{AtomCollection, Selection};
+use geo::{ChaikinSmoothing, Coord, LineString, MultiPolygon, Point, Polygon};
+use svg::node::element::{Group, Rectangle};
+use svg::Document;
+
+/// A named set of atoms drawn as one blob
+#[derive(Clone, Debug)]
+pub struct Domain {
+    pub name: String,
+    pub selection: Selection,
+}
+
+impl Domain {
+    pub fn new<S: Into<String>>(name: S, selection: Selection) -> Self {
+        Domain {
+            name: name.into(),
+            selection,
+        }
+    }
+    /// Chain of the domain's first atom, or `None` if the selection is empty or reaches
+    /// past the atoms of `ac`
+    pub fn chain_id<'a>(&self, ac: &'a AtomCollection) -> Option<&'a String> {
+        let indices = self.selection.indices();
+        if indices.iter().any(|&i| i >= ac.get_size()) {
+            return None;
+        }
+        indices.first().map(|&i| ac.get_chain_id(i))
+    }
+}
+
+/// Which atoms are merged into a blob
+#[derive(Clone, Debug, Default)]
+pub enum CartoonGrouping {
+    #[default]
+    Chain,
+    /// One blob per domain; atoms outside every domain are left out, as are domains
+    /// without a valid [`Domain::chain_id`]
+    Domains(Vec<Domain>),
+}
+
+/// A lipid bilayer drawn as a horizontal band behind the structure
+#[derive(Clone, Debug)]
+pub struct Membrane {
+    /// Height of the membrane's midplane on the page, in Å along the view's y axis
+    pub center: f64,
+    /// Thickness in Å
+    pub thickness: f64,
+    pub color: String,
+}
+
+impl Default for Membrane {
+    fn default() -> Self {
+        Self {
+            center: 0.0,
+            thickness: 30.0,
+            color: "#f2e6c9".to_string(),
+        }
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct CartoonConfig {
+    /// View, colors, stroke, shading and canvas settings; `radius` is the blob radius
+    /// around each representative atom
+    pub style: CellscapeConfig,
+    pub grouping: CartoonGrouping,
+    /// Tolerance in Å for simplifying outlines before smoothing
+    pub simplify: f64,
+    /// Rounds of Chaikin smoothing
+    pub smoothing: usize,
+    pub membrane: Option<Membrane>,
+}
+
+impl Default for CartoonConfig {
+    fn default() -> Self {
+        Self {
+            style: CellscapeConfig {
+                radius: AtomRadius::Fixed(5.0),
+                stroke_width: 0.6,
+                depth_shading: 0.3,
+                ..Default::default()
+            },
+            grouping: CartoonGrouping::Chain,
+            simplify: 1.5,
+            smoothing: 3,
+            membrane: None,
+        }
+    }
+}
+
+/// Project `ac` into one smoothed blob per chain or domain, ordered back-to-front.
+///
+/// Alpha carbons represent amino acids; groups without any are drawn from all their atoms.
+pub fn project_cartoon(ac: &AtomCollection, config: &CartoonConfig) -> Vec<Outline> {
+    // (chain, label, atoms) for each blob
+    let groups: Vec<(String, String, Vec<usize>)> = match &config.grouping {
+        CartoonGrouping::Chain => chain_order(ac)
+            .into_iter()
+            .map(|chain| {
+                let atoms = ac.select_by_chain(&chain).indices().to_vec();
+                (chain.clone(), chain, atoms)
+            })
+            .collect(),
+        CartoonGrouping::Domains(domains) => domains
+            .iter()
+            .filter_map(|domain| {
+                let chain_id = domain.chain_id(ac)?.clone();
+                let atoms = domain.selection.indices().to_vec();
+                Some((chain_id, domain.name.clone(), atoms))
+            })
+            .collect(),
+    };
+
+    let style = &config.style;
+    let mut outlines: Vec<Outline> = groups
+        .into_iter()
+        .enumerate()
+        .filter_map(|(index, (chain_id, label, atoms))| {
+            let alpha_carbons: Vec<usize> = atoms
+                .iter()
+                .copied()
+                .filter(|&i| ac.get_atom_name(i) == "CA")
+                .collect();
+            let atoms = if alpha_carbons.is_empty() {
+                atoms
+            } else {
+                alpha_carbons
+            };
+            if atoms.is_empty() {
+                return None;
+            }
+            let mut depth = 0.0;
+            let circles: Vec<MultiPolygon<f64>> = atoms
+                .iter()
+                .map(|&i| {
+                    let [x, y, z] = project(&style.view, ac.get_coord(i));
+                    depth += z;
+                    let radius = atom_radius(ac, i, style.radius);
+                    MultiPolygon(vec![create_circle(&Point::new(x, y), radius)])
+                })
+                .collect();
+            let shape = union_all(circles)
+                .0
+                .into_iter()
+                .map(|polygon| smooth_blob(&polygon, config.simplify, config.smoothing))
+                .collect();
+            let color = style
+                .colors
+                .get(index % style.colors.len().max(1))
+                .cloned()
+                .unwrap_or_else(|| "#cccccc".to_string());
+            Some(Outline {
+                chain_id,
+                label,
+                shape: MultiPolygon(shape),
+                depth: depth / atoms.len() as f64,
+                color,
+            })
+        })
+        .collect();
+    outlines.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+    outlines
+}
+
+/// Paint blobs as one group per chain above the optional membrane.
+pub fn render_cartoon(outlines: &[Outline], config: &CartoonConfig) -> Document {
+    let style = &config.style;
+    let (mut min, mut max) = shape_bounds(outlines.iter().map(|o| &o.shape));
+    // page y points down, so the slab around `center` spans -center ± thickness / 2
+    let slab = config.membrane.as_ref().map(|membrane| {
+        let top = -membrane.center - membrane.thickness / 2.0;
+        (top, membrane.thickness, membrane)
+    });
+    if let Some((top, thickness, _)) = slab {
+        min[1] = min[1].min(top);
+        max[1] = max[1].max(top + thickness);
+    }
+    let mut document = fitted_document(min, max, style);
+    if let Some((top, thickness, membrane)) = slab {
+        let rect = Rectangle::new()
+            .set("x", min[0] - style.margin)
+            .set("y", top)
+            .set("width", max[0] - min[0] + 2.0 * style.margin)
+            .set("height", thickness)
+            .set("fill", membrane.color.as_str());
+        document = document.add(Group::new().set("id", "membrane").add(rect));
+    }
+
+    // chains are layered by their farthest blob, which keeps the painter's order
+    let depths = depth_range(outlines);
+    let mut chains: Vec<&str> = Vec::new();
+    for outline in outlines {
+        if !chains.contains(&outline.chain_id.as_str()) {
+            chains.push(&outline.chain_id);
+        }
+    }
+    chains.into_iter().fold(document, |doc, chain| {
+        let group = outlines
+            .iter()
+            .filter(|outline| outline.chain_id == chain)
+            .fold(
+                Group::new().set("id", format!("chain-{}", chain)),
+                |group, outline| {
+                    group
+                        .add(outline_path(outline, depths, style).set("id", outline.label.as_str()))
+                },
+            );
+        doc.add(group)
+    })
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+/// Simplified, smoothed exterior of a polygon; holes are filled in.
+fn smooth_blob(polygon: &Polygon<f64>, tolerance: f64, smoothing: usize) -> Polygon<f64> {
+    let ring: Vec<Coord<f64>> = polygon.exterior().coords().copied().collect();
+    let simplified = simplify_ring(&ring, tolerance);
+    let ring = if simplified.len() >= 4 {
+        simplified
+    } else {
+        ring
+    };
+    Polygon::new(LineString(ring), vec![]).chaikin_smoothing(smoothing)
+}
+
+/// Ramer-Douglas-Peucker simplification of a closed ring, keeping its first point.
+fn simplify_ring(ring: &[Coord<f64>], tolerance: f64) -> Vec<Coord<f64>> {
+    if ring.len() < 4 {
+        return ring.to_vec();
+    }
+    let mut keep = vec![false; ring.len()];
+    keep[0] = true;
+    keep[ring.len() - 1] = true;
+    // split the ring at its point farthest from the start so neither half is degenerate
+    let far = (1..ring.len() - 1)
+        .max_by(|&a, &b| distance(ring[a], ring[0]).total_cmp(&distance(ring[b], ring[0])))
+        .unwrap_or(1);
+    keep[far] = true;
+    let mut stack = vec![(0, far), (far, ring.len() - 1)];
+    while let Some((start, end)) = stack.pop() {
+        if end <= start + 1 {
+            continue;
+        }
+        let (index, max) = (start + 1..end)
+            .map(|i| (i, segment_distance(ring[i], ring[start], ring[end])))
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+            .unwrap();
+        if max > tolerance {
+            keep[index] = true;
+            stack.push((start, index));
+            stack.push((index, end));
+        }
+    }
+    ring.iter()
+        .zip(keep)
+        .filter(|(_, keep)| *keep)
+        .map(|(coord, _)| *coord)
+        .collect()
+}
+
+fn distance(a: Coord<f64>, b: Coord<f64>) -> f64 {
+    (a.x - b.x).hypot(a.y - b.y)
+}
+
+/// Distance from `p` to the segment `a`-`b`
+fn segment_distance(p: Coord<f64>, a: Coord<f64>, b: Coord<f64>) -> f64 {
+    let (dx, dy) = (b.x - a.x, b.y - a.y);
+    let length = dx * dx + dy * dy;
+    if length == 0.0 {
+        return distance(p, a);
+    }
+    let t = (((p.x - a.x) * dx + (p.y - a.y) * dy) / length).clamp(0.0, 1.0);
+    distance(
+        p,
+        Coord {
+            x: a.x + t * dx,
+            y: a.y + t * dy,
+        },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cellscape::StructureFlatten;
+    use geo::Contains;
+
+    /// Projected alpha carbons of `atoms`
+    fn projected_ca(ac: &AtomCollection, atoms: &[usize], config: &CartoonConfig) -> Vec<Point> {
+        atoms
+            .iter()
+            .filter(|&&i| ac.get_atom_name(i) == "CA")
+            .map(|&i| {
+                let [x, y, _] = project(&config.style.view, ac.get_coord(i));
+                Point::new(x, y)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_cartoon_blobs() {
+        let (pdb, _) = pdbtbx::open("data/101m.cif").unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        let config = CartoonConfig {
+            membrane: Some(Membrane::default()),
+            ..Default::default()
+        };
+        let outlines = project_cartoon(&ac, &config);
+        assert_eq!(outlines.len(), chain_order(&ac).len());
+        let chain_a = outlines.iter().find(|o| o.chain_id == "A").unwrap();
+        assert_eq!(chain_a.shape.0.len(), 1);
+        let atoms = ac.select_by_chain("A").indices().to_vec();
+        for point in projected_ca(&ac, &atoms, &config) {
+            assert!(chain_a.shape.contains(&point));
+        }
+        let svg = ac.flatten_cartoon(&config).to_string();
+        assert!(svg.contains("id=\"membrane\""));
+        assert!(svg.contains("id=\"chain-A\""));
+
+        // split the chain in two domains at residue 70; empty and out-of-range
+        // selections are skipped
+        let (first, second): (Vec<usize>, Vec<usize>) =
+            (0..ac.get_size()).partition(|&i| *ac.get_res_id(i) <= 70);
+        let domains = vec![
+            Domain::new("N", Selection::new(first.clone())),
+            Domain::new("C", Selection::new(second.clone())),
+            Domain::new("empty", Selection::new(vec![])),
+            Domain::new("outside", Selection::new(vec![0, ac.get_size()])),
+        ];
+        assert!(domains[2].chain_id(&ac).is_none());
+        assert!(domains[3].chain_id(&ac).is_none());
+        let config = CartoonConfig {
+            grouping: CartoonGrouping::Domains(domains),
+            ..Default::default()
+        };
+        let outlines = project_cartoon(&ac, &config);
+        assert_eq!(outlines.len(), 2);
+        for (name, atoms) in [("N", &first), ("C", &second)] {
+            let blob = outlines.iter().find(|o| o.label == name).unwrap();
+            assert_eq!(blob.shape.0.len(), 1);
+            for point in projected_ca(&ac, atoms, &config) {
+                assert!(blob.shape.contains(&point));
+            }
+        }
+    }
+
+    #[test]
+    fn test_simplify_ring() {
+        // a square with extra points along its edges
+        let ring: Vec<Coord<f64>> = [
+            (0.0, 0.0),
+            (1.0, 0.0),
+            (2.0, 0.0),
+            (2.0, 1.0),
+            (2.0, 2.0),
+            (1.0, 2.0),
+            (0.0, 2.0),
+            (0.0, 1.0),
+            (0.0, 0.0),
+        ]
+        .map(|(x, y)| Coord { x, y })
+        .to_vec();
+        assert_eq!(simplify_ring(&ring, 0.1).len(), 5);
+    }
+}
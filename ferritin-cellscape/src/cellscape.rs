@@ -1,25 +1,119 @@
 //! Core Cellscape Namespace
 //!
+//! Illustrative outlines in the style of Cellscape's outline mode. Atoms are rotated by a
+//! [`View`], drawn as circles in the page plane and merged into one silhouette per residue
+//! or per chain. Silhouettes are painted back-to-front and shaded by depth so that closer
+//! parts of the structure stand out. The canvas is sized to fit the drawing.
 //!
-//! ** WORK IN PROGRESS **
-//!
+//! ```no_run
+//! use ferritin_cellscape::cellscape::{CellscapeConfig, OutlineLevel, StructureFlatten};
+//! use ferritin_cellscape::view::View;
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> std::io::Result<()> {
+//! let config = CellscapeConfig {
+//!     view: View::principal_axes(&ac).rotate_x(90.0),
+//!     outline: OutlineLevel::Chain,
+//!     ..Default::default()
+//! };
+//! svg::save("outline.svg", &ac.flatten_with(&config))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::cartoon::{project_cartoon, render_cartoon, CartoonConfig};
+use crate::view::View;
 use ferritin_core::AtomCollection;
 use geo::{BooleanOps, Coord, LineString, MultiPolygon, Point, Polygon};
 use std::f64::consts::PI;
-use svg::node::element::Path;
-use svg::Document;
+use svg::node::element::{Element, Path};
+use svg::{Document, Node};
 
 // Traits -------------------------------------------------------------------------------------
 
 /// Flattens 3D atomic structures into a 2D SVG representation.
 pub trait StructureFlatten {
+    /// Flatten with the default [`CellscapeConfig`].
     fn flatten_structure(&self) -> Document;
+    fn flatten_with(&self, config: &CellscapeConfig) -> Document;
+    /// Smoothed blobs per chain or domain, see [`crate::cartoon`].
+    fn flatten_cartoon(&self, config: &CartoonConfig) -> Document;
+}
+
+// Config -------------------------------------------------------------------------------------
+
+/// Which atoms are merged into a single silhouette
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum OutlineLevel {
+    #[default]
+    Residue,
+    Chain,
+}
+
+/// Radius of the circle drawn for each atom
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum AtomRadius {
+    VanDerWaals,
+    /// The same radius in Å for every atom
+    Fixed(f64),
+}
+
+#[derive(Clone, Debug)]
+pub struct CellscapeConfig {
+    pub view: View,
+    pub outline: OutlineLevel,
+    pub radius: AtomRadius,
+    /// Fill colors as hex strings, assigned to chains in order of appearance and cycled
+    pub colors: Vec<String>,
+    pub stroke: String,
+    /// Outline width in Å
+    pub stroke_width: f64,
+    /// How far the most distant silhouette is blended towards `shade_color`; 0 disables
+    /// depth shading
+    pub depth_shading: f64,
+    pub shade_color: String,
+    /// Pixels per Å
+    pub scale: f64,
+    /// Blank space around the drawing in Å
+    pub margin: f64,
+}
+
+impl Default for CellscapeConfig {
+    fn default() -> Self {
+        Self {
+            view: View::identity(),
+            outline: OutlineLevel::Residue,
+            radius: AtomRadius::VanDerWaals,
+            colors: [
+                "#8dd3c7", "#fdb462", "#bebada", "#fb8072", "#80b1d3", "#b3de69", "#fccde5",
+                "#ffffb3",
+            ]
+            .map(String::from)
+            .to_vec(),
+            stroke: "#000000".to_string(),
+            stroke_width: 0.3,
+            depth_shading: 0.5,
+            shade_color: "#000000".to_string(),
+            scale: 5.0,
+            margin: 5.0,
+        }
+    }
+}
+
+/// A silhouette in page coordinates
+#[derive(Clone, Debug)]
+pub struct Outline {
+    pub chain_id: String,
+    /// Residue (`A/42`) or chain (`A`) the silhouette covers
+    pub label: String,
+    pub shape: MultiPolygon<f64>,
+    /// Mean depth of the atoms; larger is closer to the viewer
+    pub depth: f64,
+    pub color: String,
 }
 
 // Helper Functions ---------------------------------------------------------------------------
 
 /// Creates a circular polygon from a center point and radius
-fn create_circle(center: &Point<f64>, radius: f64) -> Polygon<f64> {
+pub(crate) fn create_circle(center: &Point<f64>, radius: f64) -> Polygon<f64> {
     let num_points = 32;
     let coords: Vec<Coord<f64>> = (0..=num_points)
         .map(|i| {
@@ -34,15 +128,6 @@ fn create_circle(center: &Point<f64>, radius: f64) -> Polygon<f64> {
     Polygon::new(LineString(coords), vec![])
 }
 
-/// Creates an SVG path element from a polygon
-fn create_svg_path(polygon: &Polygon<f64>, fill: &str, stroke: &str, stroke_width: f64) -> Path {
-    Path::new()
-        .set("fill", fill)
-        .set("stroke", stroke)
-        .set("stroke-width", stroke_width)
-        .set("d", polygon_to_path_data(polygon))
-}
-
 fn polygon_to_path_data(polygon: &Polygon<f64>) -> String {
     let mut path_data = String::new();
 
@@ -71,47 +156,276 @@ fn polygon_to_path_data(polygon: &Polygon<f64>) -> String {
 
     path_data
 }
-// IMPL---------------------------------------------------------------------------
 
-impl StructureFlatten for AtomCollection {
-    fn flatten_structure(&self) -> Document {
-        let margin = 10.0;
-        let radius = 50.;
-
-        let merged_polygons: Vec<Polygon<f64>> = self
-            .iter_residues_aminoacid()
-            .flat_map(|residue| {
-                // Get atom coordinates and create circles
-                let circles: Vec<Polygon<f64>> = residue
-                    .iter_atoms()
-                    .map(|atm| {
-                        let [x, y, _] = atm.coords;
-                        create_circle(&Point::new(*x as f64, *y as f64), radius)
-                    })
-                    .collect();
-
-                // Skip empty residues
-                if circles.is_empty() {
-                    return vec![];
-                }
-
-                // Merge circles into outline
-                circles
-                    .iter()
-                    .fold(MultiPolygon(vec![circles[0].clone()]), |acc, circle| {
-                        acc.union(&circle.clone())
-                    })
-                    .0
+/// A centered text label
+pub(crate) fn text_label(x: f64, y: f64, content: &str, font_size: f64) -> Element {
+    let mut text = Element::new("text");
+    text.assign("x", x);
+    text.assign("y", y);
+    text.assign("font-size", font_size);
+    text.assign("font-family", "sans-serif");
+    text.assign("text-anchor", "middle");
+    text.append(svg::node::Text::new(content));
+    text
+}
+
+/// Merge shapes pairwise, which keeps the intermediate polygons small.
+pub(crate) fn union_all(mut shapes: Vec<MultiPolygon<f64>>) -> MultiPolygon<f64> {
+    while shapes.len() > 1 {
+        shapes = shapes
+            .chunks(2)
+            .map(|pair| match pair {
+                [a, b] => a.union(b),
+                _ => pair[0].clone(),
             })
             .collect();
+    }
+    shapes.pop().unwrap_or(MultiPolygon(vec![]))
+}
+
+/// Path data for all polygons of a shape
+fn multipolygon_to_path_data(shape: &MultiPolygon<f64>) -> String {
+    shape
+        .0
+        .iter()
+        .map(polygon_to_path_data)
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn parse_hex(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    match hex.len() {
+        6 => Some([channel(0)?, channel(2)?, channel(4)?]),
+        _ => None,
+    }
+}
+
+/// Blend `color` towards `target` by `t`; colors that are not hex are returned unchanged.
+pub(crate) fn mix_colors(color: &str, target: &str, t: f64) -> String {
+    match (parse_hex(color), parse_hex(target)) {
+        (Some(a), Some(b)) => {
+            let t = t.clamp(0.0, 1.0);
+            let [r, g, b] = std::array::from_fn(|i| {
+                (a[i] as f64 + (b[i] as f64 - a[i] as f64) * t).round() as u8
+            });
+            format!("#{:02x}{:02x}{:02x}", r, g, b)
+        }
+        _ => color.to_string(),
+    }
+}
+
+/// Chain IDs in order of first appearance
+pub(crate) fn chain_order(ac: &AtomCollection) -> Vec<String> {
+    let mut chains: Vec<String> = Vec::new();
+    for i in 0..ac.get_size() {
+        let chain = ac.get_chain_id(i);
+        if !chains.contains(chain) {
+            chains.push(chain.clone());
+        }
+    }
+    chains
+}
 
-        let base_document = Document::new().set("width", 300).set("height", 300).set(
-            "viewBox",
-            (-margin, -margin, 300.0 + margin, 300.0 + margin),
-        );
+pub(crate) fn atom_radius(ac: &AtomCollection, atom: usize, radius: AtomRadius) -> f64 {
+    match radius {
+        AtomRadius::VanDerWaals => ac
+            .get_element(atom)
+            .atomic_radius()
+            .van_der_waals
+            .unwrap_or(1.5),
+        AtomRadius::Fixed(r) => r,
+    }
+}
 
-        merged_polygons.iter().fold(base_document, |doc, polygon| {
-            doc.add(create_svg_path(polygon, "blue", "black", 2.0))
+/// Page coordinates of an atom: rotated, with y flipped so that +y points up on the page
+pub(crate) fn project(view: &View, coord: &[f32; 3]) -> [f64; 3] {
+    let [x, y, z] = view.apply(coord);
+    [x, -y, z]
+}
+
+/// Project the amino acids of `ac` into one silhouette per residue or chain, ordered
+/// back-to-front.
+pub fn project_outlines(ac: &AtomCollection, config: &CellscapeConfig) -> Vec<Outline> {
+    let chains = chain_order(ac);
+    let color_of = |chain: &str| {
+        let index = chains.iter().position(|c| c == chain).unwrap_or(0);
+        config
+            .colors
+            .get(index % config.colors.len().max(1))
+            .cloned()
+            .unwrap_or_else(|| "#cccccc".to_string())
+    };
+
+    // (chain, label, atoms) for each silhouette
+    let mut groups: Vec<(String, String, Vec<usize>)> = Vec::new();
+    for residue in ac.iter_residues_aminoacid() {
+        let atoms = residue.start_idx..residue.end_idx;
+        match config.outline {
+            OutlineLevel::Residue => groups.push((
+                residue.chain_id.clone(),
+                format!("{}/{}", residue.chain_id, residue.res_id),
+                atoms.collect(),
+            )),
+            OutlineLevel::Chain => match groups.last_mut() {
+                Some((chain, _, members)) if *chain == residue.chain_id => members.extend(atoms),
+                _ => groups.push((
+                    residue.chain_id.clone(),
+                    residue.chain_id.clone(),
+                    atoms.collect(),
+                )),
+            },
+        }
+    }
+
+    let mut outlines: Vec<Outline> = groups
+        .into_iter()
+        .filter(|(_, _, atoms)| !atoms.is_empty())
+        .map(|(chain_id, label, atoms)| {
+            let mut depth = 0.0;
+            let circles: Vec<MultiPolygon<f64>> = atoms
+                .iter()
+                .map(|&i| {
+                    let [x, y, z] = project(&config.view, ac.get_coord(i));
+                    depth += z;
+                    let radius = atom_radius(ac, i, config.radius);
+                    MultiPolygon(vec![create_circle(&Point::new(x, y), radius)])
+                })
+                .collect();
+            Outline {
+                color: color_of(&chain_id),
+                chain_id,
+                label,
+                shape: union_all(circles),
+                depth: depth / atoms.len() as f64,
+            }
+        })
+        .collect();
+    outlines.sort_by(|a, b| a.depth.total_cmp(&b.depth));
+    outlines
+}
+
+/// Paint silhouettes in order with depth shading on a canvas that fits them.
+pub fn render_outlines(outlines: &[Outline], config: &CellscapeConfig) -> Document {
+    let (min, max) = shape_bounds(outlines.iter().map(|o| &o.shape));
+    let depths = depth_range(outlines);
+    outlines
+        .iter()
+        .fold(fitted_document(min, max, config), |doc, outline| {
+            doc.add(outline_path(outline, depths, config))
         })
+}
+
+/// Depth of the closest and the farthest silhouette
+pub(crate) fn depth_range(outlines: &[Outline]) -> (f64, f64) {
+    outlines
+        .iter()
+        .fold((f64::MIN, f64::MAX), |(near, far), o| {
+            (near.max(o.depth), far.min(o.depth))
+        })
+}
+
+/// A filled, stroked path for one silhouette, shaded by its place within `depths`.
+pub(crate) fn outline_path(
+    outline: &Outline,
+    (near, far): (f64, f64),
+    config: &CellscapeConfig,
+) -> Path {
+    // 0 for the closest silhouette, 1 for the farthest
+    let distance = if near > far {
+        (near - outline.depth) / (near - far)
+    } else {
+        0.0
+    };
+    let fill = mix_colors(
+        &outline.color,
+        &config.shade_color,
+        config.depth_shading * distance,
+    );
+    Path::new()
+        .set("fill", fill)
+        .set("stroke", config.stroke.as_str())
+        .set("stroke-width", config.stroke_width)
+        .set("stroke-linejoin", "round")
+        .set("class", outline.chain_id.as_str())
+        .set("d", multipolygon_to_path_data(&outline.shape))
+}
+
+/// Smallest and largest page coordinates covered by `shapes`; zero when there are none.
+pub(crate) fn shape_bounds<'a>(
+    shapes: impl Iterator<Item = &'a MultiPolygon<f64>>,
+) -> ([f64; 2], [f64; 2]) {
+    let (mut min, mut max) = ([f64::MAX; 2], [f64::MIN; 2]);
+    for polygon in shapes.flat_map(|shape| shape.0.iter()) {
+        for coord in polygon.exterior().coords() {
+            min = [min[0].min(coord.x), min[1].min(coord.y)];
+            max = [max[0].max(coord.x), max[1].max(coord.y)];
+        }
+    }
+    if min[0] > max[0] {
+        return ([0.0; 2], [0.0; 2]);
+    }
+    (min, max)
+}
+
+/// An empty document showing `min..max` plus the configured margin.
+pub(crate) fn fitted_document(min: [f64; 2], max: [f64; 2], config: &CellscapeConfig) -> Document {
+    let margin = config.margin;
+    let (width, height) = (
+        max[0] - min[0] + 2.0 * margin,
+        max[1] - min[1] + 2.0 * margin,
+    );
+    Document::new()
+        .set("width", width * config.scale)
+        .set("height", height * config.scale)
+        .set("viewBox", (min[0] - margin, min[1] - margin, width, height))
+}
+
+// IMPL---------------------------------------------------------------------------
+
+impl StructureFlatten for AtomCollection {
+    fn flatten_structure(&self) -> Document {
+        self.flatten_with(&CellscapeConfig::default())
+    }
+    fn flatten_with(&self, config: &CellscapeConfig) -> Document {
+        render_outlines(&project_outlines(self, config), config)
+    }
+    fn flatten_cartoon(&self, config: &CartoonConfig) -> Document {
+        render_cartoon(&project_cartoon(self, config), config)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_flatten_outlines() {
+        let (pdb, _) = pdbtbx::open("data/101m.cif").unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let residues = ac.iter_residues_aminoacid().count();
+
+        let config = CellscapeConfig {
+            view: View::principal_axes(&ac),
+            ..Default::default()
+        };
+        let outlines = project_outlines(&ac, &config);
+        assert_eq!(outlines.len(), residues);
+        assert!(outlines.windows(2).all(|w| w[0].depth <= w[1].depth));
+        let svg = ac.flatten_with(&config).to_string();
+        assert_eq!(svg.matches("<path").count(), residues);
+
+        let chains = CellscapeConfig {
+            outline: OutlineLevel::Chain,
+            ..config
+        };
+        assert_eq!(project_outlines(&ac, &chains).len(), 1);
+    }
+
+    #[test]
+    fn test_mix_colors() {
+        assert_eq!(mix_colors("#ff0000", "#000000", 0.5), "#800000");
+        assert_eq!(mix_colors("blue", "#000000", 0.5), "blue");
     }
 }
@@ -5,4 +5,8 @@
 //! we aim to substitute [ferritin-core] as the backend.
 //!
 
+pub mod cartoon;
 pub mod cellscape;
+pub mod ligplot;
+pub mod topology;
+pub mod view;
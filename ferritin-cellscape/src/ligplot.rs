@@ -0,0 +1,454 @@
+//! Ligplot
+//!
+//! Two-dimensional ligand interaction diagrams in the style of LigPlot. The ligand is drawn
+//! from its bonds, flattened onto its best-fitting plane, and surrounded by the residues it
+//! touches. Hydrogen bonds (N/O pairs within `hbond_distance`) are drawn as dashed lines
+//! labelled with their length; residues that only make hydrophobic carbon-carbon contacts
+//! are drawn as LigPlot's red "eyelashes" facing the ligand. Residues are placed in the
+//! direction they lie from the ligand in 3D and spread out so their labels do not overlap.
+//!
+//! Bonds within the ligand are inferred from covalent radii, as the bond tables in
+//! ferritin-core only cover the canonical amino acids.
+//!
+//! ```no_run
+//! use ferritin_cellscape::ligplot::{ligplot, LigplotConfig};
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> std::io::Result<()> {
+//! let heme = ac.select_by_residue("HEM");
+//! svg::save("heme.svg", &ligplot(&ac, &heme, &LigplotConfig::default()))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::cellscape::text_label;
+use crate::view::View;
+use ferritin_core::{AtomCollection, Selection};
+use pdbtbx::Element;
+use std::collections::{HashMap, HashSet};
+use std::f64::consts::{FRAC_PI_2, PI, TAU};
+use svg::node::element::{Circle, Group, Line, Path, Rectangle};
+use svg::{Document, Node};
+
+/// Slack added to the sum of covalent radii when inferring ligand bonds
+const BOND_TOLERANCE: f64 = 0.45;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum InteractionKind {
+    HydrogenBond,
+    Hydrophobic,
+}
+
+/// A contact between a ligand atom and a residue atom
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Interaction {
+    pub kind: InteractionKind,
+    pub ligand_atom: usize,
+    pub residue_atom: usize,
+    pub distance: f32,
+}
+
+/// A residue in contact with the ligand
+#[derive(Clone, Debug)]
+pub struct ContactResidue {
+    pub chain_id: String,
+    pub res_id: i32,
+    pub res_name: String,
+    pub interactions: Vec<Interaction>,
+}
+
+impl ContactResidue {
+    pub fn has_hbond(&self) -> bool {
+        self.interactions
+            .iter()
+            .any(|i| i.kind == InteractionKind::HydrogenBond)
+    }
+}
+
+#[derive(Clone, Debug)]
+pub struct LigplotConfig {
+    /// Largest donor-acceptor distance in Å counted as a hydrogen bond
+    pub hbond_distance: f32,
+    /// Largest carbon-carbon distance in Å counted as a hydrophobic contact
+    pub hydrophobic_distance: f32,
+    pub include_water: bool,
+    /// Pixels per Å
+    pub scale: f64,
+    /// Gap in Å between the ligand and the ring of residues
+    pub residue_distance: f64,
+    pub font_size: f64,
+    pub bond_color: String,
+    pub hbond_color: String,
+    pub hydrophobic_color: String,
+    pub margin: f64,
+}
+
+impl Default for LigplotConfig {
+    fn default() -> Self {
+        Self {
+            hbond_distance: 3.5,
+            hydrophobic_distance: 4.0,
+            include_water: false,
+            scale: 40.0,
+            residue_distance: 3.0,
+            font_size: 12.0,
+            bond_color: "#333333".to_string(),
+            hbond_color: "#2ca02c".to_string(),
+            hydrophobic_color: "#d62728".to_string(),
+            margin: 40.0,
+        }
+    }
+}
+
+/// Residues outside `ligand` that hydrogen bond to it or touch it with carbon atoms, in
+/// the order they appear in `ac`.
+pub fn find_contacts(
+    ac: &AtomCollection,
+    ligand: &Selection,
+    config: &LigplotConfig,
+) -> Vec<ContactResidue> {
+    let ligand_atoms: HashSet<usize> = ligand.indices().iter().copied().collect();
+    let mut residues: Vec<ContactResidue> = Vec::new();
+    let mut index_of: HashMap<(String, i32), usize> = HashMap::new();
+    for j in 0..ac.get_size() {
+        if ligand_atoms.contains(&j) || (!config.include_water && is_water(ac.get_res_name(j))) {
+            continue;
+        }
+        for &i in ligand.indices() {
+            let distance = atom_distance(ac, i, j);
+            let kind = match (ac.get_element(i), ac.get_element(j)) {
+                (a, b) if is_polar(a) && is_polar(b) && distance <= config.hbond_distance => {
+                    InteractionKind::HydrogenBond
+                }
+                (Element::C, Element::C) if distance <= config.hydrophobic_distance => {
+                    InteractionKind::Hydrophobic
+                }
+                _ => continue,
+            };
+            let key = (ac.get_chain_id(j).clone(), *ac.get_res_id(j));
+            let index = *index_of.entry(key).or_insert_with(|| {
+                residues.push(ContactResidue {
+                    chain_id: ac.get_chain_id(j).clone(),
+                    res_id: *ac.get_res_id(j),
+                    res_name: ac.get_res_name(j).clone(),
+                    interactions: Vec::new(),
+                });
+                residues.len() - 1
+            });
+            residues[index].interactions.push(Interaction {
+                kind,
+                ligand_atom: i,
+                residue_atom: j,
+                distance,
+            });
+        }
+    }
+    residues
+}
+
+/// Draw the ligand selected by `ligand` and the residues it interacts with.
+pub fn ligplot(ac: &AtomCollection, ligand: &Selection, config: &LigplotConfig) -> Document {
+    let atoms = ligand.indices();
+    let contacts = find_contacts(ac, ligand, config);
+    let bonds = ligand_bonds(ac, atoms);
+
+    // flatten the ligand onto its principal plane, in page units
+    let view = View::principal_axes(&ac.subset(ligand));
+    let to_page = |coord: &[f32; 3]| {
+        let [x, y, _] = view.apply(coord);
+        (x * config.scale, -y * config.scale)
+    };
+    let position: HashMap<usize, (f64, f64)> = atoms
+        .iter()
+        .map(|&i| (i, to_page(ac.get_coord(i))))
+        .collect();
+    let n = atoms.len().max(1) as f64;
+    let centroid = position
+        .values()
+        .fold((0.0, 0.0), |(x, y), p| (x + p.0 / n, y + p.1 / n));
+    let ligand_radius = position
+        .values()
+        .map(|p| (p.0 - centroid.0).hypot(p.1 - centroid.1))
+        .fold(0.0, f64::max);
+
+    // residues sit on a ring around the ligand, in the direction of their closest contact
+    let ring = ligand_radius + config.residue_distance * config.scale;
+    let mut angles: Vec<(usize, f64)> = contacts
+        .iter()
+        .enumerate()
+        .map(|(r, residue)| {
+            let closest = residue
+                .interactions
+                .iter()
+                .min_by(|a, b| a.distance.total_cmp(&b.distance))
+                .unwrap();
+            let (x, y) = to_page(ac.get_coord(closest.residue_atom));
+            (r, (y - centroid.1).atan2(x - centroid.0))
+        })
+        .collect();
+    spread_angles(&mut angles, 4.0 * config.font_size / ring);
+    let placed: HashMap<usize, (f64, f64, f64)> = angles
+        .into_iter()
+        .map(|(r, angle)| {
+            let (x, y) = (
+                centroid.0 + ring * angle.cos(),
+                centroid.1 + ring * angle.sin(),
+            );
+            (r, (x, y, angle))
+        })
+        .collect();
+
+    let multiple_chains = contacts.iter().any(|c| c.chain_id != contacts[0].chain_id);
+    let mut interactions = Group::new().set("id", "interactions");
+    let mut residues = Group::new().set("id", "residues");
+    for (r, residue) in contacts.iter().enumerate() {
+        let (x, y, angle) = placed[&r];
+        let label = if multiple_chains {
+            format!(
+                "{} {} {}",
+                residue.res_name, residue.res_id, residue.chain_id
+            )
+        } else {
+            format!("{} {}", residue.res_name, residue.res_id)
+        };
+        if residue.has_hbond() {
+            for hbond in residue
+                .interactions
+                .iter()
+                .filter(|i| i.kind == InteractionKind::HydrogenBond)
+            {
+                let from = position[&hbond.ligand_atom];
+                interactions = interactions
+                    .add(
+                        Line::new()
+                            .set("x1", from.0)
+                            .set("y1", from.1)
+                            .set("x2", x)
+                            .set("y2", y)
+                            .set("stroke", config.hbond_color.as_str())
+                            .set("stroke-width", 1.5)
+                            .set("stroke-dasharray", "4 3"),
+                    )
+                    .add(text_label(
+                        (from.0 + x) / 2.0,
+                        (from.1 + y) / 2.0 - 3.0,
+                        &format!("{:.1}", hbond.distance),
+                        config.font_size * 0.8,
+                    ));
+            }
+            let width = config.font_size * 0.6 * label.len() as f64 + 8.0;
+            residues = residues
+                .add(
+                    Rectangle::new()
+                        .set("x", x - width / 2.0)
+                        .set("y", y - config.font_size)
+                        .set("width", width)
+                        .set("height", config.font_size * 1.6)
+                        .set("rx", 4)
+                        .set("fill", "#ffffff")
+                        .set("stroke", config.hbond_color.as_str()),
+                )
+                .add(text_label(
+                    x,
+                    y + config.font_size * 0.3,
+                    &label,
+                    config.font_size,
+                ));
+        } else {
+            // the eyelash opens towards the ligand, with the label on the far side
+            let facing = angle + PI;
+            residues = residues.add(eyelash(x, y, facing, config)).add(text_label(
+                x + 2.2 * config.font_size * angle.cos(),
+                y + 2.2 * config.font_size * angle.sin() + config.font_size * 0.3,
+                &label,
+                config.font_size,
+            ));
+        }
+    }
+
+    let mut ligand_group = Group::new()
+        .set("id", "ligand")
+        .set("stroke", config.bond_color.as_str())
+        .set("stroke-width", 2);
+    for (a, b) in &bonds {
+        let (p, q) = (position[a], position[b]);
+        ligand_group = ligand_group.add(
+            Line::new()
+                .set("x1", p.0)
+                .set("y1", p.1)
+                .set("x2", q.0)
+                .set("y2", q.1),
+        );
+    }
+    let mut atom_labels = Group::new().set("id", "atoms");
+    for &i in atoms {
+        let element = ac.get_element(i);
+        if *element == Element::C {
+            continue;
+        }
+        let (x, y) = position[&i];
+        atom_labels = atom_labels
+            .add(
+                Circle::new()
+                    .set("cx", x)
+                    .set("cy", y)
+                    .set("r", config.font_size * 0.6)
+                    .set("fill", "#ffffff"),
+            )
+            .add({
+                let mut label = text_label(
+                    x,
+                    y + config.font_size * 0.35,
+                    element.symbol(),
+                    config.font_size,
+                );
+                label.assign("fill", element_color(element));
+                label
+            });
+    }
+
+    // fit the canvas to the ring of residues and their labels
+    let extent = ring + 4.0 * config.font_size + config.margin;
+    let (min_x, min_y) = (centroid.0 - extent, centroid.1 - extent);
+    Document::new()
+        .set("width", 2.0 * extent)
+        .set("height", 2.0 * extent)
+        .set("viewBox", (min_x, min_y, 2.0 * extent, 2.0 * extent))
+        .add(interactions)
+        .add(ligand_group)
+        .add(atom_labels)
+        .add(residues)
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+fn is_water(res_name: &str) -> bool {
+    matches!(res_name, "HOH" | "WAT" | "DOD")
+}
+
+fn is_polar(element: &Element) -> bool {
+    matches!(element, Element::N | Element::O)
+}
+
+fn element_color(element: &Element) -> &'static str {
+    match element {
+        Element::N => "#1f4fd8",
+        Element::O => "#d62728",
+        Element::S => "#b8a000",
+        Element::P => "#ff8000",
+        _ => "#333333",
+    }
+}
+
+fn atom_distance(ac: &AtomCollection, i: usize, j: usize) -> f32 {
+    let (a, b) = (ac.get_coord(i), ac.get_coord(j));
+    ((a[0] - b[0]).powi(2) + (a[1] - b[1]).powi(2) + (a[2] - b[2]).powi(2)).sqrt()
+}
+
+/// Pairs of ligand atoms close enough to be covalently bonded
+fn ligand_bonds(ac: &AtomCollection, atoms: &[usize]) -> Vec<(usize, usize)> {
+    let radius = |i: usize| ac.get_element(i).atomic_radius().covalent_single;
+    let mut bonds = Vec::new();
+    for (k, &i) in atoms.iter().enumerate() {
+        for &j in &atoms[k + 1..] {
+            let limit = radius(i) + radius(j) + BOND_TOLERANCE;
+            if (atom_distance(ac, i, j) as f64) <= limit {
+                bonds.push((i, j));
+            }
+        }
+    }
+    bonds
+}
+
+/// Push angles apart until neighbours are at least `separation` radians apart, keeping
+/// their order around the circle.
+fn spread_angles(angles: &mut [(usize, f64)], separation: f64) {
+    if angles.len() < 2 {
+        return;
+    }
+    let separation = separation.min(TAU / angles.len() as f64);
+    angles.sort_by(|a, b| a.1.total_cmp(&b.1));
+    for _ in 0..100 {
+        let mut moved = false;
+        for k in 0..angles.len() {
+            let next = (k + 1) % angles.len();
+            let mut gap = angles[next].1 - angles[k].1;
+            if next == 0 {
+                gap += TAU;
+            }
+            if gap < separation {
+                let push = (separation - gap) / 2.0;
+                angles[k].1 -= push;
+                angles[next].1 += push;
+                moved = true;
+            }
+        }
+        if !moved {
+            break;
+        }
+    }
+}
+
+/// LigPlot's hydrophobic marker: an arc with spokes, opening in the direction `facing`
+fn eyelash(x: f64, y: f64, facing: f64, config: &LigplotConfig) -> Group {
+    let radius = config.font_size;
+    let point = |angle: f64, r: f64| (x + r * angle.cos(), y + r * angle.sin());
+    let (start, end) = (
+        point(facing - FRAC_PI_2, radius),
+        point(facing + FRAC_PI_2, radius),
+    );
+    let mut group = Group::new()
+        .set("stroke", config.hydrophobic_color.as_str())
+        .set("stroke-width", 1.5)
+        .set("fill", "none")
+        .add(Path::new().set(
+            "d",
+            format!(
+                "M {} {} A {} {} 0 0 1 {} {}",
+                start.0, start.1, radius, radius, end.0, end.1
+            ),
+        ));
+    for k in 0..5 {
+        let angle = facing - FRAC_PI_2 + PI * (k as f64 + 0.5) / 5.0;
+        let (inner, outer) = (point(angle, radius), point(angle, radius * 1.5));
+        group = group.add(
+            Line::new()
+                .set("x1", inner.0)
+                .set("y1", inner.1)
+                .set("x2", outer.0)
+                .set("y2", outer.1),
+        );
+    }
+    group
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ligplot_heme() {
+        let (pdb, _) = pdbtbx::open("data/101m.cif").unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let heme = ac.select_by_residue("HEM");
+        assert!(ligand_bonds(&ac, heme.indices()).len() >= heme.indices().len());
+
+        // the proximal histidine packs against the porphyrin
+        let contacts = find_contacts(&ac, &heme, &LigplotConfig::default());
+        assert!(contacts
+            .iter()
+            .any(|c| c.res_name == "HIS" && c.res_id == 93));
+        assert!(contacts.iter().all(|c| c.res_name != "HOH"));
+
+        let svg = ligplot(&ac, &heme, &LigplotConfig::default()).to_string();
+        assert!(svg.contains("HIS 93"));
+    }
+
+    #[test]
+    fn test_spread_angles() {
+        let mut angles = vec![(0, 0.0), (1, 0.05), (2, 0.1), (3, 3.0)];
+        spread_angles(&mut angles, 0.5);
+        angles.sort_by(|a, b| a.1.total_cmp(&b.1));
+        for pair in angles.windows(2) {
+            assert!(pair[1].1 - pair[0].1 >= 0.5 - 1e-9);
+        }
+    }
+}
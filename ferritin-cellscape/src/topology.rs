@@ -0,0 +1,447 @@
+//! Topology
+//!
+//! Two-dimensional topology diagrams in the style of TopDraw and Pro-origami. Helices and
+//! strands are taken from the DSSP-style assignment in ferritin-core; strands that share
+//! beta bridges are grouped into sheets and laid out side by side, ordered by their pairing,
+//! with antiparallel neighbours pointing in opposite directions. Helices are drawn as
+//! cylinders, strands as arrows, and consecutive elements are joined by loops running from
+//! the C-terminal end of one element to the N-terminal end of the next. Every element is
+//! labelled with its residue range.
+//!
+//! ```no_run
+//! use ferritin_cellscape::topology::{topology_diagram, TopologyConfig};
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: AtomCollection) -> std::io::Result<()> {
+//! svg::save("topology.svg", &topology_diagram(&ac, &TopologyConfig::default()))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::cellscape::text_label;
+use ferritin_core::{AtomCollection, SecondaryStructure};
+use std::collections::HashMap;
+use svg::node::element::{Group, Path, Polygon, Rectangle};
+use svg::Document;
+
+/// Kind of secondary structure element
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum ElementKind {
+    Helix,
+    Strand,
+}
+
+/// A helix or strand placed on the diagram
+#[derive(Clone, Debug)]
+pub struct TopologyElement {
+    pub kind: ElementKind,
+    pub chain_id: String,
+    /// Residue numbers of the first and last residue
+    pub start_res: i32,
+    pub end_res: i32,
+    /// Number of residues in the element
+    pub length: usize,
+    /// Sheet the strand belongs to, numbered in order of appearance
+    pub sheet: Option<usize>,
+    /// Whether the element runs N to C up the page
+    pub up: bool,
+    /// Horizontal position in columns
+    pub column: f64,
+}
+
+#[derive(Clone, Debug)]
+pub struct TopologyConfig {
+    pub helix_color: String,
+    pub strand_color: String,
+    pub loop_color: String,
+    pub stroke: String,
+    pub stroke_width: f64,
+    pub font_size: f64,
+    /// Horizontal distance between neighbouring elements in pixels
+    pub column_spacing: f64,
+    /// Element height per residue in pixels, clamped to `min_height..=max_height`
+    pub residue_height: f64,
+    pub min_height: f64,
+    pub max_height: f64,
+    pub strand_width: f64,
+    pub helix_width: f64,
+    /// How far loops bulge out beyond the element ends
+    pub loop_height: f64,
+    pub margin: f64,
+}
+
+impl Default for TopologyConfig {
+    fn default() -> Self {
+        Self {
+            helix_color: "#e8615a".to_string(),
+            strand_color: "#f5c842".to_string(),
+            loop_color: "#555555".to_string(),
+            stroke: "#333333".to_string(),
+            stroke_width: 1.5,
+            font_size: 10.0,
+            column_spacing: 40.0,
+            residue_height: 8.0,
+            min_height: 30.0,
+            max_height: 120.0,
+            strand_width: 20.0,
+            helix_width: 24.0,
+            loop_height: 25.0,
+            margin: 20.0,
+        }
+    }
+}
+
+/// Helices and strands of `ac` in sequence order, with sheets, directions and columns
+/// assigned.
+pub fn topology_elements(ac: &AtomCollection) -> Vec<TopologyElement> {
+    let residues: Vec<(String, i32)> = ac
+        .iter_residues_aminoacid()
+        .map(|residue| (residue.chain_id.clone(), residue.res_id))
+        .collect();
+    let dssp = ac.dssp();
+
+    // runs of helix or strand within a chain, as inclusive residue index ranges
+    let mut runs: Vec<(ElementKind, usize, usize)> = Vec::new();
+    for (k, s) in dssp.secondary_structure.iter().enumerate() {
+        let kind = match s {
+            SecondaryStructure::Helix => ElementKind::Helix,
+            SecondaryStructure::Strand => ElementKind::Strand,
+            SecondaryStructure::Coil => continue,
+        };
+        match runs.last_mut() {
+            Some((last_kind, _, end))
+                if *last_kind == kind && *end + 1 == k && residues[*end].0 == residues[k].0 =>
+            {
+                *end = k
+            }
+            _ => runs.push((kind, k, k)),
+        }
+    }
+    let mut elements: Vec<TopologyElement> = runs
+        .iter()
+        .map(|&(kind, first, last)| TopologyElement {
+            kind,
+            chain_id: residues[first].0.clone(),
+            start_res: residues[first].1,
+            end_res: residues[last].1,
+            length: last - first + 1,
+            sheet: None,
+            up: true,
+            column: 0.0,
+        })
+        .collect();
+
+    // strand pairing: (bridges, parallel bridges) between pairs of elements
+    let mut element_of = vec![None; residues.len()];
+    for (e, &(kind, first, last)) in runs.iter().enumerate() {
+        if kind == ElementKind::Strand {
+            element_of[first..=last].fill(Some(e));
+        }
+    }
+    let mut pairs: HashMap<(usize, usize), (usize, usize)> = HashMap::new();
+    for bridge in &dssp.beta_bridges {
+        if let (Some(a), Some(b)) = (element_of[bridge.i], element_of[bridge.j]) {
+            if a != b {
+                let counts = pairs.entry((a.min(b), a.max(b))).or_default();
+                counts.0 += 1;
+                counts.1 += bridge.parallel as usize;
+            }
+        }
+    }
+    // partners of each element, most bridges first
+    let mut partners: Vec<Vec<(usize, usize)>> = vec![Vec::new(); elements.len()];
+    for (&(a, b), &(count, _)) in &pairs {
+        partners[a].push((b, count));
+        partners[b].push((a, count));
+    }
+    for list in &mut partners {
+        list.sort_by(|x, y| y.1.cmp(&x.1).then(x.0.cmp(&y.0)));
+    }
+
+    // sheets are connected groups of paired strands, ordered by walking from an edge strand
+    let mut sheets: Vec<Vec<usize>> = Vec::new();
+    for e in 0..elements.len() {
+        if elements[e].kind != ElementKind::Strand || elements[e].sheet.is_some() {
+            continue;
+        }
+        let mut members = vec![e];
+        let mut k = 0;
+        while k < members.len() {
+            for &(partner, _) in &partners[members[k]] {
+                if !members.contains(&partner) {
+                    members.push(partner);
+                }
+            }
+            k += 1;
+        }
+        members.sort();
+        let start = members
+            .iter()
+            .copied()
+            .find(|&m| partners[m].len() <= 1)
+            .unwrap_or(members[0]);
+        let mut order = vec![start];
+        while order.len() < members.len() {
+            let current = *order.last().unwrap();
+            let next = partners[current]
+                .iter()
+                .map(|&(partner, _)| partner)
+                .find(|partner| !order.contains(partner))
+                .or_else(|| members.iter().copied().find(|m| !order.contains(m)))
+                .unwrap();
+            order.push(next);
+        }
+        for &m in &order {
+            elements[m].sheet = Some(sheets.len());
+        }
+        sheets.push(order);
+    }
+
+    // strand directions follow the pairing within each sheet
+    for order in &sheets {
+        for pair in order.windows(2) {
+            let (previous, current) = (pair[0], pair[1]);
+            let key = (previous.min(current), previous.max(current));
+            elements[current].up = match pairs.get(&key) {
+                Some(&(count, parallel)) => elements[previous].up == (2 * parallel > count),
+                None => true,
+            };
+        }
+    }
+    // helices point opposite to the element before them, keeping loops short
+    for e in 0..elements.len() {
+        if elements[e].kind == ElementKind::Helix {
+            elements[e].up = match e.checked_sub(1) {
+                Some(p) if elements[p].chain_id == elements[e].chain_id => !elements[p].up,
+                _ => true,
+            };
+        }
+    }
+
+    // columns: each sheet is a block of neighbouring strands, helices stand on their own
+    let mut column = 0.0;
+    let mut placed = vec![false; sheets.len()];
+    let sheet_of: Vec<Option<usize>> = elements.iter().map(|e| e.sheet).collect();
+    for (e, sheet) in sheet_of.into_iter().enumerate() {
+        match sheet {
+            Some(sheet) if !placed[sheet] => {
+                placed[sheet] = true;
+                for (offset, &m) in sheets[sheet].iter().enumerate() {
+                    elements[m].column = column + offset as f64;
+                }
+                column += sheets[sheet].len() as f64 + 0.5;
+            }
+            Some(_) => {}
+            None => {
+                elements[e].column = column;
+                column += 1.5;
+            }
+        }
+    }
+    elements
+}
+
+/// Draw a topology diagram of the helices and strands in `ac`.
+pub fn topology_diagram(ac: &AtomCollection, config: &TopologyConfig) -> Document {
+    render_topology(&topology_elements(ac), config)
+}
+
+/// Draw laid-out elements with their connecting loops, termini and residue ranges.
+pub fn render_topology(elements: &[TopologyElement], config: &TopologyConfig) -> Document {
+    let columns = elements.iter().map(|e| e.column).fold(0.0, f64::max);
+    let width = columns * config.column_spacing + 2.0 * config.margin + config.helix_width;
+    let baseline = config.margin + 2.0 * config.loop_height + config.max_height / 2.0;
+    let height = baseline
+        + config.max_height / 2.0
+        + 2.0 * config.loop_height
+        + 3.0 * config.font_size
+        + config.margin;
+
+    let x_of = |e: &TopologyElement| {
+        config.margin + config.helix_width / 2.0 + e.column * config.column_spacing
+    };
+    let half_height = |e: &TopologyElement| {
+        (e.length as f64 * config.residue_height).clamp(config.min_height, config.max_height) / 2.0
+    };
+    // (x, y) of the N- and C-terminal ends
+    let ends = |e: &TopologyElement| {
+        let (top, bottom) = (baseline - half_height(e), baseline + half_height(e));
+        if e.up {
+            ((x_of(e), bottom), (x_of(e), top))
+        } else {
+            ((x_of(e), top), (x_of(e), bottom))
+        }
+    };
+
+    // loops are drawn first so elements cover their ends
+    let mut loops = Group::new()
+        .set("id", "loops")
+        .set("fill", "none")
+        .set("stroke", config.loop_color.as_str())
+        .set("stroke-width", config.stroke_width);
+    let mut termini = Group::new().set("id", "termini");
+    let chains = elements.iter().fold(Vec::<&str>::new(), |mut chains, e| {
+        if !chains.contains(&e.chain_id.as_str()) {
+            chains.push(&e.chain_id);
+        }
+        chains
+    });
+    for chain in &chains {
+        let in_chain: Vec<&TopologyElement> =
+            elements.iter().filter(|e| e.chain_id == *chain).collect();
+        for pair in in_chain.windows(2) {
+            let (from, to) = (ends(pair[0]).1, ends(pair[1]).0);
+            let spread = ((pair[1].column - pair[0].column).abs().min(4.0) * 0.25 + 1.0)
+                * config.loop_height;
+            // leave and enter each element along its axis
+            let out = if pair[0].up { -spread } else { spread };
+            let into = if pair[1].up { spread } else { -spread };
+            loops = loops.add(Path::new().set(
+                "d",
+                format!(
+                    "M {} {} C {} {}, {} {}, {} {}",
+                    from.0,
+                    from.1,
+                    from.0,
+                    from.1 + out,
+                    to.0,
+                    to.1 + into,
+                    to.0,
+                    to.1
+                ),
+            ));
+        }
+        let suffix = if chains.len() > 1 {
+            format!(" ({})", chain)
+        } else {
+            String::new()
+        };
+        let (first, last) = (in_chain[0], in_chain[in_chain.len() - 1]);
+        for (label, (x, y), pointing_up) in [
+            ("N", ends(first).0, !first.up),
+            ("C", ends(last).1, last.up),
+        ] {
+            let y = if pointing_up {
+                y - config.font_size
+            } else {
+                y + 2.0 * config.font_size
+            };
+            termini = termini.add(text_label(
+                x,
+                y,
+                &format!("{}{}", label, suffix),
+                config.font_size * 1.2,
+            ));
+        }
+    }
+
+    let mut shapes = Group::new()
+        .set("id", "elements")
+        .set("stroke", config.stroke.as_str())
+        .set("stroke-width", config.stroke_width)
+        .set("stroke-linejoin", "round");
+    let mut labels = Group::new().set("id", "labels");
+    let label_row = baseline + config.max_height / 2.0 + 2.0 * config.loop_height;
+    for (index, e) in elements.iter().enumerate() {
+        let (x, h) = (x_of(e), half_height(e));
+        shapes = match e.kind {
+            ElementKind::Helix => shapes.add(
+                Rectangle::new()
+                    .set("x", x - config.helix_width / 2.0)
+                    .set("y", baseline - h)
+                    .set("width", config.helix_width)
+                    .set("height", 2.0 * h)
+                    .set("rx", config.helix_width / 2.0)
+                    .set("fill", config.helix_color.as_str()),
+            ),
+            ElementKind::Strand => shapes.add(
+                Polygon::new()
+                    .set(
+                        "points",
+                        strand_arrow(x, baseline, h, e.up, config.strand_width),
+                    )
+                    .set("fill", config.strand_color.as_str()),
+            ),
+        };
+        // alternate rows so labels of neighbouring columns do not overlap
+        let row = label_row + (index % 2) as f64 * 1.2 * config.font_size;
+        labels = labels.add(text_label(
+            x,
+            row,
+            &format!("{}-{}", e.start_res, e.end_res),
+            config.font_size,
+        ));
+    }
+
+    Document::new()
+        .set("width", width)
+        .set("height", height)
+        .set("viewBox", (0.0, 0.0, width, height))
+        .add(loops)
+        .add(shapes)
+        .add(termini)
+        .add(labels)
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+/// Polygon points of an arrow centered on `(x, y)` with half-length `h`
+fn strand_arrow(x: f64, y: f64, h: f64, up: bool, width: f64) -> String {
+    let (body, head) = (width / 2.0, width * 0.8);
+    let head_length = width.min(h);
+    // build pointing up, then mirror vertically for downward strands
+    let sign = if up { 1.0 } else { -1.0 };
+    let points = [
+        (-body, h),
+        (-body, -h + head_length),
+        (-head, -h + head_length),
+        (0.0, -h),
+        (head, -h + head_length),
+        (body, -h + head_length),
+        (body, h),
+    ];
+    points
+        .iter()
+        .map(|(dx, dy)| format!("{},{}", x + dx, y + sign * dy))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_topology_diagram() {
+        // FKBP12 (chain A of 1fap) is built around a five-stranded antiparallel sheet
+        let (prot_file, _temp) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let chain_a: AtomCollection = ac
+            .iter_residues_aminoacid()
+            .filter(|residue| residue.chain_id == "A")
+            .collect();
+
+        let elements = topology_elements(&chain_a);
+        let strands: Vec<&TopologyElement> = elements
+            .iter()
+            .filter(|e| e.kind == ElementKind::Strand)
+            .collect();
+        assert!(strands.len() >= 4);
+        let largest_sheet = strands
+            .iter()
+            .filter_map(|e| e.sheet)
+            .fold(HashMap::new(), |mut sizes, sheet| {
+                *sizes.entry(sheet).or_insert(0) += 1;
+                sizes
+            })
+            .into_values()
+            .max()
+            .unwrap();
+        assert!(largest_sheet >= 4);
+        assert!(elements.windows(2).all(|w| w[0].start_res < w[1].start_res));
+
+        let svg = render_topology(&elements, &TopologyConfig::default()).to_string();
+        assert_eq!(svg.matches("<polygon").count(), strands.len());
+        assert!(svg.contains(">N<") && svg.contains(">C<"));
+    }
+}
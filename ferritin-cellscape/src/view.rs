@@ -0,0 +1,182 @@
+//! View
+//!
+//! Orientation of a structure before it is projected onto the page. Projection drops the
+//! rotated z coordinate, so the viewer looks down -z: larger z is closer to the viewer.
+//!
+use ferritin_core::AtomCollection;
+
+/// A rotation applied to atom coordinates before projection.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct View {
+    /// Row-major rotation matrix
+    rotation: [[f64; 3]; 3],
+    /// Point the rotation is applied around
+    center: [f64; 3],
+}
+
+impl Default for View {
+    fn default() -> Self {
+        View::identity()
+    }
+}
+
+impl View {
+    /// Look at the structure as it is stored in the file.
+    pub fn identity() -> Self {
+        View {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            center: [0.0; 3],
+        }
+    }
+    /// Use an explicit row-major rotation matrix.
+    pub fn from_matrix(rotation: [[f64; 3]; 3]) -> Self {
+        View {
+            rotation,
+            center: [0.0; 3],
+        }
+    }
+    /// Orient the structure along its principal axes: the longest axis runs horizontally,
+    /// the second longest vertically, and the view looks down the shortest.
+    pub fn principal_axes(ac: &AtomCollection) -> Self {
+        let coords: Vec<[f64; 3]> = ac
+            .get_coords()
+            .iter()
+            .map(|c| [c[0] as f64, c[1] as f64, c[2] as f64])
+            .collect();
+        if coords.is_empty() {
+            return View::identity();
+        }
+        let n = coords.len() as f64;
+        let center: [f64; 3] =
+            std::array::from_fn(|axis| coords.iter().map(|c| c[axis]).sum::<f64>() / n);
+        let mut covariance = [[0.0; 3]; 3];
+        for c in &coords {
+            let d: [f64; 3] = std::array::from_fn(|axis| c[axis] - center[axis]);
+            for (row, di) in covariance.iter_mut().zip(d) {
+                for (value, dj) in row.iter_mut().zip(d) {
+                    *value += di * dj / n;
+                }
+            }
+        }
+        let (values, vectors) = symmetric_eigen(covariance);
+        let mut order = [0, 1, 2];
+        order.sort_by(|&a, &b| values[b].total_cmp(&values[a]));
+        let x = vectors[order[0]];
+        let y = vectors[order[1]];
+        // keep the frame right-handed so the structure is not mirrored
+        let z = cross(x, y);
+        View {
+            rotation: [x, y, z],
+            center,
+        }
+    }
+    /// Rotate the view about the page's horizontal axis.
+    pub fn rotate_x(self, degrees: f64) -> Self {
+        let (s, c) = degrees.to_radians().sin_cos();
+        self.then([[1.0, 0.0, 0.0], [0.0, c, -s], [0.0, s, c]])
+    }
+    /// Rotate the view about the page's vertical axis.
+    pub fn rotate_y(self, degrees: f64) -> Self {
+        let (s, c) = degrees.to_radians().sin_cos();
+        self.then([[c, 0.0, s], [0.0, 1.0, 0.0], [-s, 0.0, c]])
+    }
+    /// Rotate the view in the plane of the page.
+    pub fn rotate_z(self, degrees: f64) -> Self {
+        let (s, c) = degrees.to_radians().sin_cos();
+        self.then([[c, -s, 0.0], [s, c, 0.0], [0.0, 0.0, 1.0]])
+    }
+    pub fn get_rotation(&self) -> [[f64; 3]; 3] {
+        self.rotation
+    }
+    /// Rotated coordinates of a point; z is the depth towards the viewer.
+    pub fn apply(&self, point: &[f32; 3]) -> [f64; 3] {
+        let d: [f64; 3] = std::array::from_fn(|axis| point[axis] as f64 - self.center[axis]);
+        self.rotation
+            .map(|row| row[0] * d[0] + row[1] * d[1] + row[2] * d[2])
+    }
+    /// Apply `rotation` after the current one.
+    fn then(self, rotation: [[f64; 3]; 3]) -> Self {
+        View {
+            rotation: matmul(rotation, self.rotation),
+            center: self.center,
+        }
+    }
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+fn cross(a: [f64; 3], b: [f64; 3]) -> [f64; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+fn matmul(a: [[f64; 3]; 3], b: [[f64; 3]; 3]) -> [[f64; 3]; 3] {
+    std::array::from_fn(|i| std::array::from_fn(|j| (0..3).map(|k| a[i][k] * b[k][j]).sum()))
+}
+
+/// Eigenvalues and unit eigenvectors of a symmetric 3x3 matrix using Jacobi rotations.
+fn symmetric_eigen(mut m: [[f64; 3]; 3]) -> ([f64; 3], [[f64; 3]; 3]) {
+    // columns of `v` accumulate the eigenvectors
+    let mut v = [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]];
+    for _ in 0..50 {
+        let off = m[0][1].abs() + m[0][2].abs() + m[1][2].abs();
+        if off < 1e-12 {
+            break;
+        }
+        for (p, q) in [(0, 1), (0, 2), (1, 2)] {
+            if m[p][q].abs() < 1e-15 {
+                continue;
+            }
+            let theta = (m[q][q] - m[p][p]) / (2.0 * m[p][q]);
+            let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+            let c = 1.0 / (t * t + 1.0).sqrt();
+            let s = t * c;
+            // m = J^T m J
+            for row in m.iter_mut() {
+                let (mkp, mkq) = (row[p], row[q]);
+                row[p] = c * mkp - s * mkq;
+                row[q] = s * mkp + c * mkq;
+            }
+            let (rp, rq) = (m[p], m[q]);
+            m[p] = std::array::from_fn(|k| c * rp[k] - s * rq[k]);
+            m[q] = std::array::from_fn(|k| s * rp[k] + c * rq[k]);
+            for row in v.iter_mut() {
+                let (vkp, vkq) = (row[p], row[q]);
+                row[p] = c * vkp - s * vkq;
+                row[q] = s * vkp + c * vkq;
+            }
+        }
+    }
+    let values = [m[0][0], m[1][1], m[2][2]];
+    let vectors = std::array::from_fn(|i| [v[0][i], v[1][i], v[2][i]]);
+    (values, vectors)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_symmetric_eigen() {
+        let m = [[4.0, 1.0, 0.0], [1.0, 3.0, 0.5], [0.0, 0.5, 1.0]];
+        let (values, vectors) = symmetric_eigen(m);
+        for (value, vector) in values.iter().zip(vectors) {
+            let mv: [f64; 3] = std::array::from_fn(|i| (0..3).map(|j| m[i][j] * vector[j]).sum());
+            for (a, b) in mv.iter().zip(vector) {
+                assert!((a - value * b).abs() < 1e-9);
+            }
+            let norm: f64 = vector.iter().map(|x| x * x).sum();
+            assert!((norm - 1.0).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn test_rotation() {
+        let view = View::identity().rotate_z(90.0);
+        let [x, y, z] = view.apply(&[1.0, 0.0, 0.0]);
+        assert!(x.abs() < 1e-9 && (y - 1.0).abs() < 1e-9 && z.abs() < 1e-9);
+    }
+}
@@ -168,6 +168,13 @@ impl AtomCollection {
     pub fn get_coords(&self) -> &Vec<[f32; 3]> {
         self.coords.as_ref()
     }
+    /// Replace all coordinates, e.g. with another frame of a trajectory.
+    ///
+    /// Panics if the number of coordinates does not match the number of atoms.
+    pub fn set_coords(&mut self, coords: Vec<[f32; 3]>) {
+        assert_eq!(coords.len(), self.size, "one coordinate per atom");
+        self.coords = coords;
+    }
     pub fn get_element(&self, idx: usize) -> &Element {
         &self.elements[idx]
     }
@@ -223,6 +230,14 @@ impl AtomCollection {
         self.iter_residues_all()
             .filter(|residue| residue.is_amino_acid())
     }
+    pub fn iter_residues_carbohydrate(&self) -> impl Iterator<Item = ResidueAtoms<'_>> {
+        self.iter_residues_all()
+            .filter(|residue| residue.is_carbohydrate())
+    }
+    pub fn iter_residues_nucleotide(&self) -> impl Iterator<Item = ResidueAtoms<'_>> {
+        self.iter_residues_all()
+            .filter(|residue| residue.is_nucleotide())
+    }
     pub fn select(&self) -> AtomSelector {
         AtomSelector::new(self)
     }
@@ -249,6 +264,44 @@ impl AtomCollection {
     pub fn view(&self, selection: Selection) -> AtomView {
         AtomView::new(self, selection)
     }
+    /// Copy the selected atoms into a new collection.
+    ///
+    /// Atoms keep the order of the selection and bonds between two selected atoms are
+    /// re-indexed; bonds to unselected atoms are dropped.
+    pub fn subset(&self, selection: &Selection) -> AtomCollection {
+        let indices = selection.indices();
+        let mut new_index = vec![None; self.size];
+        for (new, &old) in indices.iter().enumerate() {
+            new_index[old] = Some(new as i32);
+        }
+        let bonds = self.bonds.as_ref().map(|bonds| {
+            bonds
+                .iter()
+                .filter_map(|bond| {
+                    let (a, b) = bond.get_atom_indices();
+                    Some(Bond::new(
+                        new_index[a as usize]?,
+                        new_index[b as usize]?,
+                        bond.get_order(),
+                    ))
+                })
+                .collect()
+        });
+        AtomCollection::new(
+            indices.len(),
+            indices.iter().map(|&i| self.coords[i]).collect(),
+            indices.iter().map(|&i| self.res_ids[i]).collect(),
+            indices.iter().map(|&i| self.res_names[i].clone()).collect(),
+            indices.iter().map(|&i| self.is_hetero[i]).collect(),
+            indices.iter().map(|&i| self.elements[i]).collect(),
+            indices
+                .iter()
+                .map(|&i| self.atom_names[i].clone())
+                .collect(),
+            indices.iter().map(|&i| self.chain_ids[i].clone()).collect(),
+            bonds,
+        )
+    }
 }
 
 #[cfg(test)]
@@ -278,6 +331,27 @@ mod tests {
         //     .collect();
     }
 
+    #[test]
+    fn test_subset() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let mut ac: AtomCollection = AtomCollection::from(&pdb)
+            .iter_residues_aminoacid()
+            .collect();
+        ac.connect_via_residue_names();
+
+        let gly = ac.select_by_residue("GLY");
+        let subset = ac.subset(&gly);
+        assert_eq!(subset.get_size(), gly.indices().len());
+        assert!(subset.get_resnames().iter().all(|name| name == "GLY"));
+        let bonds = subset.get_bonds().unwrap();
+        assert!(!bonds.is_empty());
+        assert!(bonds.iter().all(|bond| {
+            let (a, b) = bond.get_atom_indices();
+            (a as usize) < subset.get_size() && (b as usize) < subset.get_size()
+        }));
+    }
+
     #[test]
     fn test_residue_iterator() {
         let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
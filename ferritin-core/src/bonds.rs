@@ -2,7 +2,7 @@
 ///
 /// Struct for creating Bonds of type [BondOrder]
 ///
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Debug, PartialEq)]
 pub struct Bond {
     atom1: i32,
     atom2: i32,
@@ -24,10 +24,13 @@ impl Bond {
     pub fn get_atom_indices(&self) -> (i32, i32) {
         (self.atom1, self.atom2)
     }
+    pub fn get_order(&self) -> BondOrder {
+        self.order
+    }
 }
 
 #[repr(u8)]
-#[derive(Debug, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq)]
 /// BondOrder:
 ///
 /// Enum for defining Bond orders.
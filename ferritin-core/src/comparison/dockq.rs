@@ -0,0 +1,469 @@
+//! DockQ scoring of protein-protein interfaces.
+//!
+//! Follows DockQ (Basu & Wallner, 2016): every native chain pair in contact is scored
+//! from the fraction of native contacts (Fnat), the interface backbone RMSD (iRMSD) and
+//! the ligand backbone RMSD after superposing the receptor (LRMSD). Model chains are
+//! matched to native chains by sequence; when several mappings are possible, as in
+//! homomers, the one with the highest total DockQ is kept.
+use super::superpose::{rmsd, superpose, Superposition};
+use super::tmscore::dp_align;
+use super::trace::{ResidueTrace, ToResidueTrace};
+use crate::geometry::{distance, NeighborGrid};
+use std::collections::{HashMap, HashSet};
+
+const CONTACT_CUTOFF: f32 = 5.0;
+const INTERFACE_CUTOFF: f32 = 10.0;
+const CLASH_CUTOFF: f32 = 2.0;
+const BACKBONE_ATOMS: [&str; 4] = ["N", "CA", "C", "O"];
+/// Minimum sequence identity for a model chain to stand in for a native chain
+const MIN_CHAIN_IDENTITY: f32 = 0.8;
+/// Above this many candidate chain mappings fall back to a greedy assignment
+const MAX_MAPPINGS: usize = 10_000;
+
+/// DockQ components for one native interface
+#[derive(Debug, Clone)]
+pub struct DockQInterface {
+    pub native_chains: (String, String),
+    pub model_chains: (String, String),
+    pub dockq: f32,
+    /// Backbone RMSD of the interface residues
+    pub irmsd: f32,
+    /// Backbone RMSD of the smaller chain after superposing the larger one
+    pub lrmsd: f32,
+    /// Fraction of native residue contacts reproduced by the model
+    pub fnat: f32,
+    /// Fraction of model residue contacts absent from the native
+    pub fnonnat: f32,
+    /// Harmonic mean of contact precision and recall
+    pub f1: f32,
+    /// Model residue pairs with heavy atoms closer than 2 Å
+    pub clashes: usize,
+    pub native_contacts: usize,
+    pub model_contacts: usize,
+}
+
+/// DockQ of a model complex against a native complex
+#[derive(Debug, Clone)]
+pub struct DockQResult {
+    /// Sum of DockQ over the native interfaces
+    pub total_dockq: f32,
+    /// Number of native chain pairs in contact
+    pub native_interfaces: usize,
+    /// Native chain id -> model chain id
+    pub chain_mapping: HashMap<String, String>,
+    /// Scores for native interfaces whose chains are both mapped
+    pub interfaces: Vec<DockQInterface>,
+    /// Transform placing the model onto the native over all mapped CA atoms
+    pub superposition: Superposition,
+    pub aligned_rmsd: f32,
+}
+
+impl DockQResult {
+    /// Average DockQ over native interfaces (GlobalDockQ)
+    pub fn global_dockq(&self) -> f32 {
+        if self.native_interfaces == 0 {
+            0.0
+        } else {
+            self.total_dockq / self.native_interfaces as f32
+        }
+    }
+}
+
+struct Chain {
+    id: String,
+    residues: Vec<ResidueTrace>,
+}
+
+struct NativeInterface {
+    chains: (usize, usize),
+    contacts: HashSet<(usize, usize)>,
+    interface_residues: (Vec<usize>, Vec<usize>),
+}
+
+/// Chains, sequence alignments and native interfaces shared by every mapping candidate
+struct DockQContext {
+    model: Vec<Chain>,
+    native: Vec<Chain>,
+    /// (native chain, model chain) -> model residue for each native residue
+    residue_maps: HashMap<(usize, usize), Vec<Option<usize>>>,
+    interfaces: Vec<NativeInterface>,
+    cache: HashMap<(usize, usize, usize), DockQInterface>,
+}
+
+/// Score `model` against `native`, searching for the best chain mapping.
+pub fn dockq(model: &impl ToResidueTrace, native: &impl ToResidueTrace) -> DockQResult {
+    let mut ctx = DockQContext::new(model, native);
+    let mut candidates: Vec<Vec<usize>> = vec![Vec::new(); ctx.native.len()];
+    for (n, native_candidates) in candidates.iter_mut().enumerate() {
+        for m in 0..ctx.model.len() {
+            let (map, identity) = align_chains(&ctx.model[m], &ctx.native[n]);
+            if identity >= MIN_CHAIN_IDENTITY {
+                ctx.residue_maps.insert((n, m), map);
+                native_candidates.push(m);
+            }
+        }
+    }
+    let mapping = ctx.best_mapping(&candidates);
+    ctx.result(&mapping)
+}
+
+/// Score `model` against `native` with a fixed native -> model chain mapping.
+pub fn dockq_with_mapping(
+    model: &impl ToResidueTrace,
+    native: &impl ToResidueTrace,
+    chain_mapping: &HashMap<String, String>,
+) -> DockQResult {
+    let mut ctx = DockQContext::new(model, native);
+    let mapping: Vec<Option<usize>> = ctx
+        .native
+        .iter()
+        .map(|chain| {
+            chain_mapping
+                .get(&chain.id)
+                .and_then(|id| ctx.model.iter().position(|m| &m.id == id))
+        })
+        .collect();
+    for (n, m) in mapping.iter().enumerate() {
+        if let Some(m) = *m {
+            let (map, _) = align_chains(&ctx.model[m], &ctx.native[n]);
+            ctx.residue_maps.insert((n, m), map);
+        }
+    }
+    ctx.result(&mapping)
+}
+
+impl DockQContext {
+    fn new(model: &impl ToResidueTrace, native: &impl ToResidueTrace) -> Self {
+        let model = split_chains(model.residue_trace());
+        let native = split_chains(native.residue_trace());
+        let mut interfaces = Vec::new();
+        for a in 0..native.len() {
+            for b in (a + 1)..native.len() {
+                let (res_a, res_b) = (&native[a].residues, &native[b].residues);
+                let contacts: HashSet<(usize, usize)> =
+                    residue_contacts(res_a, res_b, CONTACT_CUTOFF)
+                        .into_keys()
+                        .collect();
+                if contacts.is_empty() {
+                    continue;
+                }
+                let near = residue_contacts(res_a, res_b, INTERFACE_CUTOFF);
+                let mut side_a: Vec<usize> = near.keys().map(|&(i, _)| i).collect();
+                let mut side_b: Vec<usize> = near.keys().map(|&(_, j)| j).collect();
+                side_a.sort_unstable();
+                side_a.dedup();
+                side_b.sort_unstable();
+                side_b.dedup();
+                interfaces.push(NativeInterface {
+                    chains: (a, b),
+                    contacts,
+                    interface_residues: (side_a, side_b),
+                });
+            }
+        }
+        DockQContext {
+            model,
+            native,
+            residue_maps: HashMap::new(),
+            interfaces,
+            cache: HashMap::new(),
+        }
+    }
+
+    /// Enumerate injective native -> model assignments, or assign greedily when
+    /// there are too many of them.
+    fn best_mapping(&mut self, candidates: &[Vec<usize>]) -> Vec<Option<usize>> {
+        let n_mappings = candidates
+            .iter()
+            .map(|c| c.len().max(1))
+            .try_fold(1usize, |acc, n| acc.checked_mul(n))
+            .unwrap_or(usize::MAX);
+
+        if n_mappings <= MAX_MAPPINGS {
+            let mut best = (f32::NEG_INFINITY, vec![None; candidates.len()]);
+            let mut current = Vec::with_capacity(candidates.len());
+            self.search(candidates, &mut current, &mut best);
+            return best.1;
+        }
+
+        let mut mapping: Vec<Option<usize>> = vec![None; candidates.len()];
+        for n in 0..candidates.len() {
+            let mut best: Option<(f32, usize)> = None;
+            for &m in &candidates[n] {
+                if mapping.contains(&Some(m)) {
+                    continue;
+                }
+                mapping[n] = Some(m);
+                let score = self.total(&mapping);
+                if !matches!(best, Some((s, _)) if s >= score) {
+                    best = Some((score, m));
+                }
+            }
+            mapping[n] = best.map(|(_, m)| m);
+        }
+        mapping
+    }
+
+    fn search(
+        &mut self,
+        candidates: &[Vec<usize>],
+        current: &mut Vec<Option<usize>>,
+        best: &mut (f32, Vec<Option<usize>>),
+    ) {
+        let n = current.len();
+        if n == candidates.len() {
+            let score = self.total(current);
+            if score > best.0 {
+                *best = (score, current.clone());
+            }
+            return;
+        }
+        let mut any = false;
+        for &m in &candidates[n] {
+            if current.contains(&Some(m)) {
+                continue;
+            }
+            any = true;
+            current.push(Some(m));
+            self.search(candidates, current, best);
+            current.pop();
+        }
+        if !any {
+            current.push(None);
+            self.search(candidates, current, best);
+            current.pop();
+        }
+    }
+
+    fn total(&mut self, mapping: &[Option<usize>]) -> f32 {
+        (0..self.interfaces.len())
+            .filter_map(|k| self.interface_score(k, mapping))
+            .map(|score| score.dockq)
+            .sum()
+    }
+
+    fn interface_score(&mut self, k: usize, mapping: &[Option<usize>]) -> Option<DockQInterface> {
+        let (a, b) = self.interfaces[k].chains;
+        let (ma, mb) = (mapping[a]?, mapping[b]?);
+        if let Some(score) = self.cache.get(&(k, ma, mb)) {
+            return Some(score.clone());
+        }
+        let score = self.score_interface(k, ma, mb);
+        self.cache.insert((k, ma, mb), score.clone());
+        Some(score)
+    }
+
+    fn score_interface(&self, k: usize, ma: usize, mb: usize) -> DockQInterface {
+        let iface = &self.interfaces[k];
+        let (a, b) = iface.chains;
+        let (native_a, native_b) = (&self.native[a], &self.native[b]);
+        let (model_a, model_b) = (&self.model[ma], &self.model[mb]);
+        let map_a = &self.residue_maps[&(a, ma)];
+        let map_b = &self.residue_maps[&(b, mb)];
+
+        // model contacts expressed in native residue indices
+        let inverse = |map: &[Option<usize>]| -> HashMap<usize, usize> {
+            map.iter()
+                .enumerate()
+                .filter_map(|(n, m)| m.map(|m| (m, n)))
+                .collect()
+        };
+        let (inv_a, inv_b) = (inverse(map_a), inverse(map_b));
+        let model_near = residue_contacts(&model_a.residues, &model_b.residues, CONTACT_CUTOFF);
+        let clashes = model_near.values().filter(|&&d| d < CLASH_CUTOFF).count();
+        let model_contacts: HashSet<(usize, usize)> = model_near
+            .keys()
+            .filter_map(|(i, j)| Some((*inv_a.get(i)?, *inv_b.get(j)?)))
+            .collect();
+
+        let shared = iface.contacts.intersection(&model_contacts).count();
+        let fnat = shared as f32 / iface.contacts.len() as f32;
+        let fnonnat = if model_contacts.is_empty() {
+            0.0
+        } else {
+            (model_contacts.len() - shared) as f32 / model_contacts.len() as f32
+        };
+        let precision = 1.0 - fnonnat;
+        let f1 = if fnat + precision > 0.0 {
+            2.0 * fnat * precision / (fnat + precision)
+        } else {
+            0.0
+        };
+
+        // interface RMSD over backbone atoms of native interface residues
+        let (mut mobile, mut target) = paired_backbone(
+            model_a,
+            native_a,
+            map_a,
+            iface.interface_residues.0.iter().copied(),
+        );
+        let (mobile_b, target_b) = paired_backbone(
+            model_b,
+            native_b,
+            map_b,
+            iface.interface_residues.1.iter().copied(),
+        );
+        mobile.extend(mobile_b);
+        target.extend(target_b);
+        let irmsd = superpose(&mobile, &target).rmsd;
+
+        // ligand RMSD after superposing the receptor (the larger native chain)
+        let ((receptor, native_r, map_r), (ligand, native_l, map_l)) =
+            if native_a.residues.len() >= native_b.residues.len() {
+                ((model_a, native_a, map_a), (model_b, native_b, map_b))
+            } else {
+                ((model_b, native_b, map_b), (model_a, native_a, map_a))
+            };
+        let (mobile_r, target_r) =
+            paired_backbone(receptor, native_r, map_r, 0..native_r.residues.len());
+        let (mobile_l, target_l) =
+            paired_backbone(ligand, native_l, map_l, 0..native_l.residues.len());
+        let fit = superpose(&mobile_r, &target_r);
+        let lrmsd = rmsd(&fit.apply_all(&mobile_l), &target_l);
+
+        let dockq =
+            (fnat + 1.0 / (1.0 + (irmsd / 1.5).powi(2)) + 1.0 / (1.0 + (lrmsd / 8.5).powi(2)))
+                / 3.0;
+
+        DockQInterface {
+            native_chains: (native_a.id.clone(), native_b.id.clone()),
+            model_chains: (model_a.id.clone(), model_b.id.clone()),
+            dockq,
+            irmsd,
+            lrmsd,
+            fnat,
+            fnonnat,
+            f1,
+            clashes,
+            native_contacts: iface.contacts.len(),
+            model_contacts: model_contacts.len(),
+        }
+    }
+
+    fn result(&mut self, mapping: &[Option<usize>]) -> DockQResult {
+        let interfaces: Vec<DockQInterface> = (0..self.interfaces.len())
+            .filter_map(|k| self.interface_score(k, mapping))
+            .collect();
+        let total_dockq = interfaces.iter().map(|i| i.dockq).sum();
+
+        let mut mobile = Vec::new();
+        let mut target = Vec::new();
+        let mut chain_mapping = HashMap::new();
+        for (n, m) in mapping.iter().enumerate() {
+            let Some(m) = *m else { continue };
+            chain_mapping.insert(self.native[n].id.clone(), self.model[m].id.clone());
+            let map = &self.residue_maps[&(n, m)];
+            for (j, i) in map.iter().enumerate() {
+                let ca = i.and_then(|i| self.model[m].residues[i].ca());
+                if let (Some(model_ca), Some(native_ca)) = (ca, self.native[n].residues[j].ca()) {
+                    mobile.push(model_ca);
+                    target.push(native_ca);
+                }
+            }
+        }
+        let superposition = superpose(&mobile, &target);
+
+        DockQResult {
+            total_dockq,
+            native_interfaces: self.interfaces.len(),
+            chain_mapping,
+            interfaces,
+            aligned_rmsd: superposition.rmsd,
+            superposition,
+        }
+    }
+}
+
+// Helper Fns ------------------------------------------------------------
+
+/// Group residues by chain id, keeping the order in which chains first appear
+fn split_chains(trace: Vec<ResidueTrace>) -> Vec<Chain> {
+    let mut chains: Vec<Chain> = Vec::new();
+    for res in trace {
+        match chains.iter_mut().find(|c| c.id == res.chain_id) {
+            Some(chain) => chain.residues.push(res),
+            None => chains.push(Chain {
+                id: res.chain_id.clone(),
+                residues: vec![res],
+            }),
+        }
+    }
+    chains
+}
+
+/// Sequence alignment of a model chain onto a native chain.
+///
+/// Returns the model residue paired with each native residue and the fraction of
+/// identical pairs relative to the shorter chain.
+fn align_chains(model: &Chain, native: &Chain) -> (Vec<Option<usize>>, f32) {
+    let similarity: Vec<Vec<f32>> = model
+        .residues
+        .iter()
+        .map(|m| {
+            native
+                .residues
+                .iter()
+                .map(|n| if m.res_name == n.res_name { 1.0 } else { 0.0 })
+                .collect()
+        })
+        .collect();
+    let mut map = vec![None; native.residues.len()];
+    let mut identical = 0;
+    for (i, j) in dp_align(&similarity, -1.0) {
+        if model.residues[i].res_name == native.residues[j].res_name {
+            map[j] = Some(i);
+            identical += 1;
+        }
+    }
+    let shorter = model.residues.len().min(native.residues.len()).max(1);
+    (map, identical as f32 / shorter as f32)
+}
+
+/// Residue pairs between two chains with heavy atoms within `cutoff`, with their
+/// closest atom distance
+fn residue_contacts(
+    a: &[ResidueTrace],
+    b: &[ResidueTrace],
+    cutoff: f32,
+) -> HashMap<(usize, usize), f32> {
+    let (residue_of, coords): (Vec<usize>, Vec<[f32; 3]>) = b
+        .iter()
+        .enumerate()
+        .flat_map(|(j, res)| res.atoms.iter().map(move |(_, coord)| (j, *coord)))
+        .unzip();
+    let grid = NeighborGrid::new(&coords, cutoff);
+    let mut contacts: HashMap<(usize, usize), f32> = HashMap::new();
+    for (i, res) in a.iter().enumerate() {
+        for (_, coord) in &res.atoms {
+            for k in grid.within(coord, cutoff) {
+                let d = distance(coord, &coords[k]);
+                let entry = contacts.entry((i, residue_of[k])).or_insert(d);
+                *entry = entry.min(d);
+            }
+        }
+    }
+    contacts
+}
+
+/// Backbone coordinates present in both the native residues and their model partners
+fn paired_backbone(
+    model: &Chain,
+    native: &Chain,
+    map: &[Option<usize>],
+    residues: impl Iterator<Item = usize>,
+) -> (Vec<[f32; 3]>, Vec<[f32; 3]>) {
+    let mut mobile = Vec::new();
+    let mut target = Vec::new();
+    for j in residues {
+        let Some(i) = map[j] else { continue };
+        for name in BACKBONE_ATOMS {
+            if let (Some(m), Some(n)) =
+                (model.residues[i].atom(name), native.residues[j].atom(name))
+            {
+                mobile.push(m);
+                target.push(n);
+            }
+        }
+    }
+    (mobile, target)
+}
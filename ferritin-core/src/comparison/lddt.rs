@@ -0,0 +1,102 @@
+//! Local Distance Difference Test (lDDT).
+//!
+//! lDDT is superposition-free: for every pair of atoms within the inclusion radius in
+//! the reference it checks whether the model preserves that distance within 0.5, 1, 2
+//! and 4 Å. Atoms absent from the model count as not preserved.
+use super::tmscore::{align_residues, AlignmentMethod};
+use super::trace::ToResidueTrace;
+use crate::geometry::{distance, NeighborGrid};
+
+const INCLUSION_RADIUS: f32 = 15.0;
+const THRESHOLDS: [f32; 4] = [0.5, 1.0, 2.0, 4.0];
+
+/// lDDT scores of a model against a reference
+#[derive(Debug, Clone)]
+pub struct LDDT {
+    /// Fraction of preserved distances over all reference pairs
+    pub global: f32,
+    /// Score for each reference residue; `None` when it has no pairs within the radius
+    pub per_residue: Vec<Option<f32>>,
+}
+
+/// Compute lDDT over all heavy atoms.
+pub fn lddt(
+    model: &impl ToResidueTrace,
+    reference: &impl ToResidueTrace,
+    method: AlignmentMethod,
+) -> LDDT {
+    compute_lddt(model, reference, method, false)
+}
+
+/// Compute lDDT over CA atoms only.
+pub fn lddt_ca(
+    model: &impl ToResidueTrace,
+    reference: &impl ToResidueTrace,
+    method: AlignmentMethod,
+) -> LDDT {
+    compute_lddt(model, reference, method, true)
+}
+
+fn compute_lddt(
+    model: &impl ToResidueTrace,
+    reference: &impl ToResidueTrace,
+    method: AlignmentMethod,
+    ca_only: bool,
+) -> LDDT {
+    let model = model.residue_trace();
+    let reference = reference.residue_trace();
+    let alignment = align_residues(&model, &reference, method);
+    let mut model_of = vec![None; reference.len()];
+    for &(i, j) in &alignment {
+        model_of[j] = Some(i);
+    }
+
+    // flatten reference atoms as (residue index, model coordinate if present, coordinate)
+    let mut residue_of = Vec::new();
+    let mut model_coords = Vec::new();
+    let mut reference_coords = Vec::new();
+    for (j, res) in reference.iter().enumerate() {
+        for (name, coord) in &res.atoms {
+            if ca_only && name != "CA" {
+                continue;
+            }
+            residue_of.push(j);
+            reference_coords.push(*coord);
+            model_coords.push(model_of[j].and_then(|i| model[i].atom(name)));
+        }
+    }
+
+    let grid = NeighborGrid::new(&reference_coords, INCLUSION_RADIUS);
+    let mut preserved = vec![0f32; reference.len()];
+    let mut total = vec![0usize; reference.len()];
+    for (a, coord) in reference_coords.iter().enumerate() {
+        let res_a = residue_of[a];
+        for b in grid.within(coord, INCLUSION_RADIUS) {
+            if residue_of[b] == res_a {
+                continue;
+            }
+            total[res_a] += 1;
+            if let (Some(ma), Some(mb)) = (model_coords[a], model_coords[b]) {
+                let diff = (distance(coord, &reference_coords[b]) - distance(&ma, &mb)).abs();
+                let hits = THRESHOLDS.iter().filter(|&&t| diff < t).count();
+                preserved[res_a] += hits as f32 / THRESHOLDS.len() as f32;
+            }
+        }
+    }
+
+    let all_pairs: usize = total.iter().sum();
+    let global = if all_pairs == 0 {
+        0.0
+    } else {
+        preserved.iter().sum::<f32>() / all_pairs as f32
+    };
+    let per_residue = preserved
+        .iter()
+        .zip(&total)
+        .map(|(&p, &t)| (t > 0).then(|| p / t as f32))
+        .collect();
+    LDDT {
+        global,
+        per_residue,
+    }
+}
@@ -0,0 +1,138 @@
+//! Structure Comparison
+//!
+//! Metrics for comparing a model against a reference structure:
+//!
+//! - [`superpose`] / [`rmsd`]: optimal rigid-body fit and RMSD
+//! - [`tm_score`]: TM-score, optionally with a TM-align structural alignment
+//! - [`gdt`]: GDT-TS and GDT-HA
+//! - [`lddt`] / [`lddt_ca`]: global and per-residue lDDT
+//! - [`dockq`]: DockQ of protein-protein interfaces, with chain mapping
+//!
+//! The metrics accept anything implementing [`ToResidueTrace`], which includes
+//! [`AtomCollection`](crate::AtomCollection) and `AtomView`.
+//!
+//! ```no_run
+//! use ferritin_core::{tm_score, AlignmentMethod, AtomCollection};
+//! # fn example(model: &AtomCollection, reference: &AtomCollection) {
+//! let result = tm_score(model, reference, AlignmentMethod::Structural);
+//! println!("TM-score: {:.3}", result.tm_score);
+//! # }
+//! ```
+mod dockq;
+mod lddt;
+mod superpose;
+mod tmscore;
+mod trace;
+
+pub use dockq::{dockq, dockq_with_mapping, DockQInterface, DockQResult};
+pub use lddt::{lddt, lddt_ca, LDDT};
+pub use superpose::{rmsd, superpose, Superposition};
+pub use tmscore::{align_residues, gdt, tm_d0, tm_score, AlignmentMethod, TMScore, GDT};
+pub use trace::{ResidueTrace, ToResidueTrace};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::AtomCollection;
+    use ferritin_test_data::TestFile;
+
+    fn transformed(ac: &AtomCollection, sup: &Superposition) -> AtomCollection {
+        renamed(ac, sup, |chain| chain.to_string())
+    }
+
+    fn renamed(
+        ac: &AtomCollection,
+        sup: &Superposition,
+        chain_name: impl Fn(&str) -> String,
+    ) -> AtomCollection {
+        let n = ac.get_size();
+        AtomCollection::new(
+            n,
+            sup.apply_all(ac.get_coords()),
+            ac.get_resids().clone(),
+            ac.get_resnames().clone(),
+            (0..n).map(|i| ac.get_is_hetero(i)).collect(),
+            ac.get_elements().clone(),
+            (0..n).map(|i| ac.get_atom_name(i).clone()).collect(),
+            (0..n).map(|i| chain_name(ac.get_chain_id(i))).collect(),
+            None,
+        )
+    }
+
+    #[test]
+    fn test_self_comparison() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        // a rigid-body copy should score perfectly
+        let moved = transformed(
+            &ac,
+            &Superposition {
+                rotation: [[0.0, -1.0, 0.0], [1.0, 0.0, 0.0], [0.0, 0.0, 1.0]],
+                translation: [10.0, -5.0, 3.0],
+                rmsd: 0.0,
+            },
+        );
+        let tm = tm_score(&moved, &ac, AlignmentMethod::ResidueNumber);
+        assert_eq!(tm.aligned_length, 154);
+        assert!((tm.tm_score - 1.0).abs() < 1e-3);
+        assert!(tm.rmsd < 1e-2);
+
+        let gdt_result = gdt(&moved, &ac, AlignmentMethod::SequenceOrder);
+        assert!((gdt_result.gdt_ts - 1.0).abs() < 1e-3);
+        assert!((gdt_result.gdt_ha - 1.0).abs() < 1e-3);
+
+        let lddt_result = lddt(&moved, &ac, AlignmentMethod::ResidueNumber);
+        assert!((lddt_result.global - 1.0).abs() < 1e-3);
+        assert_eq!(lddt_result.per_residue.len(), 154);
+    }
+
+    #[test]
+    fn test_structural_alignment_of_view() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        // a fragment aligned without relying on numbering
+        let fragment = ac
+            .select()
+            .filter(|i| (20..80).contains(ac.get_res_id(i)))
+            .collect();
+        let tm = tm_score(&fragment, &ac, AlignmentMethod::Structural);
+        assert_eq!(tm.aligned_length, 60);
+        assert!(tm.rmsd < 0.1);
+        // normalized by the shorter fragment the match is perfect
+        assert!((tm.tm_score_model - 1.0).abs() < 1e-2);
+    }
+
+    #[test]
+    fn test_dockq_self_comparison() {
+        let (prot_file, _temp) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+
+        // relabelled, moved copy: chains are matched by sequence, not by id
+        let model = renamed(
+            &ac,
+            &Superposition {
+                rotation: [[1.0, 0.0, 0.0], [0.0, 0.0, -1.0], [0.0, 1.0, 0.0]],
+                translation: [-4.0, 2.0, 7.5],
+                rmsd: 0.0,
+            },
+            |chain| format!("{}2", chain),
+        );
+        let result = dockq(&model, &ac);
+        assert_eq!(result.native_interfaces, 1);
+        assert_eq!(result.chain_mapping["A"], "A2");
+        assert_eq!(result.chain_mapping["B"], "B2");
+        assert!(result.aligned_rmsd < 1e-2);
+
+        let interface = &result.interfaces[0];
+        assert!((interface.fnat - 1.0).abs() < 1e-6);
+        assert_eq!(interface.fnonnat, 0.0);
+        assert!(interface.irmsd < 1e-2);
+        assert!(interface.lrmsd < 1e-2);
+        assert!((result.global_dockq() - 1.0).abs() < 1e-3);
+    }
+}
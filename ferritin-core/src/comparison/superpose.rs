@@ -0,0 +1,208 @@
+//! Optimal rigid-body superposition.
+//!
+//! Uses Horn's quaternion method: the rotation that minimizes the RMSD between two
+//! centered point sets is the eigenvector of the largest eigenvalue of a symmetric
+//! 4x4 matrix built from their covariance.
+use crate::geometry::{centroid, dot, sub};
+
+/// Rigid transform mapping mobile coordinates onto target coordinates.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Superposition {
+    /// Row-major rotation matrix
+    pub rotation: [[f32; 3]; 3],
+    pub translation: [f32; 3],
+    /// RMSD of the fitted points after superposition
+    pub rmsd: f32,
+}
+
+impl Superposition {
+    pub fn identity() -> Self {
+        Superposition {
+            rotation: [[1.0, 0.0, 0.0], [0.0, 1.0, 0.0], [0.0, 0.0, 1.0]],
+            translation: [0.0; 3],
+            rmsd: 0.0,
+        }
+    }
+
+    pub fn apply(&self, point: &[f32; 3]) -> [f32; 3] {
+        let r = &self.rotation;
+        let t = &self.translation;
+        [
+            r[0][0] * point[0] + r[0][1] * point[1] + r[0][2] * point[2] + t[0],
+            r[1][0] * point[0] + r[1][1] * point[1] + r[1][2] * point[2] + t[1],
+            r[2][0] * point[0] + r[2][1] * point[1] + r[2][2] * point[2] + t[2],
+        ]
+    }
+
+    pub fn apply_all(&self, points: &[[f32; 3]]) -> Vec<[f32; 3]> {
+        points.iter().map(|p| self.apply(p)).collect()
+    }
+}
+
+/// Eigen-decomposition of a symmetric 4x4 matrix by cyclic Jacobi rotations.
+/// Returns the eigenvector of the largest eigenvalue.
+fn largest_eigenvector(mut a: [[f64; 4]; 4]) -> [f64; 4] {
+    let mut v = [[0.0f64; 4]; 4];
+    for (i, row) in v.iter_mut().enumerate() {
+        row[i] = 1.0;
+    }
+    for _sweep in 0..50 {
+        let off: f64 = (0..4)
+            .flat_map(|i| ((i + 1)..4).map(move |j| (i, j)))
+            .map(|(i, j)| a[i][j] * a[i][j])
+            .sum();
+        if off < 1e-18 {
+            break;
+        }
+        for p in 0..4 {
+            for q in (p + 1)..4 {
+                if a[p][q].abs() < 1e-30 {
+                    continue;
+                }
+                let theta = (a[q][q] - a[p][p]) / (2.0 * a[p][q]);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for row in a.iter_mut() {
+                    let akp = row[p];
+                    let akq = row[q];
+                    row[p] = c * akp - s * akq;
+                    row[q] = s * akp + c * akq;
+                }
+                let (row_p, row_q) = (a[p], a[q]);
+                a[p] = std::array::from_fn(|k| c * row_p[k] - s * row_q[k]);
+                a[q] = std::array::from_fn(|k| s * row_p[k] + c * row_q[k]);
+                for row in v.iter_mut() {
+                    let vkp = row[p];
+                    let vkq = row[q];
+                    row[p] = c * vkp - s * vkq;
+                    row[q] = s * vkp + c * vkq;
+                }
+            }
+        }
+    }
+    let best = (0..4)
+        .max_by(|&i, &j| a[i][i].partial_cmp(&a[j][j]).unwrap())
+        .unwrap();
+    [v[0][best], v[1][best], v[2][best], v[3][best]]
+}
+
+/// Find the rotation and translation minimizing the RMSD of `mobile` onto `target`.
+///
+/// Both slices must be the same length; the points are paired by index.
+pub fn superpose(mobile: &[[f32; 3]], target: &[[f32; 3]]) -> Superposition {
+    assert_eq!(
+        mobile.len(),
+        target.len(),
+        "superposition requires paired coordinates"
+    );
+    if mobile.is_empty() {
+        return Superposition::identity();
+    }
+    let cm = centroid(mobile).map(f64::from);
+    let ct = centroid(target).map(f64::from);
+
+    // covariance s[i][j] = sum(mobile_i * target_j)
+    let mut s = [[0.0f64; 3]; 3];
+    for (m, t) in mobile.iter().zip(target) {
+        let m = [
+            m[0] as f64 - cm[0],
+            m[1] as f64 - cm[1],
+            m[2] as f64 - cm[2],
+        ];
+        let t = [
+            t[0] as f64 - ct[0],
+            t[1] as f64 - ct[1],
+            t[2] as f64 - ct[2],
+        ];
+        for i in 0..3 {
+            for j in 0..3 {
+                s[i][j] += m[i] * t[j];
+            }
+        }
+    }
+    let [[sxx, sxy, sxz], [syx, syy, syz], [szx, szy, szz]] = s;
+    let n = [
+        [sxx + syy + szz, syz - szy, szx - sxz, sxy - syx],
+        [syz - szy, sxx - syy - szz, sxy + syx, szx + sxz],
+        [szx - sxz, sxy + syx, -sxx + syy - szz, syz + szy],
+        [sxy - syx, szx + sxz, syz + szy, -sxx - syy + szz],
+    ];
+    let [q0, q1, q2, q3] = largest_eigenvector(n);
+    let r = [
+        [
+            q0 * q0 + q1 * q1 - q2 * q2 - q3 * q3,
+            2.0 * (q1 * q2 - q0 * q3),
+            2.0 * (q1 * q3 + q0 * q2),
+        ],
+        [
+            2.0 * (q1 * q2 + q0 * q3),
+            q0 * q0 - q1 * q1 + q2 * q2 - q3 * q3,
+            2.0 * (q2 * q3 - q0 * q1),
+        ],
+        [
+            2.0 * (q1 * q3 - q0 * q2),
+            2.0 * (q2 * q3 + q0 * q1),
+            q0 * q0 - q1 * q1 - q2 * q2 + q3 * q3,
+        ],
+    ];
+    let mut rotation = [[0.0f32; 3]; 3];
+    let mut translation = [0.0f32; 3];
+    for i in 0..3 {
+        for j in 0..3 {
+            rotation[i][j] = r[i][j] as f32;
+        }
+        translation[i] = (ct[i] - (r[i][0] * cm[0] + r[i][1] * cm[1] + r[i][2] * cm[2])) as f32;
+    }
+    let mut superposition = Superposition {
+        rotation,
+        translation,
+        rmsd: 0.0,
+    };
+    superposition.rmsd = rmsd(&superposition.apply_all(mobile), target);
+    superposition
+}
+
+/// Root-mean-square deviation between paired coordinates, without superposition.
+pub fn rmsd(a: &[[f32; 3]], b: &[[f32; 3]]) -> f32 {
+    if a.is_empty() {
+        return 0.0;
+    }
+    let sum: f32 = a
+        .iter()
+        .zip(b)
+        .map(|(p, q)| {
+            let d = sub(*p, *q);
+            dot(d, d)
+        })
+        .sum();
+    (sum / a.len() as f32).sqrt()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_superpose_recovers_transform() {
+        let mobile = vec![
+            [0.0, 0.0, 0.0],
+            [1.5, 0.0, 0.0],
+            [1.5, 1.5, 0.0],
+            [0.0, 1.5, 2.0],
+            [-1.0, 0.5, 1.0],
+        ];
+        // rotate 90 degrees around z and translate
+        let target: Vec<[f32; 3]> = mobile
+            .iter()
+            .map(|p| [-p[1] + 3.0, p[0] - 2.0, p[2] + 1.0])
+            .collect();
+        let fit = superpose(&mobile, &target);
+        assert!(fit.rmsd < 1e-4);
+        for (p, t) in fit.apply_all(&mobile).iter().zip(&target) {
+            for k in 0..3 {
+                assert!((p[k] - t[k]).abs() < 1e-4);
+            }
+        }
+    }
+}
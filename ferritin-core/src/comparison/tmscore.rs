@@ -0,0 +1,387 @@
+//! TM-score, TM-align and GDT.
+//!
+//! All three score a model against a reference after finding the superposition
+//! that maximizes the score, which is generally not the RMSD-optimal one. The search
+//! follows TM-score/LGA: superpose seed fragments of decreasing length, then
+//! repeatedly re-superpose on the residue pairs closer than a distance cutoff.
+use super::superpose::{superpose, Superposition};
+use super::trace::{ResidueTrace, ToResidueTrace};
+use crate::geometry::{distance, dot, sub};
+use std::collections::HashMap;
+
+/// How residues of the model are paired with residues of the reference
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum AlignmentMethod {
+    /// Pair residues with the same chain id and residue number
+    ResidueNumber,
+    /// Pair residues by their position in each structure
+    SequenceOrder,
+    /// TM-align: structure-based alignment independent of numbering
+    Structural,
+}
+
+/// TM-score of a model against a reference
+#[derive(Debug, Clone)]
+pub struct TMScore {
+    /// TM-score normalized by the reference length
+    pub tm_score: f32,
+    /// TM-score normalized by the model length
+    pub tm_score_model: f32,
+    /// RMSD over the aligned pairs under the TM-score superposition
+    pub rmsd: f32,
+    pub aligned_length: usize,
+    /// Fraction of aligned pairs with identical residue names
+    pub sequence_identity: f32,
+    /// Distance scale used for normalization by the reference length
+    pub d0: f32,
+    /// (model residue index, reference residue index)
+    pub alignment: Vec<(usize, usize)>,
+    /// Transform placing the model onto the reference
+    pub superposition: Superposition,
+}
+
+/// Global Distance Test scores
+#[derive(Debug, Clone)]
+pub struct GDT {
+    /// Mean fraction of residues within 1, 2, 4 and 8 Å
+    pub gdt_ts: f32,
+    /// Mean fraction of residues within 0.5, 1, 2 and 4 Å
+    pub gdt_ha: f32,
+    /// Best fraction of reference residues under each cutoff
+    pub fractions: Vec<(f32, f32)>,
+}
+
+// Helper Fns --------------------------------------
+
+/// TM-score distance scale for a structure of `length` residues
+pub fn tm_d0(length: usize) -> f32 {
+    if length <= 21 {
+        return 0.5;
+    }
+    (1.24 * ((length as f32) - 15.0).cbrt() - 1.8).max(0.5)
+}
+
+fn distances(sup: &Superposition, mobile: &[[f32; 3]], target: &[[f32; 3]]) -> Vec<f32> {
+    mobile
+        .iter()
+        .zip(target)
+        .map(|(m, t)| distance(&sup.apply(m), t))
+        .collect()
+}
+
+fn select(points: &[[f32; 3]], indices: &[usize]) -> Vec<[f32; 3]> {
+    indices.iter().map(|&i| points[i]).collect()
+}
+
+/// Search for the superposition of paired points maximizing `score`.
+///
+/// Seeds are contiguous fragments of length n, n/2, n/4, ... (at least 4). Each seed is
+/// refined by re-fitting on the pairs closer than `d_select` until the selection is stable.
+pub(crate) fn optimize_superposition(
+    mobile: &[[f32; 3]],
+    target: &[[f32; 3]],
+    d_select: f32,
+    score: impl Fn(&[f32]) -> f32,
+) -> (f32, Superposition) {
+    let n = mobile.len();
+    let mut best_sup = superpose(mobile, target);
+    let mut best = score(&distances(&best_sup, mobile, target));
+    if n < 4 {
+        return (best, best_sup);
+    }
+
+    let mut frag_len = n;
+    loop {
+        let max_start = n - frag_len;
+        let step = (max_start / 20).max(1);
+        for start in (0..=max_start).step_by(step) {
+            let mut selected: Vec<usize> = (start..start + frag_len).collect();
+            for _ in 0..20 {
+                let sup = superpose(&select(mobile, &selected), &select(target, &selected));
+                let dists = distances(&sup, mobile, target);
+                let s = score(&dists);
+                if s > best {
+                    best = s;
+                    best_sup = sup;
+                }
+                // relax the cutoff until enough pairs remain to fit
+                let mut cutoff = d_select;
+                let mut next: Vec<usize> = Vec::new();
+                while next.len() < 3 && cutoff < d_select + 10.0 {
+                    next = (0..n).filter(|&i| dists[i] < cutoff).collect();
+                    cutoff += 0.5;
+                }
+                if next.len() < 3 || next == selected {
+                    break;
+                }
+                selected = next;
+            }
+        }
+        if frag_len <= 4 {
+            break;
+        }
+        frag_len = (frag_len / 2).max(4);
+    }
+    (best, best_sup)
+}
+
+/// Needleman-Wunsch over a similarity matrix with a gap-opening penalty and free end gaps,
+/// as in TM-align.
+pub(crate) fn dp_align(similarity: &[Vec<f32>], gap_open: f32) -> Vec<(usize, usize)> {
+    let n = similarity.len();
+    let m = similarity.first().map(|row| row.len()).unwrap_or(0);
+    let mut val = vec![vec![0f32; m + 1]; n + 1];
+    let mut diag = vec![vec![false; m + 1]; n + 1];
+    for i in 1..=n {
+        for j in 1..=m {
+            let d = val[i - 1][j - 1] + similarity[i - 1][j - 1];
+            let h = val[i - 1][j] + if diag[i - 1][j] { gap_open } else { 0.0 };
+            let v = val[i][j - 1] + if diag[i][j - 1] { gap_open } else { 0.0 };
+            if d >= h && d >= v {
+                val[i][j] = d;
+                diag[i][j] = true;
+            } else {
+                val[i][j] = h.max(v);
+            }
+        }
+    }
+    let mut pairs = Vec::new();
+    let (mut i, mut j) = (n, m);
+    while i > 0 && j > 0 {
+        if diag[i][j] {
+            pairs.push((i - 1, j - 1));
+            i -= 1;
+            j -= 1;
+        } else {
+            let h = val[i - 1][j] + if diag[i - 1][j] { gap_open } else { 0.0 };
+            if (val[i][j] - h).abs() < 1e-6 {
+                i -= 1;
+            } else {
+                j -= 1;
+            }
+        }
+    }
+    pairs.reverse();
+    pairs
+}
+
+fn tm_sum(dists: &[f32], d0: f32) -> f32 {
+    dists.iter().map(|d| 1.0 / (1.0 + (d / d0).powi(2))).sum()
+}
+
+/// TM-score (normalized by `norm_length`) and superposition for a given alignment
+fn score_alignment(
+    model: &[[f32; 3]],
+    reference: &[[f32; 3]],
+    pairs: &[(usize, usize)],
+    norm_length: usize,
+) -> (f32, Superposition) {
+    if pairs.is_empty() || norm_length == 0 {
+        return (0.0, Superposition::identity());
+    }
+    let mobile: Vec<[f32; 3]> = pairs.iter().map(|&(i, _)| model[i]).collect();
+    let target: Vec<[f32; 3]> = pairs.iter().map(|&(_, j)| reference[j]).collect();
+    let d0 = tm_d0(norm_length);
+    let d_select = d0.clamp(4.5, 8.0);
+    let (sum, sup) = optimize_superposition(&mobile, &target, d_select, |dists| tm_sum(dists, d0));
+    (sum / norm_length as f32, sup)
+}
+
+/// TM-align: search gapless threadings for a seed alignment, then iteratively
+/// re-align with dynamic programming on the superposed distances.
+fn tm_align(model: &[[f32; 3]], reference: &[[f32; 3]]) -> Vec<(usize, usize)> {
+    let (n, m) = (model.len(), reference.len());
+    if n == 0 || m == 0 {
+        return Vec::new();
+    }
+    let min_overlap = (n.min(m) / 2).max(1);
+
+    // gapless threading seeds
+    let mut best_pairs: Vec<(usize, usize)> = Vec::new();
+    let mut best_score = -1.0;
+    for offset in -(n as isize - 1)..(m as isize) {
+        let pairs: Vec<(usize, usize)> = (0..n)
+            .filter_map(|i| {
+                let j = i as isize + offset;
+                (j >= 0 && (j as usize) < m).then_some((i, j as usize))
+            })
+            .collect();
+        if pairs.len() < min_overlap {
+            continue;
+        }
+        // a single fit plus a few refinements is enough to rank seeds
+        let mobile: Vec<[f32; 3]> = pairs.iter().map(|&(i, _)| model[i]).collect();
+        let target: Vec<[f32; 3]> = pairs.iter().map(|&(_, j)| reference[j]).collect();
+        let d0 = tm_d0(m);
+        let mut sup = superpose(&mobile, &target);
+        let mut score = tm_sum(&distances(&sup, &mobile, &target), d0);
+        for _ in 0..3 {
+            let dists = distances(&sup, &mobile, &target);
+            let close: Vec<usize> = (0..pairs.len())
+                .filter(|&k| dists[k] < d0.clamp(4.5, 8.0))
+                .collect();
+            if close.len() < 3 {
+                break;
+            }
+            sup = superpose(&select(&mobile, &close), &select(&target, &close));
+            score = score.max(tm_sum(&distances(&sup, &mobile, &target), d0));
+        }
+        if score > best_score {
+            best_score = score;
+            best_pairs = pairs;
+        }
+    }
+
+    // iterative dynamic programming refinement
+    let d0 = tm_d0(m);
+    let (mut best_tm, mut sup) = score_alignment(model, reference, &best_pairs, m);
+    for _ in 0..30 {
+        let moved = sup.apply_all(model);
+        let similarity: Vec<Vec<f32>> = moved
+            .iter()
+            .map(|p| {
+                reference
+                    .iter()
+                    .map(|q| {
+                        let d = sub(*p, *q);
+                        1.0 / (1.0 + dot(d, d) / (d0 * d0))
+                    })
+                    .collect()
+            })
+            .collect();
+        let pairs = dp_align(&similarity, -0.6);
+        if pairs == best_pairs {
+            break;
+        }
+        let (tm, new_sup) = score_alignment(model, reference, &pairs, m);
+        if tm <= best_tm {
+            break;
+        }
+        best_tm = tm;
+        best_pairs = pairs;
+        sup = new_sup;
+    }
+    best_pairs
+}
+
+/// Pair model and reference residues with the given method.
+pub fn align_residues(
+    model: &[ResidueTrace],
+    reference: &[ResidueTrace],
+    method: AlignmentMethod,
+) -> Vec<(usize, usize)> {
+    match method {
+        AlignmentMethod::ResidueNumber => {
+            let lookup: HashMap<(&str, i32), usize> = reference
+                .iter()
+                .enumerate()
+                .map(|(j, res)| ((res.chain_id.as_str(), res.res_id), j))
+                .collect();
+            model
+                .iter()
+                .enumerate()
+                .filter_map(|(i, res)| {
+                    lookup
+                        .get(&(res.chain_id.as_str(), res.res_id))
+                        .map(|&j| (i, j))
+                })
+                .collect()
+        }
+        AlignmentMethod::SequenceOrder => (0..model.len().min(reference.len()))
+            .map(|i| (i, i))
+            .collect(),
+        AlignmentMethod::Structural => {
+            let model_ca: Vec<[f32; 3]> = model.iter().filter_map(|r| r.ca()).collect();
+            let reference_ca: Vec<[f32; 3]> = reference.iter().filter_map(|r| r.ca()).collect();
+            tm_align(&model_ca, &reference_ca)
+        }
+    }
+}
+
+fn ca_coords(trace: &[ResidueTrace]) -> Vec<[f32; 3]> {
+    trace.iter().filter_map(|r| r.ca()).collect()
+}
+
+/// Compute the TM-score of `model` against `reference` over CA atoms.
+pub fn tm_score(
+    model: &impl ToResidueTrace,
+    reference: &impl ToResidueTrace,
+    method: AlignmentMethod,
+) -> TMScore {
+    let model = model.residue_trace();
+    let reference = reference.residue_trace();
+    let alignment = align_residues(&model, &reference, method);
+    let (model_ca, reference_ca) = (ca_coords(&model), ca_coords(&reference));
+
+    let (tm, superposition) =
+        score_alignment(&model_ca, &reference_ca, &alignment, reference.len());
+    let (tm_model, _) = score_alignment(&model_ca, &reference_ca, &alignment, model.len());
+
+    let mobile: Vec<[f32; 3]> = alignment.iter().map(|&(i, _)| model_ca[i]).collect();
+    let target: Vec<[f32; 3]> = alignment.iter().map(|&(_, j)| reference_ca[j]).collect();
+    let dists = distances(&superposition, &mobile, &target);
+    let rmsd = if dists.is_empty() {
+        0.0
+    } else {
+        (dists.iter().map(|d| d * d).sum::<f32>() / dists.len() as f32).sqrt()
+    };
+    let identical = alignment
+        .iter()
+        .filter(|&&(i, j)| model[i].res_name == reference[j].res_name)
+        .count();
+
+    TMScore {
+        tm_score: tm,
+        tm_score_model: tm_model,
+        rmsd,
+        aligned_length: alignment.len(),
+        sequence_identity: if alignment.is_empty() {
+            0.0
+        } else {
+            identical as f32 / alignment.len() as f32
+        },
+        d0: tm_d0(reference.len()),
+        alignment,
+        superposition,
+    }
+}
+
+/// Compute GDT-TS and GDT-HA of `model` against `reference` over CA atoms.
+pub fn gdt(
+    model: &impl ToResidueTrace,
+    reference: &impl ToResidueTrace,
+    method: AlignmentMethod,
+) -> GDT {
+    let model = model.residue_trace();
+    let reference = reference.residue_trace();
+    let alignment = align_residues(&model, &reference, method);
+    let (model_ca, reference_ca) = (ca_coords(&model), ca_coords(&reference));
+    let mobile: Vec<[f32; 3]> = alignment.iter().map(|&(i, _)| model_ca[i]).collect();
+    let target: Vec<[f32; 3]> = alignment.iter().map(|&(_, j)| reference_ca[j]).collect();
+    let norm = reference.len().max(1) as f32;
+
+    let fractions: Vec<(f32, f32)> = [0.5f32, 1.0, 2.0, 4.0, 8.0]
+        .iter()
+        .map(|&cutoff| {
+            if mobile.is_empty() {
+                return (cutoff, 0.0);
+            }
+            let (count, _) = optimize_superposition(&mobile, &target, cutoff, |dists| {
+                dists.iter().filter(|&&d| d <= cutoff).count() as f32
+            });
+            (cutoff, count / norm)
+        })
+        .collect();
+    let fraction = |cutoff: f32| {
+        fractions
+            .iter()
+            .find(|(c, _)| *c == cutoff)
+            .map(|(_, f)| *f)
+            .unwrap_or(0.0)
+    };
+    GDT {
+        gdt_ts: (fraction(1.0) + fraction(2.0) + fraction(4.0) + fraction(8.0)) / 4.0,
+        gdt_ha: (fraction(0.5) + fraction(1.0) + fraction(2.0) + fraction(4.0)) / 4.0,
+        fractions,
+    }
+}
@@ -0,0 +1,83 @@
+//! Residue-level coordinates used by the comparison metrics.
+use crate::selection::AtomView;
+use crate::AtomCollection;
+use pdbtbx::Element;
+
+/// Heavy atoms of one amino acid residue
+#[derive(Debug, Clone)]
+pub struct ResidueTrace {
+    pub chain_id: String,
+    pub res_id: i32,
+    pub res_name: String,
+    /// (atom name, coordinates)
+    pub atoms: Vec<(String, [f32; 3])>,
+}
+
+impl ResidueTrace {
+    pub fn atom(&self, name: &str) -> Option<[f32; 3]> {
+        self.atoms
+            .iter()
+            .find(|(atom_name, _)| atom_name == name)
+            .map(|(_, coord)| *coord)
+    }
+
+    pub fn ca(&self) -> Option<[f32; 3]> {
+        self.atom("CA")
+    }
+}
+
+/// Types that can be reduced to a list of amino acid residues with a CA atom.
+pub trait ToResidueTrace {
+    fn residue_trace(&self) -> Vec<ResidueTrace>;
+}
+
+/// Group atom indices into residues and keep the amino acids that have a CA.
+fn trace_from_indices(
+    ac: &AtomCollection,
+    indices: impl Iterator<Item = usize>,
+) -> Vec<ResidueTrace> {
+    let mut residues: Vec<ResidueTrace> = Vec::new();
+    let mut last_key: Option<(&String, i32, &String)> = None;
+    for i in indices {
+        if *ac.get_element(i) == Element::H {
+            continue;
+        }
+        let key = (ac.get_chain_id(i), *ac.get_res_id(i), ac.get_res_name(i));
+        if last_key != Some(key) {
+            residues.push(ResidueTrace {
+                chain_id: key.0.clone(),
+                res_id: key.1,
+                res_name: key.2.clone(),
+                atoms: Vec::new(),
+            });
+            last_key = Some(key);
+        }
+        if let Some(residue) = residues.last_mut() {
+            residue
+                .atoms
+                .push((ac.get_atom_name(i).clone(), *ac.get_coord(i)));
+        }
+    }
+    residues
+        .into_iter()
+        .filter(|res| crate::info::constants::is_amino_acid(&res.res_name) && res.ca().is_some())
+        .collect()
+}
+
+impl ToResidueTrace for AtomCollection {
+    fn residue_trace(&self) -> Vec<ResidueTrace> {
+        trace_from_indices(self, 0..self.get_size())
+    }
+}
+
+impl ToResidueTrace for AtomView<'_> {
+    fn residue_trace(&self) -> Vec<ResidueTrace> {
+        trace_from_indices(self.collection(), self.indices().iter().copied())
+    }
+}
+
+impl ToResidueTrace for Vec<ResidueTrace> {
+    fn residue_trace(&self) -> Vec<ResidueTrace> {
+        self.clone()
+    }
+}
@@ -1,22 +1,38 @@
 use crate::AtomCollection;
 use itertools::Itertools;
-use pdbtbx::{Element, PDB};
+use pdbtbx::{Chain, Element, Model, PDB};
 
 impl From<&PDB> for AtomCollection {
     // the PDB API requires us to iterate:
     // PDB --> Chain --> Residue --> Atom if we want data from all.
     // Here we collect all the data in one go and return an AtomCollection
     fn from(pdb_data: &PDB) -> Self {
-        let (coords, is_hetero, atom_names, res_ids, res_names, elements, chain_ids): (
-            Vec<[f32; 3]>,
-            Vec<bool>,
-            Vec<String>,
-            Vec<i32>,
-            Vec<String>,
-            Vec<Element>,
-            Vec<String>,
-        ) = pdb_data
-            .chains()
+        from_chains(pdb_data.chains())
+    }
+}
+
+/// A single model, e.g. one conformer of an NMR ensemble.
+impl From<&Model> for AtomCollection {
+    fn from(model: &Model) -> Self {
+        from_chains(model.chains())
+    }
+}
+
+/// Per-atom columns: coords, hetero flags, atom names, residue ids, residue names,
+/// elements and chain ids
+type AtomColumns = (
+    Vec<[f32; 3]>,
+    Vec<bool>,
+    Vec<String>,
+    Vec<i32>,
+    Vec<String>,
+    Vec<Element>,
+    Vec<String>,
+);
+
+fn from_chains<'a>(chains: impl Iterator<Item = &'a Chain>) -> AtomCollection {
+    let (coords, is_hetero, atom_names, res_ids, res_names, elements, chain_ids): AtomColumns =
+        chains
             .flat_map(|chain| {
                 let chain_id = chain.id().to_string();
                 chain.residues().flat_map(move |residue| {
@@ -42,21 +58,20 @@ impl From<&PDB> for AtomCollection {
             })
             .multiunzip();
 
-        let mut ac = AtomCollection::new(
-            coords.len(),
-            coords,
-            res_ids,
-            res_names,
-            is_hetero,
-            elements,
-            atom_names,
-            chain_ids,
-            None,
-        );
+    let mut ac = AtomCollection::new(
+        coords.len(),
+        coords,
+        res_ids,
+        res_names,
+        is_hetero,
+        elements,
+        atom_names,
+        chain_ids,
+        None,
+    );
 
-        ac.connect_via_residue_names();
-        ac
-    }
+    ac.connect_via_residue_names();
+    ac
 }
 
 #[cfg(test)]
@@ -111,4 +126,15 @@ mod tests {
             [Element::C, Element::N, Element::O, Element::S, Element::Fe,]
         );
     }
+
+    #[test]
+    fn test_model_from() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb_data, _) = pdbtbx::open(prot_file).unwrap();
+        let model = pdb_data.models().next().unwrap();
+        let from_model = AtomCollection::from(model);
+        let from_pdb = AtomCollection::from(&pdb_data);
+        assert_eq!(from_model.get_size(), from_pdb.get_size());
+        assert_eq!(from_model.get_coords(), from_pdb.get_coords());
+    }
 }
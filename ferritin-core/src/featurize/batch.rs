@@ -0,0 +1,547 @@
+//! Batched Featurization
+//!
+//! [`StructureFeatures`] works on a single [`AtomCollection`] and always returns a
+//! batch of one. [`StructureBatch`] featurizes many structures at once: every
+//! per-structure tensor is padded along the residue axis to the longest structure
+//! and stacked along the batch axis. A residue mask marks the real positions and a
+//! [`ResidueSource`] map ties each batch position back to its source residue.
+//!
+//! ```no_run
+//! use candle_core::Device;
+//! use ferritin_core::{AtomCollection, StructureBatch};
+//! # fn example(structures: Vec<AtomCollection>) -> candle_core::Result<()> {
+//! let batch = StructureBatch::new(structures.iter().collect());
+//! let mpnn = batch.featurize_mpnn(&Device::Cpu)?; // x: [B, L, 4, 3]
+//! let esm = batch.featurize_esm(&Device::Cpu, false)?; // input_ids: [B, L + 2]
+//! let na = batch.featurize_nucleic_acids(&Device::Cpu)?; // backbone: [B, N, 3, 3]
+//! # Ok(())
+//! # }
+//! ```
+use super::nucleic_acid_features::NucleicAcidFeatures;
+use super::structure_features::StructureFeatures;
+use super::utilities::{aa3to1, ESM_SEQUENCE_VOCAB};
+use crate::AtomCollection;
+use candle_core::{DType, Device, Result, Tensor};
+use std::collections::HashMap;
+
+/// Identifies the residue a batch position was derived from.
+#[derive(Clone, Debug, PartialEq)]
+pub struct ResidueSource {
+    /// Index of the structure within the batch
+    pub structure_idx: usize,
+    /// Position of the residue within its structure's amino acid residues
+    pub residue_idx: usize,
+    pub chain_id: String,
+    pub res_id: i32,
+    pub res_name: String,
+}
+
+/// Padded ProteinMPNN/LigandMPNN inputs for a batch of structures.
+///
+/// `B` is the number of structures, `L` the longest residue count and `M` the
+/// largest number of ligand atoms in any structure.
+pub struct MPNNBatchFeatures {
+    /// Amino acid encodings `[B, L]`
+    pub s: Tensor,
+    /// Backbone coordinates (N, CA, C, O) `[B, L, 4, 3]`
+    pub x: Tensor,
+    /// Atom37 coordinates `[B, L, 37, 3]`
+    pub x_37: Tensor,
+    /// Residue mask `[B, L]`; 1.0 for real residues, 0.0 for padding
+    pub mask: Tensor,
+    /// Residue numbers `[B, L]`
+    pub residue_idx: Tensor,
+    /// Per-structure chain index `[B, L]`, numbered in order of appearance
+    pub chain_idx: Tensor,
+    /// Nearest ligand atom coordinates `[B, L, M, 3]`
+    pub y: Tensor,
+    /// Nearest ligand atom types `[B, L, M]`
+    pub y_t: Tensor,
+    /// Nearest ligand atom mask `[B, L, M]`
+    pub y_m: Tensor,
+    /// Source residue for every unpadded position of each batch row
+    pub residue_map: Vec<Vec<ResidueSource>>,
+}
+
+/// Padded ESM token inputs for a batch of structures.
+pub struct ESMBatchInputs {
+    /// Token ids `[B, T]` in `<cls> sequence <eos>` layout, padded with `<pad>`
+    pub input_ids: Tensor,
+    /// Attention mask `[B, T]`; 1 for tokens, 0 for padding
+    pub attention_mask: Tensor,
+    /// For each token, the index into `residue_map[b]` of its residue, if any
+    pub token_to_residue: Vec<Vec<Option<usize>>>,
+    /// Source residue for every residue of each batch row
+    pub residue_map: Vec<Vec<ResidueSource>>,
+}
+
+/// Padded DNA/RNA features for a batch of structures.
+///
+/// `N` is the largest number of nucleotides in any structure; structures without nucleic
+/// acid are all padding.
+pub struct NucleicAcidBatchFeatures {
+    /// Nucleotide encodings `[B, N]`, see [`NucleicAcidFeatures::encode_nucleotides`]
+    pub s: Tensor,
+    /// Backbone coordinates (P, C4', C1') `[B, N, 3, 3]`
+    pub backbone: Tensor,
+    /// Backbone atom mask `[B, N, 3]`
+    pub backbone_mask: Tensor,
+    /// Base frame origins `[B, N, 3]`
+    pub frame_translations: Tensor,
+    /// Base frame axes as columns `[B, N, 3, 3]`
+    pub frame_rotations: Tensor,
+    /// Nucleotide mask `[B, N]`; 1.0 for real nucleotides, 0.0 for padding
+    pub mask: Tensor,
+    /// Source nucleotide for every unpadded position of each batch row
+    pub nucleotide_map: Vec<Vec<ResidueSource>>,
+}
+
+impl MPNNBatchFeatures {
+    /// Look up the source residue of a batch position. Returns `None` for padding.
+    pub fn source(&self, batch_idx: usize, position: usize) -> Option<&ResidueSource> {
+        self.residue_map.get(batch_idx)?.get(position)
+    }
+}
+
+impl ESMBatchInputs {
+    /// Look up the source residue of a token. Returns `None` for special and padding tokens.
+    pub fn source(&self, batch_idx: usize, token_idx: usize) -> Option<&ResidueSource> {
+        let residue_idx = (*self.token_to_residue.get(batch_idx)?.get(token_idx)?)?;
+        self.residue_map[batch_idx].get(residue_idx)
+    }
+}
+
+/// A collection of structures featurized together.
+pub struct StructureBatch<'a> {
+    structures: Vec<&'a AtomCollection>,
+}
+
+impl<'a> StructureBatch<'a> {
+    pub fn new(structures: Vec<&'a AtomCollection>) -> Self {
+        StructureBatch { structures }
+    }
+
+    /// Number of structures in the batch
+    pub fn len(&self) -> usize {
+        self.structures.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.structures.is_empty()
+    }
+
+    /// Length of the longest structure, in amino acid residues
+    pub fn max_residues(&self) -> usize {
+        self.structures
+            .iter()
+            .map(|ac| ac.iter_residues_aminoacid().count())
+            .max()
+            .unwrap_or(0)
+    }
+
+    /// Source residues of each structure, in featurization order
+    pub fn residue_map(&self) -> Vec<Vec<ResidueSource>> {
+        self.structures
+            .iter()
+            .enumerate()
+            .map(|(structure_idx, ac)| {
+                ac.iter_residues_aminoacid()
+                    .enumerate()
+                    .map(|(residue_idx, res)| ResidueSource {
+                        structure_idx,
+                        residue_idx,
+                        chain_id: res.chain_id,
+                        res_id: res.res_id,
+                        res_name: res.res_name,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Source nucleotides of each structure, in featurization order
+    pub fn nucleotide_map(&self) -> Vec<Vec<ResidueSource>> {
+        self.structures
+            .iter()
+            .enumerate()
+            .map(|(structure_idx, ac)| {
+                ac.iter_residues_nucleotide()
+                    .enumerate()
+                    .map(|(residue_idx, res)| ResidueSource {
+                        structure_idx,
+                        residue_idx,
+                        chain_id: res.chain_id,
+                        res_id: res.res_id,
+                        res_name: res.res_name,
+                    })
+                    .collect()
+            })
+            .collect()
+    }
+
+    /// Featurize the DNA/RNA of every structure and stack the padded results, so protein
+    /// and nucleic acid residues of a complex can be fed to a model side by side.
+    pub fn featurize_nucleic_acids(&self, device: &Device) -> Result<NucleicAcidBatchFeatures> {
+        let nucleotide_map = self.nucleotide_map();
+        let max_len = nucleotide_map.iter().map(Vec::len).max().unwrap_or(0);
+
+        let mut s = Vec::with_capacity(self.len());
+        let mut backbone = Vec::with_capacity(self.len());
+        let mut backbone_mask = Vec::with_capacity(self.len());
+        let mut frame_translations = Vec::with_capacity(self.len());
+        let mut frame_rotations = Vec::with_capacity(self.len());
+        let mut mask = Vec::with_capacity(self.len() * max_len);
+        for (ac, nucleotides) in self.structures.iter().zip(&nucleotide_map) {
+            let pad = max_len - nucleotides.len();
+            let (bb, bb_mask) = ac.to_numeric_nucleic_backbone(device)?;
+            let (translations, rotations) = ac.to_numeric_base_frames(device)?;
+            s.push(ac.encode_nucleotides(device)?.pad_with_zeros(1, 0, pad)?);
+            backbone.push(bb.pad_with_zeros(1, 0, pad)?);
+            backbone_mask.push(bb_mask.pad_with_zeros(1, 0, pad)?);
+            frame_translations.push(translations.pad_with_zeros(1, 0, pad)?);
+            frame_rotations.push(rotations.pad_with_zeros(1, 0, pad)?);
+            mask.extend(std::iter::repeat_n(1f32, nucleotides.len()));
+            mask.extend(std::iter::repeat_n(0f32, pad));
+        }
+
+        Ok(NucleicAcidBatchFeatures {
+            s: Tensor::cat(&s, 0)?,
+            backbone: Tensor::cat(&backbone, 0)?,
+            backbone_mask: Tensor::cat(&backbone_mask, 0)?,
+            frame_translations: Tensor::cat(&frame_translations, 0)?,
+            frame_rotations: Tensor::cat(&frame_rotations, 0)?,
+            mask: Tensor::from_vec(mask, (self.len(), max_len), device)?,
+            nucleotide_map,
+        })
+    }
+
+    /// Featurize every structure for ProteinMPNN/LigandMPNN and stack the padded results.
+    pub fn featurize_mpnn(&self, device: &Device) -> Result<MPNNBatchFeatures> {
+        let max_len = self.max_residues();
+        let residue_map = self.residue_map();
+
+        let mut s = Vec::with_capacity(self.len());
+        let mut x = Vec::with_capacity(self.len());
+        let mut x_37 = Vec::with_capacity(self.len());
+        let mut ligands = Vec::with_capacity(self.len());
+        for ac in &self.structures {
+            let n = ac.iter_residues_aminoacid().count();
+            let pad = max_len - n;
+            s.push(ac.encode_amino_acids(device)?.pad_with_zeros(1, 0, pad)?);
+            x.push(
+                ac.to_numeric_backbone_atoms(device)?
+                    .pad_with_zeros(1, 0, pad)?,
+            );
+            x_37.push(ac.to_numeric_atom37(device)?.pad_with_zeros(1, 0, pad)?);
+            ligands.push(ligand_features(ac, n, device)?);
+        }
+
+        // ligand tensors are padded on both the residue and ligand-atom axes
+        let max_ligand_atoms = ligands.iter().map(|(_, _, _, m)| *m).max().unwrap_or(0);
+        let mut y = Vec::with_capacity(self.len());
+        let mut y_t = Vec::with_capacity(self.len());
+        let mut y_m = Vec::with_capacity(self.len());
+        for ((lig_y, lig_t, lig_m, m), residues) in ligands.into_iter().zip(&residue_map) {
+            let pad = max_len - residues.len();
+            let pad_atoms = max_ligand_atoms - m;
+            y.push(
+                lig_y
+                    .pad_with_zeros(1, 0, pad)?
+                    .pad_with_zeros(2, 0, pad_atoms)?,
+            );
+            y_t.push(
+                lig_t
+                    .pad_with_zeros(1, 0, pad)?
+                    .pad_with_zeros(2, 0, pad_atoms)?,
+            );
+            y_m.push(
+                lig_m
+                    .pad_with_zeros(1, 0, pad)?
+                    .pad_with_zeros(2, 0, pad_atoms)?,
+            );
+        }
+
+        let mut mask = Vec::with_capacity(self.len() * max_len);
+        let mut residue_idx = Vec::with_capacity(self.len() * max_len);
+        let mut chain_idx = Vec::with_capacity(self.len() * max_len);
+        for residues in &residue_map {
+            let mut chains: HashMap<&str, i64> = HashMap::new();
+            for res in residues {
+                let next_chain = chains.len() as i64;
+                mask.push(1f32);
+                residue_idx.push(res.res_id as i64);
+                chain_idx.push(*chains.entry(res.chain_id.as_str()).or_insert(next_chain));
+            }
+            let pad = max_len - residues.len();
+            mask.extend(std::iter::repeat_n(0f32, pad));
+            residue_idx.extend(std::iter::repeat_n(0i64, pad));
+            chain_idx.extend(std::iter::repeat_n(0i64, pad));
+        }
+        let shape = (self.len(), max_len);
+
+        Ok(MPNNBatchFeatures {
+            s: Tensor::cat(&s, 0)?,
+            x: Tensor::cat(&x, 0)?,
+            x_37: Tensor::cat(&x_37, 0)?,
+            mask: Tensor::from_vec(mask, shape, device)?,
+            residue_idx: Tensor::from_vec(residue_idx, shape, device)?,
+            chain_idx: Tensor::from_vec(chain_idx, shape, device)?,
+            y: Tensor::cat(&y, 0)?,
+            y_t: Tensor::cat(&y_t, 0)?,
+            y_m: Tensor::cat(&y_m, 0)?,
+            residue_map,
+        })
+    }
+
+    /// Tokenize every structure's sequence with the ESM vocabulary and stack the padded results.
+    ///
+    /// With `chain_break` set, a `|` token is inserted between chains (ESM3/ESMC); ESM2 has
+    /// no chain break token, so chains are concatenated directly.
+    pub fn featurize_esm(&self, device: &Device, chain_break: bool) -> Result<ESMBatchInputs> {
+        let residue_map = self.residue_map();
+        let (cls, pad, eos, chain_break_id) = (
+            esm_token_id("<cls>"),
+            esm_token_id("<pad>"),
+            esm_token_id("<eos>"),
+            esm_token_id("|"),
+        );
+
+        let mut rows: Vec<(Vec<u32>, Vec<Option<usize>>)> = Vec::with_capacity(self.len());
+        for residues in &residue_map {
+            let mut tokens = vec![cls];
+            let mut token_to_residue = vec![None];
+            for (i, res) in residues.iter().enumerate() {
+                if chain_break && i > 0 && residues[i - 1].chain_id != res.chain_id {
+                    tokens.push(chain_break_id);
+                    token_to_residue.push(None);
+                }
+                tokens.push(esm_token_id(&aa3to1(&res.res_name).to_string()));
+                token_to_residue.push(Some(i));
+            }
+            tokens.push(eos);
+            token_to_residue.push(None);
+            rows.push((tokens, token_to_residue));
+        }
+
+        let max_tokens = rows.iter().map(|(t, _)| t.len()).max().unwrap_or(0);
+        let mut input_ids = Vec::with_capacity(self.len() * max_tokens);
+        let mut attention_mask = Vec::with_capacity(self.len() * max_tokens);
+        let mut token_to_residue = Vec::with_capacity(self.len());
+        for (tokens, mut row_map) in rows {
+            let pad_len = max_tokens - tokens.len();
+            attention_mask.extend(std::iter::repeat_n(1i64, tokens.len()));
+            attention_mask.extend(std::iter::repeat_n(0i64, pad_len));
+            input_ids.extend(tokens.into_iter().map(|t| t as i64));
+            input_ids.extend(std::iter::repeat_n(pad as i64, pad_len));
+            row_map.extend(std::iter::repeat_n(None, pad_len));
+            token_to_residue.push(row_map);
+        }
+        let shape = (self.len(), max_tokens);
+
+        Ok(ESMBatchInputs {
+            input_ids: Tensor::from_vec(input_ids, shape, device)?,
+            attention_mask: Tensor::from_vec(attention_mask, shape, device)?,
+            token_to_residue,
+            residue_map,
+        })
+    }
+}
+
+/// ESM token id for a single token, falling back to `<unk>`.
+fn esm_token_id(token: &str) -> u32 {
+    ESM_SEQUENCE_VOCAB
+        .iter()
+        .position(|&t| t == token)
+        .or_else(|| ESM_SEQUENCE_VOCAB.iter().position(|&t| t == "<unk>"))
+        .unwrap() as u32
+}
+
+/// Ligand context for one structure as `(y, y_t, y_m, ligand_atom_count)`.
+///
+/// Structures without ligand atoms get empty `[1, n, 0, ..]` tensors so they can be
+/// padded alongside the rest of the batch.
+fn ligand_features(
+    ac: &AtomCollection,
+    n_residues: usize,
+    device: &Device,
+) -> Result<(Tensor, Tensor, Tensor, usize)> {
+    let has_ligand = ac.iter_residues_all().any(|res| res.is_ligand_context());
+    if !has_ligand {
+        return Ok((
+            Tensor::zeros((1, n_residues, 0, 3), DType::F32, device)?,
+            Tensor::zeros((1, n_residues, 0), DType::I64, device)?,
+            Tensor::zeros((1, n_residues, 0), DType::F32, device)?,
+            0,
+        ));
+    }
+    let (y, y_t, y_m) = ac.to_numeric_ligand_atoms(device)?;
+    let m = y_t.dim(2)?;
+    Ok((y, y_t, y_m, m))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Selection;
+    use ferritin_test_data::TestFile;
+    use pdbtbx::Element;
+
+    #[test]
+    fn test_backbone_rows_follow_residue_order() {
+        // row k holds the k-th residue: the same rows as indexing by residue number for
+        // 101m, numbered from 0, and no overrun for a structure numbered from 1
+        for (test_file, first_res_id) in [(TestFile::protein_01(), 0), (TestFile::protein_04(), 1)]
+        {
+            let (file, _temp) = test_file.create_temp().unwrap();
+            let (pdb, _) = pdbtbx::open(file).unwrap();
+            let ac = AtomCollection::from(&pdb);
+            let residues: Vec<_> = ac.iter_residues_aminoacid().collect();
+            assert_eq!(residues[0].res_id, first_res_id);
+            let x = ac
+                .to_numeric_backbone_atoms(&Device::Cpu)
+                .unwrap()
+                .squeeze(0)
+                .unwrap()
+                .to_vec3::<f32>()
+                .unwrap();
+            assert_eq!(x.len(), residues.len());
+            for (row, residue) in x.iter().zip(&residues) {
+                for (coords, name) in row.iter().zip(["N", "CA", "C", "O"]) {
+                    let expected = residue
+                        .find_atom_by_name(name)
+                        .map_or([0.0; 3], |atom| *atom.coords);
+                    assert_eq!(coords.as_slice(), expected.as_slice());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_batch_padding() {
+        let (file_01, _temp_01) = TestFile::protein_01().create_temp().unwrap();
+        let (file_04, _temp_04) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb_01, _) = pdbtbx::open(file_01).unwrap();
+        let (pdb_04, _) = pdbtbx::open(file_04).unwrap();
+        let ac_01 = AtomCollection::from(&pdb_01);
+        let ac_04 = AtomCollection::from(&pdb_04);
+        let n_01 = ac_01.iter_residues_aminoacid().count();
+        let n_04 = ac_04.iter_residues_aminoacid().count();
+
+        let batch = StructureBatch::new(vec![&ac_01, &ac_04]);
+        let max_len = n_01.max(n_04);
+        assert_eq!(batch.max_residues(), max_len);
+
+        let device = Device::Cpu;
+        let features = batch.featurize_mpnn(&device).unwrap();
+        assert_eq!(features.x.dims(), &[2, max_len, 4, 3]);
+        assert_eq!(features.x_37.dims(), &[2, max_len, 37, 3]);
+        assert_eq!(features.s.dims(), &[2, max_len]);
+        assert_eq!(features.y.dim(1).unwrap(), max_len);
+
+        let mask = features.mask.to_vec2::<f32>().unwrap();
+        assert_eq!(mask[0].iter().sum::<f32>() as usize, n_01);
+        assert_eq!(mask[1].iter().sum::<f32>() as usize, n_04);
+
+        let first = features.source(0, 0).unwrap();
+        assert_eq!(first.res_name, "MET");
+        assert_eq!(first.chain_id, "A");
+        assert!(features.source(0, n_01).is_none());
+
+        let esm = batch.featurize_esm(&device, false).unwrap();
+        assert_eq!(esm.input_ids.dims(), &[2, max_len + 2]);
+        let ids = esm.input_ids.to_vec2::<i64>().unwrap();
+        assert_eq!(ids[0][0], esm_token_id("<cls>") as i64);
+        assert_eq!(ids[0][1], esm_token_id("M") as i64);
+        assert_eq!(ids[0][n_01 + 1], esm_token_id("<eos>") as i64);
+        assert_eq!(esm.source(0, 1).unwrap().res_name, "MET");
+        assert!(esm.source(0, 0).is_none());
+    }
+
+    #[test]
+    fn test_batch_nucleic_acids() {
+        // 1BC8: SAP-1 ETS domain bound to DNA, with two zinc ions
+        let (file_01, _temp_01) = TestFile::protein_01().create_temp().unwrap();
+        let (file_02, _temp_02) = TestFile::protein_03().create_temp().unwrap();
+        let (pdb_01, _) = pdbtbx::open(file_01).unwrap();
+        let (pdb_02, _) = pdbtbx::open(file_02).unwrap();
+        let ac_01 = AtomCollection::from(&pdb_01);
+        let ac_02 = AtomCollection::from(&pdb_02);
+        let n_dna = ac_02.iter_residues_nucleotide().count();
+        assert!(n_dna > 0);
+        assert_eq!(ac_01.iter_residues_nucleotide().count(), 0);
+
+        let device = Device::Cpu;
+        let batch = StructureBatch::new(vec![&ac_01, &ac_02]);
+        let na = batch.featurize_nucleic_acids(&device).unwrap();
+        assert_eq!(na.s.dims(), &[2, n_dna]);
+        assert_eq!(na.backbone.dims(), &[2, n_dna, 3, 3]);
+        assert_eq!(na.backbone_mask.dims(), &[2, n_dna, 3]);
+        assert_eq!(na.frame_translations.dims(), &[2, n_dna, 3]);
+        assert_eq!(na.frame_rotations.dims(), &[2, n_dna, 3, 3]);
+        let mask = na.mask.to_vec2::<f32>().unwrap();
+        assert_eq!(mask[0].iter().sum::<f32>(), 0.0);
+        assert_eq!(mask[1].iter().sum::<f32>() as usize, n_dna);
+        assert!(na.nucleotide_map[0].is_empty());
+        assert_eq!(na.nucleotide_map[1].len(), n_dna);
+
+        // the DNA heavy atoms stay in the ligand context next to the zinc ions
+        let zinc_atoms = ac_02
+            .iter_residues_all()
+            .filter(|res| res.res_name == "ZN")
+            .count();
+        let dna_heavy_atoms = ac_02
+            .iter_residues_nucleotide()
+            .map(|res| {
+                res.iter_atoms()
+                    .filter(|atom| !matches!(atom.element, Element::H | Element::He))
+                    .count()
+            })
+            .sum::<usize>();
+        assert!(dna_heavy_atoms > 0);
+        let n_02 = ac_02.iter_residues_aminoacid().count();
+        let (y, y_t, _, m) = ligand_features(&ac_02, n_02, &device).unwrap();
+        assert_eq!(m, zinc_atoms + dna_heavy_atoms);
+        assert_eq!(y.dims(), &[1, n_02, m, 3]);
+        let elements = y_t.flatten_all().unwrap().to_vec1::<i64>().unwrap();
+        assert!(elements.contains(&15), "phosphorus from the DNA backbone");
+    }
+
+    #[test]
+    fn test_batch_padding_mixed_ligands() {
+        let (file_01, _temp_01) = TestFile::protein_01().create_temp().unwrap();
+        let (file_04, _temp_04) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb_01, _) = pdbtbx::open(file_01).unwrap();
+        let (pdb_04, _) = pdbtbx::open(file_04).unwrap();
+        let with_ligand = AtomCollection::from(&pdb_01);
+        // drop the rapamycin and waters of 1fap so only protein atoms remain
+        let ac_04 = AtomCollection::from(&pdb_04);
+        let protein_atoms: Vec<usize> = ac_04
+            .iter_residues_aminoacid()
+            .flat_map(|res| res.atoms.indices().to_vec())
+            .collect();
+        let without_ligand = ac_04.subset(&Selection::new(protein_atoms));
+        let n_with = with_ligand.iter_residues_aminoacid().count();
+        let n_without = without_ligand.iter_residues_aminoacid().count();
+        assert_ne!(n_with, n_without);
+
+        let batch = StructureBatch::new(vec![&with_ligand, &without_ligand]);
+        let max_len = batch.max_residues();
+        let features = batch.featurize_mpnn(&Device::Cpu).unwrap();
+        let (_, _, _, m) = ligand_features(&with_ligand, n_with, &Device::Cpu).unwrap();
+        assert!(m > 0);
+        assert_eq!(features.y.dims(), &[2, max_len, m, 3]);
+        assert_eq!(features.y_t.dims(), &[2, max_len, m]);
+        assert_eq!(features.y_m.dims(), &[2, max_len, m]);
+
+        // ligand atoms only for the structure that has them, and never on padding
+        let y_m = features.y_m.to_vec3::<f32>().unwrap();
+        let ligand_atoms = |row: &[Vec<f32>]| row.iter().flatten().sum::<f32>();
+        assert!(ligand_atoms(&y_m[0][..n_with]) > 0.0);
+        assert_eq!(ligand_atoms(&y_m[0][n_with..]), 0.0);
+        assert_eq!(ligand_atoms(&y_m[1]), 0.0);
+
+        let mask = features.mask.to_vec2::<f32>().unwrap();
+        assert_eq!(mask[0].iter().sum::<f32>() as usize, n_with);
+        assert_eq!(mask[1].iter().sum::<f32>() as usize, n_without);
+    }
+}
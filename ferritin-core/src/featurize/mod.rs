@@ -9,7 +9,13 @@
 //! - Chemical features like hydrophobicity, charge
 //! - Evolutionary features from MSA profiles
 
+mod batch;
+mod nucleic_acid_features;
 mod structure_features;
-mod utilities;
+pub(crate) mod utilities;
 
+pub use batch::{
+    ESMBatchInputs, MPNNBatchFeatures, NucleicAcidBatchFeatures, ResidueSource, StructureBatch,
+};
+pub use nucleic_acid_features::{NucleicAcidFeatures, PolymerType};
 pub use structure_features::StructureFeatures;
@@ -0,0 +1,374 @@
+//! Nucleic Acid and Glycan -> Tensor utilities
+//!
+//! [`StructureFeatures`](super::StructureFeatures) only featurizes amino acids and folds
+//! every other non-water residue, nucleotides and glycans included, into the ligand context.
+//! These features also keep DNA, RNA and glycan residues as residues so that
+//! protein-nucleic-acid complexes can be featurized with per-nucleotide detail; see
+//! [`StructureBatch::featurize_nucleic_acids`](super::StructureBatch::featurize_nucleic_acids)
+//! for batches.
+use super::utilities::{glycan3to_int, na3to_int};
+use crate::geometry::{cross, dot, normalize, sub};
+use crate::residue::ResidueAtoms;
+use crate::AtomCollection;
+use candle_core::{Device, Result, Tensor};
+
+/// Polymer classification of a residue
+#[repr(u32)]
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PolymerType {
+    Protein = 0,
+    DNA = 1,
+    RNA = 2,
+    Glycan = 3,
+    Other = 4,
+}
+
+impl PolymerType {
+    pub fn from_residue(residue: &ResidueAtoms) -> Self {
+        if residue.is_amino_acid() {
+            PolymerType::Protein
+        } else if residue.is_nucleotide() {
+            if is_rna(residue) {
+                PolymerType::RNA
+            } else {
+                PolymerType::DNA
+            }
+        } else if residue.is_carbohydrate() {
+            PolymerType::Glycan
+        } else {
+            PolymerType::Other
+        }
+    }
+}
+
+// Helper Fns --------------------------------------
+
+/// RNA carries a 2' hydroxyl; deoxyribose does not.
+fn is_rna(residue: &ResidueAtoms) -> bool {
+    residue.find_atom_by_name("O2'").is_some()
+}
+
+/// Purines attach to the sugar via N9, pyrimidines via N1.
+fn is_purine(residue: &ResidueAtoms) -> bool {
+    match residue.res_name.as_str() {
+        "DA" | "DG" | "A" | "G" => true,
+        "DC" | "DT" | "DU" | "C" | "U" => false,
+        _ => residue.find_atom_by_name("N9").is_some(),
+    }
+}
+
+/// Fill a `[n, atom_names.len(), 3]` coordinate buffer and matching `[n, atom_names.len()]` mask.
+fn collect_named_atoms<'a>(
+    residues: impl Iterator<Item = ResidueAtoms<'a>>,
+    atom_names: &[&str],
+) -> (usize, Vec<f32>, Vec<f32>) {
+    let mut coords = Vec::new();
+    let mut mask = Vec::new();
+    let mut count = 0;
+    for residue in residues {
+        for name in atom_names {
+            match residue.find_atom_by_name(name) {
+                Some(atom) => {
+                    coords.extend_from_slice(atom.coords);
+                    mask.push(1.0);
+                }
+                None => {
+                    coords.extend_from_slice(&[0.0; 3]);
+                    mask.push(0.0);
+                }
+            }
+        }
+        count += 1;
+    }
+    (count, coords, mask)
+}
+
+/// Trait defining Nucleic Acid and Glycan -> Tensor utilities useful for Machine Learning
+pub trait NucleicAcidFeatures {
+    /// Convert nucleotide sequence to numeric representation
+    fn encode_nucleotides(&self, device: &Device) -> Result<Tensor>;
+
+    /// Get nucleotide residue indices
+    fn get_nucleotide_index(&self) -> Vec<u32>;
+
+    /// Extract nucleic acid backbone atom coordinates (P, C4', C1') and their mask
+    fn to_numeric_nucleic_backbone(&self, device: &Device) -> Result<(Tensor, Tensor)>;
+
+    /// Compute a rigid frame per nucleotide base as (translations, rotations)
+    fn to_numeric_base_frames(&self, device: &Device) -> Result<(Tensor, Tensor)>;
+
+    /// Convert glycan residues to numeric representation
+    fn encode_glycans(&self, device: &Device) -> Result<Tensor>;
+
+    /// Extract pyranose ring atom coordinates (C1-C5, O5) and their mask
+    fn to_numeric_glycan_ring(&self, device: &Device) -> Result<(Tensor, Tensor)>;
+
+    /// Classify every residue as a [`PolymerType`]
+    fn to_numeric_polymer_types(&self, device: &Device) -> Result<Tensor>;
+}
+
+impl NucleicAcidFeatures for AtomCollection {
+    /// Tensor of shape [1, <nucleotides>]; see `na3to_int` for the encoding
+    fn encode_nucleotides(&self, device: &Device) -> Result<Tensor> {
+        let encoded: Vec<u32> = self
+            .iter_residues_nucleotide()
+            .map(|res| na3to_int(&res.res_name, is_rna(&res)))
+            .collect();
+        let n = encoded.len();
+        Tensor::from_vec(encoded, (1, n), device)
+    }
+
+    fn get_nucleotide_index(&self) -> Vec<u32> {
+        self.iter_residues_nucleotide()
+            .map(|res| res.res_id as u32)
+            .collect()
+    }
+
+    /// create numeric Tensor of shape [1, <nucleotides>, 3, 3] where the 3 is P/C4'/C1'
+    /// and a mask of shape [1, <nucleotides>, 3]. The 5' terminal P is often absent.
+    fn to_numeric_nucleic_backbone(&self, device: &Device) -> Result<(Tensor, Tensor)> {
+        let (n, coords, mask) =
+            collect_named_atoms(self.iter_residues_nucleotide(), &["P", "C4'", "C1'"]);
+        Ok((
+            Tensor::from_vec(coords, (1, n, 3, 3), device)?,
+            Tensor::from_vec(mask, (1, n, 3), device)?,
+        ))
+    }
+
+    /// Base frames are built from C1', the glycosidic nitrogen (N9/N1) and the adjacent
+    /// ring carbon (C4/C2): the origin is C1', the x-axis points along the glycosidic bond
+    /// and the base ring lies in the xy-plane.
+    ///
+    /// Returns translations of shape [1, <nucleotides>, 3] and rotations of shape
+    /// [1, <nucleotides>, 3, 3] whose columns are the frame axes. Nucleotides missing any
+    /// of the three atoms get the identity rotation.
+    fn to_numeric_base_frames(&self, device: &Device) -> Result<(Tensor, Tensor)> {
+        let mut translations = Vec::new();
+        let mut rotations = Vec::new();
+        let mut n = 0;
+        for residue in self.iter_residues_nucleotide() {
+            let (n_name, c_name) = if is_purine(&residue) {
+                ("N9", "C4")
+            } else {
+                ("N1", "C2")
+            };
+            let frame_atoms = (
+                residue.find_atom_by_name("C1'"),
+                residue.find_atom_by_name(n_name),
+                residue.find_atom_by_name(c_name),
+            );
+            match frame_atoms {
+                (Some(c1), Some(n_glyc), Some(c_base)) => {
+                    let origin = *c1.coords;
+                    let e1 = normalize(sub(*n_glyc.coords, origin));
+                    let u2 = sub(*c_base.coords, origin);
+                    let proj = dot(u2, e1);
+                    let e2 = normalize([
+                        u2[0] - proj * e1[0],
+                        u2[1] - proj * e1[1],
+                        u2[2] - proj * e1[2],
+                    ]);
+                    let e3 = cross(e1, e2);
+                    translations.extend_from_slice(&origin);
+                    // row-major storage with the axes as columns
+                    for row in 0..3 {
+                        rotations.extend_from_slice(&[e1[row], e2[row], e3[row]]);
+                    }
+                }
+                (c1, _, _) => {
+                    let origin = c1.map(|atom| *atom.coords).unwrap_or([0.0; 3]);
+                    translations.extend_from_slice(&origin);
+                    rotations.extend_from_slice(&[1.0, 0.0, 0.0, 0.0, 1.0, 0.0, 0.0, 0.0, 1.0]);
+                }
+            }
+            n += 1;
+        }
+        Ok((
+            Tensor::from_vec(translations, (1, n, 3), device)?,
+            Tensor::from_vec(rotations, (1, n, 3, 3), device)?,
+        ))
+    }
+
+    /// Tensor of shape [1, <glycans>]; see `glycan3to_int` for the encoding
+    fn encode_glycans(&self, device: &Device) -> Result<Tensor> {
+        let encoded: Vec<u32> = self
+            .iter_residues_carbohydrate()
+            .map(|res| glycan3to_int(&res.res_name))
+            .collect();
+        let n = encoded.len();
+        Tensor::from_vec(encoded, (1, n), device)
+    }
+
+    /// create numeric Tensor of shape [1, <glycans>, 6, 3] where the 6 is C1/C2/C3/C4/C5/O5
+    /// and a mask of shape [1, <glycans>, 6]. Furanoses will be missing C5/O5 in the mask.
+    fn to_numeric_glycan_ring(&self, device: &Device) -> Result<(Tensor, Tensor)> {
+        let (n, coords, mask) = collect_named_atoms(
+            self.iter_residues_carbohydrate(),
+            &["C1", "C2", "C3", "C4", "C5", "O5"],
+        );
+        Ok((
+            Tensor::from_vec(coords, (1, n, 6, 3), device)?,
+            Tensor::from_vec(mask, (1, n, 6), device)?,
+        ))
+    }
+
+    /// Tensor of shape [1, <all residues>] holding the [`PolymerType`] of each residue
+    fn to_numeric_polymer_types(&self, device: &Device) -> Result<Tensor> {
+        let types: Vec<u32> = self
+            .iter_residues_all()
+            .map(|res| PolymerType::from_residue(&res) as u32)
+            .collect();
+        let n = types.len();
+        Tensor::from_vec(types, (1, n), device)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+    use pdbtbx::Element;
+
+    #[test]
+    fn test_dna_features() {
+        // 1BC8: SAP-1 ETS domain bound to DNA
+        let (prot_file, _temp) = TestFile::protein_03().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let device = Device::Cpu;
+
+        let n = ac.iter_residues_nucleotide().count();
+        assert!(n > 0);
+        assert!(ac
+            .iter_residues_nucleotide()
+            .all(|res| PolymerType::from_residue(&res) == PolymerType::DNA));
+
+        let encoded = ac.encode_nucleotides(&device).unwrap();
+        assert_eq!(encoded.dims(), &[1, n]);
+        assert!(encoded
+            .squeeze(0)
+            .unwrap()
+            .to_vec1::<u32>()
+            .unwrap()
+            .iter()
+            .all(|&v| v < 4));
+
+        let (backbone, mask) = ac.to_numeric_nucleic_backbone(&device).unwrap();
+        assert_eq!(backbone.dims(), &[1, n, 3, 3]);
+        assert_eq!(mask.dims(), &[1, n, 3]);
+
+        let (translations, rotations) = ac.to_numeric_base_frames(&device).unwrap();
+        assert_eq!(translations.dims(), &[1, n, 3]);
+        assert_eq!(rotations.dims(), &[1, n, 3, 3]);
+
+        // frames are orthonormal
+        let rot = rotations.squeeze(0).unwrap().to_vec3::<f32>().unwrap();
+        for r in rot {
+            let col = |j: usize| [r[0][j], r[1][j], r[2][j]];
+            assert!((dot(col(0), col(0)) - 1.0).abs() < 1e-4);
+            assert!(dot(col(0), col(1)).abs() < 1e-4);
+            assert!(dot(col(1), col(2)).abs() < 1e-4);
+        }
+
+        let polymer_types = ac.to_numeric_polymer_types(&device).unwrap();
+        assert_eq!(
+            polymer_types.dim(1).unwrap(),
+            ac.iter_residues_all().count()
+        );
+
+        // no glycans in 1BC8
+        let glycans = ac.encode_glycans(&device).unwrap();
+        assert_eq!(glycans.dims(), &[1, 0]);
+    }
+
+    /// ASN 1 of chain A carrying an N-linked NAG on ND2, and a water
+    fn glycosylated_asparagine() -> AtomCollection {
+        let atoms = [
+            ("ASN", "N", Element::N, [0.0, 0.0, 0.0]),
+            ("ASN", "CA", Element::C, [1.46, 0.0, 0.0]),
+            ("ASN", "C", Element::C, [2.0, 1.42, 0.0]),
+            ("ASN", "O", Element::O, [1.25, 2.4, 0.0]),
+            ("ASN", "CB", Element::C, [2.0, -0.8, 1.2]),
+            ("ASN", "CG", Element::C, [3.5, -0.8, 1.3]),
+            ("ASN", "OD1", Element::O, [4.2, -0.1, 0.6]),
+            ("ASN", "ND2", Element::N, [4.1, -1.6, 2.2]),
+            ("NAG", "C1", Element::C, [5.5, -1.7, 2.4]),
+            ("NAG", "C2", Element::C, [6.0, -3.1, 2.8]),
+            ("NAG", "C3", Element::C, [7.5, -3.1, 3.0]),
+            ("NAG", "C4", Element::C, [8.1, -2.4, 1.8]),
+            ("NAG", "C5", Element::C, [7.6, -1.0, 1.6]),
+            ("NAG", "C6", Element::C, [8.2, -0.2, 0.5]),
+            ("NAG", "C7", Element::C, [4.6, -4.9, 3.6]),
+            ("NAG", "C8", Element::C, [4.1, -5.6, 4.8]),
+            ("NAG", "N2", Element::N, [5.4, -3.8, 3.9]),
+            ("NAG", "O3", Element::O, [8.0, -4.4, 3.2]),
+            ("NAG", "O4", Element::O, [9.5, -2.4, 2.0]),
+            ("NAG", "O5", Element::O, [6.2, -1.0, 1.4]),
+            ("NAG", "O6", Element::O, [9.6, -0.3, 0.5]),
+            ("NAG", "O7", Element::O, [4.3, -5.2, 2.5]),
+            // residue iteration stops before the final residue, so end on a water as
+            // deposited files do
+            ("HOH", "O", Element::O, [12.0, 0.0, 0.0]),
+        ];
+        AtomCollection::new(
+            atoms.len(),
+            atoms.iter().map(|atom| atom.3).collect(),
+            atoms
+                .iter()
+                .map(|atom| if atom.0 == "ASN" { 1 } else { 2 })
+                .collect(),
+            atoms.iter().map(|atom| atom.0.to_string()).collect(),
+            atoms.iter().map(|atom| atom.0 != "ASN").collect(),
+            atoms.iter().map(|atom| atom.2).collect(),
+            atoms.iter().map(|atom| atom.1.to_string()).collect(),
+            vec!["A".to_string(); atoms.len()],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_glycan_features() {
+        let ac = glycosylated_asparagine();
+        let device = Device::Cpu;
+
+        let polymer_types = ac.to_numeric_polymer_types(&device).unwrap();
+        assert_eq!(
+            polymer_types.to_vec2::<u32>().unwrap(),
+            vec![vec![
+                PolymerType::Protein as u32,
+                PolymerType::Glycan as u32
+            ]]
+        );
+        let glycans = ac.encode_glycans(&device).unwrap();
+        assert_eq!(glycans.to_vec2::<u32>().unwrap(), vec![vec![0]]);
+
+        // ring atoms in C1/C2/C3/C4/C5/O5 order, all present for the pyranose
+        let (ring, mask) = ac.to_numeric_glycan_ring(&device).unwrap();
+        assert_eq!(ring.dims(), &[1, 1, 6, 3]);
+        assert_eq!(
+            ring.squeeze(0)
+                .unwrap()
+                .squeeze(0)
+                .unwrap()
+                .to_vec2::<f32>()
+                .unwrap(),
+            vec![
+                vec![5.5, -1.7, 2.4],
+                vec![6.0, -3.1, 2.8],
+                vec![7.5, -3.1, 3.0],
+                vec![8.1, -2.4, 1.8],
+                vec![7.6, -1.0, 1.6],
+                vec![6.2, -1.0, 1.4],
+            ]
+        );
+        assert_eq!(mask.to_vec3::<f32>().unwrap(), vec![vec![vec![1.0; 6]]]);
+
+        // the glycan is also ligand context for the asparagine
+        let context: Vec<String> = ac
+            .iter_residues_all()
+            .filter(|res| res.is_ligand_context())
+            .map(|res| res.res_name)
+            .collect();
+        assert_eq!(context, vec!["NAG".to_string()]);
+    }
+}
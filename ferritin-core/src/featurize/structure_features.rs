@@ -50,7 +50,7 @@ impl StructureFeatures for AtomCollection {
             .map(|res| aa3to1(&res))
             .map(|res| aa1to_int(res));
 
-        Ok(Tensor::from_iter(s, device)?.reshape((1, n))?)
+        Tensor::from_iter(s, device)?.reshape((1, n))
     }
 
     /// Calcualte CB for each residue
@@ -113,8 +113,7 @@ impl StructureFeatures for AtomCollection {
     fn to_numeric_backbone_atoms(&self, device: &Device) -> Result<Tensor> {
         let res_count = self.iter_residues_aminoacid().count();
         let mut backbone_data = vec![0f32; res_count * 4 * 3];
-        for residue in self.iter_residues_aminoacid() {
-            let resid = residue.res_id as usize;
+        for (resid, residue) in self.iter_residues_aminoacid().enumerate() {
             let backbone_atoms = [
                 residue.find_atom_by_name("N"),
                 residue.find_atom_by_name("CA"),
@@ -169,10 +168,7 @@ impl StructureFeatures for AtomCollection {
         // keep only the non-protein, non-water residues that are heavy
         let (coords, elements): (Vec<[f32; 3]>, Vec<Element>) = self
             .iter_residues_all()
-            .filter(|residue| {
-                let res_name = &residue.res_name;
-                !residue.is_amino_acid() && res_name != "HOH" && res_name != "WAT"
-            })
+            .filter(|residue| residue.is_ligand_context())
             .flat_map(|residue| {
                 residue
                     .iter_atoms()
@@ -44,11 +44,41 @@ pub fn int_to_aa1(aa_int: u32) -> char {
     }
 }
 
+#[rustfmt::skip]
+/// Nucleotide encoding: DNA bases 0-3, unknown DNA 4, RNA bases 5-8, unknown RNA 9.
+/// Modified nucleotides are encoded as unknown of their polymer type.
+pub fn na3to_int(na: &str, is_rna: bool) -> u32 {
+    match na {
+        "DA" => 0, "DC" => 1, "DG" => 2, "DT" => 3,
+        "A"  => 5, "C"  => 6, "G"  => 7, "U"  => 8,
+        _ if is_rna => 9,
+        _ => 4,
+    }
+}
+
+#[rustfmt::skip]
+/// Encoding for the most common glycan residues in the PDB; anything else is 12.
+pub fn glycan3to_int(glycan: &str) -> u32 {
+    match glycan {
+        "NAG" => 0,  "NDG" => 1,  "MAN" => 2,  "BMA" => 3,
+        "GAL" => 4,  "GLA" => 5,  "GLC" => 6,  "BGC" => 7,
+        "FUC" => 8,  "FUL" => 9,  "SIA" => 10, "XYP" => 11,
+        _ => 12,
+    }
+}
+
 const ALPHABET: [char; 21] = [
     'A', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'V', 'W',
     'Y', 'X',
 ];
 
+/// ESM sequence vocabulary, shared by ESM2, ESM3 and ESMC.
+/// ESM2 uses `<null_1>` where ESM3/ESMC use the `|` chain break token.
+pub const ESM_SEQUENCE_VOCAB: [&str; 33] = [
+    "<cls>", "<pad>", "<eos>", "<unk>", "L", "A", "G", "V", "S", "E", "R", "T", "I", "D", "P", "K",
+    "Q", "N", "F", "Y", "M", "H", "W", "C", "X", "B", "U", "Z", "O", ".", "-", "|", "<mask>",
+];
+
 const ELEMENT_LIST: [&str; 118] = [
     "H", "He", "Li", "Be", "B", "C", "N", "O", "F", "Ne", "Na", "Mg", "Al", "Si", "P", "S", "Cl",
     "Ar", "K", "Ca", "Sc", "Ti", "V", "Cr", "Mn", "Fe", "Co", "Ni", "Cu", "Zn", "Ga", "Ge", "As",
@@ -0,0 +1,169 @@
+//! Geometry
+//!
+//! Small vector helpers for distances, angles and dihedrals on `[f32; 3]`
+//! coordinates, and a uniform grid for fast neighbour queries.
+use std::collections::HashMap;
+
+pub(crate) fn sub(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [a[0] - b[0], a[1] - b[1], a[2] - b[2]]
+}
+
+pub(crate) fn dot(a: [f32; 3], b: [f32; 3]) -> f32 {
+    a[0] * b[0] + a[1] * b[1] + a[2] * b[2]
+}
+
+pub(crate) fn cross(a: [f32; 3], b: [f32; 3]) -> [f32; 3] {
+    [
+        a[1] * b[2] - a[2] * b[1],
+        a[2] * b[0] - a[0] * b[2],
+        a[0] * b[1] - a[1] * b[0],
+    ]
+}
+
+pub(crate) fn norm(a: [f32; 3]) -> f32 {
+    dot(a, a).sqrt()
+}
+
+pub(crate) fn normalize(a: [f32; 3]) -> [f32; 3] {
+    let n = norm(a).max(1e-8);
+    [a[0] / n, a[1] / n, a[2] / n]
+}
+
+pub(crate) fn distance(a: &[f32; 3], b: &[f32; 3]) -> f32 {
+    norm(sub(*a, *b))
+}
+
+/// Angle a-b-c in degrees
+pub(crate) fn angle(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3]) -> f32 {
+    let v1 = normalize(sub(*a, *b));
+    let v2 = normalize(sub(*c, *b));
+    dot(v1, v2).clamp(-1.0, 1.0).acos().to_degrees()
+}
+
+/// Dihedral angle a-b-c-d in degrees, in the range (-180, 180]
+pub(crate) fn dihedral(a: &[f32; 3], b: &[f32; 3], c: &[f32; 3], d: &[f32; 3]) -> f32 {
+    let b0 = sub(*a, *b);
+    let b1 = normalize(sub(*c, *b));
+    let b2 = sub(*d, *c);
+    // components of b0 and b2 perpendicular to b1
+    let v = sub(b0, scale(b1, dot(b0, b1)));
+    let w = sub(b2, scale(b1, dot(b2, b1)));
+    let x = dot(v, w);
+    let y = dot(cross(b1, v), w);
+    y.atan2(x).to_degrees()
+}
+
+pub(crate) fn scale(a: [f32; 3], s: f32) -> [f32; 3] {
+    [a[0] * s, a[1] * s, a[2] * s]
+}
+
+/// Mean position of a set of points, accumulated in f64; the origin for an empty set
+pub(crate) fn centroid(points: &[[f32; 3]]) -> [f32; 3] {
+    let n = points.len().max(1) as f64;
+    let sum = points.iter().fold([0.0f64; 3], |acc, p| {
+        [
+            acc[0] + p[0] as f64,
+            acc[1] + p[1] as f64,
+            acc[2] + p[2] as f64,
+        ]
+    });
+    sum.map(|c| (c / n) as f32)
+}
+
+/// Uniform grid over a set of points for radius queries.
+///
+/// Cells are `cell_size` wide; a query with `radius <= cell_size` only needs to
+/// visit the 27 surrounding cells.
+pub(crate) struct NeighborGrid<'a> {
+    coords: &'a [[f32; 3]],
+    cell_size: f32,
+    cells: HashMap<(i32, i32, i32), Vec<usize>>,
+}
+
+impl<'a> NeighborGrid<'a> {
+    pub(crate) fn new(coords: &'a [[f32; 3]], cell_size: f32) -> Self {
+        let mut cells: HashMap<(i32, i32, i32), Vec<usize>> = HashMap::new();
+        for (i, coord) in coords.iter().enumerate() {
+            cells
+                .entry(Self::cell(coord, cell_size))
+                .or_default()
+                .push(i);
+        }
+        NeighborGrid {
+            coords,
+            cell_size,
+            cells,
+        }
+    }
+
+    fn cell(coord: &[f32; 3], cell_size: f32) -> (i32, i32, i32) {
+        (
+            (coord[0] / cell_size).floor() as i32,
+            (coord[1] / cell_size).floor() as i32,
+            (coord[2] / cell_size).floor() as i32,
+        )
+    }
+
+    /// Indices of all points within `radius` of `point`
+    pub(crate) fn within(&self, point: &[f32; 3], radius: f32) -> Vec<usize> {
+        let reach = (radius / self.cell_size).ceil() as i32;
+        let (cx, cy, cz) = Self::cell(point, self.cell_size);
+        let mut found = Vec::new();
+        for dx in -reach..=reach {
+            for dy in -reach..=reach {
+                for dz in -reach..=reach {
+                    if let Some(indices) = self.cells.get(&(cx + dx, cy + dy, cz + dz)) {
+                        found.extend(
+                            indices
+                                .iter()
+                                .filter(|&&j| distance(point, &self.coords[j]) <= radius),
+                        );
+                    }
+                }
+            }
+        }
+        found
+    }
+
+    /// All index pairs `(i, j)` with `i < j` closer than `radius`
+    pub(crate) fn pairs_within(&self, radius: f32) -> Vec<(usize, usize, f32)> {
+        let mut pairs = Vec::new();
+        for (i, coord) in self.coords.iter().enumerate() {
+            for j in self.within(coord, radius) {
+                if i < j {
+                    pairs.push((i, j, distance(coord, &self.coords[j])));
+                }
+            }
+        }
+        pairs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_angles() {
+        let a = [1.0, 0.0, 0.0];
+        let b = [0.0, 0.0, 0.0];
+        let c = [0.0, 1.0, 0.0];
+        assert!((angle(&a, &b, &c) - 90.0).abs() < 1e-4);
+
+        // trans and cis configurations
+        let d_trans = [-1.0, 1.0, 0.0];
+        let d_cis = [1.0, 1.0, 0.0];
+        assert!((dihedral(&a, &b, &c, &d_trans).abs() - 180.0).abs() < 1e-3);
+        assert!(dihedral(&a, &b, &c, &d_cis).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_neighbor_grid() {
+        let coords = vec![[0.0, 0.0, 0.0], [1.0, 0.0, 0.0], [5.0, 0.0, 0.0]];
+        let grid = NeighborGrid::new(&coords, 2.0);
+        let mut near = grid.within(&[0.0, 0.0, 0.0], 1.5);
+        near.sort();
+        assert_eq!(near, vec![0, 1]);
+        assert_eq!(grid.pairs_within(1.5).len(), 1);
+    }
+}
@@ -13,14 +13,31 @@
 //!
 mod atomcollection;
 mod bonds;
+mod comparison;
 mod conversions;
 mod featurize;
+mod geometry;
 mod info;
 mod residue;
+mod secondary_structure;
 mod selection;
+mod validation;
 
 pub use self::atomcollection::AtomCollection;
 pub use self::bonds::{Bond, BondOrder};
-pub use self::featurize::StructureFeatures;
+pub use self::comparison::{
+    align_residues, dockq, dockq_with_mapping, gdt, lddt, lddt_ca, rmsd, superpose, tm_d0,
+    tm_score, AlignmentMethod, DockQInterface, DockQResult, ResidueTrace, Superposition, TMScore,
+    ToResidueTrace, GDT, LDDT,
+};
+pub use self::featurize::{
+    ESMBatchInputs, MPNNBatchFeatures, NucleicAcidBatchFeatures, NucleicAcidFeatures,
+    PolymerType, ResidueSource, StructureBatch, StructureFeatures,
+};
 pub use self::residue::ResidueAtoms;
+pub use self::secondary_structure::{BetaBridge, Dssp, SecondaryStructure};
 pub use self::selection::Selection;
+pub use self::validation::{
+    classify_ramachandran, Clash, GlobalValidation, RamachandranCategory, RamachandranRegion,
+    ResidueValidation, ValidationReport,
+};
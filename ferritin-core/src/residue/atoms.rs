@@ -100,6 +100,11 @@ impl<'a> ResidueAtoms<'a> {
     pub fn is_nucleotide(&self) -> bool {
         is_nucleotide(&self.res_name)
     }
+    /// Residues whose atoms are LigandMPNN context: everything but protein and water, so
+    /// nucleic acids, small molecules, ions and glycans
+    pub fn is_ligand_context(&self) -> bool {
+        !self.is_amino_acid() && !matches!(self.res_name.as_str(), "HOH" | "WAT")
+    }
 }
 
 #[cfg(test)]
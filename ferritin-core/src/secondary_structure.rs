@@ -0,0 +1,257 @@
+//! Secondary Structure
+//!
+//! A compact DSSP-style assignment (Kabsch & Sander, 1983) from backbone hydrogen bonds.
+//! Hydrogen bonds are scored with the DSSP electrostatic model; two consecutive i -> i+4
+//! turns start an alpha helix and ladders of at least two consecutive beta bridges form a
+//! strand. Everything else, including 3-10 and pi helices, is reported as coil.
+//!
+//! ```no_run
+//! use ferritin_core::{AtomCollection, SecondaryStructure};
+//! # fn example(ac: &AtomCollection) {
+//! let ss = ac.secondary_structure();
+//! let helices = ss.iter().filter(|s| **s == SecondaryStructure::Helix).count();
+//! # }
+//! ```
+use crate::geometry::{distance, normalize, sub, NeighborGrid};
+use crate::AtomCollection;
+use serde::Serialize;
+use std::collections::HashSet;
+
+/// DSSP hydrogen bond energy cutoff in kcal/mol
+const HBOND_ENERGY_CUTOFF: f32 = -0.5;
+/// Residues with CA atoms further apart than this cannot be hydrogen bonded
+const MAX_CA_DISTANCE: f32 = 9.0;
+/// Peptide C-N distances above this are treated as chain breaks
+const PEPTIDE_BREAK_DISTANCE: f32 = 2.5;
+
+/// Three-state secondary structure of an amino acid residue
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub enum SecondaryStructure {
+    Helix,
+    Strand,
+    Coil,
+}
+
+impl SecondaryStructure {
+    /// One-letter DSSP-style code: `H`, `E` or `C`
+    pub fn code(&self) -> char {
+        match self {
+            SecondaryStructure::Helix => 'H',
+            SecondaryStructure::Strand => 'E',
+            SecondaryStructure::Coil => 'C',
+        }
+    }
+}
+
+/// Backbone atoms of one residue, with the amide hydrogen placed from the previous
+/// residue's carbonyl when the two are connected
+#[derive(Debug, Clone, Copy)]
+struct Backbone {
+    n: [f32; 3],
+    ca: [f32; 3],
+    c: [f32; 3],
+    o: [f32; 3],
+    h: Option<[f32; 3]>,
+}
+
+/// A pair of amino acid residues joined by a beta bridge. Residues are indexed in the order
+/// of [`AtomCollection::iter_residues_aminoacid`], with `i < j`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize)]
+pub struct BetaBridge {
+    pub i: usize,
+    pub j: usize,
+    pub parallel: bool,
+}
+
+/// Result of one DSSP pass: the per-residue assignment and the beta bridges behind it
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Dssp {
+    /// One entry per amino acid residue, in the order of
+    /// [`AtomCollection::iter_residues_aminoacid`]
+    pub secondary_structure: Vec<SecondaryStructure>,
+    /// Bridges between residues; bridges between strand residues describe how strands
+    /// pair into sheets
+    pub beta_bridges: Vec<BetaBridge>,
+}
+
+impl AtomCollection {
+    /// Assign secondary structure to each amino acid residue, in the order of
+    /// [`AtomCollection::iter_residues_aminoacid`].
+    pub fn secondary_structure(&self) -> Vec<SecondaryStructure> {
+        self.dssp().secondary_structure
+    }
+    /// Beta bridges found during secondary structure assignment. Use
+    /// [`AtomCollection::dssp`] when the assignment is needed as well.
+    pub fn beta_bridges(&self) -> Vec<BetaBridge> {
+        self.dssp().beta_bridges
+    }
+    /// Run DSSP once and return both the secondary structure and the beta bridges.
+    pub fn dssp(&self) -> Dssp {
+        let mut backbone = Vec::new();
+        let mut chains = Vec::new();
+        for res in self.iter_residues_aminoacid() {
+            let atom = |name: &str| res.find_atom_by_name(name).map(|atom| *atom.coords);
+            backbone.push(match (atom("N"), atom("CA"), atom("C"), atom("O")) {
+                (Some(n), Some(ca), Some(c), Some(o)) => Some(Backbone {
+                    n,
+                    ca,
+                    c,
+                    o,
+                    h: None,
+                }),
+                _ => None,
+            });
+            chains.push(res.chain_id.clone());
+        }
+        let (secondary_structure, beta_bridges) = assign(&mut backbone, &chains);
+        Dssp {
+            secondary_structure,
+            beta_bridges,
+        }
+    }
+}
+
+// Helper Fns --------------------------------------
+
+/// DSSP electrostatic energy of the C=O of `acceptor` bonding to the N-H of `donor`
+fn hbond_energy(acceptor: &Backbone, donor: &Backbone) -> f32 {
+    let Some(h) = donor.h else { return 0.0 };
+    let r_on = distance(&acceptor.o, &donor.n);
+    let r_ch = distance(&acceptor.c, &h);
+    let r_oh = distance(&acceptor.o, &h);
+    let r_cn = distance(&acceptor.c, &donor.n);
+    (0.084 * 332.0 * (1.0 / r_on + 1.0 / r_ch - 1.0 / r_oh - 1.0 / r_cn)).max(-9.9)
+}
+
+fn assign(
+    backbone: &mut [Option<Backbone>],
+    chains: &[String],
+) -> (Vec<SecondaryStructure>, Vec<BetaBridge>) {
+    let n = backbone.len();
+
+    // connected[k]: residue k forms a peptide bond with residue k - 1
+    let mut connected = vec![false; n];
+    for k in 1..n {
+        if let (Some(prev), Some(cur)) = (backbone[k - 1], backbone[k]) {
+            if chains[k - 1] == chains[k] && distance(&prev.c, &cur.n) < PEPTIDE_BREAK_DISTANCE {
+                connected[k] = true;
+                let co = normalize(sub(prev.c, prev.o));
+                backbone[k].as_mut().unwrap().h =
+                    Some([cur.n[0] + co[0], cur.n[1] + co[1], cur.n[2] + co[2]]);
+            }
+        }
+    }
+    // residues i..=j form one unbroken segment
+    let segment = |i: usize, j: usize| ((i + 1)..=j).all(|k| connected[k]);
+
+    // (acceptor, donor) hydrogen bonds between residues with nearby CA atoms
+    let indices: Vec<usize> = (0..n).filter(|&k| backbone[k].is_some()).collect();
+    let ca: Vec<[f32; 3]> = indices
+        .iter()
+        .map(|&k| backbone[k].as_ref().unwrap().ca)
+        .collect();
+    let grid = NeighborGrid::new(&ca, MAX_CA_DISTANCE);
+    let mut hbonds: HashSet<(usize, usize)> = HashSet::new();
+    for (a, b, _) in grid.pairs_within(MAX_CA_DISTANCE) {
+        let (i, j) = (indices[a], indices[b]);
+        let (res_i, res_j) = (backbone[i].as_ref().unwrap(), backbone[j].as_ref().unwrap());
+        if j != i + 1 && hbond_energy(res_i, res_j) < HBOND_ENERGY_CUTOFF {
+            hbonds.insert((i, j));
+        }
+        if i != j + 1 && hbond_energy(res_j, res_i) < HBOND_ENERGY_CUTOFF {
+            hbonds.insert((j, i));
+        }
+    }
+    let hb = |acceptor: usize, donor: usize| hbonds.contains(&(acceptor, donor));
+
+    let mut ss = vec![SecondaryStructure::Coil; n];
+
+    // beta bridges, then strands from ladders of consecutive bridges
+    let mut bridged = vec![false; n];
+    let mut bridges = Vec::new();
+    for i in 1..n.saturating_sub(1) {
+        for j in (i + 3)..n.saturating_sub(1) {
+            if !(segment(i - 1, i + 1) && segment(j - 1, j + 1)) {
+                continue;
+            }
+            let parallel = (hb(i - 1, j) && hb(j, i + 1)) || (hb(j - 1, i) && hb(i, j + 1));
+            let antiparallel = (hb(i, j) && hb(j, i)) || (hb(i - 1, j + 1) && hb(j - 1, i + 1));
+            if parallel || antiparallel {
+                bridged[i] = true;
+                bridged[j] = true;
+                bridges.push(BetaBridge {
+                    i,
+                    j,
+                    parallel: !antiparallel,
+                });
+            }
+        }
+    }
+    for k in 0..n {
+        let ladder = (k > 0 && bridged[k - 1]) || (k + 1 < n && bridged[k + 1]);
+        if bridged[k] && ladder {
+            ss[k] = SecondaryStructure::Strand;
+        }
+    }
+
+    // alpha helices take precedence over strands
+    let turn4 = |i: usize| i + 4 < n && segment(i, i + 4) && hb(i, i + 4);
+    for i in 1..n {
+        if turn4(i - 1) && turn4(i) {
+            for s in &mut ss[i..i + 4] {
+                *s = SecondaryStructure::Helix;
+            }
+        }
+    }
+    (ss, bridges)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ferritin_test_data::TestFile;
+
+    #[test]
+    fn test_secondary_structure() {
+        // myoglobin is almost entirely helical
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let ss = ac.secondary_structure();
+        assert_eq!(ss.len(), 154);
+        let helix = ss
+            .iter()
+            .filter(|s| **s == SecondaryStructure::Helix)
+            .count();
+        let strand = ss
+            .iter()
+            .filter(|s| **s == SecondaryStructure::Strand)
+            .count();
+        assert!(helix as f32 / ss.len() as f32 > 0.6);
+        assert_eq!(strand, 0);
+
+        // FKBP12 (chain A of 1fap) is built around a five-stranded sheet
+        let (prot_file, _temp) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let strands = ac
+            .secondary_structure()
+            .iter()
+            .filter(|s| **s == SecondaryStructure::Strand)
+            .count();
+        assert!(strands > 20);
+
+        // every strand residue is part of a bridge
+        let Dssp {
+            secondary_structure: ss,
+            beta_bridges: bridges,
+        } = ac.dssp();
+        assert_eq!(ss, ac.secondary_structure());
+        assert!(bridges.iter().any(|b| !b.parallel));
+        for (k, s) in ss.iter().enumerate() {
+            if *s == SecondaryStructure::Strand {
+                assert!(bridges.iter().any(|b| b.i == k || b.j == k));
+            }
+        }
+    }
+}
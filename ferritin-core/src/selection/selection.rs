@@ -15,6 +15,11 @@ impl Selection {
         Selection { indices }
     }
 
+    /// Atom indices in the selection
+    pub fn indices(&self) -> &[usize] {
+        &self.indices
+    }
+
     pub fn and(&self, other: &Selection) -> Selection {
         let indices: Vec<usize> = self
             .indices
@@ -42,6 +42,14 @@ impl<'a> AtomView<'a> {
     pub fn size(&self) -> usize {
         self.selection.indices.len()
     }
+
+    pub(crate) fn collection(&self) -> &'a AtomCollection {
+        self.collection
+    }
+
+    pub(crate) fn indices(&self) -> &[usize] {
+        &self.selection.indices
+    }
 }
 
 /// A reference to an atom's properties including coordinates, residue info, and element
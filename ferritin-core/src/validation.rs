@@ -0,0 +1,720 @@
+//! Structure Validation
+//!
+//! Quick sanity checks to run before handing a structure to a design model.
+//! [`AtomCollection::validate`] produces a [`ValidationReport`] with per-residue and
+//! global metrics:
+//!
+//! - steric clashes and a heavy-atom clashscore (clashes per 1000 atoms)
+//! - backbone bond length and angle deviations from Engh & Huber ideal values
+//! - Ramachandran region classification
+//! - cis-peptides, chain breaks and missing heavy atoms
+//!
+//! The report derives `Serialize` so it can be written out as JSON alongside model outputs.
+//!
+//! ```no_run
+//! use ferritin_core::AtomCollection;
+//! # fn example(ac: &AtomCollection) {
+//! let report = ac.validate();
+//! println!("clashscore: {:.1}", report.global.clashscore);
+//! # }
+//! ```
+use crate::featurize::utilities::{aa1to_int, aa3to1, AAAtom, Residue};
+use crate::geometry::{angle, dihedral, distance, NeighborGrid};
+use crate::residue::ResidueAtoms;
+use crate::AtomCollection;
+use pdbtbx::Element;
+use serde::Serialize;
+use std::collections::VecDeque;
+
+/// Minimum van der Waals overlap, in Å, counted as a clash (as in MolProbity).
+const CLASH_OVERLAP: f32 = 0.4;
+/// Extra allowance for N/O pairs, which may be hydrogen bonded.
+const HBOND_ALLOWANCE: f32 = 0.3;
+/// Z-score beyond which a bond length or angle is an outlier.
+const GEOMETRY_OUTLIER_Z: f32 = 4.0;
+/// Peptide C-N distances above this are treated as chain breaks.
+const PEPTIDE_BREAK_DISTANCE: f32 = 2.0;
+/// |omega| below this is a cis-peptide.
+const CIS_OMEGA: f32 = 30.0;
+
+// Helper Fns --------------------------------------
+
+fn vdw_radius(element: &Element) -> f32 {
+    match element {
+        Element::H => 1.10,
+        Element::C => 1.70,
+        Element::N => 1.55,
+        Element::O => 1.52,
+        Element::S => 1.80,
+        Element::P => 1.80,
+        Element::Se => 1.90,
+        _ => 1.80,
+    }
+}
+
+fn covalent_radius(element: &Element) -> f32 {
+    match element {
+        Element::H => 0.31,
+        Element::C => 0.76,
+        Element::N => 0.71,
+        Element::O => 0.66,
+        Element::S => 1.05,
+        Element::P => 1.07,
+        Element::Se => 1.20,
+        _ => 1.40,
+    }
+}
+
+fn is_polar(element: &Element) -> bool {
+    matches!(element, Element::N | Element::O)
+}
+
+/// True if atoms `i` and `j` are separated by at most `max_bonds` covalent bonds.
+fn within_bonds(adjacency: &[Vec<usize>], i: usize, j: usize, max_bonds: usize) -> bool {
+    let mut queue = VecDeque::from([(i, 0)]);
+    let mut seen = vec![i];
+    while let Some((current, depth)) = queue.pop_front() {
+        if current == j {
+            return true;
+        }
+        if depth == max_bonds {
+            continue;
+        }
+        for &next in &adjacency[current] {
+            if !seen.contains(&next) {
+                seen.push(next);
+                queue.push_back((next, depth + 1));
+            }
+        }
+    }
+    false
+}
+
+// Ideal backbone geometry from Engh & Huber (1991): (ideal, sigma)
+const BOND_N_CA: (f32, f32) = (1.458, 0.019);
+const BOND_CA_C: (f32, f32) = (1.525, 0.021);
+const BOND_C_O: (f32, f32) = (1.231, 0.020);
+const BOND_CA_CB: (f32, f32) = (1.530, 0.020);
+const BOND_C_N: (f32, f32) = (1.329, 0.014);
+const ANGLE_N_CA_C: (f32, f32) = (111.2, 2.8);
+const ANGLE_CA_C_O: (f32, f32) = (120.1, 2.1);
+const ANGLE_N_CA_CB: (f32, f32) = (110.5, 1.7);
+const ANGLE_C_CA_CB: (f32, f32) = (110.1, 1.9);
+const ANGLE_CA_C_N: (f32, f32) = (116.2, 2.0);
+const ANGLE_O_C_N: (f32, f32) = (123.0, 1.6);
+const ANGLE_C_N_CA: (f32, f32) = (121.7, 1.8);
+
+fn z_score(value: f32, (ideal, sigma): (f32, f32)) -> f32 {
+    (value - ideal) / sigma
+}
+
+/// Ramachandran region of a residue's (phi, psi) pair
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RamachandranRegion {
+    Favored,
+    Allowed,
+    Outlier,
+    /// Termini, chain breaks and residues missing backbone atoms
+    NotApplicable,
+}
+
+/// Residue class used to pick a Ramachandran distribution
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum RamachandranCategory {
+    General,
+    Glycine,
+    Proline,
+}
+
+fn in_range(value: f32, low: f32, high: f32) -> bool {
+    value >= low && value <= high
+}
+
+fn general_favored(phi: f32, psi: f32) -> bool {
+    let beta = in_range(phi, -180.0, -45.0) && (psi >= 100.0 || psi <= -170.0);
+    let alpha_r = in_range(phi, -130.0, -35.0) && in_range(psi, -75.0, 0.0);
+    beta || alpha_r
+}
+
+/// Classify (phi, psi) using coarse rectangular approximations of the
+/// MolProbity (Top8000) favored and allowed contours.
+pub fn classify_ramachandran(
+    phi: f32,
+    psi: f32,
+    category: RamachandranCategory,
+) -> RamachandranRegion {
+    let left_alpha = in_range(phi, 30.0, 100.0) && in_range(psi, -20.0, 80.0);
+    match category {
+        RamachandranCategory::General => {
+            if general_favored(phi, psi) {
+                RamachandranRegion::Favored
+            } else if phi <= -30.0 || left_alpha {
+                RamachandranRegion::Allowed
+            } else {
+                RamachandranRegion::Outlier
+            }
+        }
+        RamachandranCategory::Glycine => {
+            // glycine is achiral so its distribution is symmetric through the origin
+            if general_favored(phi, psi) || general_favored(-phi, -psi) || left_alpha {
+                RamachandranRegion::Favored
+            } else if phi.abs() >= 30.0 {
+                RamachandranRegion::Allowed
+            } else {
+                RamachandranRegion::Outlier
+            }
+        }
+        RamachandranCategory::Proline => {
+            let helical = in_range(psi, -60.0, -10.0);
+            let polypro = psi >= 110.0 || psi <= -170.0;
+            if in_range(phi, -95.0, -45.0) && (helical || polypro) {
+                RamachandranRegion::Favored
+            } else if in_range(phi, -110.0, -35.0) {
+                RamachandranRegion::Allowed
+            } else {
+                RamachandranRegion::Outlier
+            }
+        }
+    }
+}
+
+/// A pair of non-bonded atoms whose van der Waals spheres overlap
+#[derive(Debug, Clone, Serialize)]
+pub struct Clash {
+    pub atom1: usize,
+    pub atom2: usize,
+    /// `chain/res_id/res_name/atom_name` label for `atom1`
+    pub atom1_label: String,
+    pub atom2_label: String,
+    pub distance: f32,
+    pub overlap: f32,
+}
+
+/// Validation metrics for a single amino acid residue
+#[derive(Debug, Clone, Serialize)]
+pub struct ResidueValidation {
+    pub chain_id: String,
+    pub res_id: i32,
+    pub res_name: String,
+    pub phi: Option<f32>,
+    pub psi: Option<f32>,
+    /// Peptide bond dihedral between the previous residue and this one
+    pub omega: Option<f32>,
+    pub ramachandran_category: RamachandranCategory,
+    pub ramachandran: RamachandranRegion,
+    pub is_cis: bool,
+    /// No peptide bond to the previous residue in the same chain
+    pub chain_break_before: bool,
+    /// Expected heavy atoms absent from the residue
+    pub missing_atoms: Vec<String>,
+    pub max_bond_z: Option<f32>,
+    pub max_angle_z: Option<f32>,
+    pub clash_count: usize,
+}
+
+/// Structure-wide validation metrics
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobalValidation {
+    pub atom_count: usize,
+    pub residue_count: usize,
+    /// Clashes per 1000 heavy atoms
+    pub clashscore: f32,
+    /// Root-mean-square Z-score of backbone bond lengths
+    pub bond_rmsz: f32,
+    /// Root-mean-square Z-score of backbone bond angles
+    pub angle_rmsz: f32,
+    pub bond_outliers: usize,
+    pub angle_outliers: usize,
+    /// Fractions of residues with a defined (phi, psi)
+    pub ramachandran_favored: f32,
+    pub ramachandran_allowed: f32,
+    pub ramachandran_outliers: f32,
+    pub cis_peptides: usize,
+    pub cis_nonproline: usize,
+    pub chain_breaks: usize,
+    pub residues_missing_atoms: usize,
+}
+
+/// Validation report for an [`AtomCollection`]
+#[derive(Debug, Clone, Serialize)]
+pub struct ValidationReport {
+    pub global: GlobalValidation,
+    pub residues: Vec<ResidueValidation>,
+    pub clashes: Vec<Clash>,
+}
+
+impl AtomCollection {
+    /// Run the full set of validation checks.
+    pub fn validate(&self) -> ValidationReport {
+        let clashes = self.find_clashes();
+        let residues: Vec<ResidueAtoms> = self.iter_residues_aminoacid().collect();
+
+        // per-atom owning residue for clash accounting
+        let mut atom_to_residue = vec![None; self.get_size()];
+        for (i, res) in residues.iter().enumerate() {
+            atom_to_residue[res.start_idx..res.end_idx].fill(Some(i));
+        }
+        let mut clash_counts = vec![0; residues.len()];
+        for clash in &clashes {
+            for atom in [clash.atom1, clash.atom2] {
+                if let Some(res_idx) = atom_to_residue[atom] {
+                    clash_counts[res_idx] += 1;
+                }
+            }
+        }
+
+        let mut bond_z = Vec::new();
+        let mut angle_z = Vec::new();
+        let mut report_residues = Vec::with_capacity(residues.len());
+        for (i, res) in residues.iter().enumerate() {
+            let coord = |r: &ResidueAtoms, name: &str| r.find_atom_by_name(name).map(|a| *a.coords);
+            let (n, ca, c, o, cb) = (
+                coord(res, "N"),
+                coord(res, "CA"),
+                coord(res, "C"),
+                coord(res, "O"),
+                coord(res, "CB"),
+            );
+
+            // previous / next residues are only linked within a chain and across intact peptides
+            let prev = (i > 0)
+                .then(|| &residues[i - 1])
+                .filter(|p| p.chain_id == res.chain_id);
+            let next = residues.get(i + 1).filter(|nx| nx.chain_id == res.chain_id);
+            let peptide = |a: &ResidueAtoms, b: &ResidueAtoms| match (coord(a, "C"), coord(b, "N"))
+            {
+                (Some(c), Some(n)) => distance(&c, &n) <= PEPTIDE_BREAK_DISTANCE,
+                _ => false,
+            };
+            let prev = prev.filter(|p| peptide(p, res));
+            let next = next.filter(|nx| peptide(res, nx));
+            let chain_break_before =
+                i > 0 && residues[i - 1].chain_id == res.chain_id && prev.is_none();
+
+            // backbone dihedrals
+            let (prev_c, prev_ca) = match prev {
+                Some(p) => (coord(p, "C"), coord(p, "CA")),
+                None => (None, None),
+            };
+            let next_n = next.and_then(|nx| coord(nx, "N"));
+            let phi = match (prev_c, n, ca, c) {
+                (Some(pc), Some(n), Some(ca), Some(c)) => Some(dihedral(&pc, &n, &ca, &c)),
+                _ => None,
+            };
+            let psi = match (n, ca, c, next_n) {
+                (Some(n), Some(ca), Some(c), Some(nn)) => Some(dihedral(&n, &ca, &c, &nn)),
+                _ => None,
+            };
+            let omega = match (prev_ca, prev_c, n, ca) {
+                (Some(pca), Some(pc), Some(n), Some(ca)) => Some(dihedral(&pca, &pc, &n, &ca)),
+                _ => None,
+            };
+            let is_cis = omega.map(|w| w.abs() < CIS_OMEGA).unwrap_or(false);
+
+            let category = match res.res_name.as_str() {
+                "GLY" => RamachandranCategory::Glycine,
+                "PRO" => RamachandranCategory::Proline,
+                _ => RamachandranCategory::General,
+            };
+            let ramachandran = match (phi, psi) {
+                (Some(phi), Some(psi)) => classify_ramachandran(phi, psi, category),
+                _ => RamachandranRegion::NotApplicable,
+            };
+
+            // bond lengths and angles
+            let mut res_bonds = Vec::new();
+            let mut res_angles = Vec::new();
+            let mut bond = |a: Option<[f32; 3]>, b: Option<[f32; 3]>, ideal| {
+                if let (Some(a), Some(b)) = (a, b) {
+                    res_bonds.push(z_score(distance(&a, &b), ideal));
+                }
+            };
+            bond(n, ca, BOND_N_CA);
+            bond(ca, c, BOND_CA_C);
+            bond(c, o, BOND_C_O);
+            bond(ca, cb, BOND_CA_CB);
+            bond(c, next_n, BOND_C_N);
+            let mut ang = |a: Option<[f32; 3]>, b: Option<[f32; 3]>, c: Option<[f32; 3]>, ideal| {
+                if let (Some(a), Some(b), Some(c)) = (a, b, c) {
+                    res_angles.push(z_score(angle(&a, &b, &c), ideal));
+                }
+            };
+            ang(n, ca, c, ANGLE_N_CA_C);
+            ang(ca, c, o, ANGLE_CA_C_O);
+            ang(n, ca, cb, ANGLE_N_CA_CB);
+            ang(c, ca, cb, ANGLE_C_CA_CB);
+            ang(ca, c, next_n, ANGLE_CA_C_N);
+            ang(o, c, next_n, ANGLE_O_C_N);
+            ang(c, next_n, next.and_then(|nx| coord(nx, "CA")), ANGLE_C_N_CA);
+
+            let max_abs = |zs: &[f32]| zs.iter().map(|z| z.abs()).reduce(f32::max);
+            let max_bond_z = max_abs(&res_bonds);
+            let max_angle_z = max_abs(&res_angles);
+            bond_z.extend(res_bonds);
+            angle_z.extend(res_angles);
+
+            // missing heavy atoms for the canonical residues
+            let residue_type = Residue::from_int(aa1to_int(aa3to1(&res.res_name)) as i32);
+            let missing_atoms = residue_type
+                .atoms14()
+                .iter()
+                .filter(|&&atom| atom != AAAtom::Unknown)
+                .map(|atom| atom.to_string())
+                .filter(|name| res.find_atom_by_name(name).is_none())
+                .collect();
+
+            report_residues.push(ResidueValidation {
+                chain_id: res.chain_id.clone(),
+                res_id: res.res_id,
+                res_name: res.res_name.clone(),
+                phi,
+                psi,
+                omega,
+                ramachandran_category: category,
+                ramachandran,
+                is_cis,
+                chain_break_before,
+                missing_atoms,
+                max_bond_z,
+                max_angle_z,
+                clash_count: clash_counts[i],
+            });
+        }
+
+        let heavy_atoms = self
+            .get_elements()
+            .iter()
+            .filter(|e| **e != Element::H)
+            .count();
+        let rmsz = |zs: &[f32]| {
+            if zs.is_empty() {
+                0.0
+            } else {
+                (zs.iter().map(|z| z * z).sum::<f32>() / zs.len() as f32).sqrt()
+            }
+        };
+        let rama_defined: Vec<RamachandranRegion> = report_residues
+            .iter()
+            .map(|r| r.ramachandran)
+            .filter(|r| *r != RamachandranRegion::NotApplicable)
+            .collect();
+        let rama_fraction = |region: RamachandranRegion| {
+            if rama_defined.is_empty() {
+                0.0
+            } else {
+                rama_defined.iter().filter(|r| **r == region).count() as f32
+                    / rama_defined.len() as f32
+            }
+        };
+
+        let global = GlobalValidation {
+            atom_count: self.get_size(),
+            residue_count: report_residues.len(),
+            clashscore: if heavy_atoms == 0 {
+                0.0
+            } else {
+                clashes.len() as f32 * 1000.0 / heavy_atoms as f32
+            },
+            bond_rmsz: rmsz(&bond_z),
+            angle_rmsz: rmsz(&angle_z),
+            bond_outliers: bond_z
+                .iter()
+                .filter(|z| z.abs() > GEOMETRY_OUTLIER_Z)
+                .count(),
+            angle_outliers: angle_z
+                .iter()
+                .filter(|z| z.abs() > GEOMETRY_OUTLIER_Z)
+                .count(),
+            ramachandran_favored: rama_fraction(RamachandranRegion::Favored),
+            ramachandran_allowed: rama_fraction(RamachandranRegion::Allowed),
+            ramachandran_outliers: rama_fraction(RamachandranRegion::Outlier),
+            cis_peptides: report_residues.iter().filter(|r| r.is_cis).count(),
+            cis_nonproline: report_residues
+                .iter()
+                .filter(|r| r.is_cis && r.res_name != "PRO")
+                .count(),
+            chain_breaks: report_residues
+                .iter()
+                .filter(|r| r.chain_break_before)
+                .count(),
+            residues_missing_atoms: report_residues
+                .iter()
+                .filter(|r| !r.missing_atoms.is_empty())
+                .count(),
+        };
+
+        ValidationReport {
+            global,
+            residues: report_residues,
+            clashes,
+        }
+    }
+
+    /// Find heavy-atom steric clashes.
+    ///
+    /// Covalent connectivity is inferred from interatomic distances, so ligands and
+    /// modified residues need no bond annotations. Atoms within three bonds of each other
+    /// are never counted as clashing; more distant atoms of the same residue, such as a
+    /// long side chain folded back onto its own backbone, are. Waters are left out, as in
+    /// MolProbity's clashscore.
+    pub fn find_clashes(&self) -> Vec<Clash> {
+        let heavy: Vec<usize> = (0..self.get_size())
+            .filter(|&i| *self.get_element(i) != Element::H)
+            .filter(|&i| !matches!(self.get_res_name(i).as_str(), "HOH" | "WAT" | "DOD"))
+            .collect();
+        let coords: Vec<[f32; 3]> = heavy.iter().map(|&i| *self.get_coord(i)).collect();
+        let grid = NeighborGrid::new(&coords, 4.0);
+        let pairs = grid.pairs_within(2.0 * vdw_radius(&Element::S));
+
+        // covalent connectivity over heavy atoms
+        let mut adjacency = vec![Vec::new(); heavy.len()];
+        for &(i, j, d) in &pairs {
+            let (ei, ej) = (self.get_element(heavy[i]), self.get_element(heavy[j]));
+            if d <= covalent_radius(ei) + covalent_radius(ej) + 0.45 {
+                adjacency[i].push(j);
+                adjacency[j].push(i);
+            }
+        }
+
+        let label = |idx: usize| {
+            format!(
+                "{}/{}/{}/{}",
+                self.get_chain_id(idx),
+                self.get_res_id(idx),
+                self.get_res_name(idx),
+                self.get_atom_name(idx)
+            )
+        };
+        pairs
+            .into_iter()
+            .filter_map(|(i, j, d)| {
+                let (a, b) = (heavy[i], heavy[j]);
+                let (ea, eb) = (self.get_element(a), self.get_element(b));
+                let mut threshold = CLASH_OVERLAP;
+                if is_polar(ea) && is_polar(eb) {
+                    threshold += HBOND_ALLOWANCE;
+                }
+                let overlap = vdw_radius(ea) + vdw_radius(eb) - d;
+                if overlap < threshold || within_bonds(&adjacency, i, j, 3) {
+                    return None;
+                }
+                Some(Clash {
+                    atom1: a,
+                    atom2: b,
+                    atom1_label: label(a),
+                    atom2_label: label(b),
+                    distance: d,
+                    overlap,
+                })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::geometry::{cross, normalize, scale, sub};
+    use ferritin_test_data::TestFile;
+
+    type TestAtom = (i32, &'static str, &'static str, Element, [f32; 3]);
+
+    /// Place an atom bonded to `c` from the bond length, the `b-c-d` angle and the
+    /// `a-b-c-d` dihedral (NeRF).
+    fn place(
+        a: [f32; 3],
+        b: [f32; 3],
+        c: [f32; 3],
+        bond: f32,
+        angle: f32,
+        torsion: f32,
+    ) -> [f32; 3] {
+        let (angle, torsion) = (angle.to_radians(), torsion.to_radians());
+        let bc = normalize(sub(c, b));
+        let n = normalize(cross(sub(b, a), bc));
+        let m = cross(n, bc);
+        let d = [
+            scale(bc, -bond * angle.cos()),
+            scale(m, bond * angle.sin() * torsion.cos()),
+            scale(n, bond * angle.sin() * torsion.sin()),
+        ];
+        [0, 1, 2].map(|k| c[k] + d[0][k] + d[1][k] + d[2][k])
+    }
+
+    /// Ideal beta-strand polyglycine, with `omegas[i]` the peptide dihedral before
+    /// residue `i + 1` (the first entry is unused).
+    fn glycine_strand(omegas: &[f32]) -> Vec<TestAtom> {
+        let (phi, psi) = (-120.0, 130.0);
+        let mut n = [0.0, 0.0, 0.0];
+        let mut ca = [1.458, 0.0, 0.0];
+        let mut c = place([0.0, 1.0, 0.0], n, ca, 1.525, 111.2, phi);
+        let mut atoms = Vec::new();
+        for (i, &omega) in omegas.iter().enumerate() {
+            if i > 0 {
+                let next_n = place(n, ca, c, 1.329, 116.2, psi);
+                let next_ca = place(ca, c, next_n, 1.458, 121.7, omega);
+                let next_c = place(c, next_n, next_ca, 1.525, 111.2, phi);
+                (n, ca, c) = (next_n, next_ca, next_c);
+            }
+            let o = place(n, ca, c, 1.231, 120.5, psi + 180.0);
+            let res_id = i as i32 + 1;
+            atoms.push((res_id, "GLY", "N", Element::N, n));
+            atoms.push((res_id, "GLY", "CA", Element::C, ca));
+            atoms.push((res_id, "GLY", "C", Element::C, c));
+            atoms.push((res_id, "GLY", "O", Element::O, o));
+        }
+        atoms
+    }
+
+    fn collection(mut atoms: Vec<TestAtom>) -> AtomCollection {
+        // residue iteration stops before the final residue, so end on a water as
+        // deposited files do
+        atoms.push((100, "HOH", "O", Element::O, [50.0, 50.0, 50.0]));
+        AtomCollection::new(
+            atoms.len(),
+            atoms.iter().map(|atom| atom.4).collect(),
+            atoms.iter().map(|atom| atom.0).collect(),
+            atoms.iter().map(|atom| atom.1.to_string()).collect(),
+            atoms.iter().map(|atom| atom.1 != "GLY").collect(),
+            atoms.iter().map(|atom| atom.3).collect(),
+            atoms.iter().map(|atom| atom.2.to_string()).collect(),
+            vec!["A".to_string(); atoms.len()],
+            None,
+        )
+    }
+
+    #[test]
+    fn test_ramachandran_regions() {
+        let general = RamachandranCategory::General;
+        // alpha helix and beta strand
+        assert_eq!(
+            classify_ramachandran(-63.0, -43.0, general),
+            RamachandranRegion::Favored
+        );
+        assert_eq!(
+            classify_ramachandran(-120.0, 130.0, general),
+            RamachandranRegion::Favored
+        );
+        assert_eq!(
+            classify_ramachandran(60.0, -150.0, general),
+            RamachandranRegion::Outlier
+        );
+        // left-handed helix is fine for glycine
+        assert_eq!(
+            classify_ramachandran(63.0, 43.0, RamachandranCategory::Glycine),
+            RamachandranRegion::Favored
+        );
+        assert_eq!(
+            classify_ramachandran(60.0, 140.0, RamachandranCategory::Proline),
+            RamachandranRegion::Outlier
+        );
+    }
+
+    #[test]
+    fn test_validation_report() {
+        let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let report = ac.validate();
+
+        assert_eq!(report.residues.len(), 154);
+        assert_eq!(report.residues[0].res_name, "MET");
+        // termini have no phi / psi
+        assert!(report.residues[0].phi.is_none());
+        assert!(report.residues[153].psi.is_none());
+
+        // myoglobin is a well-refined, all-helical structure
+        assert!(report.global.ramachandran_favored > 0.9);
+        assert!(report.global.ramachandran_outliers < 0.02);
+        assert!(report.global.bond_rmsz < 2.0);
+        assert!(report.global.clashscore < 20.0);
+        assert_eq!(report.global.chain_breaks, 0);
+
+        let json = serde_json::to_string(&report).unwrap();
+        assert!(json.contains("clashscore"));
+    }
+
+    #[test]
+    fn test_ideal_strand() {
+        let report = collection(glycine_strand(&[180.0; 5])).validate();
+        assert_eq!(report.residues.len(), 5);
+        assert!(report.clashes.is_empty());
+        assert_eq!(report.global.chain_breaks, 0);
+        assert_eq!(report.global.cis_peptides, 0);
+        let omega = report.residues[2].omega.unwrap();
+        assert!((omega.abs() - 180.0).abs() < 0.1);
+        assert_eq!(report.residues[2].ramachandran, RamachandranRegion::Favored);
+    }
+
+    #[test]
+    fn test_cis_peptide() {
+        let report = collection(glycine_strand(&[180.0, 180.0, 0.0, 180.0, 180.0])).validate();
+        assert_eq!(report.global.cis_peptides, 1);
+        assert_eq!(report.global.cis_nonproline, 1);
+        assert!(report.residues[2].is_cis);
+        assert!(report.residues[2].omega.unwrap().abs() < 0.1);
+        assert_eq!(report.global.chain_breaks, 0);
+    }
+
+    #[test]
+    fn test_chain_break() {
+        let mut atoms = glycine_strand(&[180.0; 5]);
+        for atom in atoms.iter_mut().filter(|atom| atom.0 >= 4) {
+            atom.4[2] += 10.0;
+        }
+        let report = collection(atoms).validate();
+        assert_eq!(report.global.chain_breaks, 1);
+        assert!(report.residues[3].chain_break_before);
+        // no peptide bond, so no dihedrals across the break
+        assert!(report.residues[2].psi.is_none());
+        assert!(report.residues[3].phi.is_none());
+        assert!(report.residues[3].omega.is_none());
+        assert_eq!(report.global.cis_peptides, 0);
+        assert!(report.clashes.is_empty());
+    }
+
+    #[test]
+    fn test_displaced_atom_clash() {
+        let mut atoms = glycine_strand(&[180.0; 5]);
+        // move the O of residue 4 to 2.6 Å off the CA of residue 1, normal to its N-CA-C plane
+        let (n, ca, c) = (atoms[0].4, atoms[1].4, atoms[2].4);
+        let normal = normalize(cross(sub(n, ca), sub(c, ca)));
+        atoms[15].4 = [0, 1, 2].map(|k| ca[k] + 2.6 * normal[k]);
+        let report = collection(atoms).validate();
+
+        assert_eq!(report.clashes.len(), 1);
+        let clash = &report.clashes[0];
+        let mut labels = [clash.atom1_label.as_str(), clash.atom2_label.as_str()];
+        labels.sort();
+        assert_eq!(labels, ["A/1/GLY/CA", "A/4/GLY/O"]);
+        assert!((clash.distance - 2.6).abs() < 1e-3);
+        let clash_counts: Vec<usize> = report.residues.iter().map(|r| r.clash_count).collect();
+        assert_eq!(clash_counts, vec![1, 0, 0, 1, 0]);
+    }
+
+    #[test]
+    fn test_intra_residue_clash() {
+        // seven carbons curled on a circle so the chain ends meet, six bonds apart
+        let radius: f32 = 1.9;
+        let step = 2.0 * (0.75 / radius).asin();
+        let names = ["C1", "C2", "C3", "C4", "C5", "C6", "C7"];
+        let atoms = names
+            .iter()
+            .enumerate()
+            .map(|(i, &name)| {
+                let theta = i as f32 * step;
+                let coord = [radius * theta.cos(), radius * theta.sin(), 0.0];
+                (1, "LIG", name, Element::C, coord)
+            })
+            .collect();
+        let clashes = collection(atoms).find_clashes();
+
+        assert_eq!(clashes.len(), 1);
+        let mut labels = [
+            clashes[0].atom1_label.as_str(),
+            clashes[0].atom2_label.as_str(),
+        ];
+        labels.sort();
+        assert_eq!(labels, ["A/1/LIG/C1", "A/1/LIG/C7"]);
+    }
+}
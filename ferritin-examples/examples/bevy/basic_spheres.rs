@@ -1,7 +1,10 @@
 //!  Example allowing custom colors and rendering options
 use anyhow::Result;
 use bevy::prelude::*;
-use ferritin_bevy::{ColorScheme, RenderOptions, StructurePlugin, StructureSettings};
+use ferritin_bevy::{
+    CameraControlPlugin, ColorScheme, OrbitCamera, RenderOptions, StructurePlugin,
+    StructureSettings,
+};
 use ferritin_test_data::TestFile;
 
 fn main() -> Result<()> {
@@ -44,6 +47,7 @@ fn main() -> Result<()> {
                 material: chalky,
             }),
         ))
+        .add_plugins(CameraControlPlugin)
         .add_systems(Startup, setup)
         .run();
     Ok(())
@@ -53,12 +57,8 @@ fn main() -> Result<()> {
 struct MainCamera;
 
 fn setup(mut commands: Commands) {
-    // Add a camera
-    commands.spawn((
-        Camera3d::default(),
-        Transform::from_xyz(0.0, 50.0, 100.0).looking_at(Vec3::ZERO, Vec3::Y),
-        MainCamera,
-    ));
+    // Add a camera that frames the structure once loaded
+    commands.spawn((Camera3d::default(), OrbitCamera::default(), MainCamera));
 
     // Key Light
     commands.spawn((
@@ -0,0 +1,380 @@
+//! Annotations from ferritin-core data.
+//!
+//! Turn atom [`Selection`]s into component selectors, and per-residue numbers such as
+//! sequence-design probabilities into MVS annotation tables that color and label the
+//! structure. Residues are addressed by author chain and residue number (`auth_asym_id`,
+//! `auth_seq_id`), which is the numbering an [`AtomCollection`] carries.
+//!
+//! ```no_run
+//! use ferritin_core::AtomCollection;
+//! use ferritin_molviewspec::molviewspec::annotations::{
+//!     annotate_from_uri, Palette, ResidueAnnotations,
+//! };
+//! use ferritin_molviewspec::molviewspec::nodes::{
+//!     ParseFormatT, ParseParams, RepresentationTypeT, State, StructureParams,
+//! };
+//!
+//! # fn example(ac: AtomCollection, probabilities: Vec<f64>) -> anyhow::Result<()> {
+//! let annotations =
+//!     ResidueAnnotations::from_residue_values(&ac, &probabilities, &Palette::viridis())?;
+//! std::fs::write("design_annotations.json", annotations.to_json()?)?;
+//!
+//! let mut state = State::new();
+//! let structure = state
+//!     .download("design.pdb")
+//!     .and_then(|node| node.parse(ParseParams { format: ParseFormatT::Pdb }))
+//!     .and_then(|node| node.model_structure(StructureParams::default()))
+//!     .unwrap();
+//! annotate_from_uri(structure, "design_annotations.json", RepresentationTypeT::Cartoon);
+//! # Ok(())
+//! # }
+//! ```
+use super::nodes::{
+    ColorFromSourceParams, ColorFromUriParams, ComponentExpression, ComponentSelector,
+    DataFromSourceParams, DataFromUriParams, Node, RepresentationTypeT, SchemaFormatT, SchemaT,
+    TooltipFromSourceParams, TooltipFromUriParams,
+};
+use anyhow::{anyhow, bail, Result};
+use ferritin_core::{AtomCollection, Selection};
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+/// Field of the annotation rows holding the color
+pub const COLOR_FIELD: &str = "color";
+/// Field of the annotation rows holding the tooltip text
+pub const TOOLTIP_FIELD: &str = "tooltip";
+
+/// Component expressions covering the selected atoms.
+///
+/// Residues whose atoms are all selected are merged into ranges of consecutive residue
+/// numbers; partially selected residues are listed atom by atom.
+pub fn selection_expressions(
+    ac: &AtomCollection,
+    selection: &Selection,
+) -> Vec<ComponentExpression> {
+    let mut residue_sizes: HashMap<(&str, i32), usize> = HashMap::new();
+    for i in 0..ac.get_size() {
+        *residue_sizes
+            .entry((ac.get_chain_id(i).as_str(), *ac.get_res_id(i)))
+            .or_default() += 1;
+    }
+
+    // selected atoms per residue, in the order residues are first selected
+    let mut residues: Vec<((&str, i32), Vec<usize>)> = Vec::new();
+    let mut residue_index: HashMap<(&str, i32), usize> = HashMap::new();
+    let mut seen = HashSet::new();
+    for &i in selection.indices() {
+        if !seen.insert(i) {
+            continue;
+        }
+        let key = (ac.get_chain_id(i).as_str(), *ac.get_res_id(i));
+        let index = *residue_index.entry(key).or_insert_with(|| {
+            residues.push((key, Vec::new()));
+            residues.len() - 1
+        });
+        residues[index].1.push(i);
+    }
+
+    let mut expressions = Vec::new();
+    // (chain, first, last) of the run of whole residues being merged
+    let mut run: Option<(&str, i32, i32)> = None;
+    for ((chain, res_id), atoms) in residues {
+        if atoms.len() == residue_sizes[&(chain, res_id)] {
+            run = match run {
+                Some((run_chain, first, last)) if run_chain == chain && res_id == last + 1 => {
+                    Some((run_chain, first, res_id))
+                }
+                previous => {
+                    expressions.extend(previous.map(residue_range));
+                    Some((chain, res_id, res_id))
+                }
+            };
+        } else {
+            expressions.extend(run.take().map(residue_range));
+            expressions.extend(atoms.into_iter().map(|i| ComponentExpression {
+                auth_asym_id: Some(chain.to_string()),
+                auth_seq_id: Some(res_id),
+                auth_atom_id: Some(ac.get_atom_name(i).clone()),
+                ..Default::default()
+            }));
+        }
+    }
+    expressions.extend(run.map(residue_range));
+    expressions
+}
+
+/// Selector for a [`Node::component`] covering the selected atoms
+pub fn selection_selector(ac: &AtomCollection, selection: &Selection) -> ComponentSelector {
+    ComponentSelector::ExpressionList(selection_expressions(ac, selection))
+}
+
+/// Colors spread evenly over a range of values
+#[derive(Clone, Debug)]
+pub struct Palette {
+    colors: Vec<[u8; 3]>,
+    /// Values mapped to the first and last color; defaults to the range of the data
+    pub range: Option<(f64, f64)>,
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Palette::viridis()
+    }
+}
+
+impl Palette {
+    /// A palette from `#rrggbb` colors, low to high.
+    pub fn from_hex(colors: &[&str]) -> Result<Self> {
+        if colors.is_empty() {
+            bail!("A palette needs at least one color");
+        }
+        let colors = colors
+            .iter()
+            .map(|color| parse_hex(color).ok_or_else(|| anyhow!("Invalid hex color {}", color)))
+            .collect::<Result<_>>()?;
+        Ok(Palette {
+            colors,
+            range: None,
+        })
+    }
+    pub fn viridis() -> Self {
+        Palette {
+            colors: vec![
+                [68, 1, 84],
+                [59, 82, 139],
+                [33, 145, 140],
+                [94, 201, 98],
+                [253, 231, 37],
+            ],
+            range: None,
+        }
+    }
+    /// Diverging palette for values around a midpoint
+    pub fn blue_white_red() -> Self {
+        Palette {
+            colors: vec![[33, 102, 172], [247, 247, 247], [178, 24, 43]],
+            range: None,
+        }
+    }
+    pub fn with_range(mut self, min: f64, max: f64) -> Self {
+        self.range = Some((min, max));
+        self
+    }
+    /// Color of `value` as `#rrggbb`, interpolated between the neighbouring colors
+    pub fn color(&self, value: f64, min: f64, max: f64) -> String {
+        let t = if max > min {
+            ((value - min) / (max - min)).clamp(0.0, 1.0)
+        } else {
+            0.5
+        };
+        let [r, g, b] = match self.colors.as_slice() {
+            [single] => *single,
+            colors => {
+                let scaled = t * (colors.len() - 1) as f64;
+                let index = (scaled.floor() as usize).min(colors.len() - 2);
+                let f = scaled - index as f64;
+                let (a, b) = (colors[index], colors[index + 1]);
+                std::array::from_fn(|c| {
+                    (a[c] as f64 + (b[c] as f64 - a[c] as f64) * f).round() as u8
+                })
+            }
+        };
+        format!("#{:02x}{:02x}{:02x}", r, g, b)
+    }
+}
+
+/// One annotated residue; the fields are the columns of the annotation table
+#[derive(Clone, Debug, Serialize)]
+pub struct AnnotationRow {
+    pub auth_asym_id: String,
+    pub auth_seq_id: i32,
+    pub color: String,
+    pub tooltip: String,
+    pub value: f64,
+}
+
+/// Per-residue values with their colors and tooltips, ready to be written as an MVS
+/// annotation file
+#[derive(Clone, Debug)]
+pub struct ResidueAnnotations {
+    rows: Vec<AnnotationRow>,
+}
+
+impl ResidueAnnotations {
+    /// Pair each amino-acid residue of `ac`, in order, with one value, e.g. the
+    /// probability of the designed residue at every position.
+    pub fn from_residue_values(
+        ac: &AtomCollection,
+        values: &[f64],
+        palette: &Palette,
+    ) -> Result<Self> {
+        let residues: Vec<_> = ac.iter_residues_aminoacid().collect();
+        if residues.len() != values.len() {
+            bail!(
+                "Got {} values for {} amino acids",
+                values.len(),
+                residues.len()
+            );
+        }
+        let (min, max) = palette.range.unwrap_or_else(|| {
+            values
+                .iter()
+                .filter(|value| value.is_finite())
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), &value| {
+                    (min.min(value), max.max(value))
+                })
+        });
+        let rows = residues
+            .iter()
+            .zip(values)
+            .map(|(residue, &value)| AnnotationRow {
+                auth_asym_id: residue.chain_id.clone(),
+                auth_seq_id: residue.res_id,
+                color: palette.color(value, min, max),
+                tooltip: format!(
+                    "{} {}{}: {:.3}",
+                    residue.res_name, residue.chain_id, residue.res_id, value
+                ),
+                value,
+            })
+            .collect();
+        Ok(ResidueAnnotations { rows })
+    }
+    pub fn get_rows(&self) -> &[AnnotationRow] {
+        &self.rows
+    }
+    /// Rows as an MVS JSON annotation file
+    pub fn to_json(&self) -> Result<String> {
+        Ok(serde_json::to_string(&self.rows)?)
+    }
+    /// Rows as an mmCIF category. With a `block_header` the text is a complete file;
+    /// without, it can be appended to the data block of the structure's own mmCIF file and
+    /// read with [`annotate_from_source`].
+    pub fn to_cif(&self, category_name: &str, block_header: Option<&str>) -> String {
+        let mut cif = String::new();
+        if let Some(header) = block_header {
+            cif.push_str(&format!("data_{}\n", header));
+        }
+        cif.push_str("#\nloop_\n");
+        for field in [
+            "auth_asym_id",
+            "auth_seq_id",
+            COLOR_FIELD,
+            TOOLTIP_FIELD,
+            "value",
+        ] {
+            cif.push_str(&format!("_{}.{}\n", category_name, field));
+        }
+        for row in &self.rows {
+            cif.push_str(&format!(
+                "{} {} {} {} {}\n",
+                cif_value(&row.auth_asym_id),
+                row.auth_seq_id,
+                cif_value(&row.color),
+                cif_value(&row.tooltip),
+                row.value
+            ));
+        }
+        cif.push_str("#\n");
+        cif
+    }
+}
+
+/// Color the whole structure and add tooltips from a JSON annotation file written with
+/// [`ResidueAnnotations::to_json`]. Returns the representation so more colors can be layered
+/// on top.
+pub fn annotate_from_uri<'a>(
+    structure: &'a mut Node,
+    uri: &str,
+    representation: RepresentationTypeT,
+) -> Option<&'a mut Node> {
+    let data = |field: &str| DataFromUriParams {
+        uri: uri.to_string(),
+        format: SchemaFormatT::Json,
+        category_name: None,
+        field_name: Some(field.to_string()),
+        block_header: None,
+        block_index: None,
+        schema_: SchemaT::AuthResidue,
+    };
+    structure.tooltip_from_uri(TooltipFromUriParams {
+        base: data(TOOLTIP_FIELD),
+    })?;
+    let representation = structure
+        .component(ComponentSelector::default())?
+        .representation(representation)?;
+    representation.color_from_uri(ColorFromUriParams {
+        base: data(COLOR_FIELD),
+    })?;
+    Some(representation)
+}
+
+/// Like [`annotate_from_uri`], reading the rows from a category of the structure's own
+/// mmCIF file, see [`ResidueAnnotations::to_cif`].
+pub fn annotate_from_source<'a>(
+    structure: &'a mut Node,
+    category_name: &str,
+    representation: RepresentationTypeT,
+) -> Option<&'a mut Node> {
+    let data = |field: &str| DataFromSourceParams {
+        category_name: category_name.to_string(),
+        field_name: Some(field.to_string()),
+        block_header: None,
+        block_index: None,
+        schema_: SchemaT::AuthResidue,
+    };
+    structure.tooltip_from_source(TooltipFromSourceParams {
+        base: data(TOOLTIP_FIELD),
+    })?;
+    let representation = structure
+        .component(ComponentSelector::default())?
+        .representation(representation)?;
+    representation.color_from_source(ColorFromSourceParams {
+        base: data(COLOR_FIELD),
+    })?;
+    Some(representation)
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+fn residue_range((chain, first, last): (&str, i32, i32)) -> ComponentExpression {
+    if first == last {
+        ComponentExpression {
+            auth_asym_id: Some(chain.to_string()),
+            auth_seq_id: Some(first),
+            ..Default::default()
+        }
+    } else {
+        ComponentExpression {
+            auth_asym_id: Some(chain.to_string()),
+            beg_auth_seq_id: Some(first),
+            end_auth_seq_id: Some(last),
+            ..Default::default()
+        }
+    }
+}
+
+fn parse_hex(color: &str) -> Option<[u8; 3]> {
+    let hex = color.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some([channel(0)?, channel(2)?, channel(4)?])
+}
+
+/// Quote a CIF value unless it is a plain token
+fn cif_value(value: &str) -> String {
+    let plain = !value.is_empty()
+        && !value.starts_with(['_', '#', '$', '\'', '"', '[', ']', ';'])
+        && value
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ".-+".contains(c));
+    if plain {
+        value.to_string()
+    } else if !value.contains('\'') {
+        format!("'{}'", value)
+    } else {
+        format!("\"{}\"", value)
+    }
+}
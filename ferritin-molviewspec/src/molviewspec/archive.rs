@@ -0,0 +1,175 @@
+//! MVSX archives.
+//!
+//! An MVSX file is a zip archive holding the state, or several snapshots, as `index.mvsj`
+//! together with the files it references through relative URIs, such as structures or
+//! annotation tables. Such an archive opens in a viewer as a single file, without a web
+//! server.
+//!
+use super::nodes::{Node, NodeParams, State};
+use super::snapshots::MvsData;
+use anyhow::{anyhow, bail, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::{BufReader, BufWriter, Read, Seek, Write};
+use std::path::Path;
+
+/// Name of the state inside an MVSX archive
+pub const MVSX_INDEX: &str = "index.mvsj";
+
+/// The contents of an MVSX archive
+#[derive(Debug)]
+pub struct MvsArchive {
+    pub data: MvsData,
+    /// Every other file in the archive, keyed by its path inside the archive
+    pub files: BTreeMap<String, Vec<u8>>,
+}
+
+impl MvsArchive {
+    /// An archive holding only a [`State`] or [`States`](super::snapshots::States); add
+    /// the files it references with [`MvsArchive::add_file`].
+    pub fn new<D: Into<MvsData>>(data: D) -> Self {
+        MvsArchive {
+            data: data.into(),
+            files: BTreeMap::new(),
+        }
+    }
+    /// Embed every file the state references through a relative URI, reading it from
+    /// `base_dir`, the directory the state would otherwise be served from.
+    pub fn bundle<D: Into<MvsData>, P: AsRef<Path>>(data: D, base_dir: P) -> Result<Self> {
+        let mut archive = MvsArchive::new(data);
+        for uri in archive.relative_uris() {
+            let data = std::fs::read(base_dir.as_ref().join(&uri))
+                .map_err(|e| anyhow!("Cannot embed {}: {}", uri, e))?;
+            archive.add_file(&uri, data);
+        }
+        Ok(archive)
+    }
+    /// Add a file under the relative URI the state uses for it, e.g. `pdb/1abc.pdb`
+    pub fn add_file(&mut self, uri: &str, data: Vec<u8>) {
+        self.files.insert(archive_name(uri).to_string(), data);
+    }
+    /// Relative URIs in the state or snapshots, which must resolve to files inside the
+    /// archive
+    pub fn relative_uris(&self) -> Vec<String> {
+        let mut uris = Vec::new();
+        for root in self.data.roots() {
+            collect_uris(root, &mut uris);
+        }
+        let mut uris: Vec<String> = uris
+            .into_iter()
+            .filter(|uri| is_relative(uri))
+            .map(|uri| archive_name(uri).to_string())
+            .collect();
+        uris.sort();
+        uris.dedup();
+        uris
+    }
+    /// Write the archive to an `.mvsx` file.
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        self.to_writer(BufWriter::new(File::create(path)?))
+    }
+    /// Write the archive, checking that the state is valid and that every relative URI
+    /// resolves to an embedded file.
+    pub fn to_writer<W: Write + Seek>(&self, writer: W) -> Result<()> {
+        self.data.validate()?;
+        let missing: Vec<String> = self
+            .relative_uris()
+            .into_iter()
+            .filter(|uri| !self.files.contains_key(uri))
+            .collect();
+        if !missing.is_empty() {
+            bail!(
+                "Files missing from the MVSX archive: {}",
+                missing.join(", ")
+            );
+        }
+
+        let mut zip = zip::ZipWriter::new(writer);
+        let options = zip::write::SimpleFileOptions::default()
+            .compression_method(zip::CompressionMethod::Deflated);
+        zip.start_file(MVSX_INDEX, options)?;
+        zip.write_all(serde_json::to_string_pretty(&self.data)?.as_bytes())?;
+        for (name, data) in &self.files {
+            zip.start_file(name.as_str(), options)?;
+            zip.write_all(data)?;
+        }
+        zip.finish()?.flush()?;
+        Ok(())
+    }
+    /// Read and validate an `.mvsx` file.
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        MvsArchive::from_reader(BufReader::new(File::open(path)?))
+    }
+    pub fn from_reader<R: Read + Seek>(reader: R) -> Result<Self> {
+        let mut zip = zip::ZipArchive::new(reader)?;
+        let mut files = BTreeMap::new();
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_dir() {
+                continue;
+            }
+            let mut data = Vec::new();
+            file.read_to_end(&mut data)?;
+            files.insert(file.name().to_string(), data);
+        }
+        let index = files
+            .remove(MVSX_INDEX)
+            .ok_or_else(|| anyhow!("MVSX archive has no {}", MVSX_INDEX))?;
+        let data = MvsData::from_mvsj(std::str::from_utf8(&index)?)?;
+        Ok(MvsArchive { data, files })
+    }
+    /// Contents of a file referenced by a relative URI such as `./pdb/1abc.pdb`
+    pub fn get_file(&self, uri: &str) -> Option<&[u8]> {
+        self.files.get(archive_name(uri)).map(Vec::as_slice)
+    }
+}
+
+impl MvsData {
+    /// Read and validate a state or snapshots from an `.mvsj` file or from the index of
+    /// an `.mvsx` archive.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<MvsData> {
+        let path = path.as_ref();
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("mvsx") => Ok(MvsArchive::read(path)?.data),
+            _ => Ok(MvsData::from_mvsj(&std::fs::read_to_string(path)?)?),
+        }
+    }
+}
+
+impl State {
+    /// Read and validate a single state, see [`MvsData::open`].
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<State> {
+        let path = path.as_ref();
+        match MvsData::open(path)? {
+            MvsData::State(state) => Ok(*state),
+            MvsData::States(_) => bail!("{} holds several snapshots", path.display()),
+        }
+    }
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+/// Path inside the archive for a relative URI
+fn archive_name(uri: &str) -> &str {
+    uri.trim_start_matches("./")
+}
+
+/// URIs without a scheme are resolved against the location of the state
+fn is_relative(uri: &str) -> bool {
+    !(uri.contains("://") || uri.starts_with('/') || uri.starts_with("data:"))
+}
+
+fn collect_uris<'a>(node: &'a Node, uris: &mut Vec<&'a str>) {
+    let uri = match &node.params {
+        Some(NodeParams::DownloadParams(params)) => Some(&params.url),
+        Some(NodeParams::ComponentFromUriParams(params)) => Some(&params.base.uri),
+        Some(NodeParams::ColorFromUriParams(params)) => Some(&params.base.uri),
+        Some(NodeParams::LabelFromUriParams(params)) => Some(&params.base.uri),
+        Some(NodeParams::TooltipFromUriParams(params)) => Some(&params.base.uri),
+        _ => None,
+    };
+    uris.extend(uri.map(String::as_str));
+    for child in node.children.iter().flatten() {
+        collect_uris(child, uris);
+    }
+}
@@ -1 +1,5 @@
+pub mod annotations;
+pub mod archive;
 pub mod nodes;
+pub mod snapshots;
+pub mod validation;
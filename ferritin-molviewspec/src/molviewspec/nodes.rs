@@ -47,6 +47,8 @@ pub enum KindT {
     LabelFromUri,
     Line,
     Parse,
+    Primitive,
+    Primitives,
     Representation,
     Sphere,
     Structure,
@@ -54,6 +56,49 @@ pub enum KindT {
     TooltipFromSource,
     TooltipFromUri,
     Transform,
+    /// A kind this crate does not model, e.g. `volume` or `clip`, kept as written
+    #[serde(untagged)]
+    Unknown(String),
+}
+impl KindT {
+    /// Node kinds that may appear as children of this kind in the MVS tree.
+    pub fn allowed_children(&self) -> &'static [KindT] {
+        match self {
+            KindT::Root => &[
+                KindT::Camera,
+                KindT::Canvas,
+                KindT::Download,
+                KindT::GenericVisuals,
+                KindT::Primitives,
+            ],
+            KindT::Download => &[KindT::Parse],
+            KindT::Parse => &[KindT::Structure],
+            KindT::Structure => &[
+                KindT::Component,
+                KindT::ComponentFromUri,
+                KindT::ComponentFromSource,
+                KindT::LabelFromUri,
+                KindT::LabelFromSource,
+                KindT::TooltipFromUri,
+                KindT::TooltipFromSource,
+                KindT::Transform,
+                KindT::Primitives,
+            ],
+            KindT::Component | KindT::ComponentFromUri | KindT::ComponentFromSource => &[
+                KindT::Representation,
+                KindT::Label,
+                KindT::Tooltip,
+                KindT::Focus,
+            ],
+            KindT::Representation => &[KindT::Color, KindT::ColorFromUri, KindT::ColorFromSource],
+            KindT::GenericVisuals => &[KindT::Sphere, KindT::Line],
+            KindT::Primitives => &[KindT::Primitive],
+            _ => &[],
+        }
+    }
+    pub fn accepts(&self, child: &KindT) -> bool {
+        self.allowed_children().contains(child)
+    }
 }
 
 // NodeParams
@@ -87,6 +132,10 @@ pub enum NodeParams {
     CanvasParams(CanvasParams),
     SphereParams(SphereParams),
     LineParams(LineParams),
+    PrimitiveParams(PrimitiveParams),
+    PrimitivesParams(PrimitivesParams),
+    /// Params of a node or primitive this crate does not model, kept as written
+    Unknown(serde_json::Value),
 }
 
 /// Node
@@ -102,6 +151,12 @@ pub struct Node {
     pub params: Option<NodeParams>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub children: Option<Vec<Node>>,
+    /// Reference other nodes can use to point at this one
+    #[serde(rename = "ref", skip_serializing_if = "Option::is_none")]
+    pub ref_: Option<String>,
+    /// Free-form data for extensions, ignored by viewers that do not know it
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub custom: Option<serde_json::Value>,
 }
 impl Node {
     // Common to All Nodes
@@ -110,6 +165,8 @@ impl Node {
             kind,
             params,
             children: None,
+            ref_: None,
+            custom: None,
         }
     }
     pub fn add_child(&mut self, node: Node) {
@@ -121,34 +178,28 @@ impl Node {
     pub fn get_kind(&self) -> &KindT {
         &self.kind
     }
-    /// Create the download node
-    pub fn download(&mut self, url: &str) -> Option<&mut Node> {
-        if self.kind == KindT::Root {
-            let url = url.to_string();
-            let download_node = Node::new(
-                KindT::Download,
-                Some(NodeParams::DownloadParams(DownloadParams { url })),
-            );
-
-            self.children
-                .get_or_insert_with(Vec::new)
-                .push(download_node);
+    /// Append `node` if the MVS tree allows it below this node and return it
+    fn add_checked(&mut self, node: Node) -> Option<&mut Node> {
+        if self.kind.accepts(&node.kind) {
+            self.children.get_or_insert_with(Vec::new).push(node);
             self.children.as_mut().unwrap().last_mut()
         } else {
             None
         }
     }
+    /// Create the download node
+    pub fn download(&mut self, url: &str) -> Option<&mut Node> {
+        let url = url.to_string();
+        let download_node = Node::new(
+            KindT::Download,
+            Some(NodeParams::DownloadParams(DownloadParams { url })),
+        );
+        self.add_checked(download_node)
+    }
     /// Parse a Download Node
     pub fn parse(&mut self, params: ParseParams) -> Option<&mut Node> {
-        println!("In the parse node!");
-        println!("{:?}", self.kind);
-        if self.kind == KindT::Download {
-            let parse_node = Node::new(KindT::Parse, Some(NodeParams::ParseParams(params)));
-            self.children.get_or_insert_with(Vec::new).push(parse_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let parse_node = Node::new(KindT::Parse, Some(NodeParams::ParseParams(params)));
+        self.add_checked(parse_node)
     }
 
     // Parse methods ------------------------------------------------------
@@ -158,195 +209,246 @@ impl Node {
     /// :param block_index: 0-based block index in case multiple mmCIF or SDF data blocks are present
     /// :param block_header: Reference a specific mmCIF or SDF data block by its block header
     /// :return: a builder that handles operations at structure level
-    pub fn model_structure() {
-        unimplemented!()
+    pub fn model_structure(&mut self, params: StructureParams) -> Option<&mut Node> {
+        let params = StructureParams {
+            structure_type: StructureTypeT::Model,
+            ..params
+        };
+        let struct_node = Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
+        self.add_checked(struct_node)
     }
-    /// Parse a Download Node
+    /// Create a structure for an assembly, e.g. the biological assembly named by
+    /// `assembly_id`
     pub fn assembly_structure(&mut self, params: StructureParams) -> Option<&mut Node> {
-        if self.kind == KindT::Parse {
-            let struct_node =
-                Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
-            self.children.get_or_insert_with(Vec::new).push(struct_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let params = StructureParams {
+            structure_type: StructureTypeT::Assembly,
+            ..params
+        };
+        let struct_node = Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
+        self.add_checked(struct_node)
     }
-    /// Symmetry Structure
+    /// Create a structure with crystal symmetry applied over the `ijk_min..ijk_max` cells
     pub fn symmetry_structure(&mut self, params: StructureParams) -> Option<&mut Node> {
-        // todo: this is the same as the regular structure bit above......
-        if self.kind == KindT::Parse {
-            let struct_node =
-                Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
-            self.children.get_or_insert_with(Vec::new).push(struct_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let params = StructureParams {
+            structure_type: StructureTypeT::Symmetry,
+            ..params
+        };
+        let struct_node = Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
+        self.add_checked(struct_node)
     }
-
-    /// Parse a Download Node
+    /// Create a structure with the symmetry mates within `radius` of the deposited model
     pub fn symmetry_mates_structure(&mut self, params: StructureParams) -> Option<&mut Node> {
-        // todo: this is the same as the regular structure bit above......
-        if self.kind == KindT::Parse {
-            let struct_node =
-                Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
-            self.children.get_or_insert_with(Vec::new).push(struct_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let params = StructureParams {
+            structure_type: StructureTypeT::SymmetryMates,
+            ..params
+        };
+        let struct_node = Node::new(KindT::Structure, Some(NodeParams::StructureParams(params)));
+        self.add_checked(struct_node)
     }
 
     // Structure methods ------------------------------------------------------
 
     /// Create a Component
     pub fn component(&mut self, selector: ComponentSelector) -> Option<&mut Node> {
-        if self.kind == KindT::Structure {
-            let component_node = match selector {
-                ComponentSelector::Selector(sel) => Node::new(
-                    KindT::Component,
-                    Some(NodeParams::ComponentInlineParams(ComponentInlineParams {
-                        selector: ComponentSelector::Selector(sel),
-                    })),
-                ),
-                ComponentSelector::Expression(expr) => Node::new(
-                    KindT::Component,
-                    Some(NodeParams::ComponentInlineParams(ComponentInlineParams {
-                        selector: ComponentSelector::Expression(expr),
-                    })),
-                ),
-                ComponentSelector::ExpressionList(expr_list) => Node::new(
-                    KindT::Component,
-                    Some(NodeParams::ComponentInlineParams(ComponentInlineParams {
-                        selector: ComponentSelector::ExpressionList(expr_list),
-                    })),
-                ),
-            };
-            self.children
-                .get_or_insert_with(Vec::new)
-                .push(component_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let component_node = Node::new(
+            KindT::Component,
+            Some(NodeParams::ComponentInlineParams(ComponentInlineParams {
+                selector,
+            })),
+        );
+        self.add_checked(component_node)
     }
-    pub fn component_from_uri() {
-        unimplemented!()
+    /// Create a Component from an annotation file at a URI
+    pub fn component_from_uri(&mut self, params: ComponentFromUriParams) -> Option<&mut Node> {
+        let component_node = Node::new(
+            KindT::ComponentFromUri,
+            Some(NodeParams::ComponentFromUriParams(params)),
+        );
+        self.add_checked(component_node)
     }
-    pub fn component_from_source() {
-        unimplemented!()
+    /// Create a Component from an annotation category of the structure file itself
+    pub fn component_from_source(
+        &mut self,
+        params: ComponentFromSourceParams,
+    ) -> Option<&mut Node> {
+        let component_node = Node::new(
+            KindT::ComponentFromSource,
+            Some(NodeParams::ComponentFromSourceParams(params)),
+        );
+        self.add_checked(component_node)
     }
-    pub fn label_from_uri() {
-        unimplemented!()
+    pub fn label_from_uri(&mut self, params: LabelFromUriParams) -> Option<&mut Node> {
+        let label_node = Node::new(
+            KindT::LabelFromUri,
+            Some(NodeParams::LabelFromUriParams(params)),
+        );
+        self.add_checked(label_node)
     }
-    pub fn label_from_source() {
-        unimplemented!()
+    pub fn label_from_source(&mut self, params: LabelFromSourceParams) -> Option<&mut Node> {
+        let label_node = Node::new(
+            KindT::LabelFromSource,
+            Some(NodeParams::LabelFromSourceParams(params)),
+        );
+        self.add_checked(label_node)
     }
-    pub fn tooltip_from_uri() {
-        unimplemented!()
+    pub fn tooltip_from_uri(&mut self, params: TooltipFromUriParams) -> Option<&mut Node> {
+        let tooltip_node = Node::new(
+            KindT::TooltipFromUri,
+            Some(NodeParams::TooltipFromUriParams(params)),
+        );
+        self.add_checked(tooltip_node)
     }
-    pub fn tooltip_from_source() {
-        unimplemented!()
+    pub fn tooltip_from_source(&mut self, params: TooltipFromSourceParams) -> Option<&mut Node> {
+        let tooltip_node = Node::new(
+            KindT::TooltipFromSource,
+            Some(NodeParams::TooltipFromSourceParams(params)),
+        );
+        self.add_checked(tooltip_node)
     }
+    /// Rotate and translate the structure. The rotation is a column-major 3x3 matrix and
+    /// must be a proper rotation.
     pub fn transform(&mut self, params: TransformParams) -> Option<&mut Node> {
-        if self.kind == KindT::Structure {
-            let transform_node =
-                Node::new(KindT::Transform, Some(NodeParams::TransformParams(params)));
-            self.children
-                .get_or_insert_with(Vec::new)
-                .push(transform_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
+        if let Some(rotation) = &params.rotation {
+            if !is_rotation_matrix(rotation) {
+                return None;
+            }
         }
-    }
-    pub fn _is_rotation_matrix() {
-        unimplemented!()
+        let transform_node = Node::new(KindT::Transform, Some(NodeParams::TransformParams(params)));
+        self.add_checked(transform_node)
     }
     // Component methods ------------------------------------------------------
 
     /// Add a representation for this component.
     /// :param type: the type of representation, defaults to 'cartoon'
     /// :return: a builder that handles operations at representation level
-
     pub fn representation(
         &mut self,
         representation_type: RepresentationTypeT,
     ) -> Option<&mut Node> {
-        if self.kind == KindT::Component {
-            let representation_node = Node::new(
-                KindT::Representation,
-                Some(NodeParams::RepresentationParams(RepresentationParams {
-                    representation_type,
-                })),
-            );
-            self.children
-                .get_or_insert_with(Vec::new)
-                .push(representation_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let representation_node = Node::new(
+            KindT::Representation,
+            Some(NodeParams::RepresentationParams(RepresentationParams {
+                representation_type,
+            })),
+        );
+        self.add_checked(representation_node)
     }
 
     pub fn label(&mut self, label: String) -> Option<&mut Node> {
-        if self.kind == KindT::Component {
-            let label_node = Node::new(
-                KindT::Label,
-                Some(NodeParams::LabelInlineParams(LabelInlineParams {
-                    text: label,
-                })),
-            );
-            self.children.get_or_insert_with(Vec::new).push(label_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let label_node = Node::new(
+            KindT::Label,
+            Some(NodeParams::LabelInlineParams(LabelInlineParams {
+                text: label,
+            })),
+        );
+        self.add_checked(label_node)
     }
-    pub fn tooltip() {
-        unimplemented!()
+    /// Text shown when hovering over the component
+    pub fn tooltip(&mut self, tooltip: String) -> Option<&mut Node> {
+        let tooltip_node = Node::new(
+            KindT::Tooltip,
+            Some(NodeParams::TooltipInlineParams(TooltipInlineParams {
+                text: tooltip,
+            })),
+        );
+        self.add_checked(tooltip_node)
     }
-    pub fn focus() {
-        unimplemented!()
+    /// Point the camera at this component when the view loads
+    pub fn focus(&mut self, params: FocusInlineParams) -> Option<&mut Node> {
+        let focus_node = Node::new(KindT::Focus, Some(NodeParams::FocusInlineParams(params)));
+        self.add_checked(focus_node)
     }
 
     // Representation methods ------------------------------------------------------
 
-    pub fn color_from_source() {
-        unimplemented!()
+    pub fn color_from_source(&mut self, params: ColorFromSourceParams) -> Option<&mut Node> {
+        let color_node = Node::new(
+            KindT::ColorFromSource,
+            Some(NodeParams::ColorFromSourceParams(params)),
+        );
+        self.add_checked(color_node)
     }
-    pub fn color_from_uri() {
-        unimplemented!()
+    pub fn color_from_uri(&mut self, params: ColorFromUriParams) -> Option<&mut Node> {
+        let color_node = Node::new(
+            KindT::ColorFromUri,
+            Some(NodeParams::ColorFromUriParams(params)),
+        );
+        self.add_checked(color_node)
     }
     // parent Kine => kindt:representation
     // node: kindt => kindt:color
     pub fn color(&mut self, color: ColorT, selector: ComponentSelector) -> Option<&mut Node> {
-        if self.kind == KindT::Representation {
-            let color_node = Node::new(
-                KindT::Color,
-                Some(NodeParams::ColorInlineParams(ColorInlineParams {
-                    base: ComponentInlineParams { selector },
-                    color,
-                })),
-            );
-            self.children.get_or_insert_with(Vec::new).push(color_node);
-            self.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let color_node = Node::new(
+            KindT::Color,
+            Some(NodeParams::ColorInlineParams(ColorInlineParams {
+                base: ComponentInlineParams { selector },
+                color,
+            })),
+        );
+        self.add_checked(color_node)
     }
 
     // GenericVisuals methods ------------------------------------------------------
-    pub fn sphere() {
-        unimplemented!()
+    pub fn sphere(&mut self, params: SphereParams) -> Option<&mut Node> {
+        let sphere_node = Node::new(KindT::Sphere, Some(NodeParams::SphereParams(params)));
+        self.add_checked(sphere_node)
+    }
+    pub fn line(&mut self, params: LineParams) -> Option<&mut Node> {
+        let line_node = Node::new(KindT::Line, Some(NodeParams::LineParams(params)));
+        self.add_checked(line_node)
+    }
+
+    // Primitives methods ------------------------------------------------------
+
+    /// Group of shapes drawn in the coordinate frame of the parent, either the root or a
+    /// structure. Add shapes with `arrow` and `primitive_label` on the returned node.
+    pub fn primitives(&mut self, params: PrimitivesParams) -> Option<&mut Node> {
+        let primitives_node = Node::new(
+            KindT::Primitives,
+            Some(NodeParams::PrimitivesParams(params)),
+        );
+        self.add_checked(primitives_node)
+    }
+    pub fn arrow(&mut self, params: ArrowParams) -> Option<&mut Node> {
+        let arrow_node = Node::new(
+            KindT::Primitive,
+            Some(NodeParams::PrimitiveParams(PrimitiveParams::Arrow(params))),
+        );
+        self.add_checked(arrow_node)
     }
-    pub fn line() {
-        unimplemented!()
+    /// Free-standing text at a position in space
+    pub fn primitive_label(&mut self, params: PrimitiveLabelParams) -> Option<&mut Node> {
+        let label_node = Node::new(
+            KindT::Primitive,
+            Some(NodeParams::PrimitiveParams(PrimitiveParams::Label(params))),
+        );
+        self.add_checked(label_node)
     }
 }
 
-#[derive(Serialize, Deserialize, Debug)]
+/// Check that a column-major 3x3 matrix is orthonormal with determinant 1
+pub(crate) fn is_rotation_matrix(rotation: &[f64]) -> bool {
+    const EPS: f64 = 1e-3;
+    if rotation.len() != 9 {
+        return false;
+    }
+    let m = |row: usize, col: usize| rotation[col * 3 + row];
+    for i in 0..3 {
+        for j in 0..3 {
+            let dot: f64 = (0..3).map(|k| m(k, i) * m(k, j)).sum();
+            let expected = if i == j { 1.0 } else { 0.0 };
+            if (dot - expected).abs() > EPS {
+                return false;
+            }
+        }
+    }
+    let det = m(0, 0) * (m(1, 1) * m(2, 2) - m(1, 2) * m(2, 1))
+        - m(0, 1) * (m(1, 0) * m(2, 2) - m(1, 2) * m(2, 0))
+        + m(0, 2) * (m(1, 0) * m(2, 1) - m(1, 1) * m(2, 0));
+    (det - 1.0).abs() < EPS
+}
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(rename_all = "snake_case")]
 pub enum DescriptionFormatT {
     Markdown,
@@ -358,7 +460,7 @@ pub enum DescriptionFormatT {
 /// The molviewspec metadata. High level info unrelated to
 /// structure visualization.
 ///
-#[derive(Serialize, Deserialize, Debug, Default)]
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
 pub struct Metadata {
     pub version: String,
     #[serde(skip_serializing_if = "Option::is_none")]
@@ -375,11 +477,16 @@ pub struct Metadata {
 ///
 /// Holds methods that modify the root node.
 ///
-#[derive(Serialize, Deserialize, Debug)]
+#[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct State {
     pub root: Node,
     pub metadata: Metadata,
 }
+impl Default for State {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 impl State {
     pub fn new() -> Self {
         State {
@@ -393,28 +500,26 @@ impl State {
     }
     /// Set Camera Location
     pub fn camera(&mut self, params: CameraParams) -> Option<&mut Node> {
-        if self.root.kind == KindT::Root {
-            let camera_node = Node::new(KindT::Camera, Some(NodeParams::CameraParams(params)));
-            self.root
-                .children
-                .get_or_insert_with(Vec::new)
-                .push(camera_node);
-            self.root.children.as_mut().unwrap().last_mut()
-        } else {
-            None
-        }
+        let camera_node = Node::new(KindT::Camera, Some(NodeParams::CameraParams(params)));
+        self.root.add_checked(camera_node)
     }
-    /// Set Canvas Information Location
-    pub fn canvas() {
-        unimplemented!()
+    /// Set Canvas Information, e.g. the background color
+    pub fn canvas(&mut self, params: CanvasParams) -> Option<&mut Node> {
+        let canvas_node = Node::new(KindT::Canvas, Some(NodeParams::CanvasParams(params)));
+        self.root.add_checked(canvas_node)
     }
     // Download a file
     pub fn download(&mut self, url: &str) -> Option<&mut Node> {
         self.root.download(url)
     }
-    /// General Lines and Spheres
-    pub fn generic_visuals() {
-        unimplemented!()
+    /// General Lines and Spheres. Add them with `sphere` and `line` on the returned node.
+    pub fn generic_visuals(&mut self) -> Option<&mut Node> {
+        self.root
+            .add_checked(Node::new(KindT::GenericVisuals, None))
+    }
+    /// Shapes placed in the global coordinate frame, see [`Node::primitives`]
+    pub fn primitives(&mut self, params: PrimitivesParams) -> Option<&mut Node> {
+        self.root.primitives(params)
     }
     pub fn to_url(&self) -> String {
         let json = serde_json::to_string(&self).expect("Json conversion");
@@ -443,19 +548,15 @@ pub struct ParseParams {
 }
 
 /// StructureType. Useful for specifying more complicated sets of structures
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 #[serde(rename_all = "snake_case")]
 pub enum StructureTypeT {
     Model,
+    #[default]
     Assembly,
     Symmetry,
     SymmetryMates,
 }
-impl Default for StructureTypeT {
-    fn default() -> Self {
-        StructureTypeT::Assembly
-    }
-}
 
 /// Structure Params
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
@@ -764,11 +865,13 @@ pub struct DataFromSourceParams {
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 pub struct ComponentInlineParams {
+    #[serde(default)]
     pub selector: ComponentSelector,
 }
 
 #[derive(Serialize, Deserialize, Debug, Clone)]
 #[serde(untagged)]
+#[allow(clippy::large_enum_variant)]
 pub enum ComponentSelector {
     Selector(ComponentSelectorT),
     Expression(ComponentExpression),
@@ -849,7 +952,7 @@ pub struct TooltipFromSourceParams {
     pub base: DataFromSourceParams,
 }
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct FocusInlineParams {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub direction: Option<(f64, f64, f64)>,
@@ -888,6 +991,74 @@ pub struct LineParams {
     pub tooltip: Option<String>,
 }
 
+/// Defaults shared by the primitives of a `primitives` node
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct PrimitivesParams {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorT>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_color: Option<ColorT>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub opacity: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_opacity: Option<f64>,
+}
+
+/// A single shape below a `primitives` node, written with its shape as `params.kind`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PrimitiveParams {
+    Arrow(ArrowParams),
+    Label(PrimitiveLabelParams),
+}
+
+/// Arrow from `start` to `end`, or along `direction` for `length`
+#[derive(Serialize, Deserialize, Debug, Clone, Default)]
+pub struct ArrowParams {
+    pub start: (f64, f64, f64),
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end: Option<(f64, f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub direction: Option<(f64, f64, f64)>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub length: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_start_cap: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_cap_length: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_cap_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_end_cap: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_cap_length: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub end_cap_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub show_tube: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tube_radius: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub color: Option<ColorT>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tooltip: Option<String>,
+}
+
+/// Text placed at a position in space, as opposed to a label attached to a component
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct PrimitiveLabelParams {
+    pub position: (f64, f64, f64),
+    pub text: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_size: Option<f64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_color: Option<ColorT>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub label_offset: Option<f64>,
+}
+
 #[derive(Serialize, Deserialize, Debug, Clone, Default)]
 pub struct CameraParams {
     pub target: (f64, f64, f64),
@@ -0,0 +1,163 @@
+//! Multi-snapshot states.
+//!
+//! A [`States`] file holds an ordered list of [`Snapshot`]s that the viewer steps through,
+//! e.g. a guided tour of a design. Each snapshot is an ordinary [`State`] tree, including
+//! its own camera, together with a title, a markdown description and how long the
+//! transition to it and the pause on it last.
+//!
+//! ```no_run
+//! use ferritin_molviewspec::molviewspec::nodes::{CameraParams, State};
+//! use ferritin_molviewspec::molviewspec::snapshots::{SnapshotMetadata, States};
+//!
+//! let mut overview = State::new();
+//! overview.download("design.pdb");
+//! let mut closeup = State::new();
+//! closeup.download("design.pdb");
+//! closeup.camera(CameraParams {
+//!     target: (10.0, 5.0, 2.0),
+//!     position: (30.0, 5.0, 2.0),
+//!     ..Default::default()
+//! });
+//!
+//! let mut story = States::new();
+//! story.metadata.title = Some("Design 12".to_string());
+//! story.add_snapshot(overview.get_snapshot(SnapshotMetadata {
+//!     title: Some("Overview".to_string()),
+//!     ..Default::default()
+//! }));
+//! story.add_snapshot(closeup.get_snapshot(SnapshotMetadata {
+//!     title: Some("Binding site".to_string()),
+//!     description: Some("The new **salt bridge**".to_string()),
+//!     transition_duration_ms: Some(1500),
+//!     ..Default::default()
+//! }));
+//! let mvsj = serde_json::to_string_pretty(&story).unwrap();
+//! ```
+use super::nodes::{DescriptionFormatT, Metadata, Node, State};
+use serde::{Deserialize, Serialize};
+
+/// Marks a file as holding several snapshots rather than a single state
+#[derive(Serialize, Deserialize, Debug, Default, Clone, PartialEq)]
+#[serde(rename_all = "snake_case")]
+pub enum StatesKindT {
+    #[default]
+    Multiple,
+}
+
+/// Title, description and timing of one snapshot
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct SnapshotMetadata {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    /// Shown next to the snapshot; markdown unless `description_format` says otherwise
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub description_format: Option<DescriptionFormatT>,
+    /// Unique name to link to the snapshot from descriptions
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key: Option<String>,
+    /// How long the snapshot is shown when the story plays on its own
+    pub linger_duration_ms: u32,
+    /// How long the animated transition from the previous snapshot takes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub transition_duration_ms: Option<u32>,
+}
+
+impl Default for SnapshotMetadata {
+    fn default() -> Self {
+        SnapshotMetadata {
+            title: None,
+            description: None,
+            description_format: None,
+            key: None,
+            linger_duration_ms: 1000,
+            transition_duration_ms: None,
+        }
+    }
+}
+
+/// One step of a story: a node tree and its metadata
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct Snapshot {
+    pub root: Node,
+    pub metadata: SnapshotMetadata,
+}
+
+/// Several snapshots in one file, serialized with `"kind": "multiple"`
+#[derive(Serialize, Deserialize, Debug, Clone)]
+pub struct States {
+    pub kind: StatesKindT,
+    /// Title and description of the whole story
+    pub metadata: Metadata,
+    pub snapshots: Vec<Snapshot>,
+}
+
+impl Default for States {
+    fn default() -> Self {
+        States::new()
+    }
+}
+
+impl States {
+    pub fn new() -> Self {
+        States {
+            kind: StatesKindT::Multiple,
+            metadata: State::new().metadata,
+            snapshots: Vec::new(),
+        }
+    }
+    /// Append a snapshot and return it for further changes
+    pub fn add_snapshot(&mut self, snapshot: Snapshot) -> &mut Snapshot {
+        self.snapshots.push(snapshot);
+        self.snapshots.last_mut().unwrap()
+    }
+}
+
+/// Contents of an `.mvsj` file or MVSX index: a single state or several snapshots
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum MvsData {
+    States(States),
+    State(Box<State>),
+}
+
+impl MvsData {
+    /// Root nodes of the state or of every snapshot
+    pub fn roots(&self) -> Vec<&Node> {
+        match self {
+            MvsData::State(state) => vec![&state.root],
+            MvsData::States(states) => states.snapshots.iter().map(|s| &s.root).collect(),
+        }
+    }
+}
+
+impl From<State> for MvsData {
+    fn from(state: State) -> Self {
+        MvsData::State(Box::new(state))
+    }
+}
+
+impl From<States> for MvsData {
+    fn from(states: States) -> Self {
+        MvsData::States(states)
+    }
+}
+
+impl State {
+    /// Turn this state into a snapshot of a [`States`] story. The title and description
+    /// fall back to the ones in the state's metadata.
+    pub fn get_snapshot(self, mut metadata: SnapshotMetadata) -> Snapshot {
+        if metadata.title.is_none() {
+            metadata.title = self.metadata.title;
+        }
+        if metadata.description.is_none() {
+            metadata.description = self.metadata.description;
+            metadata.description_format = self.metadata.description_format;
+        }
+        Snapshot {
+            root: self.root,
+            metadata,
+        }
+    }
+}
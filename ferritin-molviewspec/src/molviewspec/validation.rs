@@ -0,0 +1,691 @@
+//! Reading and validating MolViewSpec states.
+//!
+//! `.mvsj` files are read node by node: the `kind` of each node decides how its `params`
+//! are parsed, and every child is checked against the kinds the MVS tree allows below its
+//! parent. Problems are collected together with the path of the offending node, e.g.
+//! `root.children[0].children[0].params`, rather than stopping at the first one.
+//!
+//! States built in code can be checked the same way with [`State::validate`]. Stories with
+//! several snapshots are read with [`States::from_mvsj`].
+//!
+//! Node kinds and primitive shapes this crate does not model, such as `volume` or `clip`,
+//! are not errors: they are kept as written and listed by [`State::warnings`].
+//!
+use super::nodes::{is_rotation_matrix, KindT, Metadata, Node, NodeParams, State, TransformParams};
+use super::snapshots::{MvsData, Snapshot, SnapshotMetadata, States, StatesKindT};
+use serde::de::DeserializeOwned;
+use serde_json::{Map, Value};
+use std::fmt;
+
+const NODE_KEYS: [&str; 5] = ["kind", "params", "children", "ref", "custom"];
+const STATE_KEYS: [&str; 2] = ["root", "metadata"];
+const STATES_KEYS: [&str; 3] = ["kind", "metadata", "snapshots"];
+const METADATA_KEYS: [&str; 5] = [
+    "version",
+    "title",
+    "description",
+    "description_format",
+    "timestamp",
+];
+const SNAPSHOT_METADATA_KEYS: [&str; 6] = [
+    "title",
+    "description",
+    "description_format",
+    "key",
+    "linger_duration_ms",
+    "transition_duration_ms",
+];
+const URI_PARAMS: [&str; 7] = [
+    "uri",
+    "format",
+    "category_name",
+    "field_name",
+    "block_header",
+    "block_index",
+    "schema",
+];
+const SOURCE_PARAMS: [&str; 5] = [
+    "category_name",
+    "field_name",
+    "block_header",
+    "block_index",
+    "schema",
+];
+const EXPRESSION_PARAMS: [&str; 16] = [
+    "label_entity_id",
+    "label_asym_id",
+    "auth_asym_id",
+    "label_seq_id",
+    "auth_seq_id",
+    "pdbx_pdb_ins_code",
+    "beg_label_seq_id",
+    "end_label_seq_id",
+    "beg_auth_seq_id",
+    "end_auth_seq_id",
+    "residue_index",
+    "label_atom_id",
+    "auth_atom_id",
+    "type_symbol",
+    "atom_id",
+    "atom_index",
+];
+/// Primitive shapes with typed params
+const PRIMITIVE_KINDS: [&str; 2] = ["arrow", "label"];
+
+/// A problem with one part of a MolViewSpec state
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationError {
+    /// Location in the JSON tree, e.g. `root.children[0].params`
+    pub path: String,
+    pub message: String,
+}
+
+impl fmt::Display for ValidationError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}: {}", self.path, self.message)
+    }
+}
+
+/// Every problem found while reading or validating a state
+#[derive(Debug, Clone, PartialEq)]
+pub struct ValidationErrors(pub Vec<ValidationError>);
+
+impl ValidationErrors {
+    fn single(path: &str, message: String) -> Self {
+        ValidationErrors(vec![ValidationError {
+            path: path.to_string(),
+            message,
+        }])
+    }
+}
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Invalid MolViewSpec state:")?;
+        for error in &self.0 {
+            writeln!(f, "  {}", error)?;
+        }
+        Ok(())
+    }
+}
+
+impl std::error::Error for ValidationErrors {}
+
+impl State {
+    /// Parse and validate the contents of an `.mvsj` file.
+    pub fn from_mvsj(json: &str) -> Result<State, ValidationErrors> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| ValidationErrors::single("$", e.to_string()))?;
+        State::from_value(&value)
+    }
+    /// Parse and validate a state from JSON.
+    pub fn from_value(value: &Value) -> Result<State, ValidationErrors> {
+        let Some(object) = value.as_object() else {
+            return Err(ValidationErrors::single(
+                "$",
+                "expected a JSON object".to_string(),
+            ));
+        };
+        let mut errors = Vec::new();
+        unknown_keys(object, &STATE_KEYS, "$", "key", &mut errors);
+
+        let root = read_root(object, "$", &mut errors);
+        let metadata = read_metadata::<Metadata>(object, "$", &METADATA_KEYS, &mut errors);
+        match (root, metadata) {
+            (Some(root), Some(metadata)) if errors.is_empty() => Ok(State { root, metadata }),
+            _ => Err(ValidationErrors(errors)),
+        }
+    }
+    /// Check the node tree against the MVS schema: child kinds, params for every kind and
+    /// transform rotations.
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let value =
+            serde_json::to_value(self).map_err(|e| ValidationErrors::single("$", e.to_string()))?;
+        State::from_value(&value).map(|_| ())
+    }
+    /// Nodes and primitives this crate does not model. They are kept as written, so the
+    /// state still round-trips, but their params are not checked.
+    pub fn warnings(&self) -> Vec<ValidationError> {
+        let mut warnings = Vec::new();
+        unmodeled_nodes(&self.root, "root", &mut warnings);
+        warnings
+    }
+}
+
+impl States {
+    /// Parse and validate a `.mvsj` file holding several snapshots.
+    pub fn from_mvsj(json: &str) -> Result<States, ValidationErrors> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| ValidationErrors::single("$", e.to_string()))?;
+        States::from_value(&value)
+    }
+    /// Parse and validate a multi-snapshot state from JSON.
+    pub fn from_value(value: &Value) -> Result<States, ValidationErrors> {
+        let Some(object) = value.as_object() else {
+            return Err(ValidationErrors::single(
+                "$",
+                "expected a JSON object".to_string(),
+            ));
+        };
+        let mut errors = Vec::new();
+        unknown_keys(object, &STATES_KEYS, "$", "key", &mut errors);
+
+        match object.get("kind") {
+            Some(kind) if kind == "multiple" => {}
+            Some(kind) => push(
+                &mut errors,
+                "kind",
+                format!("expected `multiple`, found {}", kind),
+            ),
+            None => push(&mut errors, "$", "missing `kind`".to_string()),
+        }
+        let metadata = read_metadata::<Metadata>(object, "$", &METADATA_KEYS, &mut errors);
+        let snapshots = match object.get("snapshots") {
+            Some(Value::Array(snapshots)) => snapshots
+                .iter()
+                .enumerate()
+                .filter_map(|(i, snapshot)| {
+                    let path = format!("snapshots[{}]", i);
+                    let Some(snapshot) = snapshot.as_object() else {
+                        push(&mut errors, &path, "expected a snapshot object".to_string());
+                        return None;
+                    };
+                    unknown_keys(snapshot, &STATE_KEYS, &path, "key", &mut errors);
+                    let root = read_root(snapshot, &path, &mut errors);
+                    let metadata = read_metadata::<SnapshotMetadata>(
+                        snapshot,
+                        &path,
+                        &SNAPSHOT_METADATA_KEYS,
+                        &mut errors,
+                    );
+                    Some(Snapshot {
+                        root: root?,
+                        metadata: metadata?,
+                    })
+                })
+                .collect(),
+            Some(_) => {
+                push(&mut errors, "snapshots", "expected an array".to_string());
+                Vec::new()
+            }
+            None => {
+                push(&mut errors, "$", "missing `snapshots`".to_string());
+                Vec::new()
+            }
+        };
+        match metadata {
+            Some(metadata) if errors.is_empty() => Ok(States {
+                kind: StatesKindT::Multiple,
+                metadata,
+                snapshots,
+            }),
+            _ => Err(ValidationErrors(errors)),
+        }
+    }
+    /// Check the node tree of every snapshot, see [`State::validate`].
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let value =
+            serde_json::to_value(self).map_err(|e| ValidationErrors::single("$", e.to_string()))?;
+        States::from_value(&value).map(|_| ())
+    }
+    /// Nodes and primitives this crate does not model, see [`State::warnings`].
+    pub fn warnings(&self) -> Vec<ValidationError> {
+        let mut warnings = Vec::new();
+        for (i, snapshot) in self.snapshots.iter().enumerate() {
+            let path = format!("snapshots[{}].root", i);
+            unmodeled_nodes(&snapshot.root, &path, &mut warnings);
+        }
+        warnings
+    }
+}
+
+impl MvsData {
+    /// Parse and validate an `.mvsj` file holding either a single state or several
+    /// snapshots.
+    pub fn from_mvsj(json: &str) -> Result<MvsData, ValidationErrors> {
+        let value: Value =
+            serde_json::from_str(json).map_err(|e| ValidationErrors::single("$", e.to_string()))?;
+        MvsData::from_value(&value)
+    }
+    /// Parse and validate a state or, when the object has a `kind`, several snapshots.
+    pub fn from_value(value: &Value) -> Result<MvsData, ValidationErrors> {
+        match value.get("kind") {
+            Some(_) => States::from_value(value).map(MvsData::States),
+            None => State::from_value(value).map(MvsData::from),
+        }
+    }
+    /// Check the node trees, see [`State::validate`] and [`States::validate`].
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        match self {
+            MvsData::State(state) => state.validate(),
+            MvsData::States(states) => states.validate(),
+        }
+    }
+    /// Nodes and primitives this crate does not model, see [`State::warnings`].
+    pub fn warnings(&self) -> Vec<ValidationError> {
+        match self {
+            MvsData::State(state) => state.warnings(),
+            MvsData::States(states) => states.warnings(),
+        }
+    }
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+/// Path of `key` below the object at `path`, where `$` is the top of the file
+fn child_path(path: &str, key: &str) -> String {
+    match path {
+        "$" => key.to_string(),
+        _ => format!("{}.{}", path, key),
+    }
+}
+
+/// The `root` node of a state or snapshot object
+fn read_root(
+    object: &Map<String, Value>,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<Node> {
+    let Some(root) = object.get("root") else {
+        push(errors, path, "missing `root`".to_string());
+        return None;
+    };
+    let root_path = child_path(path, "root");
+    let root = read_node(root, &root_path, None, errors)?;
+    if root.kind != KindT::Root {
+        push(
+            errors,
+            &format!("{}.kind", root_path),
+            format!("expected `root`, found `{}`", kind_name(&root.kind)),
+        );
+    }
+    Some(root)
+}
+
+/// The `metadata` of a state, story or snapshot object
+fn read_metadata<T: DeserializeOwned>(
+    object: &Map<String, Value>,
+    path: &str,
+    keys: &[&str],
+    errors: &mut Vec<ValidationError>,
+) -> Option<T> {
+    match object.get("metadata") {
+        Some(metadata) => read_typed(metadata, keys, &child_path(path, "metadata"), errors),
+        None => {
+            push(errors, path, "missing `metadata`".to_string());
+            None
+        }
+    }
+}
+
+fn push(errors: &mut Vec<ValidationError>, path: &str, message: String) {
+    errors.push(ValidationError {
+        path: path.to_string(),
+        message,
+    });
+}
+
+/// Name of a kind as written in `.mvsj` files
+fn kind_name(kind: &KindT) -> String {
+    serde_json::to_value(kind)
+        .ok()
+        .and_then(|value| value.as_str().map(str::to_string))
+        .unwrap_or_else(|| format!("{:?}", kind))
+}
+
+fn unknown_keys(
+    object: &Map<String, Value>,
+    known: &[&str],
+    path: &str,
+    what: &str,
+    errors: &mut Vec<ValidationError>,
+) {
+    for key in object.keys() {
+        if !known.contains(&key.as_str()) {
+            push(errors, path, format!("unknown {} `{}`", what, key));
+        }
+    }
+}
+
+fn read_node(
+    value: &Value,
+    path: &str,
+    parent: Option<&KindT>,
+    errors: &mut Vec<ValidationError>,
+) -> Option<Node> {
+    let Some(object) = value.as_object() else {
+        push(errors, path, "expected a node object".to_string());
+        return None;
+    };
+    unknown_keys(object, &NODE_KEYS, path, "key", errors);
+
+    let kind_path = format!("{}.kind", path);
+    let kind: KindT = match object.get("kind") {
+        Some(kind) => match serde_json::from_value(kind.clone()) {
+            Ok(kind) => kind,
+            Err(_) => {
+                push(errors, &kind_path, format!("unknown node kind {}", kind));
+                return None;
+            }
+        },
+        None => {
+            push(errors, path, "missing `kind`".to_string());
+            return None;
+        }
+    };
+    if let Some(parent) = parent {
+        // unmodeled kinds are reported by `warnings`, wherever they sit in the tree
+        let unmodeled = matches!(kind, KindT::Unknown(_)) || matches!(parent, KindT::Unknown(_));
+        if !unmodeled && !parent.accepts(&kind) {
+            push(
+                errors,
+                &kind_path,
+                format!(
+                    "`{}` is not allowed under `{}`",
+                    kind_name(&kind),
+                    kind_name(parent)
+                ),
+            );
+        }
+    }
+
+    let params_path = format!("{}.params", path);
+    let empty = Value::Object(Map::new());
+    let params = match (&kind, object.get("params")) {
+        (KindT::Unknown(_), params) => params.cloned().map(NodeParams::Unknown),
+        (_, params) => read_params(&kind, params.unwrap_or(&empty), &params_path, errors),
+    };
+
+    let children = match object.get("children") {
+        None => None,
+        Some(Value::Array(children)) => Some(
+            children
+                .iter()
+                .enumerate()
+                .filter_map(|(i, child)| {
+                    read_node(
+                        child,
+                        &format!("{}.children[{}]", path, i),
+                        Some(&kind),
+                        errors,
+                    )
+                })
+                .collect(),
+        ),
+        Some(_) => {
+            push(
+                errors,
+                &format!("{}.children", path),
+                "expected an array".to_string(),
+            );
+            None
+        }
+    };
+    let ref_ = match object.get("ref") {
+        None => None,
+        Some(Value::String(reference)) => Some(reference.clone()),
+        Some(_) => {
+            push(
+                errors,
+                &format!("{}.ref", path),
+                "expected a string".to_string(),
+            );
+            None
+        }
+    };
+
+    Some(Node {
+        kind,
+        params,
+        children,
+        ref_,
+        custom: object.get("custom").cloned(),
+    })
+}
+
+/// Parse `params` with the type the node kind calls for.
+fn read_params(
+    kind: &KindT,
+    params: &Value,
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<NodeParams> {
+    let keys = declared_params(kind);
+    match kind {
+        KindT::Root | KindT::GenericVisuals => {
+            if !matches!(params.as_object(), Some(params) if params.is_empty()) {
+                push(
+                    errors,
+                    path,
+                    format!("`{}` nodes take no params", kind_name(kind)),
+                );
+            }
+            None
+        }
+        KindT::Camera => read_typed(params, &keys, path, errors).map(NodeParams::CameraParams),
+        KindT::Canvas => read_typed(params, &keys, path, errors).map(NodeParams::CanvasParams),
+        KindT::Color => {
+            unknown_selector_params(params, path, errors);
+            read_typed(params, &keys, path, errors).map(NodeParams::ColorInlineParams)
+        }
+        KindT::ColorFromSource => {
+            read_typed(params, &keys, path, errors).map(NodeParams::ColorFromSourceParams)
+        }
+        KindT::ColorFromUri => {
+            read_typed(params, &keys, path, errors).map(NodeParams::ColorFromUriParams)
+        }
+        KindT::Component => {
+            unknown_selector_params(params, path, errors);
+            read_typed(params, &keys, path, errors).map(NodeParams::ComponentInlineParams)
+        }
+        KindT::ComponentFromSource => {
+            read_typed(params, &keys, path, errors).map(NodeParams::ComponentFromSourceParams)
+        }
+        KindT::ComponentFromUri => {
+            read_typed(params, &keys, path, errors).map(NodeParams::ComponentFromUriParams)
+        }
+        KindT::Download => read_typed(params, &keys, path, errors).map(NodeParams::DownloadParams),
+        KindT::Focus => read_typed(params, &keys, path, errors).map(NodeParams::FocusInlineParams),
+        KindT::Label => read_typed(params, &keys, path, errors).map(NodeParams::LabelInlineParams),
+        KindT::LabelFromSource => {
+            read_typed(params, &keys, path, errors).map(NodeParams::LabelFromSourceParams)
+        }
+        KindT::LabelFromUri => {
+            read_typed(params, &keys, path, errors).map(NodeParams::LabelFromUriParams)
+        }
+        KindT::Line => read_typed(params, &keys, path, errors).map(NodeParams::LineParams),
+        KindT::Parse => read_typed(params, &keys, path, errors).map(NodeParams::ParseParams),
+        KindT::Primitive => match primitive_kind(params) {
+            Some(shape) if !PRIMITIVE_KINDS.contains(&shape) => {
+                Some(NodeParams::Unknown(params.clone()))
+            }
+            shape => {
+                let keys = declared_primitive_params(shape);
+                read_typed(params, &keys, path, errors).map(NodeParams::PrimitiveParams)
+            }
+        },
+        KindT::Primitives => {
+            read_typed(params, &keys, path, errors).map(NodeParams::PrimitivesParams)
+        }
+        KindT::Representation => {
+            read_typed(params, &keys, path, errors).map(NodeParams::RepresentationParams)
+        }
+        KindT::Sphere => read_typed(params, &keys, path, errors).map(NodeParams::SphereParams),
+        KindT::Structure => {
+            read_typed(params, &keys, path, errors).map(NodeParams::StructureParams)
+        }
+        KindT::Tooltip => {
+            read_typed(params, &keys, path, errors).map(NodeParams::TooltipInlineParams)
+        }
+        KindT::TooltipFromSource => {
+            read_typed(params, &keys, path, errors).map(NodeParams::TooltipFromSourceParams)
+        }
+        KindT::TooltipFromUri => {
+            read_typed(params, &keys, path, errors).map(NodeParams::TooltipFromUriParams)
+        }
+        KindT::Transform => {
+            let transform: TransformParams = read_typed(params, &keys, path, errors)?;
+            if let Some(rotation) = &transform.rotation {
+                if !is_rotation_matrix(rotation) {
+                    push(
+                        errors,
+                        &format!("{}.rotation", path),
+                        "expected 9 values forming a rotation matrix".to_string(),
+                    );
+                }
+            }
+            Some(NodeParams::TransformParams(transform))
+        }
+        KindT::Unknown(_) => Some(NodeParams::Unknown(params.clone())),
+    }
+}
+
+/// Params keys the MVS schema declares for `kind`
+fn declared_params(kind: &KindT) -> Vec<&'static str> {
+    match kind {
+        KindT::Root | KindT::GenericVisuals | KindT::Unknown(_) => vec![],
+        KindT::Camera => vec!["target", "position", "up"],
+        KindT::Canvas => vec!["background_color"],
+        KindT::Color => vec!["selector", "color"],
+        KindT::ColorFromSource | KindT::LabelFromSource | KindT::TooltipFromSource => {
+            SOURCE_PARAMS.to_vec()
+        }
+        KindT::ColorFromUri | KindT::LabelFromUri | KindT::TooltipFromUri => URI_PARAMS.to_vec(),
+        KindT::Component => vec!["selector"],
+        KindT::ComponentFromSource => [SOURCE_PARAMS.as_slice(), &["field_values"]].concat(),
+        KindT::ComponentFromUri => [URI_PARAMS.as_slice(), &["field_values"]].concat(),
+        KindT::Download => vec!["url"],
+        KindT::Focus => vec!["direction", "up"],
+        KindT::Label | KindT::Tooltip => vec!["text"],
+        KindT::Line => vec![
+            "position1",
+            "position2",
+            "radius",
+            "color",
+            "label",
+            "tooltip",
+        ],
+        KindT::Parse => vec!["format"],
+        KindT::Primitive => vec!["kind"],
+        KindT::Primitives => vec![
+            "color",
+            "label_color",
+            "tooltip",
+            "opacity",
+            "label_opacity",
+        ],
+        KindT::Representation => vec!["type"],
+        KindT::Sphere => vec!["position", "radius", "color", "label", "tooltip"],
+        KindT::Structure => vec![
+            "type",
+            "assembly_id",
+            "assembly_index",
+            "model_index",
+            "block_index",
+            "block_header",
+            "radius",
+            "ijk_min",
+            "ijk_max",
+        ],
+        KindT::Transform => vec!["rotation", "translation"],
+    }
+}
+
+/// Params keys declared for a primitive of the given shape
+fn declared_primitive_params(shape: Option<&str>) -> Vec<&'static str> {
+    let shape_keys: &[&str] = match shape {
+        Some("arrow") => &[
+            "start",
+            "end",
+            "direction",
+            "length",
+            "show_start_cap",
+            "start_cap_length",
+            "start_cap_radius",
+            "show_end_cap",
+            "end_cap_length",
+            "end_cap_radius",
+            "show_tube",
+            "tube_radius",
+            "color",
+            "tooltip",
+        ],
+        Some("label") => &[
+            "position",
+            "text",
+            "label_size",
+            "label_color",
+            "label_offset",
+        ],
+        _ => &[],
+    };
+    [["kind"].as_slice(), shape_keys].concat()
+}
+
+/// The shape of a `primitive` node, written as `params.kind`
+fn primitive_kind(params: &Value) -> Option<&str> {
+    params.get("kind").and_then(Value::as_str)
+}
+
+/// Deserialize `value`, reporting serde errors and keys outside the declared `keys`.
+fn read_typed<T: DeserializeOwned>(
+    value: &Value,
+    keys: &[&str],
+    path: &str,
+    errors: &mut Vec<ValidationError>,
+) -> Option<T> {
+    // serde skips unknown fields, so check the keys against the schema first
+    if let Some(object) = value.as_object() {
+        unknown_keys(object, keys, path, "parameter", errors);
+    }
+    match serde_json::from_value::<T>(value.clone()) {
+        Ok(typed) => Some(typed),
+        Err(e) => {
+            push(errors, path, e.to_string());
+            None
+        }
+    }
+}
+
+/// Report unknown keys inside a component selector, so that e.g. a typo is not read as an
+/// empty expression selecting everything.
+fn unknown_selector_params(params: &Value, path: &str, errors: &mut Vec<ValidationError>) {
+    let path = child_path(path, "selector");
+    match params.get("selector") {
+        Some(Value::Object(expression)) => {
+            unknown_keys(expression, &EXPRESSION_PARAMS, &path, "parameter", errors)
+        }
+        Some(Value::Array(expressions)) => {
+            for (i, expression) in expressions.iter().enumerate() {
+                if let Some(expression) = expression.as_object() {
+                    let path = format!("{}[{}]", path, i);
+                    unknown_keys(expression, &EXPRESSION_PARAMS, &path, "parameter", errors);
+                }
+            }
+        }
+        _ => {}
+    }
+}
+
+/// Warn about every node of an unknown kind and every primitive of an unknown shape
+fn unmodeled_nodes(node: &Node, path: &str, warnings: &mut Vec<ValidationError>) {
+    match (&node.kind, &node.params) {
+        (KindT::Unknown(kind), _) => push(
+            warnings,
+            &format!("{}.kind", path),
+            format!("node kind `{}` is not supported and was kept as is", kind),
+        ),
+        (KindT::Primitive, Some(NodeParams::Unknown(params))) => push(
+            warnings,
+            &format!("{}.params.kind", path),
+            format!(
+                "primitive kind {} is not supported and was kept as is",
+                params.get("kind").unwrap_or(&Value::Null)
+            ),
+        ),
+        _ => {}
+    }
+    for (i, child) in node.children.iter().flatten().enumerate() {
+        unmodeled_nodes(child, &format!("{}.children[{}]", path, i), warnings);
+    }
+}
@@ -1,11 +1,21 @@
+use ferritin_core::{AtomCollection, Selection};
+use ferritin_molviewspec::molviewspec::annotations::{
+    annotate_from_uri, selection_expressions, Palette, ResidueAnnotations,
+};
+use ferritin_molviewspec::molviewspec::archive::MvsArchive;
 use ferritin_molviewspec::molviewspec::nodes::{
-    ColorT, ComponentExpression, ComponentSelector, ComponentSelectorT, ParseFormatT, ParseParams,
-    RepresentationTypeT, State, StructureParams, StructureTypeT,
+    ArrowParams, CameraParams, CanvasParams, ColorFromUriParams, ColorNamesT, ColorT,
+    ComponentExpression, ComponentFromUriParams, ComponentSelector, ComponentSelectorT,
+    DataFromUriParams, DescriptionFormatT, FocusInlineParams, KindT, LineParams, ParseFormatT,
+    ParseParams, PrimitiveLabelParams, PrimitivesParams, RepresentationTypeT, SchemaFormatT,
+    SchemaT, SphereParams, State, StructureParams, StructureTypeT, TransformParams,
 };
-use serde_json::from_reader;
+use ferritin_molviewspec::molviewspec::snapshots::{MvsData, SnapshotMetadata, States};
+use ferritin_test_data::TestFile;
+use serde_json::{from_reader, json, Value};
 use std::fs::File;
 use std::io::BufReader;
-use std::io::Write;
+use std::io::{Cursor, Write};
 
 const TEST_OUTPUT_DIR: &str = "./test_temporary";
 
@@ -113,6 +123,35 @@ fn test_moviewspec_00_builder_basics() {
         .representation(cartoon_type);
     // .expect("a valid representation")
     // .color(color, color_component);
+
+    // each structure builder sets its own structure type
+    let mut types_state = State::new();
+    let parse = types_state
+        .download("https://files.wwpdb.org/download/1cbs.cif")
+        .expect("download")
+        .parse(ParseParams {
+            format: ParseFormatT::Mmcif,
+        })
+        .expect("parse");
+    parse.model_structure(StructureParams::default());
+    parse.symmetry_structure(StructureParams::default());
+    parse.symmetry_mates_structure(StructureParams::default());
+    parse.assembly_structure(StructureParams {
+        structure_type: StructureTypeT::Model,
+        ..Default::default()
+    });
+    let types: Vec<serde_json::Value> = parse
+        .children
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|node| serde_json::to_value(node).unwrap()["params"]["type"].clone())
+        .collect();
+    assert_eq!(
+        types,
+        vec!["model", "symmetry", "symmetry_mates", "assembly"]
+    );
+
     std::fs::create_dir_all(TEST_OUTPUT_DIR).expect("Failed to create output directory");
     let pretty_json = serde_json::to_string_pretty(&state).unwrap();
     let mut file = File::create(format!("{}/test_moviewspec_01.json", TEST_OUTPUT_DIR)).unwrap();
@@ -288,8 +327,27 @@ fn test_moviewspec_01_common_actions_selectors() {
 
     arg_b_537.label("aaRS Class II Signature".to_string());
 
-    // Todo: implement focus
     // focus = structure.component(selector=[mvs.ComponentExpression(label_asym_id='E'), mvs.ComponentExpression(label_asym_id="B", label_seq_id=217), mvs.ComponentExpression(label_asym_id="B", label_seq_id=537)]).focus()
+    structure
+        .component(ComponentSelector::ExpressionList(vec![
+            ComponentExpression {
+                label_asym_id: Some("E".to_string()),
+                ..Default::default()
+            },
+            ComponentExpression {
+                label_asym_id: Some("B".to_string()),
+                label_seq_id: Some(217),
+                ..Default::default()
+            },
+            ComponentExpression {
+                label_asym_id: Some("B".to_string()),
+                label_seq_id: Some(537),
+                ..Default::default()
+            },
+        ]))
+        .expect("Expectation")
+        .focus(FocusInlineParams::default())
+        .expect("focus");
 
     let pretty_json = serde_json::to_string_pretty(&state).unwrap();
     let mut file = File::create(format!(
@@ -429,3 +487,568 @@ fn test_moviewspec_01_common_actions_transform_superimpose() {
 
     //Todo
 }
+
+#[test]
+fn test_moviewspec_02_annotations_primitives_canvas() {
+    let annotations = |field_name: &str| DataFromUriParams {
+        uri: "https://molstar.org/mol-view-spec/examples/annotations/annotations-1h9t.cif"
+            .to_string(),
+        format: SchemaFormatT::Cif,
+        category_name: Some("components".to_string()),
+        field_name: Some(field_name.to_string()),
+        block_header: Some("1h9t_annotations".to_string()),
+        block_index: None,
+        schema_: SchemaT::Chain,
+    };
+
+    let mut state = State::new();
+    state
+        .canvas(CanvasParams {
+            background_color: ColorT::Named(ColorNamesT::Black),
+        })
+        .expect("canvas on the root");
+    let structure = state
+        .download("https://files.wwpdb.org/download/1h9t.cif")
+        .expect("Create a Download node with a URL")
+        .parse(ParseParams {
+            format: ParseFormatT::Mmcif,
+        })
+        .expect("Parseable option")
+        .model_structure(StructureParams::default())
+        .expect("a model structure");
+
+    let protein = structure
+        .component_from_uri(ComponentFromUriParams {
+            base: annotations("component"),
+            field_values: Some(vec!["Protein".to_string()]),
+        })
+        .expect("component from uri");
+    protein
+        .representation(RepresentationTypeT::Cartoon)
+        .expect("Representation")
+        .color_from_uri(ColorFromUriParams {
+            base: annotations("color"),
+        })
+        .expect("color from uri");
+    protein.tooltip("Protein".to_string()).expect("tooltip");
+    protein.focus(FocusInlineParams::default()).expect("focus");
+    let primitive = PrimitiveLabelParams {
+        position: (0.0, 0.0, 0.0),
+        text: "origin".to_string(),
+        label_size: None,
+        label_color: None,
+        label_offset: None,
+    };
+    assert!(protein.primitive_label(primitive).is_none());
+
+    // nodes are only added below parents the MVS tree allows
+    assert!(protein.sphere(sphere()).is_none());
+    assert!(protein.transform(TransformParams::default()).is_none());
+    assert!(state
+        .root
+        .parse(ParseParams {
+            format: ParseFormatT::Pdb
+        })
+        .is_none());
+
+    let visuals = state
+        .generic_visuals()
+        .expect("generic visuals on the root");
+    visuals.sphere(sphere()).expect("sphere");
+    visuals
+        .line(LineParams {
+            position1: (0.0, 0.0, 0.0),
+            position2: (10.0, 0.0, 0.0),
+            radius: 0.2,
+            color: ColorT::Hex("#ff0000".to_string()),
+            label: None,
+            tooltip: None,
+        })
+        .expect("line");
+    assert!(visuals.component(ComponentSelector::default()).is_none());
+    assert!(visuals.label("z".to_string()).is_none());
+
+    let primitives = state
+        .primitives(PrimitivesParams {
+            opacity: Some(0.8),
+            ..Default::default()
+        })
+        .expect("primitives on the root");
+    primitives
+        .arrow(ArrowParams {
+            start: (0.0, 0.0, 0.0),
+            end: Some((0.0, 10.0, 0.0)),
+            tube_radius: Some(0.2),
+            end_cap_radius: Some(0.5),
+            color: Some(ColorT::Hex("#00ff00".to_string())),
+            tooltip: Some("y axis".to_string()),
+            ..Default::default()
+        })
+        .expect("arrow");
+    primitives
+        .primitive_label(PrimitiveLabelParams {
+            position: (0.0, 0.0, 10.0),
+            text: "z".to_string(),
+            label_size: Some(2.0),
+            label_color: None,
+            label_offset: None,
+        })
+        .expect("label");
+    assert!(primitives.sphere(sphere()).is_none());
+    assert!(primitives.label("z".to_string()).is_none());
+
+    let kinds: Vec<&KindT> = state
+        .root
+        .children
+        .as_ref()
+        .unwrap()
+        .iter()
+        .map(|node| node.get_kind())
+        .collect();
+    assert_eq!(
+        kinds,
+        vec![
+            &KindT::Canvas,
+            &KindT::Download,
+            &KindT::GenericVisuals,
+            &KindT::Primitives
+        ]
+    );
+
+    let json = serde_json::to_string(&state).unwrap();
+    assert!(json.contains(r#""kind":"component_from_uri""#));
+    assert!(json.contains(r#""field_values":["Protein"]"#));
+    assert!(json.contains(r#""kind":"focus","params":{}"#));
+    assert!(json.contains(r#""background_color":"black""#));
+    assert!(json.contains(r#""type":"model""#));
+    assert!(json.contains(r#""kind":"primitives","params":{"opacity":0.8}"#));
+    assert!(json.contains(r#""kind":"primitive","params":{"kind":"arrow","start":[0.0,0.0,0.0]"#));
+    assert!(json.contains(r#""params":{"kind":"label","position":[0.0,0.0,10.0],"text":"z""#));
+
+    // shapes written with `params.kind` read back as primitives
+    assert!(state.validate().is_ok());
+}
+
+#[test]
+fn test_moviewspec_02_transform_rotation() {
+    // from the superposition example
+    let rotation = vec![
+        -0.7202161,
+        -0.33009904,
+        -0.61018308,
+        0.36257631,
+        0.57075962,
+        -0.73673053,
+        0.59146191,
+        -0.75184312,
+        -0.29138417,
+    ];
+    let mut state = State::new();
+    let structure = state
+        .download("https://files.wwpdb.org/download/5mjd.cif")
+        .expect("Create a Download node with a URL")
+        .parse(ParseParams {
+            format: ParseFormatT::Mmcif,
+        })
+        .expect("Parseable option")
+        .model_structure(StructureParams::default())
+        .expect("a model structure");
+    assert!(structure
+        .transform(TransformParams {
+            rotation: Some(rotation.clone()),
+            translation: Some((-12.54, 46.79, 94.5)),
+        })
+        .is_some());
+    // scaling is not a rotation
+    let scaled: Vec<f64> = rotation.iter().map(|x| x * 2.0).collect();
+    assert!(structure
+        .transform(TransformParams {
+            rotation: Some(scaled),
+            translation: None,
+        })
+        .is_none());
+}
+
+#[test]
+fn test_moviewspec_03_read_examples() {
+    for example in [
+        "annotations",
+        "basic",
+        "components",
+        "label",
+        "superposition",
+        "symmetry",
+    ] {
+        let path = format!("tests/mol-spec-examples/{}/state.mvsj", example);
+        let state = State::open(&path).unwrap_or_else(|e| panic!("{}: {}", path, e));
+        assert_eq!(state.root.get_kind(), &KindT::Root);
+        state.validate().unwrap();
+
+        // writing the state back out keeps every node and parameter
+        let original: serde_json::Value =
+            serde_json::from_str(&std::fs::read_to_string(&path).unwrap()).unwrap();
+        let written = serde_json::to_value(&state).unwrap();
+        assert_eq!(written["root"], original["root"], "{}", path);
+    }
+}
+
+#[test]
+fn test_moviewspec_03_validation_paths() {
+    let state = json!({
+        "root": {
+            "kind": "root",
+            "children": [{
+                "kind": "download",
+                "params": {"url": "https://files.wwpdb.org/download/1cbs.cif"},
+                "children": [{
+                    "kind": "parse",
+                    "params": {"format": "mmcif", "compression": "gzip"},
+                    "children": [{
+                        "kind": "structure",
+                        "params": {"type": "model"},
+                        "children": [
+                            {"kind": "sphere", "params": {"position": [0, 0, 0], "radius": 1, "color": "red"}},
+                            {"kind": "component", "params": {"selector": "protein"}, "children": [
+                                {"kind": "representation", "params": {}}
+                            ]},
+                            {"kind": "transform", "params": {"rotation": [1, 0, 0]}},
+                            {"kind": "volume"}
+                        ]
+                    }]
+                }]
+            }]
+        },
+        "metadata": {"version": "1", "timestamp": "2024-01-01T00:00:00+00:00"}
+    });
+    let errors = State::from_value(&state).unwrap_err().0;
+    let paths: Vec<&str> = errors.iter().map(|e| e.path.as_str()).collect();
+    let structure = "root.children[0].children[0].children[0]";
+    assert!(paths.contains(&"root.children[0].children[0].params"));
+    assert!(paths.contains(&format!("{}.children[0].kind", structure).as_str()));
+    assert!(paths.contains(&format!("{}.children[1].children[0].params", structure).as_str()));
+    assert!(paths.contains(&format!("{}.children[2].params.rotation", structure).as_str()));
+    assert_eq!(errors.len(), 4);
+
+    // a typo inside a selector must not fall back to selecting everything
+    let mut typo = state.clone();
+    typo["root"]["children"][0]["children"][0]["children"][0]["children"] = json!([
+        {"kind": "component", "params": {"selector": {"label_asymid": "A"}}},
+        {"kind": "component", "params": {"selector": [{"label_asym_id": "A"}, {"auth_seqid": 5}]}}
+    ]);
+    let errors: Vec<_> = State::from_value(&typo)
+        .unwrap_err()
+        .0
+        .into_iter()
+        .filter(|e| e.path.starts_with(structure))
+        .collect();
+    assert_eq!(errors.len(), 2);
+    assert_eq!(
+        errors[0].path,
+        format!("{}.children[0].params.selector", structure)
+    );
+    assert!(errors[0].message.contains("label_asymid"));
+    assert_eq!(
+        errors[1].path,
+        format!("{}.children[1].params.selector[1]", structure)
+    );
+
+    assert!(State::from_mvsj("{ not json").is_err());
+    assert!(State::from_mvsj(r#"{"root": {"kind": "root"}}"#).is_err());
+}
+
+#[test]
+fn test_moviewspec_03_unsupported_kinds() {
+    let state = json!({
+        "root": {
+            "kind": "root",
+            "children": [
+                {"kind": "download", "params": {"url": "https://www.ebi.ac.uk/pdbe/densities/x-ray/1tqn/cell?detail=3"}, "children": [
+                    {"kind": "parse", "params": {"format": "bcif"}, "children": [
+                        {"kind": "volume", "params": {"channel_id": "2FO-FC"}, "children": [
+                            {"kind": "volume_representation", "params": {"type": "isosurface"}}
+                        ]}
+                    ]}
+                ]},
+                {"kind": "primitives", "params": {"opacity": 0.5}, "children": [
+                    {"kind": "primitive", "params": {"kind": "ellipsoid", "center": [0, 0, 0]}},
+                    {"kind": "primitive", "params": {"kind": "arrow", "start": [0.0, 0.0, 0.0], "end": [1.0, 0.0, 0.0]}}
+                ]}
+            ]
+        },
+        "metadata": {"version": "1", "timestamp": "2024-01-01T00:00:00+00:00"}
+    });
+
+    // unsupported kinds are kept as written rather than rejected
+    let parsed = State::from_value(&state).unwrap();
+    let written = serde_json::to_value(&parsed).unwrap();
+    assert_eq!(written["root"], state["root"]);
+
+    let warnings = parsed.warnings();
+    let paths: Vec<&str> = warnings.iter().map(|w| w.path.as_str()).collect();
+    assert_eq!(
+        paths,
+        [
+            "root.children[0].children[0].children[0].kind",
+            "root.children[0].children[0].children[0].children[0].kind",
+            "root.children[1].children[0].params.kind",
+        ]
+    );
+    assert!(warnings[0].message.contains("volume"));
+    assert!(warnings[2].message.contains("ellipsoid"));
+
+    // keys with defaults or renames are not reported just because they are written out
+    let declared = json!({
+        "root": {
+            "kind": "root",
+            "children": [{"kind": "download", "params": {"url": "1cbs.cif"}, "children": [
+                {"kind": "parse", "params": {"format": "mmcif"}, "children": [
+                    {"kind": "structure", "params": {"type": "model", "model_index": 0}, "children": [
+                        {"kind": "component", "params": {"selector": {"label_asym_id": "A", "beg_label_seq_id": 1}}}
+                    ]}
+                ]}
+            ]}]
+        },
+        "metadata": {"version": "1", "timestamp": "2024-01-01T00:00:00+00:00"}
+    });
+    let parsed = State::from_value(&declared).unwrap();
+    assert!(parsed.warnings().is_empty());
+}
+
+#[test]
+fn test_moviewspec_03_read_mvsx() {
+    let index = std::fs::read("tests/mol-spec-examples/annotations/state.mvsj").unwrap();
+    let annotations =
+        std::fs::read("tests/mol-spec-examples/annotations/annotations-1h9t.cif").unwrap();
+
+    let mut zip = zip::ZipWriter::new(Cursor::new(Vec::new()));
+    let options = zip::write::SimpleFileOptions::default();
+    zip.start_file("index.mvsj", options).unwrap();
+    zip.write_all(&index).unwrap();
+    zip.start_file("annotations-1h9t.cif", options).unwrap();
+    zip.write_all(&annotations).unwrap();
+    let archive = zip.finish().unwrap().into_inner();
+
+    let archive = MvsArchive::from_reader(Cursor::new(archive)).unwrap();
+    match &archive.data {
+        MvsData::State(state) => assert_eq!(state.root.get_kind(), &KindT::Root),
+        MvsData::States(_) => panic!("the index holds a single state"),
+    }
+    assert_eq!(
+        archive.get_file("./annotations-1h9t.cif"),
+        Some(annotations.as_slice())
+    );
+    assert!(MvsArchive::from_reader(Cursor::new(b"not a zip".to_vec())).is_err());
+}
+
+#[test]
+fn test_moviewspec_03_write_mvsx() {
+    let mut state = State::new();
+    state
+        .download("./pdb/1cbs.pdb")
+        .and_then(|node| {
+            node.parse(ParseParams {
+                format: ParseFormatT::Pdb,
+            })
+        })
+        .and_then(|node| node.model_structure(StructureParams::default()))
+        .and_then(|node| node.component(ComponentSelector::default()))
+        .and_then(|node| node.representation(RepresentationTypeT::Cartoon))
+        .and_then(|node| {
+            node.color_from_uri(ColorFromUriParams {
+                base: DataFromUriParams {
+                    uri: "annotations.json".to_string(),
+                    format: SchemaFormatT::Json,
+                    category_name: None,
+                    field_name: Some("color".to_string()),
+                    block_header: None,
+                    block_index: None,
+                    schema_: SchemaT::AuthResidue,
+                },
+            })
+        })
+        .unwrap();
+    state.download("https://files.wwpdb.org/download/1cbs.cif");
+
+    let mut archive = MvsArchive::new(state);
+    assert_eq!(
+        archive.relative_uris(),
+        vec!["annotations.json".to_string(), "pdb/1cbs.pdb".to_string()]
+    );
+    archive.add_file("./pdb/1cbs.pdb", b"ATOM".to_vec());
+    // every relative URI has to resolve inside the archive
+    assert!(archive.to_writer(Cursor::new(Vec::new())).is_err());
+    archive.add_file("annotations.json", b"[]".to_vec());
+
+    let mut written = Cursor::new(Vec::new());
+    archive.to_writer(&mut written).unwrap();
+    let read = MvsArchive::from_reader(Cursor::new(written.into_inner())).unwrap();
+    assert_eq!(read.get_file("pdb/1cbs.pdb"), Some(b"ATOM".as_slice()));
+    assert_eq!(read.get_file("./annotations.json"), Some(b"[]".as_slice()));
+    assert_eq!(read.relative_uris(), archive.relative_uris());
+
+    // the example state with its annotation file referenced relative to the state
+    let index = std::fs::read_to_string("tests/mol-spec-examples/annotations/state.mvsj")
+        .unwrap()
+        .replace(
+            "https://molstar.org/mol-view-spec/examples/annotations/",
+            "./",
+        );
+    let state = State::from_mvsj(&index).unwrap();
+    let bundled = MvsArchive::bundle(state, "tests/mol-spec-examples/annotations").unwrap();
+    assert_eq!(
+        bundled.relative_uris(),
+        vec!["annotations-1h9t.cif".to_string()]
+    );
+    assert!(bundled.get_file("./annotations-1h9t.cif").is_some());
+    let state = State::from_mvsj(&index).unwrap();
+    assert!(MvsArchive::bundle(state, "tests/mol-spec-examples").is_err());
+}
+
+#[test]
+fn test_moviewspec_04_annotations_from_atom_collection() {
+    let (prot_file, _temp) = TestFile::protein_01().create_temp().unwrap();
+    let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+    let ac = AtomCollection::from(&pdb);
+    let residues: Vec<_> = ac.iter_residues_aminoacid().collect();
+
+    // two whole consecutive residues and one atom of a third
+    let (first, second, partial) = (&residues[0], &residues[1], &residues[3]);
+    assert_eq!(second.res_id, first.res_id + 1);
+    let mut indices: Vec<usize> = (first.start_idx..second.end_idx).collect();
+    indices.push(partial.start_idx);
+    let expressions = selection_expressions(&ac, &Selection::new(indices));
+    assert_eq!(expressions.len(), 2);
+    assert_eq!(expressions[0].auth_asym_id.as_ref(), Some(&first.chain_id));
+    assert_eq!(expressions[0].beg_auth_seq_id, Some(first.res_id));
+    assert_eq!(expressions[0].end_auth_seq_id, Some(second.res_id));
+    assert_eq!(expressions[1].auth_seq_id, Some(partial.res_id));
+    assert_eq!(
+        expressions[1].auth_atom_id.as_ref(),
+        Some(ac.get_atom_name(partial.start_idx))
+    );
+
+    // one value per amino acid, colored low to high
+    let values: Vec<f64> = (0..residues.len()).map(|i| i as f64).collect();
+    let annotations =
+        ResidueAnnotations::from_residue_values(&ac, &values, &Palette::viridis()).unwrap();
+    let rows = annotations.get_rows();
+    assert_eq!(rows.len(), residues.len());
+    assert_eq!(rows[0].color, "#440154");
+    assert_eq!(rows[rows.len() - 1].color, "#fde725");
+    assert!(
+        ResidueAnnotations::from_residue_values(&ac, &values[1..], &Palette::viridis()).is_err()
+    );
+    assert_eq!(Palette::blue_white_red().color(0.0, -1.0, 1.0), "#f7f7f7");
+    assert!(Palette::from_hex(&["#00ff00", "red"]).is_err());
+
+    let json: Value = serde_json::from_str(&annotations.to_json().unwrap()).unwrap();
+    assert_eq!(json.as_array().unwrap().len(), residues.len());
+    assert_eq!(json[0]["auth_asym_id"], json!(first.chain_id));
+    assert_eq!(json[0]["auth_seq_id"], json!(first.res_id));
+    let cif = annotations.to_cif("ferritin_design", Some("design"));
+    assert!(cif.starts_with("data_design\n#\nloop_\n_ferritin_design.auth_asym_id\n"));
+    assert!(cif.contains(&format!("'{}'", rows[0].tooltip)));
+
+    // a ready-to-open state
+    let mut state = State::new();
+    let structure = state
+        .download("design.pdb")
+        .and_then(|node| {
+            node.parse(ParseParams {
+                format: ParseFormatT::Pdb,
+            })
+        })
+        .and_then(|node| node.model_structure(StructureParams::default()))
+        .unwrap();
+    annotate_from_uri(structure, "design.json", RepresentationTypeT::Cartoon).unwrap();
+    state.validate().unwrap();
+}
+
+#[test]
+fn test_moviewspec_05_snapshots() {
+    let mut overview = State::new();
+    overview.metadata.title = Some("Overview".to_string());
+    overview.download("https://files.wwpdb.org/download/1cbs.cif");
+    let mut closeup = State::new();
+    closeup.download("https://files.wwpdb.org/download/1cbs.cif");
+    closeup.camera(CameraParams {
+        target: (17.0, 21.0, 27.0),
+        position: (41.0, 34.0, 69.0),
+        ..Default::default()
+    });
+
+    let mut story = States::new();
+    story.metadata.title = Some("A guided tour of 1cbs".to_string());
+    story.add_snapshot(overview.get_snapshot(SnapshotMetadata::default()));
+    let snapshot = story.add_snapshot(closeup.get_snapshot(SnapshotMetadata {
+        title: Some("Retinoic acid".to_string()),
+        description: Some("The ligand sits in a **beta barrel**".to_string()),
+        description_format: Some(DescriptionFormatT::Markdown),
+        transition_duration_ms: Some(1500),
+        ..Default::default()
+    }));
+    snapshot.metadata.key = Some("ligand".to_string());
+    story.validate().unwrap();
+
+    let json = serde_json::to_value(&story).unwrap();
+    assert_eq!(json["kind"], json!("multiple"));
+    assert_eq!(json["snapshots"][0]["metadata"]["title"], json!("Overview"));
+    assert_eq!(
+        json["snapshots"][0]["metadata"]["linger_duration_ms"],
+        json!(1000)
+    );
+    let closeup = &json["snapshots"][1];
+    assert_eq!(closeup["metadata"]["description_format"], json!("markdown"));
+    assert_eq!(closeup["metadata"]["transition_duration_ms"], json!(1500));
+    assert_eq!(closeup["metadata"]["key"], json!("ligand"));
+    assert_eq!(closeup["root"]["children"][1]["kind"], json!("camera"));
+
+    let read = States::from_mvsj(&json.to_string()).unwrap();
+    assert_eq!(read.snapshots.len(), 2);
+    assert_eq!(
+        read.snapshots[1].metadata.transition_duration_ms,
+        Some(1500)
+    );
+    assert!(State::from_mvsj(&json.to_string()).is_err());
+
+    // snapshots round-trip through an MVSX archive, with the files of every snapshot
+    let mut tour = story.clone();
+    tour.snapshots[1].root.download("./1cbs.pdb");
+    let mut archive = MvsArchive::new(tour);
+    assert_eq!(archive.relative_uris(), vec!["1cbs.pdb".to_string()]);
+    archive.add_file("1cbs.pdb", b"ATOM".to_vec());
+    let mut written = Cursor::new(Vec::new());
+    archive.to_writer(&mut written).unwrap();
+    let read = MvsArchive::from_reader(Cursor::new(written.into_inner())).unwrap();
+    let MvsData::States(read_story) = &read.data else {
+        panic!("the index holds several snapshots");
+    };
+    assert_eq!(read_story.snapshots.len(), 2);
+    assert_eq!(
+        read_story.snapshots[1].metadata.key,
+        Some("ligand".to_string())
+    );
+    assert_eq!(read.get_file("./1cbs.pdb"), Some(b"ATOM".as_slice()));
+    assert!(matches!(
+        MvsData::from_value(&json).unwrap(),
+        MvsData::States(_)
+    ));
+
+    let mut invalid = json.clone();
+    invalid["snapshots"][1]["root"]["kind"] = json!("download");
+    invalid["snapshots"][0]["metadata"]["linger_duration_ms"] = json!("long");
+    let errors = States::from_value(&invalid).unwrap_err().0;
+    let paths: Vec<&str> = errors.iter().map(|error| error.path.as_str()).collect();
+    assert!(paths.contains(&"snapshots[0].metadata"));
+    assert!(paths.contains(&"snapshots[1].root.kind"));
+}
+
+fn sphere() -> SphereParams {
+    SphereParams {
+        position: (0.0, 0.0, 0.0),
+        radius: 1.0,
+        color: ColorT::Named(ColorNamesT::Blue),
+        label: None,
+        tooltip: Some("origin".to_string()),
+    }
+}
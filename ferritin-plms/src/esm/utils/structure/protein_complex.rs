@@ -1,6 +1,7 @@
 use brotli::dec::BrotliDecoder;
 use brotli::enc::encode_stream;
-use candle::{Device, Result, Tensor, D};
+use candle::{DType, Device, Result, Tensor, D};
+use ferritin_core::{AlignmentMethod, ResidueTrace, Superposition, LDDT};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fs;
@@ -10,6 +11,13 @@ use std::path::{Path, PathBuf};
 const SINGLE_LETTER_CHAIN_IDS: &str =
     "ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789";
 
+/// Atom names in atom37 order
+const ATOM37_NAMES: [&str; 37] = [
+    "N", "CA", "C", "CB", "O", "CG", "CG1", "CG2", "OG", "OG1", "SG", "CD", "CD1", "CD2", "ND1",
+    "ND2", "OD1", "OD2", "SD", "CE", "CE1", "CE2", "CE3", "NE", "NE1", "NE2", "OE1", "OE2", "CH2",
+    "NH1", "NH2", "OH", "CZ", "CZ2", "CZ3", "NZ", "OXT",
+];
+
 #[derive(Debug, Clone)]
 struct ProteinComplexMetadata {
     entity_lookup: HashMap<i32, i32>,
@@ -19,25 +27,28 @@ struct ProteinComplexMetadata {
 
 #[derive(Debug, Clone)]
 pub struct DockQSingleScore {
-    native_chains: (String, String),
-    dock_q: f32,
-    interface_rms: f32,
-    ligand_rms: f32,
-    fnat: f32,
-    fnonnat: f32,
-    clashes: f32,
-    f1: f32,
-    dock_q_f1: f32,
+    pub native_chains: (String, String),
+    pub dock_q: f32,
+    pub interface_rms: f32,
+    pub ligand_rms: f32,
+    pub fnat: f32,
+    pub fnonnat: f32,
+    pub clashes: f32,
+    pub f1: f32,
+    /// DockQ with F1 in place of fnat
+    pub dock_q_f1: f32,
 }
 
 #[derive(Debug)]
 pub struct DockQResult {
-    total_dockq: f32,
-    native_interfaces: i32,
-    chain_mapping: HashMap<String, String>,
-    interfaces: HashMap<(String, String), DockQSingleScore>,
-    aligned: ProteinComplex,
-    aligned_rmsd: f32,
+    pub total_dockq: f32,
+    pub native_interfaces: i32,
+    /// Native chain id -> model chain id
+    pub chain_mapping: HashMap<String, String>,
+    pub interfaces: HashMap<(String, String), DockQSingleScore>,
+    /// The model superposed onto the native
+    pub aligned: ProteinComplex,
+    pub aligned_rmsd: f32,
 }
 
 struct AtomIndexer<'a> {
@@ -107,30 +118,217 @@ impl ProteinComplex {
         unimplemented!()
     }
 
-    fn lddt_ca(
-        &self,
-        target: &Self,
-        mobile_inds: Option<&[i32]>,
-        target_inds: Option<&[i32]>,
-        compute_chain_assignment: bool,
-    ) -> Result<Tensor> {
-        // Implementation
-        unimplemented!()
+    /// CA lDDT of this complex against `target`.
+    pub fn lddt_ca(&self, target: &Self, method: AlignmentMethod) -> Result<LDDT> {
+        Ok(ferritin_core::lddt_ca(
+            &self.residue_trace()?,
+            &target.residue_trace()?,
+            method,
+        ))
     }
 
-    fn gdt_ts(
-        &self,
-        target: &Self,
-        mobile_inds: Option<&[i32]>,
-        target_inds: Option<&[i32]>,
-        compute_chain_assignment: bool,
-    ) -> Result<Tensor> {
-        // Implementation
-        unimplemented!()
+    /// GDT-TS of this complex against `target`.
+    pub fn gdt_ts(&self, target: &Self, method: AlignmentMethod) -> Result<f32> {
+        let gdt = ferritin_core::gdt(&self.residue_trace()?, &target.residue_trace()?, method);
+        Ok(gdt.gdt_ts)
     }
 
-    fn dockq(&self, native: &Self) -> Result<DockQResult> {
-        // Implementation
-        unimplemented!()
+    /// DockQ of this complex against `native`, searching for the best chain mapping.
+    pub fn dockq(&self, native: &Self) -> Result<DockQResult> {
+        let result = ferritin_core::dockq(&self.residue_trace()?, &native.residue_trace()?);
+        let interfaces = result
+            .interfaces
+            .iter()
+            .map(|interface| {
+                let score = DockQSingleScore {
+                    native_chains: interface.native_chains.clone(),
+                    dock_q: interface.dockq,
+                    interface_rms: interface.irmsd,
+                    ligand_rms: interface.lrmsd,
+                    fnat: interface.fnat,
+                    fnonnat: interface.fnonnat,
+                    clashes: interface.clashes as f32,
+                    f1: interface.f1,
+                    dock_q_f1: interface.dockq + (interface.f1 - interface.fnat) / 3.0,
+                };
+                (interface.native_chains.clone(), score)
+            })
+            .collect();
+        Ok(DockQResult {
+            total_dockq: result.total_dockq,
+            native_interfaces: result.native_interfaces as i32,
+            chain_mapping: result.chain_mapping,
+            interfaces,
+            aligned: self.transformed(&result.superposition)?,
+            aligned_rmsd: result.aligned_rmsd,
+        })
+    }
+
+    /// Resolved atoms of each residue, skipping the chain break tokens in `sequence`.
+    fn residue_trace(&self) -> Result<Vec<ResidueTrace>> {
+        let positions = self
+            .atom37_positions
+            .to_dtype(DType::F32)?
+            .to_vec3::<f32>()?;
+        let mask = self.atom37_mask.to_dtype(DType::F32)?.to_vec2::<f32>()?;
+        let chain_ids = self.chain_id.to_dtype(DType::I64)?.to_vec1::<i64>()?;
+        let residue_index = self.residue_index.to_dtype(DType::I64)?.to_vec1::<i64>()?;
+
+        let mut residues = Vec::new();
+        for (i, aa) in self.sequence.chars().enumerate() {
+            if aa == '|' {
+                continue;
+            }
+            let atoms: Vec<(String, [f32; 3])> = ATOM37_NAMES
+                .iter()
+                .zip(positions[i].iter().zip(&mask[i]))
+                .filter(|(_, (coord, resolved))| **resolved > 0.0 && coord[0].is_finite())
+                .map(|(name, (coord, _))| (name.to_string(), [coord[0], coord[1], coord[2]]))
+                .collect();
+            let chain = chain_ids[i] as i32;
+            let residue = ResidueTrace {
+                chain_id: self
+                    .metadata
+                    .chain_lookup
+                    .get(&chain)
+                    .cloned()
+                    .unwrap_or_else(|| chain.to_string()),
+                res_id: residue_index[i] as i32,
+                res_name: aa.to_string(),
+                atoms,
+            };
+            if residue.ca().is_some() {
+                residues.push(residue);
+            }
+        }
+        Ok(residues)
+    }
+
+    /// Copy of the complex with `superposition` applied to every atom.
+    fn transformed(&self, superposition: &Superposition) -> Result<Self> {
+        let device = self.atom37_positions.device();
+        let rotation = Tensor::new(&superposition.rotation, device)?;
+        let translation = Tensor::new(&superposition.translation, device)?;
+        let atom37_positions = self
+            .atom37_positions
+            .to_dtype(DType::F32)?
+            .broadcast_matmul(&rotation.t()?.contiguous()?)?
+            .broadcast_add(&translation)?;
+        Ok(Self {
+            atom37_positions,
+            ..self.clone()
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ligandmpnn::utilities::aa3to1;
+    use ferritin_core::AtomCollection;
+    use ferritin_test_data::TestFile;
+
+    /// FKBP12 / FRB heterodimer (1FAP) as a two-chain complex
+    fn two_chain_complex() -> ProteinComplex {
+        let (prot_file, _temp) = TestFile::protein_04().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(prot_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let device = Device::Cpu;
+
+        let residues: Vec<_> = ac.iter_residues_aminoacid().collect();
+        let n = residues.len();
+        let mut chains: Vec<String> = Vec::new();
+        let (mut sequence, mut chain_id, mut residue_index) = (String::new(), vec![], vec![]);
+        let (mut positions, mut mask) = (vec![0f32; n * 37 * 3], vec![0f32; n * 37]);
+        for (i, res) in residues.iter().enumerate() {
+            if !chains.contains(&res.chain_id) {
+                chains.push(res.chain_id.clone());
+            }
+            sequence.push(aa3to1(&res.res_name));
+            chain_id.push((chains.len() - 1) as i64);
+            residue_index.push(res.res_id as i64);
+            for (j, name) in ATOM37_NAMES.iter().enumerate() {
+                if let Some(atom) = res.find_atom_by_name(name) {
+                    positions[(i * 37 + j) * 3..(i * 37 + j + 1) * 3].copy_from_slice(atom.coords);
+                    mask[i * 37 + j] = 1.0;
+                }
+            }
+        }
+        let chain_id = Tensor::new(chain_id, &device).unwrap();
+        ProteinComplex {
+            id: "1fap".to_string(),
+            sequence,
+            entity_id: chain_id.clone(),
+            sym_id: Tensor::zeros(n, DType::I64, &device).unwrap(),
+            chain_id,
+            residue_index: Tensor::new(residue_index, &device).unwrap(),
+            insertion_code: Tensor::zeros(n, DType::I64, &device).unwrap(),
+            atom37_positions: Tensor::from_vec(positions, (n, 37, 3), &device).unwrap(),
+            atom37_mask: Tensor::from_vec(mask, (n, 37), &device).unwrap(),
+            confidence: Tensor::ones(n, DType::F32, &device).unwrap(),
+            metadata: ProteinComplexMetadata {
+                entity_lookup: HashMap::new(),
+                chain_lookup: chains
+                    .into_iter()
+                    .enumerate()
+                    .map(|(i, chain)| (i as i32, chain))
+                    .collect(),
+                chain_boundaries: Vec::new(),
+            },
+        }
+    }
+
+    #[test]
+    fn test_complex_scores() {
+        let native = two_chain_complex();
+        // shift the second chain away from the first
+        let shift = Tensor::new(&[0f32, 0.0, 12.0], &Device::Cpu).unwrap();
+        let second_chain = native
+            .chain_id
+            .eq(1i64)
+            .unwrap()
+            .to_dtype(DType::F32)
+            .unwrap()
+            .reshape(((), 1, 1))
+            .unwrap();
+        let docked = ProteinComplex {
+            atom37_positions: native
+                .atom37_positions
+                .broadcast_add(&second_chain.broadcast_mul(&shift).unwrap())
+                .unwrap(),
+            ..native.clone()
+        };
+
+        let lddt = native
+            .lddt_ca(&native, AlignmentMethod::ResidueNumber)
+            .unwrap();
+        assert!((lddt.global - 1.0).abs() < 1e-6);
+        let gdt_ts = native
+            .gdt_ts(&native, AlignmentMethod::ResidueNumber)
+            .unwrap();
+        assert!((gdt_ts - 1.0).abs() < 1e-6);
+        let lddt = docked
+            .lddt_ca(&native, AlignmentMethod::ResidueNumber)
+            .unwrap();
+        assert!(lddt.global < 1.0);
+
+        let result = native.dockq(&native).unwrap();
+        assert_eq!(result.native_interfaces, 1);
+        assert_eq!(result.chain_mapping["A"], "A");
+        assert_eq!(result.chain_mapping["B"], "B");
+        let interface = &result.interfaces[&("A".to_string(), "B".to_string())];
+        assert!((interface.dock_q - 1.0).abs() < 1e-3);
+        assert!((interface.dock_q_f1 - 1.0).abs() < 1e-3);
+        assert!(result.aligned_rmsd < 1e-2);
+
+        // pulling the chains apart loses every native contact
+        let result = docked.dockq(&native).unwrap();
+        let interface = &result.interfaces[&("A".to_string(), "B".to_string())];
+        assert_eq!(interface.fnat, 0.0);
+        assert!(interface.dock_q < 0.5);
+        assert_eq!(
+            result.aligned.atom37_positions.dims(),
+            &[docked.sequence.len(), 37, 3]
+        );
     }
 }
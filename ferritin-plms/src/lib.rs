@@ -27,4 +27,5 @@ pub mod amplify;
 pub mod esm;
 pub mod esm2;
 pub mod ligandmpnn;
+pub mod plots;
 pub mod types;
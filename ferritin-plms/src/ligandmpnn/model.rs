@@ -90,6 +90,14 @@ impl ScoreOutput {
     pub fn get_log_probs(&self) -> &Tensor {
         &self.log_probs
     }
+    /// Probabilities of one batch entry as [seqlength][21], in `int_to_aa1` order
+    pub fn get_probabilities(&self, batch: usize) -> Result<Vec<Vec<f32>>> {
+        self.log_probs
+            .get(batch)?
+            .exp()?
+            .to_dtype(DType::F32)?
+            .to_vec2::<f32>()
+    }
     pub fn save_as_safetensors(&self, filename: String) -> Result<()> {
         let mut tensors = HashMap::new();
         tensors.insert("S".to_string(), self.s.clone());
@@ -154,10 +154,7 @@ impl LMPNNFeatures for AtomCollection {
         let (coords, elements): (Vec<[f32; 3]>, Vec<Element>) = self
             .iter_residues_all()
             // keep only the non-protein, non-water residues
-            .filter(|residue| {
-                let res_name = &residue.res_name;
-                !residue.is_amino_acid() && res_name != "HOH" && res_name != "WAT"
-            })
+            .filter(|residue| residue.is_ligand_context())
             // keep only the heavy atoms
             .flat_map(|residue| {
                 residue
@@ -752,6 +752,21 @@ mod tests {
         assert_eq!(elements[3], "O");
     }
 
+    #[test]
+    fn test_ligand_tensor_nucleic_acids() {
+        // 1BC8: the DNA bound to SAP-1 is context for LigandMPNN, as upstream
+        let device = Device::Cpu;
+        let (pdb_file, _temp) = TestFile::protein_03().create_temp().unwrap();
+        let (pdb, _) = pdbtbx::open(pdb_file).unwrap();
+        let ac = AtomCollection::from(&pdb);
+        let (ligand_coords, ligand_elements, _) =
+            ac.to_numeric_ligand_atoms(&device).expect("REASON");
+        let (n_atoms, _) = ligand_coords.dims2().unwrap();
+        assert!(n_atoms > 0);
+        let elements = ligand_elements.to_vec1::<f32>().unwrap();
+        assert!(elements.contains(&15.0), "phosphorus from the DNA backbone");
+    }
+
     #[test]
     fn test_backbone_tensor() {
         let device = Device::Cpu;
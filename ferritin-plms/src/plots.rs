@@ -0,0 +1,577 @@
+//! Plots
+//!
+//! Figures for reviewing model outputs: sequence logos and position × amino-acid heatmaps
+//! from per-position probabilities (LigandMPNN scores, ESM/AMPLIFY pseudo-probabilities),
+//! and contact-map images from [`ContactMap`] lists.
+//!
+//! Logos and heatmaps are written as labelled SVG documents. Heatmaps and contact maps can
+//! also be written as PNG images, one block of pixels per cell and without labels.
+//!
+//! ```no_run
+//! use ferritin_plms::plots::{sequence_logo, PlotConfig, ProbabilityMatrix};
+//! use ferritin_plms::types::PseudoProbability;
+//!
+//! # fn example(probs: Vec<PseudoProbability>) -> anyhow::Result<()> {
+//! let matrix = ProbabilityMatrix::from_pseudo_probabilities(&probs);
+//! svg::save("logo.svg", &sequence_logo(&matrix, &PlotConfig::default()))?;
+//! # Ok(())
+//! # }
+//! ```
+use crate::ligandmpnn::model::ScoreOutput;
+use crate::ligandmpnn::utilities::int_to_aa1;
+use crate::types::{ContactMap, PseudoProbability};
+use anyhow::{bail, Result};
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufWriter;
+use std::path::Path;
+use svg::node::element::{Element, Line, Rectangle};
+use svg::{Document, Node};
+
+/// The twenty canonical amino acids
+pub const AMINO_ACIDS: [char; 20] = [
+    'A', 'C', 'D', 'E', 'F', 'G', 'H', 'I', 'K', 'L', 'M', 'N', 'P', 'Q', 'R', 'S', 'T', 'V', 'W',
+    'Y',
+];
+
+// fraction of an em covered by a capital letter, used to scale logo glyphs
+const CAP_HEIGHT: f64 = 0.72;
+const GLYPH_WIDTH: f64 = 0.68;
+
+/// Probabilities per sequence position over an alphabet.
+#[derive(Clone, Debug)]
+pub struct ProbabilityMatrix {
+    alphabet: Vec<char>,
+    /// [position][letter]
+    probs: Vec<Vec<f32>>,
+}
+
+impl ProbabilityMatrix {
+    /// Every row must hold one probability per letter of the alphabet.
+    pub fn new(alphabet: Vec<char>, probs: Vec<Vec<f32>>) -> Result<Self> {
+        if let Some(i) = probs.iter().position(|row| row.len() != alphabet.len()) {
+            bail!(
+                "Position {} has {} probabilities, expected {}",
+                i,
+                probs[i].len(),
+                alphabet.len()
+            );
+        }
+        Ok(ProbabilityMatrix { alphabet, probs })
+    }
+    /// Collect ESM/AMPLIFY pseudo-probabilities. Tokens other than the canonical amino
+    /// acids are dropped and every position is renormalized over the rest.
+    pub fn from_pseudo_probabilities(probs: &[PseudoProbability]) -> Self {
+        let mut positions: BTreeMap<usize, Vec<f32>> = BTreeMap::new();
+        for prob in probs {
+            let Some(letter) = AMINO_ACIDS.iter().position(|&aa| aa == prob.amino_acid) else {
+                continue;
+            };
+            positions
+                .entry(prob.position)
+                .or_insert_with(|| vec![0.0; AMINO_ACIDS.len()])[letter] += prob.pseudo_prob;
+        }
+        let probs = positions.into_values().map(normalize).collect();
+        ProbabilityMatrix {
+            alphabet: AMINO_ACIDS.to_vec(),
+            probs,
+        }
+    }
+    /// Probabilities of one designed sequence from a LigandMPNN score, including the
+    /// unknown residue `X`.
+    pub fn from_score_output(score: &ScoreOutput, batch: usize) -> Result<Self> {
+        let probs = score.get_probabilities(batch)?;
+        let width = probs.first().map_or(0, |row| row.len());
+        let alphabet = (0..width as u32).map(int_to_aa1).collect();
+        ProbabilityMatrix::new(alphabet, probs)
+    }
+    pub fn len(&self) -> usize {
+        self.probs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.probs.is_empty()
+    }
+    pub fn get_alphabet(&self) -> &[char] {
+        &self.alphabet
+    }
+    pub fn get_probs(&self) -> &[Vec<f32>] {
+        &self.probs
+    }
+    /// Most probable letter at every position
+    pub fn consensus(&self) -> String {
+        self.probs
+            .iter()
+            .map(|row| {
+                row.iter()
+                    .enumerate()
+                    .max_by(|a, b| a.1.total_cmp(b.1))
+                    .map_or('-', |(i, _)| self.alphabet[i])
+            })
+            .collect()
+    }
+    /// Information content in bits: log2(alphabet size) minus the position's entropy.
+    pub fn information_content(&self, position: usize) -> f32 {
+        let row = normalize(self.probs[position].clone());
+        let entropy: f32 = row
+            .iter()
+            .filter(|&&p| p > 0.0)
+            .map(|&p| -p * p.log2())
+            .sum();
+        ((self.alphabet.len() as f32).log2() - entropy).max(0.0)
+    }
+}
+
+/// Sizes and colors shared by the plots.
+#[derive(Clone, Debug)]
+pub struct PlotConfig {
+    /// Width of one sequence position, and height of one heatmap row
+    pub cell_size: f64,
+    /// Height of a logo stack at maximal information content
+    pub logo_height: f64,
+    pub font_size: f64,
+    /// Label every n-th position
+    pub tick_every: usize,
+    /// Pixels per cell in PNG output
+    pub pixels_per_cell: u32,
+}
+
+impl Default for PlotConfig {
+    fn default() -> Self {
+        Self {
+            cell_size: 12.0,
+            logo_height: 120.0,
+            font_size: 8.0,
+            tick_every: 10,
+            pixels_per_cell: 4,
+        }
+    }
+}
+
+/// Sequence logo: one stack per position, letters scaled by their share of the
+/// position's information content with the most probable letter on top.
+pub fn sequence_logo(matrix: &ProbabilityMatrix, config: &PlotConfig) -> Document {
+    let max_bits = (matrix.alphabet.len() as f64).log2();
+    let margin = config.font_size * 4.0;
+    let width = matrix.len() as f64 * config.cell_size;
+    let height = config.logo_height;
+    let mut document = plot_document(margin, width, height);
+
+    for (position, row) in matrix.probs.iter().enumerate() {
+        let row = normalize(row.clone());
+        let bits = matrix.information_content(position) as f64;
+        let mut letters: Vec<(char, f64)> = matrix
+            .alphabet
+            .iter()
+            .zip(&row)
+            .map(|(&aa, &p)| (aa, p as f64 * bits / max_bits * height))
+            .filter(|(_, h)| *h > 0.01)
+            .collect();
+        letters.sort_by(|a, b| a.1.total_cmp(&b.1));
+        let x = position as f64 * config.cell_size;
+        let mut bottom = height;
+        for (aa, letter_height) in letters {
+            document = document.add(logo_glyph(
+                aa,
+                x + config.cell_size / 2.0,
+                bottom,
+                config.cell_size,
+                letter_height,
+            ));
+            bottom -= letter_height;
+        }
+    }
+
+    // bits axis
+    document = document.add(axis_line(0.0, 0.0, 0.0, height));
+    for bit in 0..=max_bits.floor() as usize {
+        let y = height - bit as f64 / max_bits * height;
+        document = document.add(axis_line(-3.0, y, 0.0, y)).add(label(
+            -5.0,
+            y + config.font_size / 3.0,
+            &bit.to_string(),
+            config,
+            "end",
+        ));
+    }
+    document = document.add(label(
+        -margin / 2.0 - config.font_size,
+        height / 2.0,
+        "bits",
+        config,
+        "middle",
+    ));
+    position_ticks(document, matrix.len(), height, config)
+}
+
+/// Heatmap of probabilities with positions along x and letters along y.
+pub fn heatmap(matrix: &ProbabilityMatrix, config: &PlotConfig) -> Document {
+    let margin = config.font_size * 4.0;
+    let width = matrix.len() as f64 * config.cell_size;
+    let height = matrix.alphabet.len() as f64 * config.cell_size;
+    let mut document = plot_document(margin, width, height);
+
+    for (position, row) in matrix.probs.iter().enumerate() {
+        for (letter, &p) in row.iter().enumerate() {
+            document = document.add(cell(
+                position as f64 * config.cell_size,
+                letter as f64 * config.cell_size,
+                config.cell_size,
+                p as f64,
+            ));
+        }
+    }
+    for (letter, aa) in matrix.alphabet.iter().enumerate() {
+        let y = (letter as f64 + 0.5) * config.cell_size + config.font_size / 3.0;
+        document = document.add(label(-3.0, y, &aa.to_string(), config, "end"));
+    }
+    position_ticks(document, matrix.len(), height, config)
+}
+
+/// Write the heatmap as a PNG image, positions along x and letters along y.
+pub fn heatmap_png<P: AsRef<Path>>(
+    matrix: &ProbabilityMatrix,
+    path: P,
+    config: &PlotConfig,
+) -> Result<()> {
+    // transpose so that rows of the image are letters
+    let values: Vec<Vec<f32>> = (0..matrix.alphabet.len())
+        .map(|letter| matrix.probs.iter().map(|row| row[letter]).collect())
+        .collect();
+    write_png(&values, 1.0, path, config.pixels_per_cell)
+}
+
+/// Symmetric matrix of contact estimates indexed by position. Several estimates for one
+/// pair keep the largest; `layer` restricts the estimates to one attention layer.
+pub fn contact_matrix(contacts: &[ContactMap], layer: Option<usize>) -> Vec<Vec<f32>> {
+    let contacts: Vec<&ContactMap> = contacts
+        .iter()
+        .filter(|contact| layer.is_none() || layer == Some(contact.layer))
+        .collect();
+    let size = contacts
+        .iter()
+        .map(|contact| contact.position_1.max(contact.position_2) + 1)
+        .max()
+        .unwrap_or(0);
+    let mut matrix = vec![vec![0.0f32; size]; size];
+    for contact in contacts {
+        let (i, j) = (contact.position_1, contact.position_2);
+        matrix[i][j] = matrix[i][j].max(contact.contact_estimate);
+        matrix[j][i] = matrix[i][j];
+    }
+    matrix
+}
+
+/// Contact map with both axes running along the sequence; colors are scaled to the
+/// strongest estimate.
+pub fn contact_map(contacts: &[ContactMap], layer: Option<usize>, config: &PlotConfig) -> Document {
+    let matrix = contact_matrix(contacts, layer);
+    let scale = max_value(&matrix);
+    let margin = config.font_size * 4.0;
+    let size = matrix.len() as f64 * config.cell_size;
+    // one background cell for the empty pairs, then only the estimated contacts
+    let mut document = plot_document(margin, size, size).add(cell(0.0, 0.0, size, 0.0));
+    for (i, row) in matrix.iter().enumerate() {
+        for (j, &value) in row.iter().enumerate().filter(|&(_, &value)| value > 0.0) {
+            document = document.add(cell(
+                j as f64 * config.cell_size,
+                i as f64 * config.cell_size,
+                config.cell_size,
+                (value / scale) as f64,
+            ));
+        }
+    }
+    for position in (0..matrix.len()).step_by(config.tick_every.max(1)) {
+        let y = (position as f64 + 0.5) * config.cell_size + config.font_size / 3.0;
+        document = document.add(label(-3.0, y, &position.to_string(), config, "end"));
+    }
+    position_ticks(document, matrix.len(), size, config)
+}
+
+/// Write the contact map as a PNG image scaled to the strongest estimate.
+pub fn contact_map_png<P: AsRef<Path>>(
+    contacts: &[ContactMap],
+    layer: Option<usize>,
+    path: P,
+    config: &PlotConfig,
+) -> Result<()> {
+    let matrix = contact_matrix(contacts, layer);
+    write_png(&matrix, max_value(&matrix), path, config.pixels_per_cell)
+}
+
+// Helper Fns ---------------------------------------------------------------
+
+fn normalize(row: Vec<f32>) -> Vec<f32> {
+    let total: f32 = row.iter().sum();
+    if total > 0.0 {
+        row.into_iter().map(|p| p / total).collect()
+    } else {
+        row
+    }
+}
+
+fn max_value(matrix: &[Vec<f32>]) -> f32 {
+    let max = matrix.iter().flatten().copied().fold(0.0f32, f32::max);
+    if max > 0.0 {
+        max
+    } else {
+        1.0
+    }
+}
+
+/// Viridis colormap for a value in [0, 1]
+fn colormap(value: f64) -> [u8; 3] {
+    const STOPS: [[f64; 3]; 5] = [
+        [68.0, 1.0, 84.0],
+        [59.0, 82.0, 139.0],
+        [33.0, 145.0, 140.0],
+        [94.0, 201.0, 98.0],
+        [253.0, 231.0, 37.0],
+    ];
+    let scaled = value.clamp(0.0, 1.0) * (STOPS.len() - 1) as f64;
+    let index = (scaled.floor() as usize).min(STOPS.len() - 2);
+    let t = scaled - index as f64;
+    let (a, b) = (STOPS[index], STOPS[index + 1]);
+    std::array::from_fn(|channel| (a[channel] + (b[channel] - a[channel]) * t).round() as u8)
+}
+
+/// Chemistry color scheme used by sequence logos
+fn residue_color(aa: char) -> &'static str {
+    match aa {
+        'G' | 'S' | 'T' | 'Y' | 'C' => "#109648",
+        'N' | 'Q' => "#5e239d",
+        'K' | 'R' | 'H' => "#255c99",
+        'D' | 'E' => "#d62839",
+        _ => "#221e22",
+    }
+}
+
+/// Document with the plot area at the origin and room for labels around it.
+fn plot_document(margin: f64, width: f64, height: f64) -> Document {
+    Document::new()
+        .set(
+            "viewBox",
+            (
+                -margin,
+                -margin / 2.0,
+                width + 1.5 * margin,
+                height + 1.5 * margin,
+            ),
+        )
+        .set("width", width + 1.5 * margin)
+        .set("height", height + 1.5 * margin)
+}
+
+fn cell(x: f64, y: f64, size: f64, value: f64) -> Rectangle {
+    let [r, g, b] = colormap(value);
+    Rectangle::new()
+        .set("x", x)
+        .set("y", y)
+        .set("width", size)
+        .set("height", size)
+        .set("fill", format!("rgb({},{},{})", r, g, b))
+}
+
+fn axis_line(x1: f64, y1: f64, x2: f64, y2: f64) -> Line {
+    Line::new()
+        .set("x1", x1)
+        .set("y1", y1)
+        .set("x2", x2)
+        .set("y2", y2)
+        .set("stroke", "black")
+        .set("stroke-width", 0.5)
+}
+
+fn label(x: f64, y: f64, content: &str, config: &PlotConfig, anchor: &str) -> Element {
+    let mut text = Element::new("text");
+    text.assign("x", x);
+    text.assign("y", y);
+    text.assign("font-size", config.font_size);
+    text.assign("font-family", "sans-serif");
+    text.assign("text-anchor", anchor);
+    text.append(svg::node::Text::new(content));
+    text
+}
+
+/// A letter stretched to fill `width` × `height` with its baseline at `bottom`
+fn logo_glyph(aa: char, center: f64, bottom: f64, width: f64, height: f64) -> Element {
+    let mut text = Element::new("text");
+    text.assign(
+        "transform",
+        format!(
+            "translate({},{}) scale({},{})",
+            center,
+            bottom,
+            width / GLYPH_WIDTH,
+            height / CAP_HEIGHT
+        ),
+    );
+    text.assign("font-size", 1);
+    text.assign("font-family", "sans-serif");
+    text.assign("font-weight", "bold");
+    text.assign("text-anchor", "middle");
+    text.assign("fill", residue_color(aa));
+    text.append(svg::node::Text::new(aa.to_string()));
+    text
+}
+
+/// Position axis below the plot area, labelled every `tick_every` positions.
+fn position_ticks(
+    mut document: Document,
+    positions: usize,
+    height: f64,
+    config: &PlotConfig,
+) -> Document {
+    let width = positions as f64 * config.cell_size;
+    document = document.add(axis_line(0.0, height, width, height));
+    for position in (0..positions).step_by(config.tick_every.max(1)) {
+        let x = (position as f64 + 0.5) * config.cell_size;
+        document = document
+            .add(axis_line(x, height, x, height + 3.0))
+            .add(label(
+                x,
+                height + 3.0 + config.font_size,
+                &position.to_string(),
+                config,
+                "middle",
+            ));
+    }
+    document
+}
+
+/// Write `values` (rows of the image) as an RGB PNG, each cell a square of pixels.
+fn write_png<P: AsRef<Path>>(
+    values: &[Vec<f32>],
+    scale: f32,
+    path: P,
+    pixels_per_cell: u32,
+) -> Result<()> {
+    let rows = values.len() as u32;
+    let columns = values.first().map_or(0, |row| row.len()) as u32;
+    if rows == 0 || columns == 0 {
+        bail!("Nothing to plot");
+    }
+    let pixels = pixels_per_cell.max(1);
+    let (width, height) = (columns * pixels, rows * pixels);
+    let mut data = Vec::with_capacity((width * height * 3) as usize);
+    for row in values {
+        let line: Vec<u8> = row
+            .iter()
+            .flat_map(|&value| colormap((value / scale) as f64).repeat(pixels as usize))
+            .collect();
+        for _ in 0..pixels {
+            data.extend_from_slice(&line);
+        }
+    }
+    let file = BufWriter::new(File::create(path)?);
+    let mut encoder = png::Encoder::new(file, width, height);
+    encoder.set_color(png::ColorType::Rgb);
+    encoder.set_depth(png::BitDepth::Eight);
+    let mut writer = encoder.write_header()?;
+    writer.write_image_data(&data)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn pseudo_probabilities() -> Vec<PseudoProbability> {
+        // position 0 is certain, position 1 is split between two residues, and the
+        // special token '<' is ignored
+        [(0, 'M', 0.9), (0, '<', 0.1), (1, 'K', 0.5), (1, 'R', 0.5)]
+            .into_iter()
+            .map(|(position, amino_acid, pseudo_prob)| PseudoProbability {
+                position,
+                amino_acid,
+                pseudo_prob,
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_probability_matrix() {
+        let matrix = ProbabilityMatrix::from_pseudo_probabilities(&pseudo_probabilities());
+        assert_eq!(matrix.len(), 2);
+        assert_eq!(matrix.consensus().chars().next(), Some('M'));
+        assert!((matrix.information_content(0) - 20f32.log2()).abs() < 1e-5);
+        assert!((matrix.information_content(1) - (20f32.log2() - 1.0)).abs() < 1e-5);
+        assert!(ProbabilityMatrix::new(vec!['A', 'C'], vec![vec![1.0]]).is_err());
+
+        let logo = sequence_logo(&matrix, &PlotConfig::default()).to_string();
+        assert!(logo.contains(">M<"));
+        assert!(logo.contains(">K<") && logo.contains(">R<"));
+        let heatmap = heatmap(&matrix, &PlotConfig::default()).to_string();
+        assert_eq!(heatmap.matches("<rect").count(), 2 * AMINO_ACIDS.len());
+    }
+
+    #[test]
+    fn test_score_output_probabilities() {
+        let device = candle_core::Device::Cpu;
+        // batch 0 is uniform; batch 1 favours W, A and then the unknown residue X
+        let mut logits = vec![0f32; 2 * 3 * 21];
+        for (position, aa) in [18, 0, 20].into_iter().enumerate() {
+            logits[(3 + position) * 21 + aa] = 4.0;
+        }
+        let logits = candle_core::Tensor::from_vec(logits, (2, 3, 21), &device).unwrap();
+        let score = ScoreOutput {
+            s: candle_core::Tensor::zeros((2, 3), candle_core::DType::U32, &device).unwrap(),
+            log_probs: candle_nn::ops::log_softmax(&logits, candle_core::D::Minus1).unwrap(),
+            logits,
+            decoding_order: candle_core::Tensor::zeros((2, 3), candle_core::DType::U32, &device)
+                .unwrap(),
+        };
+
+        let probs = score.get_probabilities(0).unwrap();
+        assert_eq!(probs.len(), 3);
+        for row in &probs {
+            assert_eq!(row.len(), 21);
+            assert!(row.iter().all(|&p| (p - 1.0 / 21.0).abs() < 1e-6));
+        }
+        let probs = score.get_probabilities(1).unwrap();
+        for row in &probs {
+            assert!((row.iter().sum::<f32>() - 1.0).abs() < 1e-5);
+        }
+
+        // columns follow the model's alphabet, with X last
+        let matrix = ProbabilityMatrix::from_score_output(&score, 1).unwrap();
+        assert_eq!(
+            matrix.get_alphabet().iter().collect::<String>(),
+            "ACDEFGHIKLMNPQRSTVWYX"
+        );
+        assert_eq!(matrix.get_probs(), probs.as_slice());
+        assert_eq!(matrix.consensus(), "WAX");
+    }
+
+    #[test]
+    fn test_contact_map() {
+        let contacts: Vec<ContactMap> = [(0, 1, 1, 0.2), (0, 1, 2, 0.8), (2, 0, 1, 0.4)]
+            .into_iter()
+            .map(
+                |(position_1, position_2, layer, contact_estimate)| ContactMap {
+                    position_1,
+                    position_2,
+                    amino_acid_1: 'A',
+                    amino_acid_2: 'A',
+                    layer,
+                    contact_estimate,
+                },
+            )
+            .collect();
+        let matrix = contact_matrix(&contacts, None);
+        assert_eq!(matrix.len(), 3);
+        assert_eq!(matrix[0][1], 0.8);
+        assert_eq!(matrix[1][0], 0.8);
+        assert_eq!(matrix[0][2], 0.4);
+        assert_eq!(contact_matrix(&contacts, Some(1))[0][1], 0.2);
+
+        let svg = contact_map(&contacts, None, &PlotConfig::default()).to_string();
+        // background plus the four estimated cells
+        assert_eq!(svg.matches("<rect").count(), 5);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("contacts.png");
+        contact_map_png(&contacts, None, &path, &PlotConfig::default()).unwrap();
+        let bytes = std::fs::read(&path).unwrap();
+        assert_eq!(&bytes[1..4], b"PNG");
+    }
+}
@@ -15,6 +15,8 @@
 //! let psedata = PSEData::load("path/to/file.pse").expect("local pse path");
 //! // Work with the loaded PSE data
 //! psedata.to_disk_full("my_output_directory");
+//! // or as a single file that opens without a web server
+//! psedata.to_mvsx("my_session.mvsx").expect("writable output");
 //! ```
 //!
 pub mod pymolparsing;
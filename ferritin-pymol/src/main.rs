@@ -8,9 +8,13 @@ struct Args {
     #[arg(short, long)]
     psefile: String,
 
-    /// Output directory
+    /// Output directory, or the `.mvsx` file to write with `--mvsx`
     #[arg(short, long)]
     outputdir: String,
+
+    /// Write a single self-contained MVSX archive instead of a directory
+    #[arg(long)]
+    mvsx: bool,
 }
 
 fn main() {
@@ -20,5 +24,11 @@ fn main() {
     println!("Output directory: {}", args.outputdir);
 
     let psedata: PSEData = PSEData::load(&args.psefile).expect("Reachable PSE file");
-    let _ = psedata.to_disk_full(&args.outputdir);
+    if args.mvsx {
+        psedata
+            .to_mvsx(&args.outputdir)
+            .expect("Writable MVSX file");
+    } else {
+        let _ = psedata.to_disk_full(&args.outputdir);
+    }
 }
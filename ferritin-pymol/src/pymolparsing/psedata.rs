@@ -10,6 +10,7 @@ use crate::pymolparsing::parsing::{
     PyObjectMolecule, PymolSessionObjectData, SceneView, SessionName, SessionSelectorList,
     Settings, SettingsEnum,
 };
+use ferritin_molviewspec::molviewspec::archive::MvsArchive;
 use ferritin_molviewspec::molviewspec::nodes::{self as mvsnodes, ColorNamesT, State};
 use pdbtbx::PDB;
 use serde::{Deserialize, Serialize};
@@ -18,7 +19,7 @@ use serde_pickle::de::{from_reader, DeOptions};
 use std::collections::HashMap;
 use std::fs;
 use std::fs::File;
-use std::io::Read;
+use std::io::{BufWriter, Read};
 use std::path::Path;
 
 /// PSEData represents the structure of a PyMOL Session File (PSE).
@@ -292,6 +293,22 @@ impl PSEData {
         let _ = self.to_disk(file_path);
         Ok(())
     }
+    /// Pymol --> MVSX. A single archive holding the state and the PDB files it loads, which
+    /// can be shared and opened without a web server or the html/js resources.
+    pub fn to_mvsx(&self, file_path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let mut archive = MvsArchive::new(self.create_molviewspec());
+        for molecule in self.get_molecule_data() {
+            let mut pdb_bytes = Vec::new();
+            pdbtbx::save_pdb_raw(
+                &molecule.to_pdb(),
+                BufWriter::new(&mut pdb_bytes),
+                pdbtbx::StrictnessLevel::Strict,
+            );
+            archive.add_file(&format!("pdb/{}.pdb", molecule.get_name()), pdb_bytes);
+        }
+        archive.write(file_path)?;
+        Ok(())
+    }
     /// To loadable MSVJ URL
     /// See also [msvj URL encoding](https://molstar.org/mol-view-spec-docs/mvs-molstar-extension/)
     pub fn to_mvsj_url(&self) -> String {
@@ -1,3 +1,4 @@
+use ferritin_molviewspec::molviewspec::archive::MvsArchive;
 use ferritin_pymol::pymolparsing::colors::Color;
 use ferritin_pymol::pymolparsing::parsing::{CoordSet, CustomValue, SettingsEnum};
 use ferritin_pymol::pymolparsing::representation::RepBitmask;
@@ -111,6 +112,21 @@ fn test_pdb_01() {
     println!("{}", url);
 }
 
+#[test]
+fn test_mvsx() {
+    let psedata: PSEData = PSEData::load("tests/data/example.pse").unwrap();
+    std::fs::create_dir_all(TEST_OUTPUT_DIR).unwrap();
+    let mvsx = format!("{}/example.mvsx", TEST_OUTPUT_DIR);
+    psedata.to_mvsx(&mvsx).unwrap();
+
+    let archive = MvsArchive::read(&mvsx).unwrap();
+    let uris = archive.relative_uris();
+    assert_eq!(uris.len(), psedata.get_molecule_data().len());
+    for uri in uris {
+        assert!(!archive.get_file(&uri).unwrap().is_empty());
+    }
+}
+
 #[test]
 fn test_colors() {
     // https://github.com/schrodinger/pymol-open-source/blob/master/layer1/Color.cpp#L880